@@ -0,0 +1,259 @@
+//! The standard procedures.
+//!
+//! Each submodule defines one group of R7RS procedures and registers them
+//! with `install`. Groups that reach the host system are only installed
+//! when the interpreter has their capability. The helpers here check and
+//! unpack arguments, producing errors that name the procedure.
+
+mod chars;
+mod control;
+mod files;
+mod hash_tables;
+mod json;
+mod lists;
+pub(crate) mod load;
+mod net;
+mod numbers;
+mod output;
+pub(crate) mod ports;
+mod process;
+mod srfi1;
+mod strings;
+mod time;
+mod vectors;
+
+pub(crate) use control::{guard, parameterize, raise};
+pub(crate) use lists::{append, cons};
+pub(crate) use vectors::list_to_vector;
+
+use crate::capability::Capability;
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::limits;
+use crate::num::Number;
+use crate::proc::Procedure;
+use crate::symbol::Symbol;
+use crate::value::{Bytevector, SchemeString, Value, Vector};
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+
+pub(crate) fn install(interp: &mut Interpreter) {
+    chars::install(interp);
+    control::install(interp);
+    hash_tables::install(interp);
+    json::install(interp);
+    lists::install(interp);
+    numbers::install(interp);
+    output::install(interp);
+    ports::install(interp);
+    srfi1::install(interp);
+    strings::install(interp);
+    time::install(interp);
+    vectors::install(interp);
+    if interp.capabilities().contains(Capability::Filesystem) {
+        files::install(interp);
+        load::install(interp);
+        ports::install_files(interp);
+    }
+    if interp.capabilities().contains(Capability::Network) {
+        net::install(interp);
+    }
+    if interp.capabilities().contains(Capability::Process) {
+        process::install(interp);
+    }
+}
+
+fn number<'a>(name: &str, value: &'a Value) -> Result<&'a Number, Error> {
+    match value {
+        Value::Number(n) => Ok(n),
+        _ => Err(Error::wrong_type(name, "a number", value)),
+    }
+}
+
+fn integer<'a>(name: &str, value: &'a Value) -> Result<&'a Number, Error> {
+    match value {
+        Value::Number(n) if n.is_integer() => Ok(n),
+        _ => Err(Error::wrong_type(name, "an integer", value)),
+    }
+}
+
+/// The most memory, in bytes, that one call may allocate for a new vector,
+/// string, bytevector or list.
+const MAX_ALLOCATION: usize = 1 << 30;
+
+/// An empty `Vec` with room for `len` items, or an implementation
+/// restriction if that is more than [`MAX_ALLOCATION`] or more than can be
+/// allocated, so that huge lengths fail instead of aborting the host.
+fn reserve<T>(name: &str, len: usize) -> Result<Vec<T>, Error> {
+    let mut items = Vec::new();
+    let bytes = len.saturating_mul(std::mem::size_of::<T>());
+    if bytes > MAX_ALLOCATION || items.try_reserve_exact(len).is_err() {
+        return Err(Error::new(
+            ConditionKind::ImplementationRestriction,
+            format!("{}: length too large", name),
+            vec![Value::from(len as i64)],
+        ));
+    }
+    Ok(items)
+}
+
+/// An exact non-negative integer that fits in a `usize`, such as an index
+/// or a length.
+fn index(name: &str, value: &Value) -> Result<usize, Error> {
+    match value {
+        Value::Number(n) => n
+            .to_i64()
+            .and_then(|i| usize::try_from(i).ok())
+            .ok_or_else(|| Error::wrong_type(name, "an exact non-negative integer", value)),
+        _ => Err(Error::wrong_type(
+            name,
+            "an exact non-negative integer",
+            value,
+        )),
+    }
+}
+
+/// The optional `start` and `end` arguments at `args[at..]`, defaulting to
+/// the whole of a sequence of length `len`.
+fn range(name: &str, args: &[Value], at: usize, len: usize) -> Result<Range<usize>, Error> {
+    let start = match args.get(at) {
+        Some(start) => index(name, start)?,
+        None => 0,
+    };
+    let end = match args.get(at + 1) {
+        Some(end) => index(name, end)?,
+        None => len,
+    };
+    if start > end || end > len {
+        return Err(Error::new(
+            crate::eval::ConditionKind::Range,
+            format!(
+                "{}: invalid range {}..{} for length {}",
+                name, start, end, len
+            ),
+            Vec::new(),
+        ));
+    }
+    Ok(start..end)
+}
+
+fn character(name: &str, value: &Value) -> Result<char, Error> {
+    match value {
+        Value::Character(c) => Ok(*c),
+        _ => Err(Error::wrong_type(name, "a character", value)),
+    }
+}
+
+fn string<'a>(name: &str, value: &'a Value) -> Result<&'a Rc<RefCell<SchemeString>>, Error> {
+    match value {
+        Value::String(s) => Ok(s),
+        _ => Err(Error::wrong_type(name, "a string", value)),
+    }
+}
+
+fn symbol(name: &str, value: &Value) -> Result<Symbol, Error> {
+    match value {
+        Value::Symbol(sym) => Ok(*sym),
+        _ => Err(Error::wrong_type(name, "a symbol", value)),
+    }
+}
+
+fn vector<'a>(name: &str, value: &'a Value) -> Result<&'a Rc<RefCell<Vector>>, Error> {
+    match value {
+        Value::Vector(items) => Ok(items),
+        _ => Err(Error::wrong_type(name, "a vector", value)),
+    }
+}
+
+fn bytevector<'a>(name: &str, value: &'a Value) -> Result<&'a Rc<RefCell<Bytevector>>, Error> {
+    match value {
+        Value::Bytevector(bytes) => Ok(bytes),
+        _ => Err(Error::wrong_type(name, "a bytevector", value)),
+    }
+}
+
+fn procedure<'a>(name: &str, value: &'a Value) -> Result<&'a Rc<Procedure>, Error> {
+    match value {
+        Value::Procedure(procedure) => Ok(procedure),
+        _ => Err(Error::wrong_type(name, "a procedure", value)),
+    }
+}
+
+/// The elements of a proper list.
+fn list(name: &str, value: &Value) -> Result<Vec<Value>, Error> {
+    let items = value
+        .list_to_vec()
+        .ok_or_else(|| Error::wrong_type(name, "a proper list", value))?;
+    limits::charge(items.len() as u64);
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+
+    /// The procedures of the R7RS `(scheme base)` library.
+    const SCHEME_BASE: &str = "\
+        * + - / < <= = > >= abs append apply assoc assq assv binary-port? boolean=? boolean? \
+        bytevector bytevector-append bytevector-copy bytevector-copy! bytevector-length \
+        bytevector-u8-ref bytevector-u8-set! bytevector? caar cadr \
+        call-with-current-continuation call-with-port call-with-values call/cc car cdar cddr \
+        cdr ceiling char->integer char-ready? char<=? char<? char=? char>=? char>? char? \
+        close-input-port close-output-port close-port complex? cons current-error-port \
+        current-input-port current-output-port denominator dynamic-wind eof-object eof-object? \
+        eq? equal? eqv? error error-object-irritants error-object-message error-object? even? \
+        exact exact-integer-sqrt exact-integer? exact? expt features file-error? floor \
+        floor-quotient floor-remainder floor/ flush-output-port for-each gcd \
+        get-output-bytevector get-output-string inexact inexact? input-port-open? input-port? \
+        integer->char integer? lcm length list list->string list->vector list-copy list-ref \
+        list-set! list-tail list? make-bytevector make-list make-parameter make-string \
+        make-vector map max member memq memv min modulo negative? newline not null? \
+        number->string number? numerator odd? open-input-bytevector open-input-string \
+        open-output-bytevector open-output-string output-port-open? output-port? pair? \
+        peek-char peek-u8 positive? procedure? quotient raise raise-continuable rational? \
+        rationalize read-bytevector read-bytevector! read-char read-error? read-line \
+        read-string read-u8 real? remainder reverse round set-car! set-cdr! square string \
+        string->list string->number string->symbol string->utf8 string->vector string-append \
+        string-copy string-copy! string-fill! string-for-each string-length string-map \
+        string-ref string-set! string<=? string<? string=? string>=? string>? string? \
+        substring symbol->string symbol=? symbol? textual-port? truncate truncate-quotient \
+        truncate-remainder truncate/ u8-ready? utf8->string values vector vector->list \
+        vector->string vector-append vector-copy vector-copy! vector-fill! vector-for-each \
+        vector-length vector-map vector-ref vector-set! vector? with-exception-handler \
+        write-bytevector write-char write-string write-u8 zero?";
+
+    #[test]
+    fn scheme_base_is_complete() {
+        let mut scheme = Scheme::new();
+        let interp = scheme.interpreter();
+        let missing: Vec<_> = SCHEME_BASE
+            .split_whitespace()
+            .filter(|name| !matches!(interp.lookup(name), Some(Value::Procedure(_))))
+            .collect();
+        assert!(missing.is_empty(), "missing {:?}", missing);
+    }
+
+    #[test]
+    fn huge_requests_raise_implementation_restrictions() {
+        let mut scheme = Scheme::new();
+        for text in [
+            "(make-vector 4611686018427387904 0)",
+            "(make-string 4611686018427387904)",
+            "(make-bytevector 4611686018427387904)",
+            "(make-list 4611686018427387904)",
+            "(expt 2 4000000000)",
+            "(expt 7/3 1000000)",
+        ] {
+            let err = scheme.eval_str(text).unwrap_err();
+            let kind = err.condition().map(|condition| condition.kind);
+            assert_eq!(
+                kind,
+                Some(ConditionKind::ImplementationRestriction),
+                "{}",
+                text
+            );
+        }
+        assert!(scheme.eval_str("(expt 2 100000)").is_ok());
+    }
+}
@@ -0,0 +1,127 @@
+//! Characters.
+
+use super::{character, index};
+use crate::chars;
+use crate::eval::{Error, Interpreter};
+use crate::proc::Arity;
+use crate::value::Value;
+
+/// Defines a variadic comparison over characters, after mapping each one
+/// through `$key`.
+macro_rules! char_comparisons {
+    ($($func:ident, $name:literal, $key:expr, $op:tt;)*) => {
+        $(
+            fn $func(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+                let chars = args
+                    .iter()
+                    .map(|arg| character($name, arg).map($key))
+                    .collect::<Result<Vec<char>, _>>()?;
+                Ok(Value::Boolean(chars.windows(2).all(|w| w[0] $op w[1])))
+            }
+        )*
+
+        fn install_comparisons(interp: &mut Interpreter) {
+            $(interp.define_primitive($name, Arity::at_least(1), $func);)*
+        }
+    };
+}
+
+char_comparisons! {
+    char_eq, "char=?", |c| c, ==;
+    char_lt, "char<?", |c| c, <;
+    char_gt, "char>?", |c| c, >;
+    char_le, "char<=?", |c| c, <=;
+    char_ge, "char>=?", |c| c, >=;
+    char_ci_eq, "char-ci=?", chars::foldcase, ==;
+    char_ci_lt, "char-ci<?", chars::foldcase, <;
+    char_ci_gt, "char-ci>?", chars::foldcase, >;
+    char_ci_le, "char-ci<=?", chars::foldcase, <=;
+    char_ci_ge, "char-ci>=?", chars::foldcase, >=;
+}
+
+pub(super) fn install(interp: &mut Interpreter) {
+    install_comparisons(interp);
+    interp.define_primitive("char?", Arity::exactly(1), is_char);
+    interp.define_primitive("char->integer", Arity::exactly(1), char_to_integer);
+    interp.define_primitive("integer->char", Arity::exactly(1), integer_to_char);
+    interp.define_primitive("char-alphabetic?", Arity::exactly(1), is_alphabetic);
+    interp.define_primitive("char-numeric?", Arity::exactly(1), is_numeric);
+    interp.define_primitive("char-whitespace?", Arity::exactly(1), is_whitespace);
+    interp.define_primitive("char-upper-case?", Arity::exactly(1), is_upper_case);
+    interp.define_primitive("char-lower-case?", Arity::exactly(1), is_lower_case);
+    interp.define_primitive("digit-value", Arity::exactly(1), digit_value);
+    interp.define_primitive("char-upcase", Arity::exactly(1), upcase);
+    interp.define_primitive("char-downcase", Arity::exactly(1), downcase);
+    interp.define_primitive("char-foldcase", Arity::exactly(1), foldcase);
+}
+
+fn is_char(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Character(_))))
+}
+
+fn char_to_integer(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::from(character("char->integer", &args[0])? as i64))
+}
+
+fn integer_to_char(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let code = index("integer->char", &args[0])?;
+    u32::try_from(code)
+        .ok()
+        .and_then(char::from_u32)
+        .map(Value::Character)
+        .ok_or_else(|| Error::wrong_type("integer->char", "a Unicode scalar value", &args[0]))
+}
+
+fn test(name: &str, args: &[Value], test: fn(char) -> bool) -> Result<Value, Error> {
+    Ok(Value::Boolean(test(character(name, &args[0])?)))
+}
+
+fn is_alphabetic(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    test("char-alphabetic?", args, chars::is_alphabetic)
+}
+
+fn is_numeric(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    test("char-numeric?", args, chars::is_numeric)
+}
+
+fn is_whitespace(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    test("char-whitespace?", args, chars::is_whitespace)
+}
+
+fn is_upper_case(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    test("char-upper-case?", args, chars::is_upper_case)
+}
+
+fn is_lower_case(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    test("char-lower-case?", args, chars::is_lower_case)
+}
+
+fn digit_value(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(
+        match chars::digit_value(character("digit-value", &args[0])?) {
+            Some(d) => Value::from(d as i64),
+            None => Value::Boolean(false),
+        },
+    )
+}
+
+fn upcase(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Character(chars::upcase(character(
+        "char-upcase",
+        &args[0],
+    )?)))
+}
+
+fn downcase(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Character(chars::downcase(character(
+        "char-downcase",
+        &args[0],
+    )?)))
+}
+
+fn foldcase(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Character(chars::foldcase(character(
+        "char-foldcase",
+        &args[0],
+    )?)))
+}
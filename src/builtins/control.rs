@@ -0,0 +1,822 @@
+//! Procedures, control flow, exceptions and equivalence.
+
+use super::lists::{shape, Shape};
+use super::{list, procedure, string};
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::limits;
+use crate::proc::{Arity, Parameter, Primitive, Procedure};
+use crate::value::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("eq?", Arity::exactly(2), is_eq);
+    interp.define_primitive("eqv?", Arity::exactly(2), is_eqv);
+    interp.define_primitive("equal?", Arity::exactly(2), is_equal);
+    interp.define_primitive("not", Arity::exactly(1), not);
+    interp.define_primitive("boolean?", Arity::exactly(1), is_boolean);
+    interp.define_primitive("boolean=?", Arity::at_least(1), boolean_eq);
+    interp.define_primitive("procedure?", Arity::exactly(1), is_procedure);
+    interp.define_primitive("trace", Arity::exactly(1), trace);
+    interp.define_primitive("untrace", Arity::exactly(1), untrace);
+    interp.define_primitive("apply", Arity::at_least(1), apply);
+    interp.define_primitive("eval", Arity::between(1, 2), eval);
+    interp.define_primitive(
+        "interaction-environment",
+        Arity::exactly(0),
+        interaction_environment,
+    );
+    interp.define_primitive("map", Arity::at_least(2), map);
+    interp.define_primitive("for-each", Arity::at_least(2), for_each);
+    interp.define_primitive("call-with-current-continuation", Arity::exactly(1), call_cc);
+    interp.define_primitive("call/cc", Arity::exactly(1), call_cc);
+    interp.define_primitive("values", Arity::at_least(0), values);
+    interp.define_primitive("call-with-values", Arity::exactly(2), call_with_values);
+    interp.define_primitive("dynamic-wind", Arity::exactly(3), dynamic_wind);
+    interp.define_primitive("make-parameter", Arity::between(1, 2), make_parameter);
+    interp.define_primitive("error", Arity::at_least(1), error);
+    interp.define_primitive("raise", Arity::exactly(1), raise);
+    interp.define_primitive("raise-continuable", Arity::exactly(1), raise_continuable);
+    interp.define_primitive(
+        "with-exception-handler",
+        Arity::exactly(2),
+        with_exception_handler,
+    );
+    interp.define_primitive(
+        "current-continuation-marks",
+        Arity::exactly(0),
+        current_continuation_marks,
+    );
+    interp.define_primitive(
+        "continuation-mark-set->list",
+        Arity::exactly(2),
+        continuation_mark_set_to_list,
+    );
+    interp.define_primitive(
+        "continuation-mark-set-first",
+        Arity::between(2, 3),
+        continuation_mark_set_first,
+    );
+    interp.define_primitive("error-object?", Arity::exactly(1), is_error_object);
+    interp.define_primitive(
+        "error-object-message",
+        Arity::exactly(1),
+        error_object_message,
+    );
+    interp.define_primitive(
+        "error-object-irritants",
+        Arity::exactly(1),
+        error_object_irritants,
+    );
+    interp.define_primitive("file-error?", Arity::exactly(1), is_file_error);
+    interp.define_primitive("read-error?", Arity::exactly(1), is_read_error);
+    interp.define_primitive("features", Arity::exactly(0), features);
+}
+
+pub(super) fn is_eq(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(args[0].is_eq(&args[1])))
+}
+
+pub(super) fn is_eqv(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(args[0].is_eqv(&args[1])))
+}
+
+pub(super) fn is_equal(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(args[0].is_equal(&args[1])))
+}
+
+fn not(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(!args[0].is_true()))
+}
+
+fn is_boolean(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Boolean(_))))
+}
+
+fn boolean_eq(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let booleans = args
+        .iter()
+        .map(|arg| match arg {
+            Value::Boolean(b) => Ok(*b),
+            _ => Err(Error::wrong_type("boolean=?", "a boolean", arg)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::Boolean(booleans.windows(2).all(|w| w[0] == w[1])))
+}
+
+fn is_procedure(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Procedure(_))))
+}
+
+fn trace(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    set_traced("trace", &args[0], true)
+}
+
+fn untrace(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    set_traced("untrace", &args[0], false)
+}
+
+/// Marks a closure so that its calls are printed or no longer printed.
+/// Every call to the closure is affected, including recursive ones.
+fn set_traced(name: &str, value: &Value, traced: bool) -> Result<Value, Error> {
+    match &**procedure(name, value)? {
+        Procedure::Closure(closure) => {
+            closure.traced.set(traced);
+            Ok(Value::Unspecified)
+        }
+        _ => Err(Error::wrong_type(name, "a closure", value)),
+    }
+}
+
+fn apply(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (last, init) = args[1..]
+        .split_last()
+        .map_or((None, &[][..]), |(last, init)| (Some(last), init));
+    let mut call_args = init.to_vec();
+    if let Some(last) = last {
+        call_args.extend(list("apply", last)?);
+    }
+    interp.apply(&args[0], &call_args)
+}
+
+/// `(eval expr [environment])`, evaluating `expr` at top level. The global
+/// environment is the only one there is, so it is the default.
+fn eval(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match args.get(1) {
+        None | Some(Value::Environment) => interp.eval(&args[0]),
+        Some(other) => Err(Error::wrong_type("eval", "an environment", other)),
+    }
+}
+
+fn interaction_environment(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Environment)
+}
+
+/// The elements of `lists`, column by column up to the length of the
+/// shortest list. Some of the lists may be circular, but not all of them.
+pub(super) fn columns(name: &str, lists: &[Value]) -> Result<Vec<Vec<Value>>, Error> {
+    if !lists.is_empty()
+        && lists
+            .iter()
+            .all(|list| matches!(shape(list), Shape::Circular))
+    {
+        return Err(Error::wrong_type(name, "a finite list", &lists[0]));
+    }
+    let mut columns = Columns::new(name, lists);
+    let mut result = Vec::new();
+    while let Some(column) = columns.next()? {
+        result.push(column);
+    }
+    Ok(result)
+}
+
+/// Walks lists in step, a column of elements at a time, for procedures
+/// that may stop before the end and so accept lists that are all circular.
+pub(super) struct Columns<'a> {
+    name: &'a str,
+    lists: &'a [Value],
+    rests: Vec<Value>,
+}
+
+impl<'a> Columns<'a> {
+    pub(super) fn new(name: &'a str, lists: &'a [Value]) -> Self {
+        Self {
+            name,
+            lists,
+            rests: lists.to_vec(),
+        }
+    }
+
+    /// The next column, or `None` once the shortest list has run out.
+    pub(super) fn next(&mut self) -> Result<Option<Vec<Value>>, Error> {
+        if self.rests.is_empty() {
+            return Ok(None);
+        }
+        let mut column = Vec::with_capacity(self.rests.len());
+        for (rest, list) in self.rests.iter_mut().zip(self.lists) {
+            let next = match &*rest {
+                Value::Pair(pair) => {
+                    column.push(pair.car());
+                    pair.cdr()
+                }
+                Value::Null => return Ok(None),
+                _ => return Err(Error::wrong_type(self.name, "a proper list", list)),
+            };
+            *rest = next;
+        }
+        limits::charge(1);
+        Ok(Some(column))
+    }
+}
+
+fn map(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("map", &args[0])?;
+    let results = columns("map", &args[1..])?
+        .into_iter()
+        .map(|column| interp.apply(&args[0], &column))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::list(results))
+}
+
+fn for_each(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("for-each", &args[0])?;
+    for column in columns("for-each", &args[1..])? {
+        interp.apply(&args[0], &column)?;
+    }
+    Ok(Value::Unspecified)
+}
+
+fn call_cc(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("call/cc", &args[0])?;
+    interp.call_with_escape(&args[0])
+}
+
+fn values(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::values(args.to_vec()))
+}
+
+fn call_with_values(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let produced = interp.apply(&args[0], &[])?;
+    match produced {
+        Value::Values(values) => interp.apply(&args[1], &values),
+        value => interp.apply(&args[1], &[value]),
+    }
+}
+
+fn dynamic_wind(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    interp.apply(&args[0], &[])?;
+    let result = interp.apply(&args[1], &[]);
+    interp.apply(&args[2], &[])?;
+    result
+}
+
+fn make_parameter(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let converter = args.get(1).cloned();
+    let value = match &converter {
+        Some(converter) => {
+            procedure("make-parameter", converter)?;
+            interp.apply(converter, &args[..1])?
+        }
+        None => args[0].clone(),
+    };
+    Ok(Value::Procedure(Rc::new(Procedure::Parameter(Parameter {
+        value: RefCell::new(value),
+        converter,
+    }))))
+}
+
+/// What `parameterize` is lowered to: calls the thunk in `args[0]` with
+/// each parameter in the rest of `args` set to the value following it.
+pub(crate) fn parameterize(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (thunk, bindings) = args.split_first().expect("arity checked");
+    let mut converted = Vec::with_capacity(bindings.len() / 2);
+    for binding in bindings.chunks(2) {
+        let (parameter, value) = (&binding[0], &binding[1]);
+        let Value::Procedure(procedure) = parameter else {
+            return Err(Error::wrong_type("parameterize", "a parameter", parameter));
+        };
+        let Procedure::Parameter(param) = &**procedure else {
+            return Err(Error::wrong_type("parameterize", "a parameter", parameter));
+        };
+        let value = match &param.converter {
+            Some(converter) => interp.apply(converter, std::slice::from_ref(value))?,
+            None => value.clone(),
+        };
+        converted.push((procedure.clone(), value));
+    }
+    bind_parameters(interp, &converted, thunk)
+}
+
+fn bind_parameters(
+    interp: &mut Interpreter,
+    bindings: &[(Rc<Procedure>, Value)],
+    thunk: &Value,
+) -> Result<Value, Error> {
+    let Some(((procedure, value), rest)) = bindings.split_first() else {
+        return interp.apply(thunk, &[]);
+    };
+    let Procedure::Parameter(parameter) = &**procedure else {
+        unreachable!("checked by parameterize");
+    };
+    parameter.with(value.clone(), || bind_parameters(interp, rest, thunk))
+}
+
+fn error(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let message = string("error", &args[0])?.borrow().to_string();
+    Err(Error::new(
+        ConditionKind::Error,
+        message,
+        args[1..].to_vec(),
+    ))
+}
+
+pub(crate) fn raise(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Err(Error::Raise(args[0].clone()))
+}
+
+/// Calls the current handler directly and returns what it returns. The
+/// handler runs with the outer handlers installed.
+fn raise_continuable(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let Some(handler) = interp.handlers.pop() else {
+        return Err(Error::Raise(args[0].clone()));
+    };
+    let result = interp.apply(&handler, &args[..1]);
+    interp.handlers.push(handler);
+    result
+}
+
+/// Non-continuable exceptions unwind to here before the handler is called,
+/// so `dynamic-wind` after thunks inside `thunk` have already run by the
+/// time the handler sees the exception.
+fn with_exception_handler(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (handler, thunk) = (&args[0], &args[1]);
+    procedure("with-exception-handler", handler)?;
+    interp.handlers.push(handler.clone());
+    let result = interp.apply(thunk, &[]);
+    interp.handlers.pop();
+    match result {
+        Err(Error::Raise(obj)) => {
+            interp.apply(handler, std::slice::from_ref(&obj))?;
+            Err(Error::new(
+                ConditionKind::Error,
+                "exception handler returned from a non-continuable exception",
+                vec![obj],
+            ))
+        }
+        result => result,
+    }
+}
+
+/// What `guard` is lowered to: calls the thunk in `args[0]` and, if it
+/// raises an object, the handler in `args[1]` with the object. The handler
+/// runs once the thunk has been unwound, with the handlers outside the
+/// `guard` installed. While the thunk runs, `raise-continuable` unwinds to
+/// here too.
+pub(crate) fn guard(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let unwind = Primitive {
+        name: "raise",
+        arity: Arity::exactly(1),
+        func: raise,
+    };
+    interp
+        .handlers
+        .push(Value::Procedure(Rc::new(Procedure::Primitive(unwind))));
+    let result = interp.apply(&args[0], &[]);
+    interp.handlers.pop();
+    match result {
+        Err(Error::Raise(obj)) => interp.apply(&args[1], &[obj]),
+        result => result,
+    }
+}
+
+fn current_continuation_marks(interp: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(interp.continuation_marks())
+}
+
+/// The values marked with `key` in a set from `current-continuation-marks`,
+/// innermost first.
+fn marks(name: &str, set: &Value, key: &Value) -> Result<Vec<Value>, Error> {
+    let mut values = Vec::new();
+    for mark in list(name, set)? {
+        let Some(mark) = mark.as_pair() else {
+            return Err(Error::wrong_type(name, "a continuation mark set", set));
+        };
+        if mark.car().is_eqv(key) {
+            values.push(mark.cdr());
+        }
+    }
+    Ok(values)
+}
+
+fn continuation_mark_set_to_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let values = marks("continuation-mark-set->list", &args[0], &args[1])?;
+    Ok(Value::list(values))
+}
+
+fn continuation_mark_set_first(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let values = marks("continuation-mark-set-first", &args[0], &args[1])?;
+    let default = args.get(2).cloned().unwrap_or(Value::Boolean(false));
+    Ok(values.into_iter().next().unwrap_or(default))
+}
+
+fn is_error_object(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Condition(_))))
+}
+
+fn error_object_message(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Condition(condition) => Ok(Value::string(&condition.message)),
+        value => Err(Error::wrong_type(
+            "error-object-message",
+            "an error object",
+            value,
+        )),
+    }
+}
+
+fn error_object_irritants(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Condition(condition) => Ok(Value::list(condition.irritants.iter().cloned())),
+        value => Err(Error::wrong_type(
+            "error-object-irritants",
+            "an error object",
+            value,
+        )),
+    }
+}
+
+/// Any failure to open, read, write or close a file, port or socket counts
+/// as a file error.
+fn is_file_error(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(is_condition(&args[0], ConditionKind::Io)))
+}
+
+fn is_read_error(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(is_condition(&args[0], ConditionKind::Read)))
+}
+
+fn is_condition(value: &Value, kind: ConditionKind) -> bool {
+    matches!(value, Value::Condition(condition) if condition.kind == kind)
+}
+
+/// The feature identifiers of R7RS appendix B that apply, followed by the
+/// host's operating system, architecture and byte order.
+fn features(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    let endianness = if cfg!(target_endian = "little") {
+        "little-endian"
+    } else {
+        "big-endian"
+    };
+    let features = [
+        "r7rs",
+        "exact-closed",
+        "exact-complex",
+        "ieee-float",
+        "full-unicode",
+        "ratios",
+        std::env::consts::FAMILY,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        endianness,
+        "scheme-rs",
+    ];
+    Ok(Value::list(features.into_iter().map(Value::symbol)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    #[test]
+    fn continuation_marks_follow_the_dynamic_extent() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str(
+                "(define (marks) (continuation-mark-set->list (current-continuation-marks) 'k))
+                 (define (loop n) (if (= n 0) (marks) (with-continuation-mark 'k n (loop (- n 1)))))",
+            )
+            .unwrap();
+        for (text, expected) in [
+            (
+                "(with-continuation-mark 'k 1 (list (with-continuation-mark 'k 2 (marks))))",
+                "((2 1))",
+            ),
+            (
+                "(with-continuation-mark 'k 1 (with-continuation-mark 'k 2 (marks)))",
+                "(2)",
+            ),
+            ("(loop 1000)", "(1)"),
+            ("(begin (with-continuation-mark 'k 1 #f) (marks))", "()"),
+            (
+                "(with-continuation-mark 'k 1
+                   (call/cc (lambda (k) (with-continuation-mark 'k 2 (k (marks))))))",
+                "(2 1)",
+            ),
+            (
+                "(continuation-mark-set-first (current-continuation-marks) 'missing 'none)",
+                "none",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        assert_eq!(scheme.eval_str("(marks)").unwrap().to_string(), "()");
+    }
+
+    #[test]
+    fn continuations_escape_from_their_extent() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(+ 1 (call/cc (lambda (k) (+ 10 (k 1)))))", "2"),
+            ("(+ 1 (call/cc (lambda (k) 10)))", "11"),
+            (
+                "(call/cc (lambda (outer) (call/cc (lambda (inner) (outer 'out))) 'after))",
+                "out",
+            ),
+            (
+                "(call-with-current-continuation
+                   (lambda (return) (for-each (lambda (x) (if (negative? x) (return x))) '(1 -2 3)) 'none))",
+                "-2",
+            ),
+            (
+                "(call-with-values (lambda () (call/cc (lambda (k) (k 1 2)))) list)",
+                "(1 2)",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn continuations_cannot_be_reentered() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str("(define saved #f) (+ 1 (call/cc (lambda (k) (set! saved k) 1)))")
+            .unwrap();
+        let err = scheme.eval_str("(saved 5)").unwrap_err();
+        assert_eq!(
+            err.condition().map(|condition| condition.kind),
+            Some(ConditionKind::Error)
+        );
+    }
+
+    #[test]
+    fn dynamic_wind_runs_the_after_thunks_on_every_exit() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str(
+                "(define trail '())
+                 (define (note x) (set! trail (cons x trail)))
+                 (define (wind tag thunk)
+                   (dynamic-wind (lambda () (note (list 'in tag)))
+                                 thunk
+                                 (lambda () (note (list 'out tag)))))
+                 (define (run thunk) (set! trail '()) (let ((v (thunk))) (list v (reverse trail))))",
+            )
+            .unwrap();
+        for (text, expected) in [
+            (
+                "(run (lambda () (wind 1 (lambda () (wind 2 (lambda () 'done))))))",
+                "(done ((in 1) (in 2) (out 2) (out 1)))",
+            ),
+            (
+                "(run (lambda () (call/cc (lambda (k) (wind 1 (lambda () (wind 2 (lambda () (k 'escaped)))))))))",
+                "(escaped ((in 1) (in 2) (out 2) (out 1)))",
+            ),
+            (
+                "(run (lambda ()
+                   (call/cc (lambda (k)
+                     (with-exception-handler (lambda (e) (k e))
+                       (lambda () (wind 1 (lambda () (raise 'boom)))))))))",
+                "(boom ((in 1) (out 1)))",
+            ),
+            (
+                "(run (lambda ()
+                   (with-exception-handler (lambda (e) 'handled)
+                     (lambda () (wind 1 (lambda () (raise-continuable 'oops)))))))",
+                "(handled ((in 1) (out 1)))",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn error_predicates_sort_conditions_by_kind() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str(
+                "(define (catch thunk)
+                   (call/cc (lambda (k) (with-exception-handler k thunk))))",
+            )
+            .unwrap();
+        for (text, expected) in [
+            (
+                "(let ((e (catch (lambda () (read (open-input-string \"(1\"))))))
+                   (list (read-error? e) (file-error? e) (error-object? e)))",
+                "(#t #f #t)",
+            ),
+            (
+                "(let ((e (catch (lambda () (open-input-file \"/no/such/file\")))))
+                   (list (read-error? e) (file-error? e)))",
+                "(#f #t)",
+            ),
+            (
+                "(let ((e (catch (lambda () (error \"oops\" 1 2)))))
+                   (list (read-error? e) (file-error? e) (error-object-message e) (error-object-irritants e)))",
+                "(#f #f oops (1 2))",
+            ),
+            ("(file-error? 'not-a-condition)", "#f"),
+            ("(and (memq 'r7rs (features)) #t)", "#t"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn guard_chooses_a_clause_for_the_raised_object() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(guard (e (#t (list 'caught e))) (raise 'boom))", "(caught boom)"),
+            (
+                "(guard (e ((symbol? e) 'symbol) ((string? e) (string-append e \"!\"))) (raise \"x\"))",
+                "x!",
+            ),
+            ("(guard (e ((assq 'a e) => cdr) ((assq 'b e))) (raise '((a . 42))))", "42"),
+            ("(guard (e ((assq 'a e) => cdr) ((assq 'b e))) (raise '((b . 23))))", "(b . 23)"),
+            (
+                "(guard (e ((error-object? e) (error-object-message e))) (error \"oops\"))",
+                "oops",
+            ),
+            ("(guard (e (else 'else)) (+ 1 (raise-continuable 'c)))", "else"),
+            ("(guard (e (#f 'no)) 'normal)", "normal"),
+            ("(let ((e 5)) (guard (x ((= x e) 'same)) (raise 5)))", "same"),
+            (
+                "(guard (e ((symbol? e) (guard (e (#t (list e 'inner))) (raise 'again))))
+                   (raise 'outer))",
+                "(again inner)",
+            ),
+            (
+                "(let ((trail '()))
+                   (guard (e (#t (reverse (cons e trail))))
+                     (dynamic-wind (lambda () (set! trail (cons 'in trail)))
+                                   (lambda () (raise 'raised))
+                                   (lambda () (set! trail (cons 'out trail))))))",
+                "(in out raised)",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn guard_raises_again_when_no_clause_applies() {
+        let mut scheme = Scheme::new();
+        let err = scheme
+            .eval_str("(guard (e ((string? e) 'string)) (raise 'unhandled))")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "uncaught exception: unhandled");
+        let value = scheme
+            .eval_str(
+                "(guard (outer (#t (list 'outer outer)))
+                   (guard (inner ((string? inner) 'string)) (raise 'unhandled)))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "(outer unhandled)");
+    }
+
+    #[test]
+    fn call_with_values_spreads_the_values_over_the_arguments() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(call-with-values (lambda () (values 1 2)) +)", "3"),
+            ("(call-with-values (lambda () (values)) list)", "()"),
+            ("(call-with-values (lambda () 5) list)", "(5)"),
+            (
+                "(call-with-values (lambda () (values 1 2 3)) list)",
+                "(1 2 3)",
+            ),
+            ("(+ 1 (values 2))", "3"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        let err = scheme
+            .eval_str("(call-with-values (lambda () (values 1 2)) (lambda (a) a))")
+            .unwrap_err();
+        assert_eq!(
+            err.condition().map(|c| c.kind),
+            Some(ConditionKind::WrongArity)
+        );
+    }
+
+    #[test]
+    fn parameterize_converts_and_restores_the_values() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str(
+                "(define p (make-parameter 10 (lambda (x) (* x 2))))
+                 (define q (make-parameter 'outer))",
+            )
+            .unwrap();
+        for (text, expected) in [
+            ("(p)", "20"),
+            ("(parameterize ((p 3)) (p))", "6"),
+            (
+                "(parameterize ((p 1) (q 'inner)) (list (p) (q)))",
+                "(2 inner)",
+            ),
+            ("(parameterize ((q 'a)) (parameterize ((q 'b)) (q)))", "b"),
+            ("(list (p) (q))", "(20 outer)"),
+            (
+                "(call/cc (lambda (k) (parameterize ((q 'escaped)) (k (q)))))",
+                "escaped",
+            ),
+            ("(q)", "outer"),
+            (
+                "(let ((port (open-output-string)))
+                   (parameterize ((current-output-port port)) (display \"hi\"))
+                   (get-output-string port))",
+                "hi",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        assert!(scheme
+            .eval_str("(parameterize ((q 'failed)) (car '()))")
+            .is_err());
+        assert_eq!(scheme.eval_str("(q)").unwrap().to_string(), "outer");
+        let err = scheme.eval_str("(parameterize ((car 1)) 1)").unwrap_err();
+        assert_eq!(
+            err.condition().map(|c| c.kind),
+            Some(ConditionKind::WrongType)
+        );
+    }
+
+    #[test]
+    fn apply_spreads_its_last_argument() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str("(define (f a . rest) (list a rest))")
+            .unwrap();
+        for (text, expected) in [
+            ("(apply + '(1 2 3))", "6"),
+            ("(apply + 1 2 '(3 4))", "10"),
+            ("(apply list '())", "()"),
+            ("(apply f 1 '(2 3))", "(1 (2 3))"),
+            ("(apply f '(1))", "(1 ())"),
+            ("(apply apply list '((1 2)))", "(1 2)"),
+            ("(apply (lambda args args) 'a '(b))", "(a b)"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        for (text, kind) in [
+            ("(apply + 1 2)", ConditionKind::WrongType),
+            ("(apply + '(1 . 2))", ConditionKind::WrongType),
+            ("(apply f '())", ConditionKind::WrongArity),
+            ("(apply 5 '(1))", ConditionKind::WrongType),
+        ] {
+            let err = scheme.eval_str(text).unwrap_err();
+            assert_eq!(err.condition().map(|c| c.kind), Some(kind), "{}", text);
+        }
+    }
+
+    #[test]
+    fn eval_runs_generated_code_at_top_level() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(eval '(+ 1 2) (interaction-environment))", "3"),
+            ("(eval (list 'define 'x 10))", "#<unspecified>"),
+            ("(let ((x 1)) (eval 'x))", "10"),
+            (
+                "(eval '(let loop ((i 0)) (if (< i 3) (loop (+ i 1)) i)))",
+                "3",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        let err = scheme
+            .eval_str("(eval 'x 'not-an-environment)")
+            .unwrap_err();
+        assert_eq!(
+            err.condition().map(|condition| condition.kind),
+            Some(ConditionKind::WrongType)
+        );
+    }
+
+    #[test]
+    fn map_stops_at_the_shortest_list() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str("(define circ (list 1 2)) (set-cdr! (cdr circ) circ)")
+            .unwrap();
+        for (text, expected) in [
+            ("(map + '(1 2 3) '(10 20))", "(11 22)"),
+            ("(map + circ '(1 2 3))", "(2 4 4)"),
+            (
+                "(let ((n 0)) (for-each (lambda (x y) (set! n (+ n x y))) '(1 2 3) circ) n)",
+                "10",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        for text in ["(map + circ circ)", "(map + '(1 2 . 3) '(1 2 3))"] {
+            let err = scheme.eval_str(text).unwrap_err();
+            assert_eq!(
+                err.condition().map(|condition| condition.kind),
+                Some(ConditionKind::WrongType),
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Files and directories, only defined with the filesystem capability.
+
+use super::string;
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::proc::Arity;
+use crate::value::Value;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("file-exists?", Arity::exactly(1), file_exists);
+    interp.define_primitive("delete-file", Arity::exactly(1), delete_file);
+    interp.define_primitive("directory-list", Arity::exactly(1), directory_list);
+    interp.define_primitive("create-directory", Arity::exactly(1), create_directory);
+    interp.define_primitive("file-size", Arity::exactly(1), file_size);
+    interp.define_primitive("file-mtime", Arity::exactly(1), file_mtime);
+    interp.define_primitive("current-directory", Arity::exactly(0), current_directory);
+    interp.define_primitive(
+        "set-current-directory!",
+        Arity::exactly(1),
+        set_current_directory,
+    );
+}
+
+/// Runs `f` on the path in `args[0]`, reporting errors against the path.
+fn with_path<T>(
+    name: &str,
+    args: &[Value],
+    f: impl FnOnce(&Path) -> io::Result<T>,
+) -> Result<T, Error> {
+    let path = string(name, &args[0])?.borrow().to_string();
+    f(Path::new(&path)).map_err(|err| {
+        Error::new(
+            ConditionKind::Io,
+            format!("{}: {}: {}", name, path, err),
+            Vec::new(),
+        )
+    })
+}
+
+fn file_exists(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let exists = with_path("file-exists?", args, |path| Ok(path.exists()))?;
+    Ok(Value::Boolean(exists))
+}
+
+fn delete_file(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    with_path("delete-file", args, |path| fs::remove_file(path))?;
+    Ok(Value::Unspecified)
+}
+
+/// The names of the entries in a directory, sorted and without `.` and
+/// `..`.
+fn directory_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut names = with_path("directory-list", args, |path| {
+        fs::read_dir(path)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<io::Result<Vec<_>>>()
+    })?;
+    names.sort();
+    Ok(Value::list(names.iter().map(|name| Value::string(name))))
+}
+
+fn create_directory(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    with_path("create-directory", args, |path| fs::create_dir(path))?;
+    Ok(Value::Unspecified)
+}
+
+fn file_size(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let metadata = with_path("file-size", args, |path| fs::metadata(path))?;
+    Ok(Value::from(metadata.len() as i64))
+}
+
+/// The time the file was last modified, in seconds since the Unix epoch.
+fn file_mtime(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let seconds = with_path("file-mtime", args, |path| {
+        let modified = fs::metadata(path)?.modified()?;
+        let since_epoch = modified
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(since_epoch.as_secs())
+    })?;
+    Ok(Value::from(seconds as i64))
+}
+
+fn current_directory(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    let dir = env::current_dir().map_err(|err| {
+        Error::new(
+            ConditionKind::Io,
+            format!("current-directory: {}", err),
+            Vec::new(),
+        )
+    })?;
+    Ok(Value::string(&dir.to_string_lossy()))
+}
+
+fn set_current_directory(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    with_path("set-current-directory!", args, |path| {
+        env::set_current_dir(path)
+    })?;
+    Ok(Value::Unspecified)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    #[test]
+    fn directories_and_files_are_created_listed_and_deleted() {
+        let dir = std::env::temp_dir().join(format!("scheme-files-{}", std::process::id()));
+        let dir = dir.to_str().unwrap().replace('\\', "\\\\");
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str(&format!("(define dir \"{}\")", dir))
+            .unwrap();
+        let value = scheme
+            .eval_str(
+                "(create-directory dir)
+                 (define file (string-append dir \"/data.txt\"))
+                 (call-with-port (open-output-file file)
+                   (lambda (port) (write-string \"12345\" port)))
+                 (list (file-exists? file)
+                       (file-size file)
+                       (> (file-mtime file) 0)
+                       (directory-list dir))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "(#t 5 #t (data.txt))");
+        let value = scheme
+            .eval_str("(delete-file file) (list (file-exists? file) (directory-list dir))")
+            .unwrap();
+        std::fs::remove_dir(&dir).unwrap();
+        assert_eq!(value.to_string(), "(#f ())");
+        let err = scheme.eval_str("(file-size file)").unwrap_err();
+        assert_eq!(
+            err.condition().map(|condition| condition.kind),
+            Some(ConditionKind::Io)
+        );
+    }
+
+    #[test]
+    fn current_directory_can_be_set() {
+        let mut scheme = Scheme::new();
+        let value = scheme
+            .eval_str(
+                "(define here (current-directory))
+                 (set-current-directory! here)
+                 (equal? here (current-directory))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "#t");
+        assert!(scheme
+            .eval_str("(set-current-directory! \"/no/such/directory\")")
+            .is_err());
+    }
+}
@@ -0,0 +1,561 @@
+//! Hash tables, as in SRFI 69.
+//!
+//! Tables compare keys with `eq?`, `eqv?` and `equal?` and hash them with
+//! `hash-by-identity` and `hash` without calling back into the interpreter.
+//! Any other procedures are called, and may change the table while they
+//! run.
+
+use super::control::{is_eq, is_equal, is_eqv};
+use super::{integer, list, procedure, string};
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::hash_table::HashTable;
+use crate::proc::{Arity, Primitive, PrimitiveFn, Procedure};
+use crate::value::{hash_equal, hash_eqv, Value};
+use std::rc::Rc;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("make-hash-table", Arity::between(0, 2), make_hash_table);
+    interp.define_primitive("make-eq-hash-table", Arity::exactly(0), make_eq_hash_table);
+    interp.define_primitive(
+        "make-eqv-hash-table",
+        Arity::exactly(0),
+        make_eqv_hash_table,
+    );
+    interp.define_primitive(
+        "make-equal-hash-table",
+        Arity::exactly(0),
+        make_equal_hash_table,
+    );
+    interp.define_primitive("hash-table?", Arity::exactly(1), is_hash_table);
+    interp.define_primitive(
+        "alist->hash-table",
+        Arity::between(1, 3),
+        alist_to_hash_table,
+    );
+    interp.define_primitive(
+        "hash-table-equivalence-function",
+        Arity::exactly(1),
+        hash_table_equivalence_function,
+    );
+    interp.define_primitive(
+        "hash-table-hash-function",
+        Arity::exactly(1),
+        hash_table_hash_function,
+    );
+    interp.define_primitive("hash-table-ref", Arity::between(2, 4), hash_table_ref);
+    interp.define_primitive(
+        "hash-table-ref/default",
+        Arity::exactly(3),
+        hash_table_ref_default,
+    );
+    interp.define_primitive("hash-table-set!", Arity::exactly(3), hash_table_set);
+    interp.define_primitive("hash-table-delete!", Arity::exactly(2), hash_table_delete);
+    interp.define_primitive("hash-table-exists?", Arity::exactly(2), hash_table_exists);
+    interp.define_primitive("hash-table-contains?", Arity::exactly(2), hash_table_exists);
+    interp.define_primitive(
+        "hash-table-update!",
+        Arity::between(3, 4),
+        hash_table_update,
+    );
+    interp.define_primitive(
+        "hash-table-update!/default",
+        Arity::exactly(4),
+        hash_table_update_default,
+    );
+    interp.define_primitive("hash-table-size", Arity::exactly(1), hash_table_size);
+    interp.define_primitive("hash-table-keys", Arity::exactly(1), hash_table_keys);
+    interp.define_primitive("hash-table-values", Arity::exactly(1), hash_table_values);
+    interp.define_primitive("hash-table-walk", Arity::exactly(2), hash_table_walk);
+    interp.define_primitive("hash-table-fold", Arity::exactly(3), hash_table_fold);
+    interp.define_primitive("hash-table->alist", Arity::exactly(1), hash_table_to_alist);
+    interp.define_primitive("hash-table-copy", Arity::between(1, 2), hash_table_copy);
+    interp.define_primitive("hash-table-clear!", Arity::exactly(1), hash_table_clear);
+    interp.define_primitive("hash", Arity::between(1, 2), hash);
+    interp.define_primitive("string-hash", Arity::between(1, 2), string_hash);
+    interp.define_primitive("string-ci-hash", Arity::between(1, 2), string_ci_hash);
+    interp.define_primitive("hash-by-identity", Arity::between(1, 2), hash_by_identity);
+}
+
+fn hash_table<'a>(name: &str, value: &'a Value) -> Result<&'a Rc<HashTable>, Error> {
+    match value {
+        Value::HashTable(table) => Ok(table),
+        _ => Err(Error::wrong_type(name, "a hash table", value)),
+    }
+}
+
+/// A standard procedure as a value, whatever the program has bound its name
+/// to.
+fn primitive(name: &'static str, arity: Arity, func: PrimitiveFn) -> Value {
+    Value::Procedure(Rc::new(Procedure::Primitive(Primitive {
+        name,
+        arity,
+        func,
+    })))
+}
+
+/// The name of `value` if it is a standard procedure.
+fn primitive_name(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::Procedure(procedure) => match &**procedure {
+            Procedure::Primitive(primitive) => Some(primitive.name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A table comparing keys with `args[at]`, or `equal?`, and hashing them
+/// with `args[at + 1]`, or the hash function that goes with the comparison.
+fn new_table(name: &str, args: &[Value], at: usize) -> Result<HashTable, Error> {
+    let equivalence = match args.get(at) {
+        Some(equivalence) => {
+            procedure(name, equivalence)?;
+            equivalence.clone()
+        }
+        None => primitive("equal?", Arity::exactly(2), is_equal),
+    };
+    let hash = match args.get(at + 1) {
+        Some(hash) => {
+            procedure(name, hash)?;
+            hash.clone()
+        }
+        None => match primitive_name(&equivalence) {
+            Some("eq?" | "eqv?") => {
+                primitive("hash-by-identity", Arity::between(1, 2), hash_by_identity)
+            }
+            Some("string=?") => primitive("string-hash", Arity::between(1, 2), string_hash),
+            Some("string-ci=?") => {
+                primitive("string-ci-hash", Arity::between(1, 2), string_ci_hash)
+            }
+            _ => primitive("hash", Arity::between(1, 2), hash),
+        },
+    };
+    Ok(HashTable::new(equivalence, hash))
+}
+
+/// The hash of `key` in `table`.
+fn hash_key(
+    interp: &mut Interpreter,
+    name: &str,
+    table: &HashTable,
+    key: &Value,
+) -> Result<u64, Error> {
+    match primitive_name(&table.hash) {
+        Some("hash-by-identity") => Ok(hash_eqv(key)),
+        Some("hash") => Ok(hash_equal(key)),
+        _ => {
+            let hash = interp.apply(&table.hash, std::slice::from_ref(key))?;
+            integer(name, &hash)?;
+            Ok(hash_equal(&hash))
+        }
+    }
+}
+
+/// The hash of `key` in `table`, and the key stored in `table` that is the
+/// same as `key`, if there is one.
+fn find(
+    interp: &mut Interpreter,
+    name: &str,
+    table: &HashTable,
+    key: &Value,
+) -> Result<(u64, Option<Value>), Error> {
+    let hash = hash_key(interp, name, table, key)?;
+    for stored in table.candidates(hash) {
+        let same = match primitive_name(&table.equivalence) {
+            Some("eq?" | "eqv?") => stored.is_eqv(key),
+            Some("equal?") => stored.is_equal(key),
+            _ => interp
+                .apply(&table.equivalence, &[stored.clone(), key.clone()])?
+                .is_true(),
+        };
+        if same {
+            return Ok((hash, Some(stored)));
+        }
+    }
+    Ok((hash, None))
+}
+
+/// The value of `key` in `table`, if it has one.
+fn lookup(
+    interp: &mut Interpreter,
+    name: &str,
+    table: &HashTable,
+    key: &Value,
+) -> Result<Option<Value>, Error> {
+    let (hash, stored) = find(interp, name, table, key)?;
+    Ok(stored.and_then(|stored| table.get(hash, &stored)))
+}
+
+fn store(
+    interp: &mut Interpreter,
+    name: &str,
+    table: &HashTable,
+    key: &Value,
+    value: Value,
+) -> Result<(), Error> {
+    let (hash, stored) = find(interp, name, table, key)?;
+    table.insert(hash, stored.unwrap_or_else(|| key.clone()), value);
+    Ok(())
+}
+
+fn no_value(name: &str, key: &Value) -> Error {
+    Error::new(
+        ConditionKind::Range,
+        format!("{}: no value for key", name),
+        vec![key.clone()],
+    )
+}
+
+/// `(make-hash-table [equal? [hash]])`.
+fn make_hash_table(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::HashTable(Rc::new(new_table(
+        "make-hash-table",
+        args,
+        0,
+    )?)))
+}
+
+fn make_eq_hash_table(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    let eq = primitive("eq?", Arity::exactly(2), is_eq);
+    Ok(Value::HashTable(Rc::new(new_table(
+        "make-eq-hash-table",
+        &[eq],
+        0,
+    )?)))
+}
+
+fn make_eqv_hash_table(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    let eqv = primitive("eqv?", Arity::exactly(2), is_eqv);
+    Ok(Value::HashTable(Rc::new(new_table(
+        "make-eqv-hash-table",
+        &[eqv],
+        0,
+    )?)))
+}
+
+fn make_equal_hash_table(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::HashTable(Rc::new(new_table(
+        "make-equal-hash-table",
+        &[],
+        0,
+    )?)))
+}
+
+fn is_hash_table(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::HashTable(_))))
+}
+
+/// `(alist->hash-table alist [equal? [hash]])`, where earlier associations
+/// take precedence over later ones with the same key.
+fn alist_to_hash_table(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "alist->hash-table";
+    let table = new_table(name, args, 1)?;
+    for association in list(name, &args[0])? {
+        let Value::Pair(pair) = &association else {
+            return Err(Error::wrong_type(name, "a pair", &association));
+        };
+        let (hash, stored) = find(interp, name, &table, &pair.car())?;
+        if stored.is_none() {
+            table.insert(hash, pair.car(), pair.cdr());
+        }
+    }
+    Ok(Value::HashTable(Rc::new(table)))
+}
+
+fn hash_table_equivalence_function(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(hash_table("hash-table-equivalence-function", &args[0])?
+        .equivalence
+        .clone())
+}
+
+fn hash_table_hash_function(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(hash_table("hash-table-hash-function", &args[0])?
+        .hash
+        .clone())
+}
+
+/// `(hash-table-ref table key [thunk [success]])`: `(success value)` if
+/// `key` has a value, and `(thunk)` or an error if not.
+fn hash_table_ref(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-ref";
+    let table = hash_table(name, &args[0])?;
+    match lookup(interp, name, table, &args[1])? {
+        Some(value) => match args.get(3) {
+            Some(success) => interp.apply(success, &[value]),
+            None => Ok(value),
+        },
+        None => match args.get(2) {
+            Some(thunk) => interp.apply(thunk, &[]),
+            None => Err(no_value(name, &args[1])),
+        },
+    }
+}
+
+fn hash_table_ref_default(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-ref/default";
+    let table = hash_table(name, &args[0])?;
+    Ok(lookup(interp, name, table, &args[1])?.unwrap_or_else(|| args[2].clone()))
+}
+
+fn hash_table_set(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-set!";
+    let table = hash_table(name, &args[0])?;
+    store(interp, name, table, &args[1], args[2].clone())?;
+    Ok(Value::Unspecified)
+}
+
+fn hash_table_delete(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-delete!";
+    let table = hash_table(name, &args[0])?;
+    if let (hash, Some(stored)) = find(interp, name, table, &args[1])? {
+        table.remove(hash, &stored);
+    }
+    Ok(Value::Unspecified)
+}
+
+fn hash_table_exists(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-exists?";
+    let table = hash_table(name, &args[0])?;
+    Ok(Value::Boolean(
+        lookup(interp, name, table, &args[1])?.is_some(),
+    ))
+}
+
+/// `(hash-table-update! table key proc [thunk])`, setting `key` to
+/// `(proc value)`, where `value` is the value of `key`, or `(thunk)` if it
+/// has none.
+fn hash_table_update(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-update!";
+    let table = hash_table(name, &args[0])?;
+    procedure(name, &args[2])?;
+    let value = match lookup(interp, name, table, &args[1])? {
+        Some(value) => value,
+        None => match args.get(3) {
+            Some(thunk) => interp.apply(thunk, &[])?,
+            None => return Err(no_value(name, &args[1])),
+        },
+    };
+    let value = interp.apply(&args[2], &[value])?;
+    store(interp, name, table, &args[1], value)?;
+    Ok(Value::Unspecified)
+}
+
+fn hash_table_update_default(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-update!/default";
+    let table = hash_table(name, &args[0])?;
+    procedure(name, &args[2])?;
+    let value = lookup(interp, name, table, &args[1])?.unwrap_or_else(|| args[3].clone());
+    let value = interp.apply(&args[2], &[value])?;
+    store(interp, name, table, &args[1], value)?;
+    Ok(Value::Unspecified)
+}
+
+fn hash_table_size(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::from(
+        hash_table("hash-table-size", &args[0])?.len() as i64
+    ))
+}
+
+fn hash_table_keys(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let entries = hash_table("hash-table-keys", &args[0])?.entries();
+    Ok(Value::list(entries.into_iter().map(|(key, _)| key)))
+}
+
+fn hash_table_values(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let entries = hash_table("hash-table-values", &args[0])?.entries();
+    Ok(Value::list(entries.into_iter().map(|(_, value)| value)))
+}
+
+/// `(hash-table-walk table proc)`, calling `(proc key value)` for the
+/// entries the table has when the walk starts.
+fn hash_table_walk(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-walk";
+    let table = hash_table(name, &args[0])?;
+    procedure(name, &args[1])?;
+    for (key, value) in table.entries() {
+        interp.apply(&args[1], &[key, value])?;
+    }
+    Ok(Value::Unspecified)
+}
+
+/// `(hash-table-fold table kons knil)`, with `(kons key value acc)`.
+fn hash_table_fold(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "hash-table-fold";
+    let table = hash_table(name, &args[0])?;
+    procedure(name, &args[1])?;
+    let mut acc = args[2].clone();
+    for (key, value) in table.entries() {
+        acc = interp.apply(&args[1], &[key, value, acc])?;
+    }
+    Ok(acc)
+}
+
+fn hash_table_to_alist(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let entries = hash_table("hash-table->alist", &args[0])?.entries();
+    Ok(Value::list(
+        entries
+            .into_iter()
+            .map(|(key, value)| Value::cons(key, value)),
+    ))
+}
+
+/// `(hash-table-copy table [mutable?])`. Tables are always mutable.
+fn hash_table_copy(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let table = hash_table("hash-table-copy", &args[0])?;
+    Ok(Value::HashTable(Rc::new(table.copy())))
+}
+
+fn hash_table_clear(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    hash_table("hash-table-clear!", &args[0])?.clear();
+    Ok(Value::Unspecified)
+}
+
+/// A hash as an exact integer below the optional bound at `args[1]`.
+fn bounded(name: &str, args: &[Value], hash: u64) -> Result<Value, Error> {
+    let bound = match args.get(1) {
+        Some(bound) => match integer(name, bound)?.to_i64() {
+            Some(bound) if bound > 0 => bound as u64,
+            _ => return Err(Error::wrong_type(name, "a positive fixnum", bound)),
+        },
+        None => i64::MAX as u64,
+    };
+    Ok(Value::from((hash % bound) as i64))
+}
+
+/// `(hash obj [bound])`, consistent with `equal?`.
+fn hash(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    bounded("hash", args, hash_equal(&args[0]))
+}
+
+fn string_hash(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    string("string-hash", &args[0])?;
+    bounded("string-hash", args, hash_equal(&args[0]))
+}
+
+/// `(string-ci-hash string [bound])`, consistent with `string-ci=?`.
+fn string_ci_hash(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let folded = string("string-ci-hash", &args[0])?.borrow().foldcase();
+    let folded = Value::String(Rc::new(std::cell::RefCell::new(folded)));
+    bounded("string-ci-hash", args, hash_equal(&folded))
+}
+
+/// `(hash-by-identity obj [bound])`, consistent with `eq?` and `eqv?`.
+fn hash_by_identity(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    bounded("hash-by-identity", args, hash_eqv(&args[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Scheme;
+
+    fn eval(source: &str) -> String {
+        Scheme::new().eval_str(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn tables_store_and_remove_values() {
+        let source = "(let ((t (make-hash-table)))
+                        (hash-table-set! t '(1 2) 'a)
+                        (hash-table-set! t \"b\" 'b)
+                        (hash-table-set! t (list 1 2) 'c)
+                        (hash-table-delete! t \"b\")
+                        (list (hash-table-ref t '(1 2))
+                              (hash-table-ref/default t \"b\" 'none)
+                              (hash-table-exists? t \"b\")
+                              (hash-table-size t)))";
+        assert_eq!(eval(source), "(c none #f 1)");
+        assert!(Scheme::new()
+            .eval_str("(hash-table-ref (make-hash-table) 'missing)")
+            .is_err());
+        assert_eq!(
+            eval("(hash-table-ref (make-hash-table) 'k (lambda () 'default))"),
+            "default"
+        );
+        assert_eq!(
+            eval(
+                "(let ((t (make-hash-table)))
+                   (hash-table-set! t 'k 1)
+                   (hash-table-ref t 'k (lambda () 0) (lambda (v) (+ v 10))))"
+            ),
+            "11"
+        );
+    }
+
+    #[test]
+    fn eq_tables_compare_keys_by_identity() {
+        let source = "(let ((t (make-eq-hash-table)) (k (list 1)))
+                        (hash-table-set! t k 'same)
+                        (set-car! k 2)
+                        (list (hash-table-ref/default t k 'none)
+                              (hash-table-ref/default t (list 2) 'none)))";
+        assert_eq!(eval(source), "(same none)");
+        assert_eq!(
+            eval(
+                "(let ((t (make-hash-table eqv?)))
+                   (hash-table-set! t 100000000000000000000 'big)
+                   (hash-table-ref t 100000000000000000000))"
+            ),
+            "big"
+        );
+    }
+
+    #[test]
+    fn tables_take_custom_procedures() {
+        let source = "(let ((t (make-hash-table
+                                 (lambda (a b) (= (modulo a 10) (modulo b 10)))
+                                 (lambda (k) (modulo k 10)))))
+                        (hash-table-set! t 3 'three)
+                        (hash-table-set! t 13 'thirteen)
+                        (list (hash-table-size t) (hash-table-ref t 23)))";
+        assert_eq!(eval(source), "(1 thirteen)");
+        assert_eq!(
+            eval(
+                "(let ((t (make-hash-table string-ci=?)))
+                   (hash-table-set! t \"Key\" 1)
+                   (hash-table-ref t \"KEY\"))"
+            ),
+            "1"
+        );
+    }
+
+    #[test]
+    fn update_walk_and_fold() {
+        let source = "(let ((t (make-hash-table)))
+                        (for-each (lambda (w) (hash-table-update!/default t w (lambda (n) (+ n 1)) 0))
+                                  '(a b a c a b))
+                        (hash-table-update! t 'c (lambda (n) (* n 10)))
+                        (hash-table-update! t 'd (lambda (n) n) (lambda () 7))
+                        (list (hash-table-ref t 'a) (hash-table-ref t 'b)
+                              (hash-table-ref t 'c) (hash-table-ref t 'd)
+                              (hash-table-fold t (lambda (k v acc) (+ v acc)) 0)))";
+        assert_eq!(eval(source), "(3 2 10 7 22)");
+        let walk = "(let ((t (alist->hash-table '((a . 1) (b . 2) (a . 3)))))
+                      (hash-table-walk t (lambda (k v) (hash-table-delete! t k)))
+                      (list (hash-table-size t)))";
+        assert_eq!(eval(walk), "(0)");
+        assert_eq!(
+            eval(
+                "(let* ((t (alist->hash-table '((a . 1) (a . 3))))
+                        (c (hash-table-copy t)))
+                   (hash-table-set! c 'a 2)
+                   (list (hash-table-ref t 'a) (hash-table-ref c 'a)
+                         (hash-table->alist c)))"
+            ),
+            "(1 2 ((a . 2)))"
+        );
+    }
+
+    #[test]
+    fn hashes_are_bounded_and_consistent() {
+        assert_eq!(
+            eval("(= (hash (list 1 \"a\")) (hash (list 1 \"a\")))"),
+            "#t"
+        );
+        assert_eq!(eval("(< (hash 'anything 10) 10)"), "#t");
+        assert_eq!(
+            eval("(= (string-ci-hash \"ABC\") (string-ci-hash \"abc\"))"),
+            "#t"
+        );
+        assert_eq!(eval("(hash-table? (make-equal-hash-table))"), "#t");
+    }
+}
@@ -0,0 +1,450 @@
+//! JSON, mapped to Scheme values as in SRFI 180.
+//!
+//! Objects are association lists with symbol keys, arrays are vectors,
+//! `null` is the symbol `null`, and strings, numbers and booleans are
+//! themselves. Integers without a fraction or exponent read as exact.
+
+use super::ports::{write_to, Chars};
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::num::Number;
+use crate::proc::Arity;
+use crate::symbol::Symbol;
+use crate::value::Value;
+use std::fmt::Write as _;
+
+/// How deeply arrays and objects may nest. Deeper input is rejected, which
+/// also stops `json-write` on circular data. Reading and writing recurse on
+/// the Rust stack, so this has to fit in the room the evaluator leaves for
+/// primitives.
+const MAX_DEPTH: usize = 128;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("json-read", Arity::between(0, 1), json_read);
+    interp.define_primitive("json-write", Arity::between(1, 2), json_write);
+    interp.define_primitive("json-null?", Arity::exactly(1), is_json_null);
+}
+
+fn null() -> Value {
+    Value::Symbol(Symbol::intern("null"))
+}
+
+enum ReadError {
+    Port(Error),
+    Syntax(String),
+}
+
+impl From<Error> for ReadError {
+    fn from(err: Error) -> Self {
+        Self::Port(err)
+    }
+}
+
+/// Reads one JSON value, or returns the end-of-file object if only
+/// whitespace is left.
+fn json_read(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let port = Chars::new(interp, "json-read", args, 0)?;
+    match (Reader { port }).read_top() {
+        Ok(value) => Ok(value),
+        Err(ReadError::Syntax(message)) => Err(Error::new(
+            ConditionKind::Read,
+            format!("json-read: {}", message),
+            Vec::new(),
+        )),
+        Err(ReadError::Port(err)) => Err(err),
+    }
+}
+
+struct Reader<'a> {
+    port: Chars<'a>,
+}
+
+impl Reader<'_> {
+    fn read_top(&mut self) -> Result<Value, ReadError> {
+        match self.skip_whitespace()? {
+            None => Ok(Value::Eof),
+            Some(_) => self.value(0),
+        }
+    }
+
+    /// Skips whitespace, returning the next character without reading it.
+    fn skip_whitespace(&mut self) -> Result<Option<char>, ReadError> {
+        loop {
+            match self.port.peek_char()? {
+                Some(' ' | '\t' | '\n' | '\r') => {
+                    self.port.read_char()?;
+                }
+                c => return Ok(c),
+            }
+        }
+    }
+
+    fn next(&mut self) -> Result<char, ReadError> {
+        self.port
+            .read_char()?
+            .ok_or_else(|| syntax("unexpected end of input"))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ReadError> {
+        match self.skip_whitespace()? {
+            Some(c) if c == expected => {
+                self.port.read_char()?;
+                Ok(())
+            }
+            Some(c) => Err(syntax(format!("expected {:?}, found {:?}", expected, c))),
+            None => Err(syntax("unexpected end of input")),
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, ReadError> {
+        if depth > MAX_DEPTH {
+            return Err(syntax("nested too deeply"));
+        }
+        match self.skip_whitespace()? {
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some('"') => {
+                self.port.read_char()?;
+                Ok(Value::string(&self.string()?))
+            }
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.literal("true", Value::Boolean(true)),
+            Some('f') => self.literal("false", Value::Boolean(false)),
+            Some('n') => self.literal("null", null()),
+            Some(c) => Err(syntax(format!("unexpected {:?}", c))),
+            None => Err(syntax("unexpected end of input")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, ReadError> {
+        for expected in word.chars() {
+            if self.next()? != expected {
+                return Err(syntax(format!("expected {}", word)));
+            }
+        }
+        Ok(value)
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, ReadError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        if self.skip_whitespace()? == Some('}') {
+            self.port.read_char()?;
+            return Ok(Value::Null);
+        }
+        loop {
+            self.expect('"')?;
+            let key = Value::Symbol(Symbol::intern(&self.string()?));
+            self.expect(':')?;
+            members.push(Value::cons(key, self.value(depth + 1)?));
+            match self.skip_whitespace()? {
+                Some(',') => {
+                    self.port.read_char()?;
+                }
+                Some('}') => {
+                    self.port.read_char()?;
+                    return Ok(Value::list(members));
+                }
+                _ => return Err(syntax("expected ',' or '}' in an object")),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, ReadError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        if self.skip_whitespace()? == Some(']') {
+            self.port.read_char()?;
+            return Ok(Value::vector(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            match self.skip_whitespace()? {
+                Some(',') => {
+                    self.port.read_char()?;
+                }
+                Some(']') => {
+                    self.port.read_char()?;
+                    return Ok(Value::vector(items));
+                }
+                _ => return Err(syntax("expected ',' or ']' in an array")),
+            }
+        }
+    }
+
+    /// The rest of a string, after its opening quote.
+    fn string(&mut self) -> Result<String, ReadError> {
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(s),
+                '\\' => {
+                    let c = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => self.unicode_escape()?,
+                        c => return Err(syntax(format!("unknown escape \\{}", c))),
+                    };
+                    s.push(c);
+                }
+                c if c < ' ' => return Err(syntax("control character in a string")),
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\u` escape, which may be the first half of a
+    /// surrogate pair.
+    fn unicode_escape(&mut self) -> Result<char, ReadError> {
+        let first = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&first) {
+            return char::from_u32(first).ok_or_else(|| syntax("unpaired surrogate escape"));
+        }
+        if self.next()? != '\\' || self.next()? != 'u' {
+            return Err(syntax("unpaired surrogate escape"));
+        }
+        let second = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&second) {
+            return Err(syntax("unpaired surrogate escape"));
+        }
+        let code = 0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00);
+        char::from_u32(code).ok_or_else(|| syntax("invalid surrogate escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, ReadError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.next()?.to_digit(16);
+            code = code * 16 + digit.ok_or_else(|| syntax("expected four hex digits"))?;
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Value, ReadError> {
+        let mut text = String::new();
+        while let Some(c @ ('-' | '+' | '.' | 'e' | 'E' | '0'..='9')) = self.port.peek_char()? {
+            text.push(c);
+            self.port.read_char()?;
+        }
+        if !is_json_number(&text) {
+            return Err(syntax(format!("invalid number {}", text)));
+        }
+        let n =
+            Number::parse(&text, 10).ok_or_else(|| syntax(format!("invalid number {}", text)))?;
+        Ok(Value::Number(n))
+    }
+}
+
+fn syntax(message: impl Into<String>) -> ReadError {
+    ReadError::Syntax(message.into())
+}
+
+/// Whether `text` follows JSON's number grammar, which is stricter than
+/// Scheme's.
+fn is_json_number(text: &str) -> bool {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let s = text.strip_prefix('-').unwrap_or(text);
+    let int = digits(s);
+    if int == 0 || (int > 1 && s.starts_with('0')) {
+        return false;
+    }
+    let mut s = &s[int..];
+    if let Some(rest) = s.strip_prefix('.') {
+        let frac = digits(rest);
+        if frac == 0 {
+            return false;
+        }
+        s = &rest[frac..];
+    }
+    if let Some(rest) = s.strip_prefix(['e', 'E']) {
+        let rest = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+        let exp = digits(rest);
+        if exp == 0 {
+            return false;
+        }
+        s = &rest[exp..];
+    }
+    s.is_empty()
+}
+
+fn json_write(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut out = String::new();
+    write_json(&args[0], 0, &mut out)?;
+    write_to(interp, "json-write", args, 1, |port| port.write_str(&out))
+}
+
+fn is_json_null(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(args[0].is_eq(&null())))
+}
+
+fn not_json(value: &Value) -> Error {
+    Error::wrong_type("json-write", "a value representable in JSON", value)
+}
+
+fn write_json(value: &Value, depth: usize, out: &mut String) -> Result<(), Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::new(
+            ConditionKind::Range,
+            "json-write: nested too deeply".to_string(),
+            Vec::new(),
+        ));
+    }
+    match value {
+        Value::Boolean(true) => out.push_str("true"),
+        Value::Boolean(false) => out.push_str("false"),
+        Value::Symbol(sym) if sym.name() == "null" => out.push_str("null"),
+        Value::Number(n) => write_number(n, value, out)?,
+        Value::String(s) => write_string(&s.borrow().to_string(), out),
+        Value::Vector(items) => {
+            out.push('[');
+            for (i, item) in items.borrow().iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json(item, depth + 1, out)?;
+            }
+            out.push(']');
+        }
+        Value::Null | Value::Pair(_) => {
+            let members = value.list_to_vec().ok_or_else(|| not_json(value))?;
+            out.push('{');
+            for (i, member) in members.iter().enumerate() {
+                let Value::Pair(pair) = member else {
+                    return Err(not_json(value));
+                };
+                let key = match pair.car() {
+                    Value::Symbol(sym) => sym.name().to_string(),
+                    Value::String(s) => s.borrow().to_string(),
+                    _ => return Err(not_json(value)),
+                };
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(&key, out);
+                out.push(':');
+                write_json(&pair.cdr(), depth + 1, out)?;
+            }
+            out.push('}');
+        }
+        _ => return Err(not_json(value)),
+    }
+    Ok(())
+}
+
+fn write_number(n: &Number, value: &Value, out: &mut String) -> Result<(), Error> {
+    match n {
+        Number::Fixnum(_) | Number::Bignum(_) => write!(out, "{}", n),
+        Number::Rational(_) | Number::Real(_) if n.to_f64().is_finite() => {
+            write!(out, "{:?}", n.to_f64())
+        }
+        _ => return Err(not_json(value)),
+    }
+    .expect("writing to a String cannot fail");
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => {
+                write!(out, "\\u{:04x}", c as u32).expect("writing to a String cannot fail")
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    #[test]
+    fn json_reads_as_scheme_values() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            (
+                r#"(json-read (open-input-string "{\"a\": [1, 2.5, true], \"b\": null}"))"#,
+                "((a . #(1 2.5 #t)) (b . null))",
+            ),
+            (r#"(exact? (json-read (open-input-string "12")))"#, "#t"),
+            (r#"(exact? (json-read (open-input-string "12e0")))"#, "#f"),
+            (
+                r#"(json-read (open-input-string "\"a\\nb\\u0041\""))"#,
+                "a\nbA",
+            ),
+            (
+                r#"(json-null? (json-read (open-input-string "null")))"#,
+                "#t",
+            ),
+            (
+                r#"(eof-object? (json-read (open-input-string "  ")))"#,
+                "#t",
+            ),
+            (
+                r#"(let ((port (open-input-string "1 [2]"))) (let* ((a (json-read port)) (b (json-read port))) (list a b)))"#,
+                "(1 #(2))",
+            ),
+        ] {
+            assert_eq!(
+                scheme.eval_str(text).unwrap().to_string(),
+                expected,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn json_writes_what_it_reads() {
+        let mut scheme = Scheme::new();
+        let text = r#"{"a":[1,2.5,true,false],"b":null,"c":{"d":"x\"y\n"}}"#;
+        let value = scheme
+            .eval_str(&format!(
+                "(let ((port (open-output-string)))
+                   (json-write (json-read (open-input-string {:?})) port)
+                   (get-output-string port))",
+                text
+            ))
+            .unwrap();
+        assert_eq!(value.to_string(), text);
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        let mut scheme = Scheme::new();
+        for text in [
+            r#"(json-read (open-input-string "01"))"#,
+            r#"(json-read (open-input-string "[1,]"))"#,
+            r#"(json-read (open-input-string "{\"a\" 1}"))"#,
+            r#"(json-read (open-input-string (make-string 2000 #\[)))"#,
+        ] {
+            let err = scheme.eval_str(text).unwrap_err();
+            assert_eq!(
+                err.condition().map(|c| c.kind),
+                Some(ConditionKind::Read),
+                "{}",
+                text
+            );
+        }
+        for text in [
+            "(json-write (list 1 2) (open-output-string))",
+            "(json-write +inf.0 (open-output-string))",
+            "(let ((v (vector 1))) (vector-set! v 0 v) (json-write v (open-output-string)))",
+        ] {
+            assert!(scheme.eval_str(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! Pairs and lists.
+
+use super::{index, list, reserve};
+use crate::eval::{Error, Interpreter};
+use crate::limits;
+use crate::proc::Arity;
+use crate::value::Value;
+use std::rc::Rc;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("pair?", Arity::exactly(1), is_pair);
+    interp.define_primitive("cons", Arity::exactly(2), cons);
+    interp.define_primitive("car", Arity::exactly(1), car);
+    interp.define_primitive("cdr", Arity::exactly(1), cdr);
+    interp.define_primitive("set-car!", Arity::exactly(2), set_car);
+    interp.define_primitive("set-cdr!", Arity::exactly(2), set_cdr);
+    interp.define_primitive("caar", Arity::exactly(1), caar);
+    interp.define_primitive("cadr", Arity::exactly(1), cadr);
+    interp.define_primitive("cdar", Arity::exactly(1), cdar);
+    interp.define_primitive("cddr", Arity::exactly(1), cddr);
+    interp.define_primitive("caddr", Arity::exactly(1), caddr);
+    interp.define_primitive("cdddr", Arity::exactly(1), cdddr);
+    interp.define_primitive("cadddr", Arity::exactly(1), cadddr);
+    interp.define_primitive("null?", Arity::exactly(1), is_null);
+    interp.define_primitive("list?", Arity::exactly(1), is_list);
+    interp.define_primitive("make-list", Arity::between(1, 2), make_list);
+    interp.define_primitive("list", Arity::at_least(0), list_);
+    interp.define_primitive("length", Arity::exactly(1), length);
+    interp.define_primitive("append", Arity::at_least(0), append);
+    interp.define_primitive("reverse", Arity::exactly(1), reverse);
+    interp.define_primitive("list-tail", Arity::exactly(2), list_tail);
+    interp.define_primitive("list-ref", Arity::exactly(2), list_ref);
+    interp.define_primitive("list-set!", Arity::exactly(3), list_set);
+    interp.define_primitive("list-copy", Arity::exactly(1), list_copy);
+    interp.define_primitive("memq", Arity::exactly(2), memq);
+    interp.define_primitive("memv", Arity::exactly(2), memv);
+    interp.define_primitive("member", Arity::between(2, 3), member);
+    interp.define_primitive("assq", Arity::exactly(2), assq);
+    interp.define_primitive("assv", Arity::exactly(2), assv);
+    interp.define_primitive("assoc", Arity::between(2, 3), assoc);
+}
+
+/// The car and cdr of a pair argument.
+fn parts(name: &str, value: &Value) -> Result<(Value, Value), Error> {
+    match value {
+        Value::Pair(pair) => Ok((pair.car(), pair.cdr())),
+        _ => Err(Error::wrong_type(name, "a pair", value)),
+    }
+}
+
+/// Follows a path of `a`s and `d`s, applied right to left as in the name
+/// of `cadr`.
+fn cxr(name: &str, path: &str, value: &Value) -> Result<Value, Error> {
+    let mut value = value.clone();
+    for step in path.chars().rev() {
+        let (car, cdr) = parts(name, &value)?;
+        value = if step == 'a' { car } else { cdr };
+    }
+    Ok(value)
+}
+
+fn is_pair(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Pair(_))))
+}
+
+pub(crate) fn cons(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::cons(args[0].clone(), args[1].clone()))
+}
+
+fn car(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(parts("car", &args[0])?.0)
+}
+
+fn cdr(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(parts("cdr", &args[0])?.1)
+}
+
+fn set_car(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Pair(pair) => pair.set_car(args[1].clone()),
+        value => return Err(Error::wrong_type("set-car!", "a pair", value)),
+    }
+    Ok(Value::Unspecified)
+}
+
+fn set_cdr(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Pair(pair) => pair.set_cdr(args[1].clone()),
+        value => return Err(Error::wrong_type("set-cdr!", "a pair", value)),
+    }
+    Ok(Value::Unspecified)
+}
+
+fn caar(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    cxr("caar", "aa", &args[0])
+}
+
+fn cadr(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    cxr("cadr", "ad", &args[0])
+}
+
+fn cdar(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    cxr("cdar", "da", &args[0])
+}
+
+fn cddr(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    cxr("cddr", "dd", &args[0])
+}
+
+fn caddr(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    cxr("caddr", "add", &args[0])
+}
+
+fn cdddr(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    cxr("cdddr", "ddd", &args[0])
+}
+
+fn cadddr(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    cxr("cadddr", "addd", &args[0])
+}
+
+fn is_null(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(args[0].is_null()))
+}
+
+fn is_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(args[0].list_to_vec().is_some()))
+}
+
+fn make_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let len = index("make-list", &args[0])?;
+    let fill = args.get(1).cloned().unwrap_or(Value::Unspecified);
+    let mut items = reserve("make-list", len)?;
+    items.resize(len, fill);
+    Ok(Value::list(items))
+}
+
+fn list_(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::list(args.iter().cloned()))
+}
+
+fn length(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::from(list("length", &args[0])?.len() as i64))
+}
+
+pub(crate) fn append(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let Some((last, init)) = args.split_last() else {
+        return Ok(Value::Null);
+    };
+    let mut items = Vec::new();
+    for arg in init {
+        items.extend(list("append", arg)?);
+    }
+    Ok(Value::list_with_tail(items, last.clone()))
+}
+
+fn reverse(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut items = list("reverse", &args[0])?;
+    items.reverse();
+    Ok(Value::list(items))
+}
+
+fn tail(name: &str, list: &Value, k: usize) -> Result<Value, Error> {
+    let mut value = list.clone();
+    for _ in 0..k {
+        value = parts(name, &value)?.1;
+    }
+    Ok(value)
+}
+
+fn list_tail(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    tail("list-tail", &args[0], index("list-tail", &args[1])?)
+}
+
+fn list_ref(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let rest = tail("list-ref", &args[0], index("list-ref", &args[1])?)?;
+    Ok(parts("list-ref", &rest)?.0)
+}
+
+fn list_set(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let rest = tail("list-set!", &args[0], index("list-set!", &args[1])?)?;
+    match &rest {
+        Value::Pair(pair) => pair.set_car(args[2].clone()),
+        value => return Err(Error::wrong_type("list-set!", "a pair", value)),
+    }
+    Ok(Value::Unspecified)
+}
+
+fn list_copy(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    if let Shape::Circular = shape(&args[0]) {
+        return Err(Error::wrong_type("list-copy", "a finite list", &args[0]));
+    }
+    // Improper lists are copied up to their tail, which is shared.
+    let mut items = Vec::new();
+    let mut value = args[0].clone();
+    while let Value::Pair(pair) = value {
+        items.push(pair.car());
+        value = pair.cdr();
+    }
+    Ok(Value::list_with_tail(items, value))
+}
+
+/// Whether a list ends in the empty list, in another value, or not at all.
+pub(super) enum Shape {
+    Proper(usize),
+    Dotted(usize),
+    Circular,
+}
+
+pub(super) fn shape(value: &Value) -> Shape {
+    let mut len = 0;
+    let mut slow = value.clone();
+    let mut fast = value.clone();
+    loop {
+        for _ in 0..2 {
+            match fast {
+                Value::Pair(pair) => fast = pair.cdr(),
+                Value::Null => return Shape::Proper(len),
+                _ => return Shape::Dotted(len),
+            }
+            len += 1;
+        }
+        if let Value::Pair(pair) = slow {
+            slow = pair.cdr();
+        }
+        if let (Value::Pair(a), Value::Pair(b)) = (&slow, &fast) {
+            if Rc::ptr_eq(a, b) {
+                return Shape::Circular;
+            }
+        }
+    }
+}
+
+/// The first tail of `list` whose car satisfies `found`, or `#f`.
+fn find_tail(
+    name: &str,
+    list: &Value,
+    mut found: impl FnMut(&Value) -> Result<bool, Error>,
+) -> Result<Value, Error> {
+    let mut value = list.clone();
+    // Moves at half speed, to catch circular lists.
+    let mut slow = list.clone();
+    let mut steps = 0u64;
+    loop {
+        match &value {
+            Value::Pair(pair) => {
+                if found(&pair.car())? {
+                    return Ok(value);
+                }
+                let next = pair.cdr();
+                value = next;
+            }
+            Value::Null => return Ok(Value::Boolean(false)),
+            _ => return Err(Error::wrong_type(name, "a proper list", list)),
+        }
+        limits::charge(1);
+        steps += 1;
+        if steps.is_multiple_of(2) {
+            slow = slow.as_pair().expect("behind a pair").cdr();
+            if value.is_eq(&slow) {
+                return Err(Error::wrong_type(name, "a proper list", list));
+            }
+        }
+    }
+}
+
+/// The first pair in the association list `alist` whose car satisfies
+/// `found`, or `#f`.
+fn find_entry(
+    name: &str,
+    alist: &Value,
+    mut found: impl FnMut(&Value) -> Result<bool, Error>,
+) -> Result<Value, Error> {
+    for entry in list(name, alist)? {
+        let (key, _) = parts(name, &entry)?;
+        if found(&key)? {
+            return Ok(entry);
+        }
+    }
+    Ok(Value::Boolean(false))
+}
+
+fn memq(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    find_tail("memq", &args[1], |item| Ok(args[0].is_eq(item)))
+}
+
+fn memv(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    find_tail("memv", &args[1], |item| Ok(args[0].is_eqv(item)))
+}
+
+fn member(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match args.get(2) {
+        Some(compare) => find_tail("member", &args[1], |item| {
+            Ok(interp
+                .apply(compare, &[args[0].clone(), item.clone()])?
+                .is_true())
+        }),
+        None => find_tail("member", &args[1], |item| Ok(args[0].is_equal(item))),
+    }
+}
+
+fn assq(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    find_entry("assq", &args[1], |key| Ok(args[0].is_eq(key)))
+}
+
+fn assv(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    find_entry("assv", &args[1], |key| Ok(args[0].is_eqv(key)))
+}
+
+fn assoc(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match args.get(2) {
+        Some(compare) => find_entry("assoc", &args[1], |key| {
+            Ok(interp
+                .apply(compare, &[args[0].clone(), key.clone()])?
+                .is_true())
+        }),
+        None => find_entry("assoc", &args[1], |key| Ok(args[0].is_equal(key))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    #[test]
+    fn list_copy_shares_only_the_tail() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            (
+                "(let* ((l (list 1 2)) (c (list-copy l))) (set-car! c 9) l)",
+                "(1 2)",
+            ),
+            ("(list-copy '(1 2 . 3))", "(1 2 . 3)"),
+            ("(list-copy 5)", "5"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        let err = scheme
+            .eval_str("(define circ (list 1 2)) (set-cdr! (cdr circ) circ) (list-copy circ)")
+            .unwrap_err();
+        assert_eq!(
+            err.condition().map(|condition| condition.kind),
+            Some(ConditionKind::WrongType)
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! Loading source files.
+
+use super::string;
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::parse;
+use crate::proc::Arity;
+use crate::value::Value;
+use std::fs;
+use std::path::Path;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("load", Arity::exactly(1), load);
+}
+
+/// Reads and evaluates every form in the file at `path`, as in `load`.
+pub(crate) fn load_file(interp: &mut Interpreter, path: &Path) -> Result<(), Error> {
+    let text = fs::read_to_string(path).map_err(|err| {
+        Error::new(
+            ConditionKind::Io,
+            format!("{}: {}", path.display(), err),
+            Vec::new(),
+        )
+    })?;
+    let file = path.display().to_string();
+    let forms = parse::parse_syntax(&text, Some(&file))
+        .map_err(|err| Error::new(ConditionKind::Read, format!("{}:{}", file, err), Vec::new()))?;
+    for form in forms {
+        interp.eval_syntax(&form)?;
+    }
+    Ok(())
+}
+
+fn load(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let path = string("load", &args[0])?.borrow().to_string();
+    load_file(interp, Path::new(&path))?;
+    Ok(Value::Unspecified)
+}
@@ -0,0 +1,194 @@
+//! TCP and UDP sockets, only defined with the network capability.
+
+use super::{bytevector, index, string};
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::net::{self, Socket};
+use crate::ports::{InputPort, OutputPort, Port};
+use crate::proc::Arity;
+use crate::value::Value;
+use std::io;
+use std::net::SocketAddr;
+use std::rc::Rc;
+
+/// The host listeners and UDP sockets bind to when none is given.
+const ANY_HOST: &str = "0.0.0.0";
+
+/// The largest datagram `udp-receive` keeps by default.
+const DATAGRAM_SIZE: usize = 65536;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("tcp-connect", Arity::exactly(2), tcp_connect);
+    interp.define_primitive("tcp-listen", Arity::between(1, 2), tcp_listen);
+    interp.define_primitive("tcp-accept", Arity::exactly(1), tcp_accept);
+    interp.define_primitive("udp-open", Arity::between(0, 2), udp_open);
+    interp.define_primitive("udp-send", Arity::exactly(4), udp_send);
+    interp.define_primitive("udp-receive", Arity::between(1, 2), udp_receive);
+    interp.define_primitive("socket?", Arity::exactly(1), is_socket);
+    interp.define_primitive("socket-port-number", Arity::exactly(1), socket_port_number);
+    interp.define_primitive("socket-close", Arity::exactly(1), socket_close);
+}
+
+fn socket<'a>(name: &str, value: &'a Value) -> Result<&'a Rc<Socket>, Error> {
+    match value {
+        Value::Socket(socket) => Ok(socket),
+        _ => Err(Error::wrong_type(name, "a socket", value)),
+    }
+}
+
+fn port_number(name: &str, value: &Value) -> Result<u16, Error> {
+    u16::try_from(index(name, value)?).map_err(|_| Error::wrong_type(name, "a port number", value))
+}
+
+fn net_error(name: &str, err: io::Error) -> Error {
+    Error::new(ConditionKind::Io, format!("{}: {}", name, err), Vec::new())
+}
+
+/// A connection's input and output ports as two values.
+fn connection(input: InputPort, output: OutputPort) -> Value {
+    Value::values(vec![
+        Value::Port(Rc::new(Port::from(input))),
+        Value::Port(Rc::new(Port::from(output))),
+    ])
+}
+
+fn host(addr: SocketAddr) -> Value {
+    Value::string(&addr.ip().to_string())
+}
+
+/// `(tcp-connect host port)`, returning the connection's input and output
+/// ports.
+fn tcp_connect(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let host = string("tcp-connect", &args[0])?.borrow().to_string();
+    let port = port_number("tcp-connect", &args[1])?;
+    let (input, output) =
+        net::connect((host.as_str(), port)).map_err(|err| net_error("tcp-connect", err))?;
+    Ok(connection(input, output))
+}
+
+/// `(tcp-listen port [host])`. Port 0 picks a free port, which
+/// `socket-port-number` reports.
+fn tcp_listen(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let port = port_number("tcp-listen", &args[0])?;
+    let host = match args.get(1) {
+        Some(host) => string("tcp-listen", host)?.borrow().to_string(),
+        None => ANY_HOST.to_string(),
+    };
+    let listener =
+        Socket::listen((host.as_str(), port)).map_err(|err| net_error("tcp-listen", err))?;
+    Ok(Value::Socket(Rc::new(listener)))
+}
+
+/// Waits for a connection, returning its input and output ports.
+fn tcp_accept(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let listener = socket("tcp-accept", &args[0])?;
+    let (input, output, _) = listener
+        .accept()
+        .map_err(|err| net_error("tcp-accept", err))?;
+    Ok(connection(input, output))
+}
+
+/// `(udp-open [port [host]])`, binding to any free port by default.
+fn udp_open(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let port = match args.first() {
+        Some(port) => port_number("udp-open", port)?,
+        None => 0,
+    };
+    let host = match args.get(1) {
+        Some(host) => string("udp-open", host)?.borrow().to_string(),
+        None => ANY_HOST.to_string(),
+    };
+    let socket = Socket::udp((host.as_str(), port)).map_err(|err| net_error("udp-open", err))?;
+    Ok(Value::Socket(Rc::new(socket)))
+}
+
+/// `(udp-send socket bytevector host port)`.
+fn udp_send(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let socket = socket("udp-send", &args[0])?;
+    let bytes = bytevector("udp-send", &args[1])?.borrow();
+    let host = string("udp-send", &args[2])?.borrow().to_string();
+    let port = port_number("udp-send", &args[3])?;
+    socket
+        .send_to(bytes.as_bytes(), (host.as_str(), port))
+        .map_err(|err| net_error("udp-send", err))?;
+    Ok(Value::Unspecified)
+}
+
+/// `(udp-receive socket [size])`, returning the datagram as a bytevector
+/// and the host and port it came from.
+fn udp_receive(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let socket = socket("udp-receive", &args[0])?;
+    let size = match args.get(1) {
+        Some(size) => index("udp-receive", size)?,
+        None => DATAGRAM_SIZE,
+    };
+    let (bytes, addr) = socket
+        .receive(size)
+        .map_err(|err| net_error("udp-receive", err))?;
+    Ok(Value::values(vec![
+        Value::bytevector(bytes),
+        host(addr),
+        Value::from(addr.port() as i64),
+    ]))
+}
+
+fn is_socket(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Socket(_))))
+}
+
+fn socket_port_number(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let socket = socket("socket-port-number", &args[0])?;
+    let addr = socket
+        .local_addr()
+        .map_err(|err| net_error("socket-port-number", err))?;
+    Ok(Value::from(addr.port() as i64))
+}
+
+fn socket_close(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    socket("socket-close", &args[0])?.close();
+    Ok(Value::Unspecified)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Scheme;
+
+    #[test]
+    fn tcp_connections_carry_text_both_ways() {
+        let mut scheme = Scheme::new();
+        let value = scheme
+            .eval_str(
+                "(define listener (tcp-listen 0 \"127.0.0.1\"))
+                 (define port (socket-port-number listener))
+                 (call-with-values (lambda () (tcp-connect \"127.0.0.1\" port))
+                   (lambda (client-in client-out)
+                     (call-with-values (lambda () (tcp-accept listener))
+                       (lambda (server-in server-out)
+                         (write-string \"ping\\n\" client-out)
+                         (flush-output-port client-out)
+                         (let ((request (read-line server-in)))
+                           (write-string \"pong\\n\" server-out)
+                           (flush-output-port server-out)
+                           (socket-close listener)
+                           (list (socket? listener) request (read-line client-in)))))))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "(#t ping pong)");
+        assert!(scheme.eval_str("(tcp-accept listener)").is_err());
+    }
+
+    #[test]
+    fn udp_sockets_exchange_datagrams() {
+        let mut scheme = Scheme::new();
+        let value = scheme
+            .eval_str(
+                "(define a (udp-open 0 \"127.0.0.1\"))
+                 (define b (udp-open 0 \"127.0.0.1\"))
+                 (udp-send a (bytevector 1 2 3) \"127.0.0.1\" (socket-port-number b))
+                 (call-with-values (lambda () (udp-receive b))
+                   (lambda (bytes host port)
+                     (list bytes host (= port (socket-port-number a)))))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "(#u8(1 2 3) 127.0.0.1 #t)");
+    }
+}
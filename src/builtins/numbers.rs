@@ -0,0 +1,599 @@
+//! Numeric procedures.
+
+use super::{integer, number, string};
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::num::Number;
+use crate::proc::Arity;
+use crate::value::Value;
+use std::cmp::Ordering;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("+", Arity::at_least(0), add);
+    interp.define_primitive("*", Arity::at_least(0), mul);
+    interp.define_primitive("-", Arity::at_least(1), sub);
+    interp.define_primitive("/", Arity::at_least(1), div);
+    interp.define_primitive("=", Arity::at_least(1), num_eq);
+    interp.define_primitive("<", Arity::at_least(1), lt);
+    interp.define_primitive(">", Arity::at_least(1), gt);
+    interp.define_primitive("<=", Arity::at_least(1), le);
+    interp.define_primitive(">=", Arity::at_least(1), ge);
+    interp.define_primitive("number?", Arity::exactly(1), is_number);
+    interp.define_primitive("complex?", Arity::exactly(1), is_number);
+    interp.define_primitive("real?", Arity::exactly(1), is_real);
+    interp.define_primitive("rational?", Arity::exactly(1), is_rational);
+    interp.define_primitive("integer?", Arity::exactly(1), is_integer);
+    interp.define_primitive("exact?", Arity::exactly(1), is_exact);
+    interp.define_primitive("inexact?", Arity::exactly(1), is_inexact);
+    interp.define_primitive("exact-integer?", Arity::exactly(1), is_exact_integer);
+    interp.define_primitive("nan?", Arity::exactly(1), is_nan);
+    interp.define_primitive("infinite?", Arity::exactly(1), is_infinite);
+    interp.define_primitive("finite?", Arity::exactly(1), is_finite);
+    interp.define_primitive("zero?", Arity::exactly(1), is_zero);
+    interp.define_primitive("positive?", Arity::exactly(1), is_positive);
+    interp.define_primitive("negative?", Arity::exactly(1), is_negative);
+    interp.define_primitive("odd?", Arity::exactly(1), is_odd);
+    interp.define_primitive("even?", Arity::exactly(1), is_even);
+    interp.define_primitive("max", Arity::at_least(1), max);
+    interp.define_primitive("min", Arity::at_least(1), min);
+    interp.define_primitive("abs", Arity::exactly(1), abs);
+    interp.define_primitive("quotient", Arity::exactly(2), quotient);
+    interp.define_primitive("remainder", Arity::exactly(2), remainder);
+    interp.define_primitive("modulo", Arity::exactly(2), modulo);
+    interp.define_primitive("truncate-quotient", Arity::exactly(2), quotient);
+    interp.define_primitive("truncate-remainder", Arity::exactly(2), remainder);
+    interp.define_primitive("floor-quotient", Arity::exactly(2), floor_quotient);
+    interp.define_primitive("floor-remainder", Arity::exactly(2), modulo);
+    interp.define_primitive("truncate/", Arity::exactly(2), truncate_div);
+    interp.define_primitive("floor/", Arity::exactly(2), floor_div);
+    interp.define_primitive("gcd", Arity::at_least(0), gcd);
+    interp.define_primitive("lcm", Arity::at_least(0), lcm);
+    interp.define_primitive("numerator", Arity::exactly(1), numerator);
+    interp.define_primitive("denominator", Arity::exactly(1), denominator);
+    interp.define_primitive("floor", Arity::exactly(1), floor);
+    interp.define_primitive("ceiling", Arity::exactly(1), ceiling);
+    interp.define_primitive("truncate", Arity::exactly(1), truncate);
+    interp.define_primitive("round", Arity::exactly(1), round);
+    interp.define_primitive("rationalize", Arity::exactly(2), rationalize);
+    interp.define_primitive("exp", Arity::exactly(1), exp);
+    interp.define_primitive("log", Arity::between(1, 2), log);
+    interp.define_primitive("sin", Arity::exactly(1), sin);
+    interp.define_primitive("cos", Arity::exactly(1), cos);
+    interp.define_primitive("tan", Arity::exactly(1), tan);
+    interp.define_primitive("asin", Arity::exactly(1), asin);
+    interp.define_primitive("acos", Arity::exactly(1), acos);
+    interp.define_primitive("atan", Arity::between(1, 2), atan);
+    interp.define_primitive("square", Arity::exactly(1), square);
+    interp.define_primitive("sqrt", Arity::exactly(1), sqrt);
+    interp.define_primitive("exact-integer-sqrt", Arity::exactly(1), exact_integer_sqrt);
+    interp.define_primitive("expt", Arity::exactly(2), expt);
+    interp.define_primitive("make-rectangular", Arity::exactly(2), make_rectangular);
+    interp.define_primitive("make-polar", Arity::exactly(2), make_polar);
+    interp.define_primitive("real-part", Arity::exactly(1), real_part);
+    interp.define_primitive("imag-part", Arity::exactly(1), imag_part);
+    interp.define_primitive("magnitude", Arity::exactly(1), magnitude);
+    interp.define_primitive("angle", Arity::exactly(1), angle);
+    interp.define_primitive("exact", Arity::exactly(1), exact);
+    interp.define_primitive("inexact", Arity::exactly(1), inexact);
+    interp.define_primitive("inexact->exact", Arity::exactly(1), exact);
+    interp.define_primitive("exact->inexact", Arity::exactly(1), inexact);
+    interp.define_primitive("number->string", Arity::between(1, 2), number_to_string);
+    interp.define_primitive("string->number", Arity::between(1, 2), string_to_number);
+}
+
+fn real<'a>(name: &str, value: &'a Value) -> Result<&'a Number, Error> {
+    match value {
+        Value::Number(n) if n.is_real() => Ok(n),
+        _ => Err(Error::wrong_type(name, "a real number", value)),
+    }
+}
+
+fn radix(name: &str, args: &[Value], at: usize) -> Result<u32, Error> {
+    match args.get(at) {
+        None => Ok(10),
+        Some(value @ Value::Number(n)) => match n.to_i64() {
+            Some(r @ (2 | 8 | 10 | 16)) => Ok(r as u32),
+            _ => Err(Error::wrong_type(name, "a radix of 2, 8, 10 or 16", value)),
+        },
+        Some(value) => Err(Error::wrong_type(name, "a radix of 2, 8, 10 or 16", value)),
+    }
+}
+
+fn fold(
+    name: &str,
+    args: &[Value],
+    init: Number,
+    op: impl Fn(&Number, &Number) -> Result<Number, Error>,
+) -> Result<Value, Error> {
+    let mut acc = init;
+    for arg in args {
+        acc = op(&acc, number(name, arg)?)?;
+    }
+    Ok(Value::Number(acc))
+}
+
+fn add(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    fold("+", args, Number::Fixnum(0), |a, b| Ok(a + b))
+}
+
+fn mul(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    fold("*", args, Number::Fixnum(1), |a, b| Ok(a * b))
+}
+
+fn sub(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let first = number("-", &args[0])?;
+    if args.len() == 1 {
+        return Ok(Value::Number(-first));
+    }
+    fold("-", &args[1..], first.clone(), |a, b| Ok(a - b))
+}
+
+fn div(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let first = number("/", &args[0])?;
+    if args.len() == 1 {
+        return Ok(Value::Number(Number::Fixnum(1).div(first)?));
+    }
+    fold("/", &args[1..], first.clone(), |a, b| Ok(a.div(b)?))
+}
+
+/// Checks that `holds` is true of every adjacent pair of arguments.
+fn compare(
+    name: &str,
+    args: &[Value],
+    holds: fn(Option<Ordering>) -> bool,
+) -> Result<Value, Error> {
+    let numbers = args
+        .iter()
+        .map(|arg| real(name, arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::Boolean(
+        numbers
+            .windows(2)
+            .all(|pair| holds(pair[0].partial_cmp(pair[1]))),
+    ))
+}
+
+fn num_eq(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let numbers = args
+        .iter()
+        .map(|arg| number("=", arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::Boolean(
+        numbers.windows(2).all(|pair| pair[0] == pair[1]),
+    ))
+}
+
+fn lt(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    compare("<", args, |o| o == Some(Ordering::Less))
+}
+
+fn gt(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    compare(">", args, |o| o == Some(Ordering::Greater))
+}
+
+fn le(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    compare("<=", args, |o| {
+        matches!(o, Some(Ordering::Less | Ordering::Equal))
+    })
+}
+
+fn ge(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    compare(">=", args, |o| {
+        matches!(o, Some(Ordering::Greater | Ordering::Equal))
+    })
+}
+
+fn is_number(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Number(_))))
+}
+
+fn is_real(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(
+        matches!(&args[0], Value::Number(n) if n.is_real()),
+    ))
+}
+
+fn is_rational(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(
+        matches!(&args[0], Value::Number(n) if n.is_real() && (n.is_exact() || n.to_f64().is_finite())),
+    ))
+}
+
+fn is_integer(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(
+        matches!(&args[0], Value::Number(n) if n.is_integer()),
+    ))
+}
+
+fn is_exact(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(number("exact?", &args[0])?.is_exact()))
+}
+
+fn is_inexact(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(number("inexact?", &args[0])?.is_inexact()))
+}
+
+fn is_exact_integer(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(
+        matches!(&args[0], Value::Number(n) if n.is_exact_integer()),
+    ))
+}
+
+/// Whether `test` holds of the real or the imaginary part.
+fn either_part(name: &str, value: &Value, test: fn(f64) -> bool) -> Result<Value, Error> {
+    let n = number(name, value)?;
+    Ok(Value::Boolean(
+        n.is_inexact() && (test(n.real_part().to_f64()) || test(n.imag_part().to_f64())),
+    ))
+}
+
+fn is_nan(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    either_part("nan?", &args[0], f64::is_nan)
+}
+
+fn is_infinite(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    either_part("infinite?", &args[0], f64::is_infinite)
+}
+
+fn is_finite(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = number("finite?", &args[0])?;
+    let finite = n.real_part().to_f64().is_finite() && n.imag_part().to_f64().is_finite();
+    Ok(Value::Boolean(n.is_exact() || finite))
+}
+
+fn is_zero(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(number("zero?", &args[0])?.is_zero()))
+}
+
+fn is_positive(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = real("positive?", &args[0])?;
+    Ok(Value::Boolean(n > &Number::Fixnum(0)))
+}
+
+fn is_negative(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(real("negative?", &args[0])?.is_negative()))
+}
+
+fn is_odd(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = integer("odd?", &args[0])?;
+    Ok(Value::Boolean(!n.remainder(&Number::Fixnum(2))?.is_zero()))
+}
+
+fn is_even(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = integer("even?", &args[0])?;
+    Ok(Value::Boolean(n.remainder(&Number::Fixnum(2))?.is_zero()))
+}
+
+/// `max` and `min`: the result is inexact if any argument is.
+fn extremum(name: &str, args: &[Value], keep: Ordering) -> Result<Value, Error> {
+    let mut best = real(name, &args[0])?.clone();
+    let mut inexact = best.is_inexact();
+    for arg in &args[1..] {
+        let n = real(name, arg)?;
+        inexact |= n.is_inexact();
+        if n.partial_cmp(&best) == Some(keep) || n.to_f64().is_nan() {
+            best = n.clone();
+        }
+    }
+    Ok(Value::Number(if inexact {
+        best.to_inexact()
+    } else {
+        best
+    }))
+}
+
+fn max(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    extremum("max", args, Ordering::Greater)
+}
+
+fn min(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    extremum("min", args, Ordering::Less)
+}
+
+fn abs(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("abs", &args[0])?.abs()))
+}
+
+fn quotient(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = integer("quotient", &args[0])?;
+    Ok(Value::Number(n.quotient(integer("quotient", &args[1])?)?))
+}
+
+fn remainder(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = integer("remainder", &args[0])?;
+    Ok(Value::Number(n.remainder(integer("remainder", &args[1])?)?))
+}
+
+fn modulo(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = integer("modulo", &args[0])?;
+    Ok(Value::Number(n.modulo(integer("modulo", &args[1])?)?))
+}
+
+/// The quotient and remainder of flooring division.
+fn floor_div_rem(name: &str, args: &[Value]) -> Result<(Number, Number), Error> {
+    let n = integer(name, &args[0])?;
+    let m = integer(name, &args[1])?;
+    let q = n.quotient(m)?;
+    let r = n.remainder(m)?;
+    if !r.is_zero() && r.is_negative() != m.is_negative() {
+        Ok((&q - &Number::Fixnum(1), &r + m))
+    } else {
+        Ok((q, r))
+    }
+}
+
+fn floor_quotient(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(floor_div_rem("floor-quotient", args)?.0))
+}
+
+fn floor_div(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (q, r) = floor_div_rem("floor/", args)?;
+    Ok(Value::values(vec![Value::Number(q), Value::Number(r)]))
+}
+
+fn truncate_div(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = integer("truncate/", &args[0])?;
+    let m = integer("truncate/", &args[1])?;
+    Ok(Value::values(vec![
+        Value::Number(n.quotient(m)?),
+        Value::Number(n.remainder(m)?),
+    ]))
+}
+
+fn gcd(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut acc = Number::Fixnum(0);
+    for arg in args {
+        acc = acc.gcd(integer("gcd", arg)?)?;
+    }
+    Ok(Value::Number(acc))
+}
+
+fn lcm(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut acc = Number::Fixnum(1);
+    for arg in args {
+        acc = acc.lcm(integer("lcm", arg)?)?;
+    }
+    Ok(Value::Number(acc))
+}
+
+fn numerator(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("numerator", &args[0])?.numerator()))
+}
+
+fn denominator(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("denominator", &args[0])?.denominator()))
+}
+
+fn floor(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("floor", &args[0])?.floor()?))
+}
+
+fn ceiling(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("ceiling", &args[0])?.ceiling()?))
+}
+
+fn truncate(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("truncate", &args[0])?.truncate()?))
+}
+
+fn round(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("round", &args[0])?.round()?))
+}
+
+fn rationalize(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let x = real("rationalize", &args[0])?;
+    let y = real("rationalize", &args[1])?;
+    Ok(Value::Number(x.rationalize(y)?))
+}
+
+fn exp(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("exp", &args[0])?.exp()?))
+}
+
+fn log(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let z = number("log", &args[0])?.log()?;
+    match args.get(1) {
+        Some(base) => Ok(Value::Number(z.div(&number("log", base)?.log()?)?)),
+        None => Ok(Value::Number(z)),
+    }
+}
+
+fn sin(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("sin", &args[0])?.sin()?))
+}
+
+fn cos(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("cos", &args[0])?.cos()?))
+}
+
+fn tan(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("tan", &args[0])?.tan()?))
+}
+
+fn asin(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("asin", &args[0])?.asin()?))
+}
+
+fn acos(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(real("acos", &args[0])?.acos()?))
+}
+
+fn atan(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let y = real("atan", &args[0])?;
+    match args.get(1) {
+        Some(x) => Ok(Value::Number(Number::atan2(y, real("atan", x)?)?)),
+        None => Ok(Value::Number(y.atan()?)),
+    }
+}
+
+fn square(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = number("square", &args[0])?;
+    Ok(Value::Number(n * n))
+}
+
+fn sqrt(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(number("sqrt", &args[0])?.sqrt()))
+}
+
+fn exact_integer_sqrt(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = number("exact-integer-sqrt", &args[0])?;
+    let (s, r) = n.exact_integer_sqrt().map_err(|_| {
+        Error::wrong_type(
+            "exact-integer-sqrt",
+            "an exact non-negative integer",
+            &args[0],
+        )
+    })?;
+    Ok(Value::values(vec![Value::Number(s), Value::Number(r)]))
+}
+
+fn expt(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let base = number("expt", &args[0])?;
+    Ok(Value::Number(base.expt(number("expt", &args[1])?)?))
+}
+
+fn make_rectangular(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let re = real("make-rectangular", &args[0])?;
+    let im = real("make-rectangular", &args[1])?;
+    Ok(Value::Number(Number::make_rectangular(re, im)))
+}
+
+fn make_polar(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let magnitude = real("make-polar", &args[0])?;
+    let angle = real("make-polar", &args[1])?;
+    Ok(Value::Number(Number::make_polar(magnitude, angle)))
+}
+
+fn real_part(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(number("real-part", &args[0])?.real_part()))
+}
+
+fn imag_part(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(number("imag-part", &args[0])?.imag_part()))
+}
+
+fn magnitude(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(number("magnitude", &args[0])?.magnitude()))
+}
+
+fn angle(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(number("angle", &args[0])?.angle()))
+}
+
+fn exact(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(number("exact", &args[0])?.to_exact()?))
+}
+
+fn inexact(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Number(number("inexact", &args[0])?.to_inexact()))
+}
+
+fn number_to_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = number("number->string", &args[0])?;
+    let radix = radix("number->string", args, 1)?;
+    if radix != 10 && !n.is_exact() {
+        return Err(Error::new(
+            ConditionKind::ImplementationRestriction,
+            "number->string: inexact numbers can only be written in radix 10",
+            args.to_vec(),
+        ));
+    }
+    Ok(Value::string(&n.to_string_radix(radix)))
+}
+
+fn string_to_number(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let text = string("string->number", &args[0])?.borrow().to_string();
+    let radix = radix("string->number", args, 1)?;
+    Ok(match Number::parse(&text, radix) {
+        Some(n) => Value::Number(n),
+        None => Value::Boolean(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    #[test]
+    fn exactness_propagates_through_arithmetic() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            (
+                "(list (exact? 1/2) (inexact? 0.5) (exact-integer? 5) (exact-integer? 5.0))",
+                "(#t #t #t #f)",
+            ),
+            ("(+ 1/2 0.5)", "1.0"),
+            ("(* 2 1.5)", "3.0"),
+            ("(exact? (* 0 1.5))", "#f"),
+            ("(exact 2.5)", "5/2"),
+            ("(exact 0.1)", "3602879701896397/36028797018963968"),
+            ("(inexact 1/4)", "0.25"),
+            ("(exact->inexact 1/3)", "0.3333333333333333"),
+            ("(inexact->exact 4.0)", "4"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        for text in ["(exact +inf.0)", "(exact +nan.0)"] {
+            assert!(scheme.eval_str(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rationalize_finds_the_simplest_rational() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(rationalize (exact .3) 1/10)", "1/3"),
+            ("(rationalize .3 1/10)", "0.3333333333333333"),
+            ("(rationalize -3/10 1/10)", "-1/3"),
+            ("(rationalize 5/2 1/2)", "2"),
+            ("(rationalize 1/4 1/2)", "0"),
+            ("(rationalize 22/7 0)", "22/7"),
+            ("(rationalize (exact 3.14159) 1/1000)", "201/64"),
+            ("(rationalize 3 +inf.0)", "0.0"),
+            ("(rationalize +inf.0 3)", "+inf.0"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn number_to_string_takes_a_radix() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(number->string 255 16)", "ff"),
+            ("(number->string -10 2)", "-1010"),
+            ("(number->string 7/8 8)", "7/10"),
+            ("(number->string 1.5)", "1.5"),
+            ("(number->string (expt 2 70) 16)", "400000000000000000"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        let err = scheme.eval_str("(number->string 1.5 16)").unwrap_err();
+        assert_eq!(
+            err.condition().map(|condition| condition.kind),
+            Some(ConditionKind::ImplementationRestriction)
+        );
+    }
+
+    #[test]
+    fn string_to_number_reads_prefixes_and_exponents() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(string->number \"ff\" 16)", "255"),
+            ("(string->number \"#b-101\")", "-5"),
+            ("(string->number \"#x1/A\")", "1/10"),
+            ("(string->number \"#e1.25e2\")", "125"),
+            ("(string->number \"#e1.5e-1\")", "3/20"),
+            ("(string->number \"#i1/4\")", "0.25"),
+            ("(string->number \"1e3\")", "1000.0"),
+            ("(string->number \"12abc\")", "#f"),
+            ("(string->number \"#e1e100000000\")", "#f"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+}
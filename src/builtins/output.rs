@@ -0,0 +1,75 @@
+//! Textual output, to the given port or the current output port.
+
+use super::ports::write_to;
+use super::{character, range, string};
+use crate::eval::{Error, Interpreter};
+use crate::pretty_print;
+use crate::print;
+use crate::proc::Arity;
+use crate::value::Value;
+use std::fmt;
+
+/// The line width `pretty-print` aims for.
+const PRETTY_WIDTH: usize = 79;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("display", Arity::between(1, 2), display);
+    interp.define_primitive("write", Arity::between(1, 2), write);
+    interp.define_primitive("write-shared", Arity::between(1, 2), write_shared);
+    interp.define_primitive("write-simple", Arity::between(1, 2), write_simple);
+    interp.define_primitive("pretty-print", Arity::between(1, 2), pretty_print);
+    interp.define_primitive("newline", Arity::between(0, 1), newline);
+    interp.define_primitive("write-char", Arity::between(1, 2), write_char);
+    interp.define_primitive("write-string", Arity::between(1, 4), write_string);
+}
+
+fn print_with(
+    interp: &mut Interpreter,
+    name: &str,
+    args: &[Value],
+    printer: fn(&Value, &mut String) -> fmt::Result,
+) -> Result<Value, Error> {
+    let mut out = String::new();
+    printer(&args[0], &mut out).expect("writing to a String cannot fail");
+    write_to(interp, name, args, 1, |port| port.write_str(&out))
+}
+
+fn display(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    print_with(interp, "display", args, print::display)
+}
+
+fn write(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    print_with(interp, "write", args, print::write)
+}
+
+fn write_shared(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    print_with(interp, "write-shared", args, print::write_shared)
+}
+
+fn write_simple(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    print_with(interp, "write-simple", args, print::write_simple)
+}
+
+fn pretty_print(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut out = String::new();
+    pretty_print::pretty_print(&args[0], PRETTY_WIDTH, &mut out)
+        .expect("writing to a String cannot fail");
+    out.push('\n');
+    write_to(interp, "pretty-print", args, 1, |port| port.write_str(&out))
+}
+
+fn newline(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    write_to(interp, "newline", args, 0, |port| port.write_char('\n'))
+}
+
+fn write_char(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let c = character("write-char", &args[0])?;
+    write_to(interp, "write-char", args, 1, |port| port.write_char(c))
+}
+
+fn write_string(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let s = string("write-string", &args[0])?.borrow();
+    let range = range("write-string", args, 2, s.len())?;
+    let s: String = s.chars()[range].iter().collect();
+    write_to(interp, "write-string", args, 1, |port| port.write_str(&s))
+}
@@ -0,0 +1,1245 @@
+//! Ports, the current port parameters, transcoders and input, including
+//! `read`.
+
+use super::vectors::byte;
+use super::{bytevector, index, procedure, range, string, symbol};
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::lexer::CharSource;
+use crate::parse::Parser;
+use crate::ports::{
+    Codec, EolStyle, ErrorMode, InputPort, OutputPort, Port, PortBackend, PortError, PortKind,
+    Transcoder,
+};
+use crate::proc::{Arity, Parameter, Primitive, PrimitiveFn, Procedure};
+use crate::symbol::Symbol;
+use crate::value::{Bytevector, Value};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    let parameters = [
+        ("current-input-port", interp.current_input.clone()),
+        ("current-output-port", interp.current_output.clone()),
+        ("current-error-port", interp.current_error.clone()),
+    ];
+    for (name, parameter) in parameters {
+        interp.define(name, Value::Procedure(parameter));
+    }
+    interp.define_primitive("port?", Arity::exactly(1), is_port);
+    interp.define_primitive("input-port?", Arity::exactly(1), is_input_port);
+    interp.define_primitive("output-port?", Arity::exactly(1), is_output_port);
+    interp.define_primitive("textual-port?", Arity::exactly(1), is_textual_port);
+    interp.define_primitive("binary-port?", Arity::exactly(1), is_binary_port);
+    interp.define_primitive("input-port-open?", Arity::exactly(1), is_input_port_open);
+    interp.define_primitive("output-port-open?", Arity::exactly(1), is_output_port_open);
+    interp.define_primitive("close-port", Arity::exactly(1), close_port);
+    interp.define_primitive("close-input-port", Arity::exactly(1), close_input_port);
+    interp.define_primitive("close-output-port", Arity::exactly(1), close_output_port);
+    interp.define_primitive("open-input-string", Arity::exactly(1), open_input_string);
+    interp.define_primitive("open-output-string", Arity::exactly(0), open_output_string);
+    interp.define_primitive("get-output-string", Arity::exactly(1), get_output_string);
+    interp.define_primitive("flush-output-port", Arity::between(0, 1), flush_output_port);
+    interp.define_primitive("read-char", Arity::between(0, 1), read_char);
+    interp.define_primitive("peek-char", Arity::between(0, 1), peek_char);
+    interp.define_primitive("char-ready?", Arity::between(0, 1), is_char_ready);
+    interp.define_primitive("read-line", Arity::between(0, 1), read_line);
+    interp.define_primitive("read-string", Arity::between(1, 2), read_string);
+    interp.define_primitive(
+        "open-input-bytevector",
+        Arity::exactly(1),
+        open_input_bytevector,
+    );
+    interp.define_primitive(
+        "open-output-bytevector",
+        Arity::exactly(0),
+        open_output_bytevector,
+    );
+    interp.define_primitive(
+        "get-output-bytevector",
+        Arity::exactly(1),
+        get_output_bytevector,
+    );
+    interp.define_primitive("read-u8", Arity::between(0, 1), read_u8);
+    interp.define_primitive("peek-u8", Arity::between(0, 1), peek_u8);
+    interp.define_primitive("u8-ready?", Arity::between(0, 1), is_u8_ready);
+    interp.define_primitive("read-bytevector", Arity::between(1, 2), read_bytevector);
+    interp.define_primitive(
+        "read-bytevector!",
+        Arity::between(1, 4),
+        read_bytevector_into,
+    );
+    interp.define_primitive("write-u8", Arity::between(1, 2), write_u8);
+    interp.define_primitive("write-bytevector", Arity::between(1, 4), write_bytevector);
+    interp.define_primitive("utf-8-codec", Arity::exactly(0), utf8_codec);
+    interp.define_primitive("utf-16-codec", Arity::exactly(0), utf16_codec);
+    interp.define_primitive("latin-1-codec", Arity::exactly(0), latin1_codec);
+    interp.define_primitive("native-transcoder", Arity::exactly(0), native_transcoder);
+    interp.define_primitive("make-transcoder", Arity::between(1, 3), make_transcoder);
+    interp.define_primitive("transcoder?", Arity::exactly(1), is_transcoder);
+    interp.define_primitive("transcoder-codec", Arity::exactly(1), transcoder_codec);
+    interp.define_primitive(
+        "transcoder-eol-style",
+        Arity::exactly(1),
+        transcoder_eol_style,
+    );
+    interp.define_primitive(
+        "transcoder-error-handling-mode",
+        Arity::exactly(1),
+        transcoder_error_handling_mode,
+    );
+    interp.define_primitive(
+        "make-custom-binary-input-port",
+        Arity::exactly(5),
+        make_custom_binary_input_port,
+    );
+    interp.define_primitive(
+        "make-custom-binary-output-port",
+        Arity::exactly(5),
+        make_custom_binary_output_port,
+    );
+    interp.define_primitive("transcoded-port", Arity::exactly(2), transcoded_port);
+    interp.define_primitive(
+        "bytevector->string",
+        Arity::exactly(2),
+        bytevector_to_string,
+    );
+    interp.define_primitive(
+        "string->bytevector",
+        Arity::exactly(2),
+        string_to_bytevector,
+    );
+    interp.define_primitive("read", Arity::between(0, 1), read);
+    interp.define_primitive("eof-object", Arity::exactly(0), eof_object);
+    interp.define_primitive("eof-object?", Arity::exactly(1), is_eof_object);
+    interp.define_primitive("call-with-port", Arity::exactly(2), call_with_port);
+    interp.define_primitive(
+        "call-with-output-string",
+        Arity::exactly(1),
+        call_with_output_string,
+    );
+    interp.define_primitive(
+        "with-output-to-string",
+        Arity::exactly(1),
+        with_output_to_string,
+    );
+    interp.define_primitive(
+        "with-input-from-string",
+        Arity::exactly(2),
+        with_input_from_string,
+    );
+}
+
+/// File ports, which are only defined with the filesystem capability.
+pub(super) fn install_files(interp: &mut Interpreter) {
+    interp.define_primitive("open-input-file", Arity::exactly(1), open_input_file);
+    interp.define_primitive(
+        "open-binary-input-file",
+        Arity::exactly(1),
+        open_binary_input_file,
+    );
+    interp.define_primitive("open-output-file", Arity::exactly(1), open_output_file);
+    interp.define_primitive(
+        "open-binary-output-file",
+        Arity::exactly(1),
+        open_binary_output_file,
+    );
+}
+
+/// A parameter holding an input port, which only accepts input ports.
+pub(crate) fn input_parameter(port: InputPort) -> Rc<Procedure> {
+    port_parameter(Port::from(port), "current-input-port", check_input_port)
+}
+
+/// A parameter holding an output port, which only accepts output ports.
+pub(crate) fn output_parameter(port: OutputPort) -> Rc<Procedure> {
+    port_parameter(Port::from(port), "current-output-port", check_output_port)
+}
+
+fn port_parameter(port: Port, name: &'static str, check: PrimitiveFn) -> Rc<Procedure> {
+    let converter = Procedure::Primitive(Primitive {
+        name,
+        arity: Arity::exactly(1),
+        func: check,
+    });
+    Rc::new(Procedure::Parameter(Parameter {
+        value: RefCell::new(Value::Port(Rc::new(port))),
+        converter: Some(Value::Procedure(Rc::new(converter))),
+    }))
+}
+
+fn check_input_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    input_port("current-input-port", &args[0])?;
+    Ok(args[0].clone())
+}
+
+fn check_output_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    output_port("current-output-port", &args[0])?;
+    Ok(args[0].clone())
+}
+
+/// Replaces the port held by one of the current port parameters, outside
+/// any `parameterize`.
+pub(crate) fn set_current(current: &Procedure, port: Port) {
+    *parameter(current).value.borrow_mut() = Value::Port(Rc::new(port));
+}
+
+/// Writes `text` to the current output port, as `display` does.
+pub(crate) fn write_current(interp: &mut Interpreter, name: &str, text: &str) -> Result<(), Error> {
+    write_to(interp, name, &[], 0, |port| port.write_str(text)).map(drop)
+}
+
+/// Flushes the current output and error ports, ignoring errors.
+pub(crate) fn flush_current(interp: &Interpreter) {
+    for current in [&interp.current_output, &interp.current_error] {
+        if let Value::Port(port) = parameter(current).get() {
+            if let Port::Output(port) = &*port {
+                port.borrow_mut().flush().ok();
+            }
+        }
+    }
+}
+
+fn parameter(procedure: &Procedure) -> &Parameter {
+    match procedure {
+        Procedure::Parameter(parameter) => parameter,
+        _ => unreachable!("current ports are held by parameters"),
+    }
+}
+
+fn input_port<'a>(name: &str, value: &'a Value) -> Result<&'a RefCell<InputPort>, Error> {
+    match value {
+        Value::Port(port) => match &**port {
+            Port::Input(port) => Ok(port),
+            Port::Output(_) => Err(Error::wrong_type(name, "an input port", value)),
+        },
+        _ => Err(Error::wrong_type(name, "an input port", value)),
+    }
+}
+
+fn output_port<'a>(name: &str, value: &'a Value) -> Result<&'a RefCell<OutputPort>, Error> {
+    match value {
+        Value::Port(port) => match &**port {
+            Port::Output(port) => Ok(port),
+            Port::Input(_) => Err(Error::wrong_type(name, "an output port", value)),
+        },
+        _ => Err(Error::wrong_type(name, "an output port", value)),
+    }
+}
+
+fn port<'a>(name: &str, value: &'a Value) -> Result<&'a Rc<Port>, Error> {
+    match value {
+        Value::Port(port) => Ok(port),
+        _ => Err(Error::wrong_type(name, "a port", value)),
+    }
+}
+
+/// How many bytes a custom input port asks its backend for at a time.
+const CUSTOM_READ_SIZE: usize = 4096;
+
+pub(super) fn port_error(name: &str, err: PortError) -> Error {
+    Error::new(ConditionKind::Io, format!("{}: {}", name, err), Vec::new())
+}
+
+/// The input port in `args[at]`, or the current input port if it is not
+/// given.
+fn input_port_arg(
+    interp: &Interpreter,
+    name: &str,
+    args: &[Value],
+    at: usize,
+) -> Result<Rc<Port>, Error> {
+    let value = match args.get(at) {
+        Some(value) => value.clone(),
+        None => parameter(&interp.current_input).get(),
+    };
+    input_port(name, &value)?;
+    match value {
+        Value::Port(port) => Ok(port),
+        _ => unreachable!("checked to be an input port"),
+    }
+}
+
+/// Reads from the input port in `args[at]`, or from the current input
+/// port if it is not given.
+pub(super) fn read_from<T>(
+    interp: &mut Interpreter,
+    name: &str,
+    args: &[Value],
+    at: usize,
+    read: impl FnMut(&mut InputPort) -> Result<T, PortError>,
+) -> Result<T, Error> {
+    let port = input_port_arg(interp, name, args, at)?;
+    let Port::Input(port) = &*port else {
+        unreachable!("checked to be an input port")
+    };
+    read_port(interp, name, port, read)
+}
+
+/// Runs `read` on `port`. A custom port is filled from its backend and
+/// `read` retried each time `read` runs out of input, so `read` should be
+/// short: longer reads go through [`Chars`] instead.
+fn read_port<T>(
+    interp: &mut Interpreter,
+    name: &str,
+    port: &RefCell<InputPort>,
+    mut read: impl FnMut(&mut InputPort) -> Result<T, PortError>,
+) -> Result<T, Error> {
+    loop {
+        let mut input = port.borrow_mut();
+        let checkpoint = input.checkpoint();
+        match read(&mut input) {
+            Err(PortError::Starved) => {
+                input.rewind(checkpoint);
+                let backend = input.backend().expect("only custom ports are starved");
+                drop(input);
+                let mut buf = vec![0; CUSTOM_READ_SIZE];
+                let n = with_backend(name, &backend, |backend| backend.read(interp, &mut buf))?;
+                port.borrow_mut().supply(&buf[..n.min(buf.len())]);
+            }
+            result => return result.map_err(|err| port_error(name, err)),
+        }
+    }
+}
+
+/// An input port read a character at a time, so that a custom port is
+/// filled from its backend as the reading goes rather than starting it over.
+pub(super) struct Chars<'a> {
+    interp: &'a mut Interpreter,
+    name: &'a str,
+    port: Rc<Port>,
+}
+
+impl<'a> Chars<'a> {
+    /// Reads the input port in `args[at]`, or the current input port if it
+    /// is not given.
+    pub(super) fn new(
+        interp: &'a mut Interpreter,
+        name: &'a str,
+        args: &[Value],
+        at: usize,
+    ) -> Result<Self, Error> {
+        let port = input_port_arg(interp, name, args, at)?;
+        Ok(Self { interp, name, port })
+    }
+
+    pub(super) fn read_char(&mut self) -> Result<Option<char>, Error> {
+        self.read(InputPort::read_char)
+    }
+
+    pub(super) fn peek_char(&mut self) -> Result<Option<char>, Error> {
+        self.read(InputPort::peek_char)
+    }
+
+    fn read<T>(
+        &mut self,
+        read: impl FnMut(&mut InputPort) -> Result<T, PortError>,
+    ) -> Result<T, Error> {
+        let Port::Input(port) = &*self.port else {
+            unreachable!("checked to be an input port")
+        };
+        read_port(self.interp, self.name, port, read)
+    }
+}
+
+/// Writes to the output port in `args[at]`, or to the current output port
+/// if it is not given.
+pub(super) fn write_to(
+    interp: &mut Interpreter,
+    name: &str,
+    args: &[Value],
+    at: usize,
+    write: impl FnOnce(&mut OutputPort) -> Result<(), PortError>,
+) -> Result<Value, Error> {
+    let value = match args.get(at) {
+        Some(value) => value.clone(),
+        None => parameter(&interp.current_output).get(),
+    };
+    let port = output_port(name, &value)?;
+    let result = write(&mut port.borrow_mut());
+    result.map_err(|err| port_error(name, err))?;
+    let backend = port.borrow().backend();
+    if let Some(backend) = backend {
+        let bytes = port.borrow_mut().take_output();
+        if !bytes.is_empty() {
+            with_backend(name, &backend, |backend| backend.write(interp, &bytes))?;
+        }
+    }
+    Ok(Value::Unspecified)
+}
+
+/// Runs `f` on a custom port's backend, which is in use until it returns.
+fn with_backend<T>(
+    name: &str,
+    backend: &RefCell<dyn PortBackend>,
+    f: impl FnOnce(&mut dyn PortBackend) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let mut backend = backend.try_borrow_mut().map_err(|_| {
+        Error::new(
+            ConditionKind::Io,
+            format!("{}: custom port used by its own procedures", name),
+            Vec::new(),
+        )
+    })?;
+    f(&mut *backend)
+}
+
+/// Closes `port`, calling the close procedure of a custom port the first
+/// time.
+fn close(interp: &mut Interpreter, name: &str, port: &Port) -> Result<(), Error> {
+    let backend = match port {
+        Port::Input(port) => port.borrow().backend(),
+        Port::Output(port) => port.borrow().backend(),
+    };
+    port.close().map_err(|err| port_error(name, err))?;
+    match backend {
+        Some(backend) => with_backend(name, &backend, |backend| backend.close(interp)),
+        None => Ok(()),
+    }
+}
+
+fn is_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Port(_))))
+}
+
+fn is_input_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(
+        matches!(&args[0], Value::Port(port) if matches!(**port, Port::Input(_))),
+    ))
+}
+
+fn is_output_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(
+        matches!(&args[0], Value::Port(port) if matches!(**port, Port::Output(_))),
+    ))
+}
+
+fn is_textual_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(
+        matches!(&args[0], Value::Port(port) if port.kind() == PortKind::Textual),
+    ))
+}
+
+fn is_binary_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(
+        matches!(&args[0], Value::Port(port) if port.kind() == PortKind::Binary),
+    ))
+}
+
+fn is_input_port_open(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let port = input_port("input-port-open?", &args[0])?;
+    Ok(Value::Boolean(port.borrow().is_open()))
+}
+
+fn is_output_port_open(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let port = output_port("output-port-open?", &args[0])?;
+    Ok(Value::Boolean(port.borrow().is_open()))
+}
+
+fn close_port(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    close(interp, "close-port", port("close-port", &args[0])?)?;
+    Ok(Value::Unspecified)
+}
+
+fn close_input_port(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    input_port("close-input-port", &args[0])?;
+    close(
+        interp,
+        "close-input-port",
+        port("close-input-port", &args[0])?,
+    )?;
+    Ok(Value::Unspecified)
+}
+
+fn close_output_port(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    output_port("close-output-port", &args[0])?;
+    close(
+        interp,
+        "close-output-port",
+        port("close-output-port", &args[0])?,
+    )?;
+    Ok(Value::Unspecified)
+}
+
+fn open_input_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let s = string("open-input-string", &args[0])?.borrow().to_string();
+    Ok(Value::Port(Rc::new(Port::from(InputPort::from_string(&s)))))
+}
+
+fn open_output_string(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Port(Rc::new(Port::from(OutputPort::string()))))
+}
+
+fn get_output_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let port = output_port("get-output-string", &args[0])?;
+    let result = port.borrow().get_output_string();
+    let s = result.map_err(|err| port_error("get-output-string", err))?;
+    Ok(Value::string(&s))
+}
+
+fn flush_output_port(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    write_to(interp, "flush-output-port", args, 0, OutputPort::flush)
+}
+
+/// A character read by `read-char` or `peek-char`, or the end-of-file
+/// object.
+fn char_or_eof(c: Option<char>) -> Value {
+    c.map_or(Value::Eof, Value::Character)
+}
+
+fn read_char(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    read_from(interp, "read-char", args, 0, InputPort::read_char).map(char_or_eof)
+}
+
+fn peek_char(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    read_from(interp, "peek-char", args, 0, InputPort::peek_char).map(char_or_eof)
+}
+
+fn is_char_ready(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    read_from(interp, "char-ready?", args, 0, |port| {
+        port.is_ready(PortKind::Textual)
+    })
+    .map(Value::Boolean)
+}
+
+/// As [`InputPort::read_line`].
+fn read_line(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut chars = Chars::new(interp, "read-line", args, 0)?;
+    let mut line = String::new();
+    loop {
+        match chars.read_char()? {
+            None if line.is_empty() => return Ok(Value::Eof),
+            None | Some('\n') => break,
+            Some('\r') => {
+                if chars.peek_char()? == Some('\n') {
+                    chars.read_char()?;
+                }
+                break;
+            }
+            Some(c) => line.push(c),
+        }
+    }
+    Ok(Value::string(&line))
+}
+
+fn read_string(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let k = index("read-string", &args[0])?;
+    let mut chars = Chars::new(interp, "read-string", args, 1)?;
+    let mut s = String::new();
+    for _ in 0..k {
+        match chars.read_char()? {
+            Some(c) => s.push(c),
+            None if s.is_empty() => return Ok(Value::Eof),
+            None => break,
+        }
+    }
+    Ok(Value::string(&s))
+}
+
+fn open_input_bytevector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let bytes = bytevector("open-input-bytevector", &args[0])?.borrow();
+    let port = InputPort::from_bytevector(bytes.as_bytes().to_vec());
+    Ok(Value::Port(Rc::new(Port::from(port))))
+}
+
+fn open_output_bytevector(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Port(Rc::new(Port::from(OutputPort::bytevector()))))
+}
+
+fn get_output_bytevector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let port = output_port("get-output-bytevector", &args[0])?;
+    let result = port.borrow().get_output_bytevector();
+    let bytes = result.map_err(|err| port_error("get-output-bytevector", err))?;
+    Ok(Value::bytevector(bytes))
+}
+
+/// A byte read by `read-u8` or `peek-u8`, or the end-of-file object.
+fn byte_or_eof(byte: Option<u8>) -> Value {
+    byte.map_or(Value::Eof, |byte| Value::from(byte as i64))
+}
+
+fn read_u8(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    read_from(interp, "read-u8", args, 0, InputPort::read_u8).map(byte_or_eof)
+}
+
+fn peek_u8(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    read_from(interp, "peek-u8", args, 0, InputPort::peek_u8).map(byte_or_eof)
+}
+
+fn is_u8_ready(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    read_from(interp, "u8-ready?", args, 0, |port| {
+        port.is_ready(PortKind::Binary)
+    })
+    .map(Value::Boolean)
+}
+
+fn read_bytevector(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let k = index("read-bytevector", &args[0])?;
+    match read_bytes(interp, "read-bytevector", args, 1, k)? {
+        Some(bytes) => Ok(Value::bytevector(bytes)),
+        None => Ok(Value::Eof),
+    }
+}
+
+/// `(read-bytevector! bytevector [port [start [end]]])`, returning the
+/// number of bytes read into the bytevector.
+fn read_bytevector_into(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let target = bytevector("read-bytevector!", &args[0])?.clone();
+    let range = range("read-bytevector!", args, 2, target.borrow().len())?;
+    match read_bytes(interp, "read-bytevector!", args, 1, range.len())? {
+        Some(bytes) => {
+            let n = bytes.len();
+            let bytes = Bytevector::from(bytes);
+            target.borrow_mut().copy_from(range.start, &bytes, 0..n)?;
+            Ok(Value::from(n as i64))
+        }
+        None => Ok(Value::Eof),
+    }
+}
+
+/// Reads up to `k` bytes from the input port in `args[at]`, or `None` at
+/// the end of input.
+fn read_bytes(
+    interp: &mut Interpreter,
+    name: &str,
+    args: &[Value],
+    at: usize,
+    k: usize,
+) -> Result<Option<Vec<u8>>, Error> {
+    let port = input_port_arg(interp, name, args, at)?;
+    let Port::Input(port) = &*port else {
+        unreachable!("checked to be an input port")
+    };
+    // Read what is available at a time, so that a custom port is not read
+    // over from the start each time it is filled.
+    let mut bytes = Vec::new();
+    while bytes.len() < k {
+        let wanted = k - bytes.len();
+        match read_port(interp, name, port, |port| port.read_some(wanted))? {
+            Some(chunk) => bytes.extend(chunk),
+            None if bytes.is_empty() => return Ok(None),
+            None => break,
+        }
+    }
+    Ok(Some(bytes))
+}
+
+fn write_u8(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let b = byte("write-u8", &args[0])?;
+    write_to(interp, "write-u8", args, 1, |port| port.write_u8(b))
+}
+
+fn write_bytevector(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let bytes = bytevector("write-bytevector", &args[0])?.borrow();
+    let range = range("write-bytevector", args, 2, bytes.len())?;
+    let bytes = bytes.as_bytes()[range].to_vec();
+    write_to(interp, "write-bytevector", args, 1, |port| {
+        port.write_bytevector(&bytes)
+    })
+}
+
+/// Codecs, end-of-line styles and error handling modes are symbols, as in
+/// R6RS.
+fn named<T>(
+    name: &str,
+    what: &str,
+    from_name: fn(&str) -> Option<T>,
+    value: &Value,
+) -> Result<T, Error> {
+    let sym = symbol(name, value)?;
+    from_name(sym.name()).ok_or_else(|| Error::wrong_type(name, what, value))
+}
+
+fn transcoder(name: &str, value: &Value) -> Result<Transcoder, Error> {
+    match value {
+        Value::Transcoder(transcoder) => Ok(*transcoder),
+        _ => Err(Error::wrong_type(name, "a transcoder", value)),
+    }
+}
+
+fn utf8_codec(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Symbol(Symbol::intern(Codec::Utf8.name())))
+}
+
+fn utf16_codec(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Symbol(Symbol::intern(Codec::Utf16.name())))
+}
+
+fn latin1_codec(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Symbol(Symbol::intern(Codec::Latin1.name())))
+}
+
+fn native_transcoder(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Transcoder(Transcoder::default()))
+}
+
+/// `(make-transcoder codec [eol-style [handling-mode]])`, defaulting to no
+/// line ending translation and replacing invalid input.
+fn make_transcoder(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "make-transcoder";
+    let mut transcoder = Transcoder::new(named(name, "a codec", Codec::from_name, &args[0])?);
+    if let Some(style) = args.get(1) {
+        transcoder.eol_style = named(name, "an end-of-line style", EolStyle::from_name, style)?;
+    }
+    if let Some(mode) = args.get(2) {
+        transcoder.error_mode = named(name, "an error handling mode", ErrorMode::from_name, mode)?;
+    }
+    Ok(Value::Transcoder(transcoder))
+}
+
+fn is_transcoder(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Transcoder(_))))
+}
+
+fn transcoder_codec(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let transcoder = transcoder("transcoder-codec", &args[0])?;
+    Ok(Value::Symbol(Symbol::intern(transcoder.codec.name())))
+}
+
+fn transcoder_eol_style(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let transcoder = transcoder("transcoder-eol-style", &args[0])?;
+    Ok(Value::Symbol(Symbol::intern(transcoder.eol_style.name())))
+}
+
+fn transcoder_error_handling_mode(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let transcoder = transcoder("transcoder-error-handling-mode", &args[0])?;
+    Ok(Value::Symbol(Symbol::intern(transcoder.error_mode.name())))
+}
+
+/// A custom port's backend, calling the procedures given to
+/// `make-custom-binary-input-port` or `make-custom-binary-output-port`.
+struct SchemeBackend {
+    name: &'static str,
+    /// `read!` or `write!`, called with a bytevector, a start index and a
+    /// count.
+    transfer: Value,
+    close: Option<Value>,
+}
+
+impl SchemeBackend {
+    /// The backend for `args`, which are an id, `read!` or `write!`, the
+    /// position procedures and the close procedure. The position procedures
+    /// are accepted but never called, since ports have no positions.
+    fn new(name: &'static str, args: &[Value]) -> Result<Self, Error> {
+        string(name, &args[0])?;
+        procedure(name, &args[1])?;
+        for optional in &args[2..] {
+            if !matches!(optional, Value::Boolean(false)) {
+                procedure(name, optional)?;
+            }
+        }
+        Ok(Self {
+            name,
+            transfer: args[1].clone(),
+            close: args[4].is_true().then(|| args[4].clone()),
+        })
+    }
+
+    /// Calls `read!` or `write!`, returning the count it returns if that is
+    /// within `count`.
+    fn transfer(
+        &self,
+        interp: &mut Interpreter,
+        bytes: &Value,
+        start: usize,
+        count: usize,
+    ) -> Result<usize, Error> {
+        let args = [
+            bytes.clone(),
+            Value::from(start as i64),
+            Value::from(count as i64),
+        ];
+        let result = interp.apply(&self.transfer, &args)?;
+        match index(self.name, &result) {
+            Ok(n) if n <= count => Ok(n),
+            _ => Err(Error::wrong_type(
+                self.name,
+                "a count no greater than the one asked for",
+                &result,
+            )),
+        }
+    }
+}
+
+impl PortBackend for SchemeBackend {
+    fn read(&mut self, interp: &mut Interpreter, buf: &mut [u8]) -> Result<usize, Error> {
+        let bytes = Value::bytevector(vec![0; buf.len()]);
+        let n = self.transfer(interp, &bytes, 0, buf.len())?;
+        if let Value::Bytevector(bytes) = &bytes {
+            buf[..n].copy_from_slice(&bytes.borrow().as_bytes()[..n]);
+        }
+        Ok(n)
+    }
+
+    fn write(&mut self, interp: &mut Interpreter, bytes: &[u8]) -> Result<(), Error> {
+        let value = Value::bytevector(bytes.to_vec());
+        let mut start = 0;
+        while start < bytes.len() {
+            match self.transfer(interp, &value, start, bytes.len() - start)? {
+                0 => {
+                    return Err(Error::new(
+                        ConditionKind::Io,
+                        format!("{}: write! wrote nothing", self.name),
+                        Vec::new(),
+                    ))
+                }
+                n => start += n,
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, interp: &mut Interpreter) -> Result<(), Error> {
+        match &self.close {
+            Some(close) => interp.apply(close, &[]).map(drop),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `(make-custom-binary-input-port id read! get-position set-position!
+/// close)`, as in R6RS.
+fn make_custom_binary_input_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let backend = SchemeBackend::new("make-custom-binary-input-port", args)?;
+    let port = InputPort::custom(backend, PortKind::Binary);
+    Ok(Value::Port(Rc::new(Port::from(port))))
+}
+
+/// `(make-custom-binary-output-port id write! get-position set-position!
+/// close)`, as in R6RS.
+fn make_custom_binary_output_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let backend = SchemeBackend::new("make-custom-binary-output-port", args)?;
+    let port = OutputPort::custom(backend, PortKind::Binary);
+    Ok(Value::Port(Rc::new(Port::from(port))))
+}
+
+/// A textual port over a binary port, which is left closed.
+fn transcoded_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "transcoded-port";
+    let transcoder = transcoder(name, &args[1])?;
+    let result = match &**port(name, &args[0])? {
+        Port::Input(port) => port.borrow_mut().transcoded(transcoder).map(Port::from),
+        Port::Output(port) => port.borrow_mut().transcoded(transcoder).map(Port::from),
+    };
+    let port = result.map_err(|err| port_error(name, err))?;
+    Ok(Value::Port(Rc::new(port)))
+}
+
+fn bytevector_to_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "bytevector->string";
+    let bytes = bytevector(name, &args[0])?.borrow().as_bytes().to_vec();
+    let transcoder = transcoder(name, &args[1])?;
+    let decode = || {
+        let mut port = InputPort::from_bytevector(bytes).transcoded(transcoder)?;
+        let mut s = String::new();
+        while let Some(c) = port.read_char()? {
+            s.push(c);
+        }
+        Ok(s)
+    };
+    let s = decode().map_err(|err| port_error(name, err))?;
+    Ok(Value::string(&s))
+}
+
+fn string_to_bytevector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "string->bytevector";
+    let s = string(name, &args[0])?.borrow().to_string();
+    let transcoder = transcoder(name, &args[1])?;
+    let bytes = transcoder.encode(&s).map_err(|err| port_error(name, err))?;
+    Ok(Value::bytevector(bytes))
+}
+
+/// Opens the file named by `args[0]`, reporting errors against its path.
+fn open_file<T>(
+    name: &str,
+    args: &[Value],
+    open: fn(PathBuf, PortKind) -> std::io::Result<T>,
+    kind: PortKind,
+) -> Result<Value, Error>
+where
+    Port: From<T>,
+{
+    let path = string(name, &args[0])?.borrow().to_string();
+    let port = open(PathBuf::from(&path), kind)
+        .map_err(|err| Error::new(ConditionKind::Io, format!("{}: {}", path, err), Vec::new()))?;
+    Ok(Value::Port(Rc::new(Port::from(port))))
+}
+
+fn open_input_file(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    open_file(
+        "open-input-file",
+        args,
+        InputPort::open_file,
+        PortKind::Textual,
+    )
+}
+
+fn open_binary_input_file(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    open_file(
+        "open-binary-input-file",
+        args,
+        InputPort::open_file,
+        PortKind::Binary,
+    )
+}
+
+fn open_output_file(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    open_file(
+        "open-output-file",
+        args,
+        OutputPort::open_file,
+        PortKind::Textual,
+    )
+}
+
+fn open_binary_output_file(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    open_file(
+        "open-binary-output-file",
+        args,
+        OutputPort::open_file,
+        PortKind::Binary,
+    )
+}
+
+/// Feeds the reader from an input port, keeping the first error since the
+/// reader itself only sees the end of input.
+struct PortSource<'a> {
+    chars: Chars<'a>,
+    error: Option<Error>,
+}
+
+impl PortSource<'_> {
+    fn check(&mut self, result: Result<Option<char>, Error>) -> Option<char> {
+        result.unwrap_or_else(|err| {
+            self.error.get_or_insert(err);
+            None
+        })
+    }
+}
+
+impl CharSource for PortSource<'_> {
+    fn peek(&mut self) -> Option<char> {
+        let result = self.chars.peek_char();
+        self.check(result)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let result = self.chars.read_char();
+        self.check(result)
+    }
+}
+
+/// Reads one datum, leaving the rest of the input in the port.
+fn read(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut chars = Chars::new(interp, "read", args, 0)?;
+    // Reading from a closed or binary port fails before the reader starts.
+    chars.peek_char()?;
+    let mut parser = Parser::new(PortSource { chars, error: None });
+    let datum = parser.next_datum();
+    if let Some(err) = parser.into_source().error {
+        return Err(err);
+    }
+    Ok(datum?.unwrap_or(Value::Eof))
+}
+
+fn eof_object(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Eof)
+}
+
+fn is_eof_object(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Eof)))
+}
+
+/// Calls `proc` with the port and closes the port if it returns.
+fn call_with_port(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let port = port("call-with-port", &args[0])?;
+    procedure("call-with-port", &args[1])?;
+    let result = interp.apply(&args[1], std::slice::from_ref(&args[0]))?;
+    close(interp, "call-with-port", port)?;
+    Ok(result)
+}
+
+fn call_with_output_string(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("call-with-output-string", &args[0])?;
+    let port = Value::Port(Rc::new(Port::from(OutputPort::string())));
+    interp.apply(&args[0], std::slice::from_ref(&port))?;
+    get_output_string(interp, &[port])
+}
+
+/// Calls `thunk` with the current output port set to a new string port,
+/// returning what was written to it.
+fn with_output_to_string(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("with-output-to-string", &args[0])?;
+    let port = Value::Port(Rc::new(Port::from(OutputPort::string())));
+    let current = interp.current_output.clone();
+    parameter(&current).with(port.clone(), || interp.apply(&args[0], &[]))?;
+    get_output_string(interp, &[port])
+}
+
+/// Calls `thunk` with the current input port set to read from a string.
+fn with_input_from_string(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let s = string("with-input-from-string", &args[0])?
+        .borrow()
+        .to_string();
+    procedure("with-input-from-string", &args[1])?;
+    let port = Value::Port(Rc::new(Port::from(InputPort::from_string(&s))));
+    let current = interp.current_input.clone();
+    parameter(&current).with(port, || interp.apply(&args[1], &[]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::{ConditionKind, Error, Interpreter};
+    use crate::ports::{InputPort, PortBackend, PortKind};
+    use crate::Scheme;
+
+    /// Supplies its input a few bytes at a time.
+    struct Trickle {
+        input: Vec<u8>,
+        pos: usize,
+        chunk: usize,
+    }
+
+    impl PortBackend for Trickle {
+        fn read(&mut self, _: &mut Interpreter, buf: &mut [u8]) -> Result<usize, Error> {
+            let n = self.chunk.min(buf.len()).min(self.input.len() - self.pos);
+            buf[..n].copy_from_slice(&self.input[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    fn trickle(scheme: &mut Scheme, input: &str, chunk: usize) {
+        let backend = Trickle {
+            input: input.as_bytes().to_vec(),
+            pos: 0,
+            chunk,
+        };
+        let mut port = InputPort::custom(backend, PortKind::Binary);
+        let port = port.transcoded(Default::default()).unwrap();
+        scheme.set_current_input_port(port);
+    }
+
+    #[test]
+    fn custom_ports_read_across_refills() {
+        let mut scheme = Scheme::new();
+        trickle(&mut scheme, "(a \"é€\" #(1 2)) line one\nabcdéf tail", 1);
+        for (text, expected) in [
+            ("(read)", "(a é€ #(1 2))"),
+            ("(read-line)", " line one"),
+            ("(read-string 5)", "abcdé"),
+            ("(read-char)", "f"),
+            ("(read-string 100)", " tail"),
+            ("(read-char)", "#<eof>"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn custom_ports_read_long_data_in_small_chunks() {
+        let mut scheme = Scheme::new();
+        let datum = format!("({})", "ab ".repeat(40000));
+        trickle(&mut scheme, &datum, 3);
+        let value = scheme.eval_str("(length (read))").unwrap();
+        assert_eq!(value.to_string(), "40000");
+    }
+
+    #[test]
+    fn custom_ports_from_scheme() {
+        let mut scheme = Scheme::new();
+        let value = scheme
+            .eval_str(
+                "(define source (string->utf8 \"hello, world\"))
+                 (define pos 0)
+                 (define (read! bv start count)
+                   (let ((n (min 2 count (- (bytevector-length source) pos))))
+                     (bytevector-copy! bv start source pos (+ pos n))
+                     (set! pos (+ pos n))
+                     n))
+                 (define in (make-custom-binary-input-port \"in\" read! #f #f #f))
+                 (define out-bytes '())
+                 (define (write! bv start count)
+                   (set! out-bytes (cons (bytevector-copy bv start (+ start count)) out-bytes))
+                   count)
+                 (define out (make-custom-binary-output-port \"out\" write! #f #f #f))
+                 (write-bytevector (read-bytevector 5 in) out)
+                 (list (utf8->string (apply bytevector-append (reverse out-bytes)))
+                       (read-u8 in)
+                       (utf8->string (read-bytevector 100 in))
+                       (eof-object? (read-bytevector 1 in)))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "(hello 44  world #t)");
+    }
+
+    #[test]
+    fn string_and_bytevector_ports_read_and_write() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            (
+                "(let ((p (open-input-string \"ab\\ncd\")))
+                   (list (peek-char p) (read-char p) (read-line p) (read-line p) (read-line p)))",
+                "(a a b cd #<eof>)",
+            ),
+            (
+                "(let ((p (open-output-string)))
+                   (write-char #\\x p)
+                   (write-string \"yz\" p)
+                   (get-output-string p))",
+                "xyz",
+            ),
+            (
+                "(let ((p (open-input-bytevector (bytevector 1 2 3))))
+                   (list (peek-u8 p) (read-u8 p) (read-bytevector 5 p) (read-u8 p)))",
+                "(1 1 #u8(2 3) #<eof>)",
+            ),
+            (
+                "(let ((p (open-output-bytevector)))
+                   (write-u8 7 p)
+                   (write-bytevector (bytevector 8 9 10) p 1)
+                   (get-output-bytevector p))",
+                "#u8(7 9 10)",
+            ),
+            (
+                "(let ((p (open-input-string \"x\")))
+                   (close-port p)
+                   (list (input-port? p) (input-port-open? p)))",
+                "(#t #f)",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        assert!(scheme
+            .eval_str("(let ((p (open-input-string \"x\"))) (close-port p) (read-char p))")
+            .is_err());
+    }
+
+    #[test]
+    fn read_bytevector_fills_part_of_a_bytevector() {
+        let mut scheme = Scheme::new();
+        let value = scheme
+            .eval_str(
+                "(let ((bv (make-bytevector 5 0)) (p (open-input-bytevector (bytevector 1 2 3))))
+                   (list (read-bytevector! bv p 1) bv (read-bytevector! bv p 0 0) (read-bytevector! bv p)))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "(3 #u8(0 1 2 3 0) 0 #<eof>)");
+        assert!(scheme
+            .eval_str(
+                "(read-bytevector! (make-bytevector 2) (open-input-bytevector (bytevector 1)) 1 3)"
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn ports_that_may_wait_are_ready_once_peeked() {
+        let mut scheme = Scheme::new();
+        let value = scheme
+            .eval_str(
+                "(list (char-ready? (open-input-string \"\"))
+                       (u8-ready? (open-input-bytevector (bytevector 1))))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "(#t #t)");
+        let stdin = InputPort::from_reader(&b"ab"[..], PortKind::Textual);
+        scheme.set_current_input_port(stdin);
+        let value = scheme
+            .eval_str("(let* ((before (char-ready?)) (c (peek-char))) (list before (char-ready?)))")
+            .unwrap();
+        assert_eq!(value.to_string(), "(#f #t)");
+        trickle(&mut scheme, "xy", 1);
+        let value = scheme
+            .eval_str("(let* ((before (char-ready?)) (c (peek-char))) (list before (char-ready?)))")
+            .unwrap();
+        assert_eq!(value.to_string(), "(#f #t)");
+    }
+
+    #[test]
+    fn file_ports_write_and_read_back() {
+        let path = std::env::temp_dir().join(format!("scheme-ports-{}", std::process::id()));
+        let path = path.to_str().unwrap().replace('\\', "\\\\");
+        let mut scheme = Scheme::new();
+        let value = scheme
+            .eval_str(&format!(
+                "(let ((out (open-output-file \"{0}\")))
+                   (write-string \"first\\nsecond\\n\" out)
+                   (close-port out))
+                 (let* ((in (open-input-file \"{0}\"))
+                        (lines (list (read-line in) (read-line in) (read-line in))))
+                   (close-port in)
+                   lines)",
+                path
+            ))
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(value.to_string(), "(first second #<eof>)");
+    }
+
+    #[test]
+    fn string_port_helpers_redirect_the_current_ports() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            (
+                "(with-output-to-string (lambda () (display \"a\") (write 'b)))",
+                "ab",
+            ),
+            (
+                "(call-with-output-string (lambda (port) (write \"q\" port)))",
+                "\"q\"",
+            ),
+            (
+                "(with-input-from-string \"12 (x)\" (lambda () (list (read) (read) (read))))",
+                "(12 (x) #<eof>)",
+            ),
+            (
+                "(let ((p (open-input-string \"z\")))
+                   (list (call-with-port p read-char) (input-port-open? p)))",
+                "(z #f)",
+            ),
+            (
+                "(let ((out (open-output-string)))
+                   (parameterize ((current-output-port out)) (display 42))
+                   (get-output-string out))",
+                "42",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        let err = scheme
+            .eval_str("(with-output-to-string (lambda () (display 1) (car '())))")
+            .unwrap_err();
+        assert!(err.condition().is_some());
+        assert!(scheme
+            .eval_str("(parameterize ((current-output-port 5)) 1)")
+            .is_err());
+    }
+
+    #[test]
+    fn read_returns_data_until_the_end_of_file() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            (
+                "(let ((p (open-input-string \"(a . b) #(1 \\\"s\\\") 'q ; comment\\n#\\\\x\")))
+                   (list (read p) (read p) (read p) (read p) (eof-object? (read p))))",
+                "((a . b) #(1 s) (quote q) x #t)",
+            ),
+            ("(eof-object? (eof-object))", "#t"),
+            ("(eof-object? \"\")", "#f"),
+            ("(symbol? (read (open-input-string \"abc\")))", "#t"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        let err = scheme
+            .eval_str("(read (open-input-string \"(1 2\"))")
+            .unwrap_err();
+        assert_eq!(
+            err.condition().map(|condition| condition.kind),
+            Some(ConditionKind::Read)
+        );
+    }
+}
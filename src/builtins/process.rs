@@ -0,0 +1,209 @@
+//! Subprocesses, the environment and exiting, only defined with the
+//! process capability.
+
+use super::ports::port_error;
+use super::{integer, string};
+use crate::eval::{ConditionKind, Error, Interpreter};
+use crate::proc::Arity;
+use crate::process::Process;
+use crate::value::Value;
+use std::env;
+use std::process;
+use std::rc::Rc;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("spawn-process", Arity::at_least(1), spawn_process);
+    interp.define_primitive("process?", Arity::exactly(1), is_process);
+    interp.define_primitive("process-id", Arity::exactly(1), process_id);
+    interp.define_primitive("process-input-port", Arity::exactly(1), process_input_port);
+    interp.define_primitive(
+        "process-output-port",
+        Arity::exactly(1),
+        process_output_port,
+    );
+    interp.define_primitive("process-error-port", Arity::exactly(1), process_error_port);
+    interp.define_primitive("process-wait", Arity::exactly(1), process_wait);
+    interp.define_primitive("process-kill", Arity::exactly(1), process_kill);
+    interp.define_primitive(
+        "get-environment-variable",
+        Arity::exactly(1),
+        get_environment_variable,
+    );
+    interp.define_primitive(
+        "get-environment-variables",
+        Arity::exactly(0),
+        get_environment_variables,
+    );
+    interp.define_primitive("exit", Arity::between(0, 1), exit);
+    interp.define_primitive("emergency-exit", Arity::between(0, 1), emergency_exit);
+}
+
+fn process<'a>(name: &str, value: &'a Value) -> Result<&'a Rc<Process>, Error> {
+    match value {
+        Value::Process(process) => Ok(process),
+        _ => Err(Error::wrong_type(name, "a process", value)),
+    }
+}
+
+/// `(spawn-process program arg ...)`, looking `program` up on the path.
+fn spawn_process(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let args = args
+        .iter()
+        .map(|arg| Ok(string("spawn-process", arg)?.borrow().to_string()))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let process = Process::spawn(&args[0], &args[1..]).map_err(|err| {
+        Error::new(
+            ConditionKind::Io,
+            format!("spawn-process: {}: {}", args[0], err),
+            Vec::new(),
+        )
+    })?;
+    Ok(Value::Process(Rc::new(process)))
+}
+
+fn is_process(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Process(_))))
+}
+
+fn process_id(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let process = process("process-id", &args[0])?;
+    Ok(Value::from(process.id() as i64))
+}
+
+fn process_input_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let process = process("process-input-port", &args[0])?;
+    Ok(Value::Port(process.stdin.clone()))
+}
+
+fn process_output_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let process = process("process-output-port", &args[0])?;
+    Ok(Value::Port(process.stdout.clone()))
+}
+
+fn process_error_port(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let process = process("process-error-port", &args[0])?;
+    Ok(Value::Port(process.stderr.clone()))
+}
+
+/// Waits for the process to exit, returning its exit code, or #f if it was
+/// ended by a signal.
+fn process_wait(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let process = process("process-wait", &args[0])?;
+    let status = process
+        .wait()
+        .map_err(|err| port_error("process-wait", err))?;
+    Ok(status
+        .code()
+        .map_or(Value::Boolean(false), |code| Value::from(code as i64)))
+}
+
+fn process_kill(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let process = process("process-kill", &args[0])?;
+    process.kill().map_err(|err| {
+        Error::new(
+            ConditionKind::Io,
+            format!("process-kill: {}", err),
+            Vec::new(),
+        )
+    })?;
+    Ok(Value::Unspecified)
+}
+
+fn get_environment_variable(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = string("get-environment-variable", &args[0])?
+        .borrow()
+        .to_string();
+    Ok(env::var_os(name).map_or(Value::Boolean(false), |value| {
+        Value::string(&value.to_string_lossy())
+    }))
+}
+
+/// The environment as a list of `(name . value)` pairs.
+fn get_environment_variables(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::list(env::vars_os().map(|(name, value)| {
+        Value::cons(
+            Value::string(&name.to_string_lossy()),
+            Value::string(&value.to_string_lossy()),
+        )
+    })))
+}
+
+/// The status an `exit` or `emergency-exit` argument stands for. As in
+/// R7RS, #t or no argument means success, #f failure, and an integer is
+/// the exit code.
+fn exit_code(name: &str, arg: Option<&Value>) -> Result<i32, Error> {
+    match arg {
+        None | Some(Value::Boolean(true)) => Ok(0),
+        Some(Value::Boolean(false)) => Ok(1),
+        Some(value) => integer(name, value)?
+            .to_i64()
+            .and_then(|code| i32::try_from(code).ok())
+            .ok_or_else(|| Error::wrong_type(name, "an exit code", value)),
+    }
+}
+
+/// Unwinds out of the program, running the after thunks of `dynamic-wind`.
+/// The interpreter flushes the current output ports when it reaches the
+/// top, and leaves exiting to the host.
+fn exit(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Err(Error::Exit(exit_code("exit", args.first())?))
+}
+
+/// Exits the host process at once, without unwinding.
+fn emergency_exit(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    process::exit(exit_code("emergency-exit", args.first())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::Error;
+    use crate::Scheme;
+
+    fn eval(source: &str) -> String {
+        Scheme::new().eval_str(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn processes_read_their_input_and_give_an_exit_code() {
+        let source =
+            "(let ((p (spawn-process \"sh\" \"-c\" \"read line; echo got $line; exit 3\")))
+                        (write-string \"hello\" (process-input-port p))
+                        (newline (process-input-port p))
+                        (let ((code (process-wait p)))
+                          (list code (read-line (process-output-port p)))))";
+        assert_eq!(eval(source), "(3 got hello)");
+    }
+
+    #[test]
+    fn wait_keeps_output_larger_than_a_pipe() {
+        let source = "(let ((p (spawn-process \"sh\" \"-c\" \"yes | head -c 300000; yes | head -c 200000 >&2\")))
+                        (list (process-wait p)
+                              (string-length (read-string 1000000 (process-output-port p)))
+                              (string-length (read-string 1000000 (process-error-port p)))))";
+        assert_eq!(eval(source), "(0 300000 200000)");
+    }
+
+    #[test]
+    fn kill_ends_a_running_process() {
+        let source = "(let ((p (spawn-process \"sleep\" \"10\")))
+                        (process-kill p)
+                        (process-wait p))";
+        assert_eq!(eval(source), "#f");
+    }
+
+    #[test]
+    fn exit_unwinds_through_dynamic_wind() {
+        let mut scheme = Scheme::new();
+        let err = scheme
+            .eval_str(
+                "(define unwound #f)
+                 (dynamic-wind
+                   (lambda () #f)
+                   (lambda () (with-exception-handler (lambda (e) 0) (lambda () (exit 3))))
+                   (lambda () (set! unwound #t)))",
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::Exit(3)));
+        assert_eq!(scheme.lookup("unwound").unwrap().to_string(), "#t");
+    }
+}
@@ -0,0 +1,1101 @@
+//! The SRFI 1 list library, beyond the procedures R7RS already has.
+//!
+//! The linear-update variants such as `filter!` are the same procedures as
+//! their pure counterparts, which SRFI 1 allows.
+
+use super::control::{columns, Columns};
+use super::lists::{shape, Shape};
+use super::{append, index, list, number, procedure, reserve};
+use crate::eval::{Error, Interpreter};
+use crate::num::Number;
+use crate::proc::Arity;
+use crate::value::Value;
+
+/// Linear-update procedures, paired with the procedure they are.
+const LINEAR_UPDATE: &[(&str, &str)] = &[
+    ("take!", "take"),
+    ("drop-right!", "drop-right"),
+    ("split-at!", "split-at"),
+    ("append!", "append"),
+    ("append-reverse!", "append-reverse"),
+    ("reverse!", "reverse"),
+    ("append-map!", "append-map"),
+    ("map!", "map"),
+    ("concatenate!", "concatenate"),
+    ("filter!", "filter"),
+    ("remove!", "remove"),
+    ("partition!", "partition"),
+    ("delete!", "delete"),
+    ("delete-duplicates!", "delete-duplicates"),
+    ("take-while!", "take-while"),
+    ("span!", "span"),
+    ("break!", "break"),
+    ("alist-delete!", "alist-delete"),
+    ("lset-union!", "lset-union"),
+    ("lset-intersection!", "lset-intersection"),
+    ("lset-difference!", "lset-difference"),
+    ("lset-xor!", "lset-xor"),
+    ("lset-diff+intersection!", "lset-diff+intersection"),
+];
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("xcons", Arity::exactly(2), xcons);
+    interp.define_primitive("cons*", Arity::at_least(1), cons_star);
+    interp.define_primitive("list-tabulate", Arity::exactly(2), list_tabulate);
+    interp.define_primitive("iota", Arity::between(1, 3), iota);
+    interp.define_primitive("circular-list", Arity::at_least(1), circular_list);
+    interp.define_primitive("proper-list?", Arity::exactly(1), is_proper_list);
+    interp.define_primitive("dotted-list?", Arity::exactly(1), is_dotted_list);
+    interp.define_primitive("circular-list?", Arity::exactly(1), is_circular_list);
+    interp.define_primitive("null-list?", Arity::exactly(1), is_null_list);
+    interp.define_primitive("not-pair?", Arity::exactly(1), is_not_pair);
+    interp.define_primitive("list=", Arity::at_least(1), list_eq);
+    interp.define_primitive("first", Arity::exactly(1), first);
+    interp.define_primitive("second", Arity::exactly(1), second);
+    interp.define_primitive("third", Arity::exactly(1), third);
+    interp.define_primitive("fourth", Arity::exactly(1), fourth);
+    interp.define_primitive("fifth", Arity::exactly(1), fifth);
+    interp.define_primitive("sixth", Arity::exactly(1), sixth);
+    interp.define_primitive("seventh", Arity::exactly(1), seventh);
+    interp.define_primitive("eighth", Arity::exactly(1), eighth);
+    interp.define_primitive("ninth", Arity::exactly(1), ninth);
+    interp.define_primitive("tenth", Arity::exactly(1), tenth);
+    interp.define_primitive("car+cdr", Arity::exactly(1), car_cdr);
+    interp.define_primitive("take", Arity::exactly(2), take);
+    interp.define_primitive("drop", Arity::exactly(2), drop);
+    interp.define_primitive("take-right", Arity::exactly(2), take_right);
+    interp.define_primitive("drop-right", Arity::exactly(2), drop_right);
+    interp.define_primitive("split-at", Arity::exactly(2), split_at);
+    interp.define_primitive("last", Arity::exactly(1), last);
+    interp.define_primitive("last-pair", Arity::exactly(1), last_pair);
+    interp.define_primitive("length+", Arity::exactly(1), length_plus);
+    interp.define_primitive("concatenate", Arity::exactly(1), concatenate);
+    interp.define_primitive("append-reverse", Arity::exactly(2), append_reverse);
+    interp.define_primitive("zip", Arity::at_least(1), zip);
+    interp.define_primitive("unzip1", Arity::exactly(1), unzip1);
+    interp.define_primitive("unzip2", Arity::exactly(1), unzip2);
+    interp.define_primitive("unzip3", Arity::exactly(1), unzip3);
+    interp.define_primitive("unzip4", Arity::exactly(1), unzip4);
+    interp.define_primitive("unzip5", Arity::exactly(1), unzip5);
+    interp.define_primitive("count", Arity::at_least(2), count);
+    interp.define_primitive("fold", Arity::at_least(3), fold);
+    interp.define_primitive("fold-right", Arity::at_least(3), fold_right);
+    interp.define_primitive("reduce", Arity::exactly(3), reduce);
+    interp.define_primitive("reduce-right", Arity::exactly(3), reduce_right);
+    interp.define_primitive("pair-fold", Arity::at_least(3), pair_fold);
+    interp.define_primitive("pair-fold-right", Arity::at_least(3), pair_fold_right);
+    interp.define_primitive("pair-for-each", Arity::at_least(2), pair_for_each);
+    interp.define_primitive("unfold", Arity::between(4, 5), unfold);
+    interp.define_primitive("unfold-right", Arity::between(4, 5), unfold_right);
+    interp.define_primitive("append-map", Arity::at_least(2), append_map);
+    interp.define_primitive("filter-map", Arity::at_least(2), filter_map);
+    interp.define_primitive("filter", Arity::exactly(2), filter);
+    interp.define_primitive("remove", Arity::exactly(2), remove);
+    interp.define_primitive("partition", Arity::exactly(2), partition);
+    interp.define_primitive("find", Arity::exactly(2), find);
+    interp.define_primitive("find-tail", Arity::exactly(2), find_tail);
+    interp.define_primitive("any", Arity::at_least(2), any);
+    interp.define_primitive("every", Arity::at_least(2), every);
+    interp.define_primitive("list-index", Arity::at_least(2), list_index);
+    interp.define_primitive("take-while", Arity::exactly(2), take_while);
+    interp.define_primitive("drop-while", Arity::exactly(2), drop_while);
+    interp.define_primitive("span", Arity::exactly(2), span);
+    interp.define_primitive("break", Arity::exactly(2), break_);
+    interp.define_primitive("delete", Arity::between(2, 3), delete);
+    interp.define_primitive("delete-duplicates", Arity::between(1, 2), delete_duplicates);
+    interp.define_primitive("alist-cons", Arity::exactly(3), alist_cons);
+    interp.define_primitive("alist-copy", Arity::exactly(1), alist_copy);
+    interp.define_primitive("alist-delete", Arity::between(2, 3), alist_delete);
+    interp.define_primitive("lset-adjoin", Arity::at_least(1), lset_adjoin);
+    interp.define_primitive("lset-union", Arity::at_least(1), lset_union);
+    interp.define_primitive("lset-intersection", Arity::at_least(2), lset_intersection);
+    interp.define_primitive("lset-difference", Arity::at_least(2), lset_difference);
+    interp.define_primitive("lset-xor", Arity::at_least(1), lset_xor);
+    interp.define_primitive("lset<=", Arity::at_least(1), lset_subset);
+    interp.define_primitive("lset=", Arity::at_least(1), lset_equal);
+    interp.define_primitive(
+        "lset-diff+intersection",
+        Arity::at_least(2),
+        lset_diff_intersection,
+    );
+    for (alias, name) in LINEAR_UPDATE {
+        let procedure = interp
+            .lookup(name)
+            .expect("linear-update procedures alias defined procedures");
+        interp.define(alias, procedure);
+    }
+    // `map` already applies its procedure to the elements in order.
+    let map = interp.lookup("map").expect("map is defined");
+    interp.define("map-in-order", map);
+}
+
+/// Calls `proc` and tests its result, as for a predicate.
+fn test(interp: &mut Interpreter, proc: &Value, args: &[Value]) -> Result<bool, Error> {
+    Ok(interp.apply(proc, args)?.is_true())
+}
+
+/// Compares `a` and `b` with `args[at]` if it is given, and with `equal?`
+/// otherwise.
+fn same(
+    interp: &mut Interpreter,
+    args: &[Value],
+    at: usize,
+    a: &Value,
+    b: &Value,
+) -> Result<bool, Error> {
+    match args.get(at) {
+        Some(compare) => test(interp, compare, &[a.clone(), b.clone()]),
+        None => Ok(a.is_equal(b)),
+    }
+}
+
+/// The first `k` elements of a list, and the rest of it.
+fn split(name: &str, value: &Value, k: usize) -> Result<(Vec<Value>, Value), Error> {
+    let mut items = Vec::with_capacity(k);
+    let mut rest = value.clone();
+    for _ in 0..k {
+        match rest {
+            Value::Pair(pair) => {
+                items.push(pair.car());
+                rest = pair.cdr();
+            }
+            _ => {
+                return Err(Error::wrong_type(
+                    name,
+                    "a list of at least k elements",
+                    value,
+                ))
+            }
+        }
+    }
+    Ok((items, rest))
+}
+
+fn xcons(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::cons(args[1].clone(), args[0].clone()))
+}
+
+/// Like `list`, with the last argument as the tail.
+fn cons_star(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (tail, items) = args
+        .split_last()
+        .expect("cons* takes at least one argument");
+    Ok(Value::list_with_tail(items.to_vec(), tail.clone()))
+}
+
+fn list_tabulate(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let n = index("list-tabulate", &args[0])?;
+    procedure("list-tabulate", &args[1])?;
+    let items = (0..n)
+        .map(|i| interp.apply(&args[1], &[Value::from(i as i64)]))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::list(items))
+}
+
+/// `(iota count [start [step]])`.
+fn iota(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let count = index("iota", &args[0])?;
+    let start = match args.get(1) {
+        Some(start) => number("iota", start)?.clone(),
+        None => Number::from(0),
+    };
+    let step = match args.get(2) {
+        Some(step) => number("iota", step)?.clone(),
+        None => Number::from(1),
+    };
+    let mut items = reserve("iota", count)?;
+    items.extend((0..count).map(|i| Value::Number(&start + &(&Number::from(i as i64) * &step))));
+    Ok(Value::list(items))
+}
+
+fn circular_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let list = Value::list(args.iter().cloned());
+    let mut last = list.clone();
+    while let Some(next) = last.as_pair().map(|pair| pair.cdr()) {
+        if matches!(next, Value::Null) {
+            break;
+        }
+        last = next;
+    }
+    last.as_pair()
+        .expect("at least one element")
+        .set_cdr(list.clone());
+    Ok(list)
+}
+
+fn is_proper_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(shape(&args[0]), Shape::Proper(_))))
+}
+
+fn is_dotted_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(shape(&args[0]), Shape::Dotted(_))))
+}
+
+fn is_circular_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(shape(&args[0]), Shape::Circular)))
+}
+
+fn is_null_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Null => Ok(Value::Boolean(true)),
+        Value::Pair(_) => Ok(Value::Boolean(false)),
+        value => Err(Error::wrong_type("null-list?", "a list", value)),
+    }
+}
+
+fn is_not_pair(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(!matches!(args[0], Value::Pair(_))))
+}
+
+/// `(list= elt= list ...)`: whether each list has the same length as the
+/// next and `elt=` holds between their elements in turn.
+fn list_eq(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("list=", &args[0])?;
+    let lists = args[1..]
+        .iter()
+        .map(|arg| list("list=", arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    for pair in lists.windows(2) {
+        if pair[0].len() != pair[1].len() {
+            return Ok(Value::Boolean(false));
+        }
+        for (a, b) in pair[0].iter().zip(&pair[1]) {
+            if !test(interp, &args[0], &[a.clone(), b.clone()])? {
+                return Ok(Value::Boolean(false));
+            }
+        }
+    }
+    Ok(Value::Boolean(true))
+}
+
+macro_rules! nth_accessors {
+    ($($name:ident $k:literal;)*) => {
+        $(
+            fn $name(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+                let name = stringify!($name);
+                match split(name, &args[0], $k)?.1 {
+                    Value::Pair(pair) => Ok(pair.car()),
+                    _ => Err(Error::wrong_type(name, "a list of at least k elements", &args[0])),
+                }
+            }
+        )*
+    };
+}
+
+nth_accessors! {
+    first 0;
+    second 1;
+    third 2;
+    fourth 3;
+    fifth 4;
+    sixth 5;
+    seventh 6;
+    eighth 7;
+    ninth 8;
+    tenth 9;
+}
+
+fn car_cdr(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match &args[0] {
+        Value::Pair(pair) => Ok(Value::values(vec![pair.car(), pair.cdr()])),
+        value => Err(Error::wrong_type("car+cdr", "a pair", value)),
+    }
+}
+
+fn take(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (items, _) = split("take", &args[0], index("take", &args[1])?)?;
+    Ok(Value::list(items))
+}
+
+fn drop(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(split("drop", &args[0], index("drop", &args[1])?)?.1)
+}
+
+/// The number of pairs in a list that is not circular.
+fn pairs(name: &str, value: &Value) -> Result<usize, Error> {
+    match shape(value) {
+        Shape::Proper(len) | Shape::Dotted(len) => Ok(len),
+        Shape::Circular => Err(Error::wrong_type(name, "a finite list", value)),
+    }
+}
+
+/// The last `k` pairs of a list, sharing them with it.
+fn take_right(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let k = index("take-right", &args[1])?;
+    let len = pairs("take-right", &args[0])?;
+    let skip = len.checked_sub(k).ok_or_else(|| {
+        Error::wrong_type("take-right", "a list of at least k elements", &args[0])
+    })?;
+    Ok(split("take-right", &args[0], skip)?.1)
+}
+
+fn drop_right(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let k = index("drop-right", &args[1])?;
+    let len = pairs("drop-right", &args[0])?;
+    let keep = len.checked_sub(k).ok_or_else(|| {
+        Error::wrong_type("drop-right", "a list of at least k elements", &args[0])
+    })?;
+    Ok(Value::list(split("drop-right", &args[0], keep)?.0))
+}
+
+fn split_at(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (items, rest) = split("split-at", &args[0], index("split-at", &args[1])?)?;
+    Ok(Value::values(vec![Value::list(items), rest]))
+}
+
+fn last_pair(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let len = pairs("last-pair", &args[0])?;
+    if len == 0 {
+        return Err(Error::wrong_type("last-pair", "a pair", &args[0]));
+    }
+    Ok(split("last-pair", &args[0], len - 1)?.1)
+}
+
+fn last(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match last_pair(interp, args)? {
+        Value::Pair(pair) => Ok(pair.car()),
+        _ => unreachable!("last-pair returns a pair"),
+    }
+}
+
+/// The length of a list, or #f if it is circular.
+fn length_plus(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match shape(&args[0]) {
+        Shape::Proper(len) | Shape::Dotted(len) => Ok(Value::from(len as i64)),
+        Shape::Circular => Ok(Value::Boolean(false)),
+    }
+}
+
+fn concatenate(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    append(interp, &list("concatenate", &args[0])?)
+}
+
+/// `(append-reverse rev-head tail)`, as `(append (reverse rev-head) tail)`.
+fn append_reverse(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut items = list("append-reverse", &args[0])?;
+    items.reverse();
+    Ok(Value::list_with_tail(items, args[1].clone()))
+}
+
+fn zip(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::list(
+        columns("zip", args)?.into_iter().map(Value::list),
+    ))
+}
+
+/// The first `n` elements of each list in `args[0]`, as `n` lists.
+fn unzip(name: &str, args: &[Value], n: usize) -> Result<Vec<Value>, Error> {
+    let mut unzipped = vec![Vec::new(); n];
+    for item in list(name, &args[0])? {
+        let (heads, _) = split(name, &item, n)?;
+        for (unzipped, head) in unzipped.iter_mut().zip(heads) {
+            unzipped.push(head);
+        }
+    }
+    Ok(unzipped.into_iter().map(Value::list).collect())
+}
+
+fn unzip1(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(unzip("unzip1", args, 1)?.remove(0))
+}
+
+fn unzip2(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::values(unzip("unzip2", args, 2)?))
+}
+
+fn unzip3(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::values(unzip("unzip3", args, 3)?))
+}
+
+fn unzip4(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::values(unzip("unzip4", args, 4)?))
+}
+
+fn unzip5(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::values(unzip("unzip5", args, 5)?))
+}
+
+fn count(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("count", &args[0])?;
+    let mut n = 0;
+    for column in columns("count", &args[1..])? {
+        if test(interp, &args[0], &column)? {
+            n += 1;
+        }
+    }
+    Ok(Value::from(n))
+}
+
+/// `(fold kons knil list ...)`, calling `kons` with the elements and then
+/// the accumulated value.
+fn fold(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("fold", &args[0])?;
+    let mut acc = args[1].clone();
+    for mut column in columns("fold", &args[2..])? {
+        column.push(acc);
+        acc = interp.apply(&args[0], &column)?;
+    }
+    Ok(acc)
+}
+
+fn fold_right(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("fold-right", &args[0])?;
+    let mut acc = args[1].clone();
+    for mut column in columns("fold-right", &args[2..])?.into_iter().rev() {
+        column.push(acc);
+        acc = interp.apply(&args[0], &column)?;
+    }
+    Ok(acc)
+}
+
+/// `(reduce f ridentity list)`, which is `ridentity` for an empty list.
+fn reduce(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("reduce", &args[0])?;
+    let mut items = list("reduce", &args[2])?.into_iter();
+    let Some(mut acc) = items.next() else {
+        return Ok(args[1].clone());
+    };
+    for item in items {
+        acc = interp.apply(&args[0], &[item, acc])?;
+    }
+    Ok(acc)
+}
+
+fn reduce_right(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("reduce-right", &args[0])?;
+    let mut items = list("reduce-right", &args[2])?.into_iter().rev();
+    let Some(mut acc) = items.next() else {
+        return Ok(args[1].clone());
+    };
+    for item in items {
+        acc = interp.apply(&args[0], &[item, acc])?;
+    }
+    Ok(acc)
+}
+
+/// The pairs of `lists`, column by column up to the length of the shortest
+/// list. They are all found before any is passed to a procedure, which may
+/// then change their cdrs.
+fn pair_columns(name: &str, lists: &[Value]) -> Result<Vec<Vec<Value>>, Error> {
+    let len = columns(name, lists)?.len();
+    let mut rests = lists.to_vec();
+    let mut result = Vec::with_capacity(len);
+    for _ in 0..len {
+        let next = rests
+            .iter()
+            .map(|rest| rest.as_pair().expect("within the shortest list").cdr())
+            .collect();
+        result.push(std::mem::replace(&mut rests, next));
+    }
+    Ok(result)
+}
+
+fn pair_fold(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("pair-fold", &args[0])?;
+    let mut acc = args[1].clone();
+    for mut column in pair_columns("pair-fold", &args[2..])? {
+        column.push(acc);
+        acc = interp.apply(&args[0], &column)?;
+    }
+    Ok(acc)
+}
+
+fn pair_fold_right(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("pair-fold-right", &args[0])?;
+    let mut acc = args[1].clone();
+    for mut column in pair_columns("pair-fold-right", &args[2..])?
+        .into_iter()
+        .rev()
+    {
+        column.push(acc);
+        acc = interp.apply(&args[0], &column)?;
+    }
+    Ok(acc)
+}
+
+fn pair_for_each(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("pair-for-each", &args[0])?;
+    for column in pair_columns("pair-for-each", &args[1..])? {
+        interp.apply(&args[0], &column)?;
+    }
+    Ok(Value::Unspecified)
+}
+
+/// `(unfold stop? mapper successor seed [tail-gen])`.
+fn unfold(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut items = Vec::new();
+    let mut seed = args[3].clone();
+    while !test(interp, &args[0], std::slice::from_ref(&seed))? {
+        items.push(interp.apply(&args[1], std::slice::from_ref(&seed))?);
+        seed = interp.apply(&args[2], &[seed])?;
+    }
+    let tail = match args.get(4) {
+        Some(tail_gen) => interp.apply(tail_gen, &[seed])?,
+        None => Value::Null,
+    };
+    Ok(Value::list_with_tail(items, tail))
+}
+
+/// `(unfold-right stop? mapper successor seed [tail])`, building the list
+/// from its end.
+fn unfold_right(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut items = Vec::new();
+    let mut seed = args[3].clone();
+    while !test(interp, &args[0], std::slice::from_ref(&seed))? {
+        items.push(interp.apply(&args[1], std::slice::from_ref(&seed))?);
+        seed = interp.apply(&args[2], &[seed])?;
+    }
+    items.reverse();
+    let tail = args.get(4).cloned().unwrap_or(Value::Null);
+    Ok(Value::list_with_tail(items, tail))
+}
+
+fn append_map(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("append-map", &args[0])?;
+    let results = columns("append-map", &args[1..])?
+        .into_iter()
+        .map(|column| interp.apply(&args[0], &column))
+        .collect::<Result<Vec<_>, _>>()?;
+    append(interp, &results)
+}
+
+/// Like `map`, keeping only the true results.
+fn filter_map(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("filter-map", &args[0])?;
+    let mut results = Vec::new();
+    for column in columns("filter-map", &args[1..])? {
+        let result = interp.apply(&args[0], &column)?;
+        if result.is_true() {
+            results.push(result);
+        }
+    }
+    Ok(Value::list(results))
+}
+
+/// The elements of `args[1]` that do and do not satisfy `args[0]`.
+fn sort_out(
+    interp: &mut Interpreter,
+    name: &str,
+    args: &[Value],
+) -> Result<(Vec<Value>, Vec<Value>), Error> {
+    procedure(name, &args[0])?;
+    let mut kept = Vec::new();
+    let mut removed = Vec::new();
+    for item in list(name, &args[1])? {
+        if test(interp, &args[0], std::slice::from_ref(&item))? {
+            kept.push(item);
+        } else {
+            removed.push(item);
+        }
+    }
+    Ok((kept, removed))
+}
+
+fn filter(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::list(sort_out(interp, "filter", args)?.0))
+}
+
+fn remove(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::list(sort_out(interp, "remove", args)?.1))
+}
+
+fn partition(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (kept, removed) = sort_out(interp, "partition", args)?;
+    Ok(Value::values(vec![Value::list(kept), Value::list(removed)]))
+}
+
+/// The first tail of `args[1]` whose car satisfies `args[0]`, or #f.
+fn search(interp: &mut Interpreter, name: &str, args: &[Value]) -> Result<Value, Error> {
+    procedure(name, &args[0])?;
+    let mut value = args[1].clone();
+    while let Value::Pair(pair) = &value {
+        if test(interp, &args[0], &[pair.car()])? {
+            return Ok(value);
+        }
+        let next = pair.cdr();
+        value = next;
+    }
+    Ok(Value::Boolean(false))
+}
+
+fn find(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    match search(interp, "find", args)? {
+        Value::Pair(pair) => Ok(pair.car()),
+        not_found => Ok(not_found),
+    }
+}
+
+fn find_tail(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    search(interp, "find-tail", args)
+}
+
+/// The first true result of `pred` on the elements, or #f.
+fn any(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("any", &args[0])?;
+    let mut columns = Columns::new("any", &args[1..]);
+    while let Some(column) = columns.next()? {
+        let result = interp.apply(&args[0], &column)?;
+        if result.is_true() {
+            return Ok(result);
+        }
+    }
+    Ok(Value::Boolean(false))
+}
+
+/// The last result of `pred` if it is true for every element, or #f.
+fn every(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("every", &args[0])?;
+    let mut result = Value::Boolean(true);
+    let mut columns = Columns::new("every", &args[1..]);
+    while let Some(column) = columns.next()? {
+        result = interp.apply(&args[0], &column)?;
+        if !result.is_true() {
+            return Ok(result);
+        }
+    }
+    Ok(result)
+}
+
+fn list_index(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("list-index", &args[0])?;
+    let mut columns = Columns::new("list-index", &args[1..]);
+    let mut i = 0;
+    while let Some(column) = columns.next()? {
+        if test(interp, &args[0], &column)? {
+            return Ok(Value::from(i));
+        }
+        i += 1;
+    }
+    Ok(Value::Boolean(false))
+}
+
+/// The longest prefix of `args[1]` whose elements satisfy `args[0]`, and
+/// the rest of the list.
+fn prefix(
+    interp: &mut Interpreter,
+    name: &str,
+    args: &[Value],
+    satisfy: bool,
+) -> Result<(Vec<Value>, Value), Error> {
+    procedure(name, &args[0])?;
+    let mut items = Vec::new();
+    let mut rest = args[1].clone();
+    while let Value::Pair(pair) = &rest {
+        let item = pair.car();
+        if test(interp, &args[0], std::slice::from_ref(&item))? != satisfy {
+            break;
+        }
+        items.push(item);
+        let next = pair.cdr();
+        rest = next;
+    }
+    Ok((items, rest))
+}
+
+fn take_while(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::list(prefix(interp, "take-while", args, true)?.0))
+}
+
+fn drop_while(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(prefix(interp, "drop-while", args, true)?.1)
+}
+
+fn span(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (items, rest) = prefix(interp, "span", args, true)?;
+    Ok(Value::values(vec![Value::list(items), rest]))
+}
+
+fn break_(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let (items, rest) = prefix(interp, "break", args, false)?;
+    Ok(Value::values(vec![Value::list(items), rest]))
+}
+
+/// `(delete x list [=])`, removing the elements `e` for which `(= x e)`.
+fn delete(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut kept = Vec::new();
+    for item in list("delete", &args[1])? {
+        if !same(interp, args, 2, &args[0], &item)? {
+            kept.push(item);
+        }
+    }
+    Ok(Value::list(kept))
+}
+
+/// Keeps the first of each run of equal elements, comparing earlier
+/// elements with later ones.
+fn delete_duplicates(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut kept: Vec<Value> = Vec::new();
+    'items: for item in list("delete-duplicates", &args[0])? {
+        for earlier in &kept {
+            if same(interp, args, 1, earlier, &item)? {
+                continue 'items;
+            }
+        }
+        kept.push(item);
+    }
+    Ok(Value::list(kept))
+}
+
+fn alist_cons(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let entry = Value::cons(args[0].clone(), args[1].clone());
+    Ok(Value::cons(entry, args[2].clone()))
+}
+
+fn alist_copy(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let entries = list("alist-copy", &args[0])?
+        .into_iter()
+        .map(|entry| match &entry {
+            Value::Pair(pair) => Ok(Value::cons(pair.car(), pair.cdr())),
+            _ => Err(Error::wrong_type(
+                "alist-copy",
+                "an association list",
+                &args[0],
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::list(entries))
+}
+
+/// `(alist-delete key alist [=])`, removing the entries whose key `k` has
+/// `(= key k)`.
+fn alist_delete(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut kept = Vec::new();
+    for entry in list("alist-delete", &args[1])? {
+        let Value::Pair(pair) = &entry else {
+            return Err(Error::wrong_type(
+                "alist-delete",
+                "an association list",
+                &args[1],
+            ));
+        };
+        if !same(interp, args, 2, &args[0], &pair.car())? {
+            kept.push(entry);
+        }
+    }
+    Ok(Value::list(kept))
+}
+
+/// Whether `set` has an element `e` with `(= e item)`.
+fn contains(
+    interp: &mut Interpreter,
+    compare: &Value,
+    set: &[Value],
+    item: &Value,
+) -> Result<bool, Error> {
+    for e in set {
+        if test(interp, compare, &[e.clone(), item.clone()])? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `(lset-adjoin = list elt ...)`, adding the elements not already in the
+/// list to its front.
+fn lset_adjoin(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("lset-adjoin", &args[0])?;
+    let mut set = list("lset-adjoin", &args[1])?;
+    let mut added = Vec::new();
+    for item in &args[2..] {
+        if !contains(interp, &args[0], &set, item)? {
+            set.push(item.clone());
+            added.push(item.clone());
+        }
+    }
+    added.reverse();
+    Ok(Value::list_with_tail(added, args[1].clone()))
+}
+
+fn lset_union(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("lset-union", &args[0])?;
+    let Some(first) = args.get(1) else {
+        return Ok(Value::Null);
+    };
+    let mut set = list("lset-union", first)?;
+    let mut added = Vec::new();
+    for other in &args[2..] {
+        for item in list("lset-union", other)? {
+            if !contains(interp, &args[0], &set, &item)? {
+                set.push(item.clone());
+                added.push(item);
+            }
+        }
+    }
+    added.reverse();
+    Ok(Value::list_with_tail(added, first.clone()))
+}
+
+fn lset_intersection(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("lset-intersection", &args[0])?;
+    let others = args[2..]
+        .iter()
+        .map(|other| list("lset-intersection", other))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut kept = Vec::new();
+    'items: for item in list("lset-intersection", &args[1])? {
+        for other in &others {
+            if !contains(interp, &args[0], other, &item)? {
+                continue 'items;
+            }
+        }
+        kept.push(item);
+    }
+    Ok(Value::list(kept))
+}
+
+fn lset_difference(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("lset-difference", &args[0])?;
+    let others = args[2..]
+        .iter()
+        .map(|other| list("lset-difference", other))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut kept = Vec::new();
+    'items: for item in list("lset-difference", &args[1])? {
+        for other in &others {
+            if contains(interp, &args[0], other, &item)? {
+                continue 'items;
+            }
+        }
+        kept.push(item);
+    }
+    Ok(Value::list(kept))
+}
+
+/// The elements of `a` that are not in `b`.
+fn set_difference(
+    interp: &mut Interpreter,
+    compare: &Value,
+    a: &[Value],
+    b: &[Value],
+) -> Result<Vec<Value>, Error> {
+    let mut kept = Vec::new();
+    for item in a {
+        if !contains(interp, compare, b, item)? {
+            kept.push(item.clone());
+        }
+    }
+    Ok(kept)
+}
+
+fn lset_xor(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("lset-xor", &args[0])?;
+    let mut result = Vec::new();
+    for other in &args[1..] {
+        let other = list("lset-xor", other)?;
+        let mut xor = set_difference(interp, &args[0], &result, &other)?;
+        xor.extend(set_difference(interp, &args[0], &other, &result)?);
+        result = xor;
+    }
+    Ok(Value::list(result))
+}
+
+/// Whether each list in `args[1..]` is a subset of the next, or also a
+/// superset if `equal` is set.
+fn subsets(
+    interp: &mut Interpreter,
+    name: &str,
+    args: &[Value],
+    equal: bool,
+) -> Result<Value, Error> {
+    procedure(name, &args[0])?;
+    let lists = args[1..]
+        .iter()
+        .map(|arg| list(name, arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    for pair in lists.windows(2) {
+        if !set_difference(interp, &args[0], &pair[0], &pair[1])?.is_empty()
+            || equal && !set_difference(interp, &args[0], &pair[1], &pair[0])?.is_empty()
+        {
+            return Ok(Value::Boolean(false));
+        }
+    }
+    Ok(Value::Boolean(true))
+}
+
+fn lset_subset(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    subsets(interp, "lset<=", args, false)
+}
+
+fn lset_equal(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    subsets(interp, "lset=", args, true)
+}
+
+/// `(lset-diff+intersection = list1 list2 ...)`: the elements of `list1`
+/// in none of the other lists, and those in at least one of them.
+fn lset_diff_intersection(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = "lset-diff+intersection";
+    procedure(name, &args[0])?;
+    let mut others = Vec::new();
+    for other in &args[2..] {
+        others.extend(list(name, other)?);
+    }
+    let mut difference = Vec::new();
+    let mut intersection = Vec::new();
+    for item in list(name, &args[1])? {
+        if contains(interp, &args[0], &others, &item)? {
+            intersection.push(item);
+        } else {
+            difference.push(item);
+        }
+    }
+    Ok(Value::values(vec![
+        Value::list(difference),
+        Value::list(intersection),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    fn check(cases: &[(&str, &str)]) {
+        let mut scheme = Scheme::new();
+        for (text, expected) in cases {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), *expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn folds_pass_the_element_before_the_accumulator() {
+        check(&[
+            ("(fold cons '() '(1 2 3))", "(3 2 1)"),
+            ("(fold cons* '() '(a b c) '(1 2 3))", "(c 3 b 2 a 1)"),
+            ("(fold-right cons '() '(1 2 3))", "(1 2 3)"),
+            ("(fold-right cons* '() '(a b c) '(1 2 3))", "(a 1 b 2 c 3)"),
+            ("(reduce - 0 '(1 2 3 4))", "2"),
+            ("(reduce-right - 0 '(1 2 3 4))", "-2"),
+            ("(reduce + 0 '())", "0"),
+        ]);
+    }
+
+    #[test]
+    fn delete_duplicates_keeps_first_occurrences_in_order() {
+        check(&[
+            ("(delete-duplicates '(a b a c a b c z))", "(a b c z)"),
+            (
+                "(delete-duplicates '((a . 3) (b . 7) (a . 9) (c . 1))
+                                    (lambda (x y) (eq? (car x) (car y))))",
+                "((a . 3) (b . 7) (c . 1))",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn splitters_return_two_values() {
+        check(&[
+            (
+                "(call-with-values (lambda () (partition even? '(1 2 3 4 5))) list)",
+                "((2 4) (1 3 5))",
+            ),
+            (
+                "(call-with-values (lambda () (span even? '(2 4 5 6))) list)",
+                "((2 4) (5 6))",
+            ),
+            (
+                "(call-with-values (lambda () (break even? '(1 3 4 5))) list)",
+                "((1 3) (4 5))",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn take_and_drop_reject_short_lists() {
+        let mut scheme = Scheme::new();
+        for text in ["(take '(1 2) 3)", "(drop '(1 2) 3)"] {
+            let err = scheme.eval_str(text).unwrap_err();
+            let kind = err.condition().map(|condition| condition.kind);
+            assert_eq!(kind, Some(ConditionKind::WrongType), "{}", text);
+        }
+    }
+
+    #[test]
+    fn length_plus_handles_improper_lists() {
+        check(&[
+            ("(length+ '(1 2 3))", "3"),
+            ("(length+ '(1 2 . 3))", "2"),
+            (
+                "(let ((l (list 1 2 3))) (set-cdr! (cddr l) l) (length+ l))",
+                "#f",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn circular_lists_work_where_another_list_is_finite() {
+        check(&[
+            ("(circular-list? (circular-list 1 2))", "#t"),
+            ("(any odd? (circular-list 1 2))", "#t"),
+            ("(every odd? (circular-list 1 2))", "#f"),
+            ("(list-index even? (circular-list 1 2))", "1"),
+            ("(fold + 0 (circular-list 1 2) '(1 2 3))", "10"),
+            ("(zip '(a b c) (circular-list 1 2))", "((a 1) (b 2) (c 1))"),
+            (
+                "(pair-fold (lambda (p q acc) (+ (car p) (car q) acc)) 0 '(1 2 3) (circular-list 1 2))",
+                "10",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn zip_and_unzip_are_inverses() {
+        check(&[
+            ("(zip '(1 2 3) '(a b c))", "((1 a) (2 b) (3 c))"),
+            ("(unzip1 '((1) (2)))", "(1 2)"),
+            (
+                "(call-with-values (lambda () (unzip2 '((1 a) (2 b)))) list)",
+                "((1 2) (a b))",
+            ),
+            (
+                "(call-with-values (lambda () (unzip3 '((1 a x) (2 b y)))) list)",
+                "((1 2) (a b) (x y))",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn pair_procedures_see_the_tails() {
+        check(&[
+            ("(pair-fold cons '() '(a b c))", "((c) (b c) (a b c))"),
+            ("(pair-fold-right cons '() '(a b c))", "((a b c) (b c) (c))"),
+            (
+                "(let ((tails '())) (pair-for-each (lambda (p) (set! tails (cons p tails))) '(1 2)) tails)",
+                "((2) (1 2))",
+            ),
+            // The next pair is found before the procedure changes this one.
+            (
+                "(let ((l (list 1 2 3))) (pair-for-each (lambda (p) (set-cdr! p '())) l) l)",
+                "(1)",
+            ),
+            ("(map-in-order + '(1 2) '(10 20))", "(11 22)"),
+        ]);
+    }
+
+    #[test]
+    fn list_eq_compares_lengths_and_elements() {
+        check(&[
+            ("(list= eq?)", "#t"),
+            ("(list= = '(1 2) '(1 2) '(1 2))", "#t"),
+            ("(list= = '(1 2) '(1 2 3))", "#f"),
+            ("(list= = '(1 2) '(1 3))", "#f"),
+        ]);
+    }
+
+    #[test]
+    fn lset_comparisons_and_xor() {
+        check(&[
+            ("(lset<= eq? '(a) '(a b a) '(a b c c))", "#t"),
+            ("(lset<= eq? '(a d) '(a b))", "#f"),
+            ("(lset= eq? '(b e a) '(a e b) '(e e b a))", "#t"),
+            ("(lset= eq? '(a) '(a b))", "#f"),
+            ("(lset-xor eq? '(a b c d e) '(a e i o u))", "(b c d i o u)"),
+            ("(lset-xor eq?)", "()"),
+            (
+                "(call-with-values (lambda () (lset-diff+intersection eq? '(a b c d) '(c e) '(a))) list)",
+                "((b d) (a c))",
+            ),
+            ("(concatenate! (list (list 1) (list 2 3)))", "(1 2 3)"),
+        ]);
+    }
+}
@@ -0,0 +1,271 @@
+//! Strings and symbols.
+
+use super::{character, index, list, procedure, range, reserve, string, symbol, vector};
+use crate::eval::{Error, Interpreter};
+use crate::proc::Arity;
+use crate::symbol::Symbol;
+use crate::value::{SchemeString, Value};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+/// Defines a variadic comparison over strings, using `$cmp` to order two
+/// strings.
+macro_rules! string_comparisons {
+    ($($func:ident, $name:literal, $cmp:expr, $($ordering:ident)|+;)*) => {
+        $(
+            fn $func(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+                let strings = args
+                    .iter()
+                    .map(|arg| string($name, arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let cmp: fn(&SchemeString, &SchemeString) -> Ordering = $cmp;
+                Ok(Value::Boolean(strings.windows(2).all(|w| {
+                    matches!(cmp(&w[0].borrow(), &w[1].borrow()), $(Ordering::$ordering)|+)
+                })))
+            }
+        )*
+
+        fn install_comparisons(interp: &mut Interpreter) {
+            $(interp.define_primitive($name, Arity::at_least(1), $func);)*
+        }
+    };
+}
+
+string_comparisons! {
+    string_eq, "string=?", SchemeString::cmp, Equal;
+    string_lt, "string<?", SchemeString::cmp, Less;
+    string_gt, "string>?", SchemeString::cmp, Greater;
+    string_le, "string<=?", SchemeString::cmp, Less | Equal;
+    string_ge, "string>=?", SchemeString::cmp, Greater | Equal;
+    string_ci_eq, "string-ci=?", SchemeString::cmp_ci, Equal;
+    string_ci_lt, "string-ci<?", SchemeString::cmp_ci, Less;
+    string_ci_gt, "string-ci>?", SchemeString::cmp_ci, Greater;
+    string_ci_le, "string-ci<=?", SchemeString::cmp_ci, Less | Equal;
+    string_ci_ge, "string-ci>=?", SchemeString::cmp_ci, Greater | Equal;
+}
+
+pub(super) fn install(interp: &mut Interpreter) {
+    install_comparisons(interp);
+    interp.define_primitive("string?", Arity::exactly(1), is_string);
+    interp.define_primitive("make-string", Arity::between(1, 2), make_string);
+    interp.define_primitive("string", Arity::at_least(0), string_);
+    interp.define_primitive("string-length", Arity::exactly(1), string_length);
+    interp.define_primitive("string-ref", Arity::exactly(2), string_ref);
+    interp.define_primitive("string-set!", Arity::exactly(3), string_set);
+    interp.define_primitive("substring", Arity::exactly(3), substring);
+    interp.define_primitive("string-append", Arity::at_least(0), string_append);
+    interp.define_primitive("string-copy", Arity::between(1, 3), string_copy);
+    interp.define_primitive("string-copy!", Arity::between(3, 5), string_copy_to);
+    interp.define_primitive("string-fill!", Arity::between(2, 4), string_fill);
+    interp.define_primitive("string->list", Arity::between(1, 3), string_to_list);
+    interp.define_primitive("list->string", Arity::exactly(1), list_to_string);
+    interp.define_primitive("string->vector", Arity::between(1, 3), string_to_vector);
+    interp.define_primitive("vector->string", Arity::between(1, 3), vector_to_string);
+    interp.define_primitive("string-upcase", Arity::exactly(1), string_upcase);
+    interp.define_primitive("string-downcase", Arity::exactly(1), string_downcase);
+    interp.define_primitive("string-foldcase", Arity::exactly(1), string_foldcase);
+    interp.define_primitive("string-map", Arity::at_least(2), string_map);
+    interp.define_primitive("string-for-each", Arity::at_least(2), string_for_each);
+    interp.define_primitive("symbol?", Arity::exactly(1), is_symbol);
+    interp.define_primitive("symbol=?", Arity::at_least(1), symbol_eq);
+    interp.define_primitive("symbol->string", Arity::exactly(1), symbol_to_string);
+    interp.define_primitive("string->symbol", Arity::exactly(1), string_to_symbol);
+}
+
+fn new_string(s: SchemeString) -> Value {
+    Value::String(Rc::new(RefCell::new(s)))
+}
+
+fn is_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::String(_))))
+}
+
+fn make_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let len = index("make-string", &args[0])?;
+    let fill = match args.get(1) {
+        Some(fill) => character("make-string", fill)?,
+        None => ' ',
+    };
+    let mut chars = reserve("make-string", len)?;
+    chars.resize(len, fill);
+    Ok(new_string(SchemeString::from(chars)))
+}
+
+fn string_(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let chars = args
+        .iter()
+        .map(|arg| character("string", arg))
+        .collect::<Result<SchemeString, _>>()?;
+    Ok(new_string(chars))
+}
+
+fn string_length(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::from(
+        string("string-length", &args[0])?.borrow().len() as i64,
+    ))
+}
+
+fn string_ref(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let s = string("string-ref", &args[0])?.borrow();
+    Ok(Value::Character(s.get(index("string-ref", &args[1])?)?))
+}
+
+fn string_set(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let k = index("string-set!", &args[1])?;
+    let c = character("string-set!", &args[2])?;
+    string("string-set!", &args[0])?.borrow_mut().set(k, c)?;
+    Ok(Value::Unspecified)
+}
+
+fn substring(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let s = string("substring", &args[0])?.borrow();
+    let range = range("substring", args, 1, s.len())?;
+    Ok(new_string(s.substring(range)?))
+}
+
+fn string_append(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut result = SchemeString::default();
+    for arg in args {
+        result = result.append(&string("string-append", arg)?.borrow());
+    }
+    Ok(new_string(result))
+}
+
+fn string_copy(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let s = string("string-copy", &args[0])?.borrow();
+    let range = range("string-copy", args, 1, s.len())?;
+    Ok(new_string(s.substring(range)?))
+}
+
+fn string_copy_to(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let to = string("string-copy!", &args[0])?;
+    let at = index("string-copy!", &args[1])?;
+    let from = string("string-copy!", &args[2])?;
+    if Rc::ptr_eq(to, from) {
+        let mut s = to.borrow_mut();
+        let range = range("string-copy!", args, 3, s.len())?;
+        s.copy_within(at, range)?;
+    } else {
+        let from = from.borrow();
+        let range = range("string-copy!", args, 3, from.len())?;
+        to.borrow_mut().copy_from(at, &from, range)?;
+    }
+    Ok(Value::Unspecified)
+}
+
+fn string_fill(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let c = character("string-fill!", &args[1])?;
+    let mut s = string("string-fill!", &args[0])?.borrow_mut();
+    let range = range("string-fill!", args, 2, s.len())?;
+    s.fill(c, range)?;
+    Ok(Value::Unspecified)
+}
+
+/// The characters of a string argument between optional `start` and `end`
+/// arguments.
+fn chars_in_range(name: &str, args: &[Value]) -> Result<Vec<char>, Error> {
+    let s = string(name, &args[0])?.borrow();
+    let range = range(name, args, 1, s.len())?;
+    Ok(s.chars()[range].to_vec())
+}
+
+fn string_to_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let chars = chars_in_range("string->list", args)?;
+    Ok(Value::list(chars.into_iter().map(Value::Character)))
+}
+
+fn list_to_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let chars = list("list->string", &args[0])?
+        .iter()
+        .map(|item| character("list->string", item))
+        .collect::<Result<SchemeString, _>>()?;
+    Ok(new_string(chars))
+}
+
+fn string_to_vector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let chars = chars_in_range("string->vector", args)?;
+    Ok(Value::vector(
+        chars.into_iter().map(Value::Character).collect(),
+    ))
+}
+
+fn vector_to_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let items = vector("vector->string", &args[0])?.borrow();
+    let range = range("vector->string", args, 1, items.len())?;
+    let chars = items[range]
+        .iter()
+        .map(|item| character("vector->string", item))
+        .collect::<Result<SchemeString, _>>()?;
+    Ok(new_string(chars))
+}
+
+fn string_upcase(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(new_string(
+        string("string-upcase", &args[0])?.borrow().upcase(),
+    ))
+}
+
+fn string_downcase(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(new_string(
+        string("string-downcase", &args[0])?.borrow().downcase(),
+    ))
+}
+
+fn string_foldcase(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(new_string(
+        string("string-foldcase", &args[0])?.borrow().foldcase(),
+    ))
+}
+
+/// The characters of the string arguments `args[1..]`, column by column up
+/// to the length of the shortest.
+fn columns(name: &str, args: &[Value]) -> Result<Vec<Vec<Value>>, Error> {
+    let strings = args[1..]
+        .iter()
+        .map(|arg| Ok(string(name, arg)?.borrow().chars().to_vec()))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let len = strings.iter().map(Vec::len).min().unwrap_or(0);
+    Ok((0..len)
+        .map(|i| strings.iter().map(|s| Value::Character(s[i])).collect())
+        .collect())
+}
+
+fn string_map(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("string-map", &args[0])?;
+    let mut chars = Vec::new();
+    for column in columns("string-map", args)? {
+        let c = interp.apply(&args[0], &column)?;
+        chars.push(character("string-map", &c)?);
+    }
+    Ok(new_string(chars.into_iter().collect()))
+}
+
+fn string_for_each(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("string-for-each", &args[0])?;
+    for column in columns("string-for-each", args)? {
+        interp.apply(&args[0], &column)?;
+    }
+    Ok(Value::Unspecified)
+}
+
+fn is_symbol(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Symbol(_))))
+}
+
+fn symbol_eq(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let symbols = args
+        .iter()
+        .map(|arg| symbol("symbol=?", arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::Boolean(symbols.windows(2).all(|w| w[0] == w[1])))
+}
+
+fn symbol_to_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::string(symbol("symbol->string", &args[0])?.name()))
+}
+
+fn string_to_symbol(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let name = string("string->symbol", &args[0])?.borrow().to_string();
+    Ok(Value::Symbol(Symbol::intern(&name)))
+}
@@ -0,0 +1,37 @@
+//! The clock, as in R7RS `(scheme time)`.
+
+use crate::eval::{Error, Interpreter};
+use crate::num::Number;
+use crate::proc::Arity;
+use crate::value::Value;
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Jiffies are microseconds.
+const JIFFIES_PER_SECOND: i64 = 1_000_000;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("current-second", Arity::exactly(0), current_second);
+    interp.define_primitive("current-time", Arity::exactly(0), current_second);
+    interp.define_primitive("current-jiffy", Arity::exactly(0), current_jiffy);
+    interp.define_primitive("jiffies-per-second", Arity::exactly(0), jiffies_per_second);
+}
+
+/// Seconds since the Unix epoch, as an inexact number.
+fn current_second(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(Value::Number(Number::Real(since_epoch.as_secs_f64())))
+}
+
+/// Jiffies since the first call, which R7RS allows as the epoch.
+fn current_jiffy(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    static START: OnceLock<Instant> = OnceLock::new();
+    let elapsed = START.get_or_init(Instant::now).elapsed();
+    Ok(Value::from(elapsed.as_micros() as i64))
+}
+
+fn jiffies_per_second(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::from(JIFFIES_PER_SECOND))
+}
@@ -0,0 +1,524 @@
+//! Vectors and bytevectors.
+
+use super::{bytevector, index, integer, list, procedure, range, reserve, string, vector};
+use crate::convert::IntoValue;
+use crate::eval::{Error, Interpreter};
+use crate::num::Number;
+use crate::proc::Arity;
+use crate::value::{Bytevector, Endianness, Value};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub(super) fn install(interp: &mut Interpreter) {
+    interp.define_primitive("vector?", Arity::exactly(1), is_vector);
+    interp.define_primitive("make-vector", Arity::between(1, 2), make_vector);
+    interp.define_primitive("vector", Arity::at_least(0), vector_);
+    interp.define_primitive("vector-length", Arity::exactly(1), vector_length);
+    interp.define_primitive("vector-ref", Arity::exactly(2), vector_ref);
+    interp.define_primitive("vector-set!", Arity::exactly(3), vector_set);
+    interp.define_primitive("vector->list", Arity::between(1, 3), vector_to_list);
+    interp.define_primitive("list->vector", Arity::exactly(1), list_to_vector);
+    interp.define_primitive("vector-fill!", Arity::between(2, 4), vector_fill);
+    interp.define_primitive("vector-copy", Arity::between(1, 3), vector_copy);
+    interp.define_primitive("vector-copy!", Arity::between(3, 5), vector_copy_to);
+    interp.define_primitive("vector-append", Arity::at_least(0), vector_append);
+    interp.define_primitive("vector-map", Arity::at_least(2), vector_map);
+    interp.define_primitive("vector-for-each", Arity::at_least(2), vector_for_each);
+    interp.define_primitive("vector-fold", Arity::at_least(3), vector_fold);
+    interp.define_primitive(
+        "vector-binary-search",
+        Arity::between(3, 5),
+        vector_binary_search,
+    );
+    interp.define_primitive("vector-sort!", Arity::between(2, 4), vector_sort);
+    interp.define_primitive("bytevector?", Arity::exactly(1), is_bytevector);
+    interp.define_primitive("make-bytevector", Arity::between(1, 2), make_bytevector);
+    interp.define_primitive("bytevector", Arity::at_least(0), bytevector_);
+    interp.define_primitive("bytevector-length", Arity::exactly(1), bytevector_length);
+    interp.define_primitive("bytevector-u8-ref", Arity::exactly(2), bytevector_u8_ref);
+    interp.define_primitive("bytevector-u8-set!", Arity::exactly(3), bytevector_u8_set);
+    interp.define_primitive("bytevector-s8-ref", Arity::exactly(2), bytevector_s8_ref);
+    interp.define_primitive("bytevector-s8-set!", Arity::exactly(3), bytevector_s8_set);
+    interp.define_primitive("native-endianness", Arity::exactly(0), native_endianness);
+    for &(name, arity, f) in ENDIAN_ACCESSORS {
+        interp.define_primitive(name, Arity::exactly(arity), f);
+    }
+    interp.define_primitive("bytevector-copy", Arity::between(1, 3), bytevector_copy);
+    interp.define_primitive("bytevector-copy!", Arity::between(3, 5), bytevector_copy_to);
+    interp.define_primitive("bytevector-append", Arity::at_least(0), bytevector_append);
+    interp.define_primitive("utf8->string", Arity::between(1, 3), utf8_to_string);
+    interp.define_primitive("string->utf8", Arity::between(1, 3), string_to_utf8);
+}
+
+pub(super) fn byte(name: &str, value: &Value) -> Result<u8, Error> {
+    match value {
+        Value::Number(n) => n
+            .to_i64()
+            .and_then(|i| u8::try_from(i).ok())
+            .ok_or_else(|| Error::wrong_type(name, "a byte", value)),
+        _ => Err(Error::wrong_type(name, "a byte", value)),
+    }
+}
+
+/// An `(endianness big)` or `(endianness little)` argument.
+fn endianness(name: &str, value: &Value) -> Result<Endianness, Error> {
+    match value {
+        Value::Symbol(sym) if sym.name() == "big" => Ok(Endianness::Big),
+        Value::Symbol(sym) if sym.name() == "little" => Ok(Endianness::Little),
+        _ => Err(Error::wrong_type(name, "an endianness", value)),
+    }
+}
+
+/// An exact integer that fits in the field being stored.
+fn field<T: TryFrom<i128>>(name: &str, expected: &str, value: &Value) -> Result<T, Error> {
+    let wide = match value {
+        Value::Number(Number::Fixnum(i)) => Some(*i as i128),
+        Value::Number(Number::Bignum(b)) => b.to_i128(),
+        _ => None,
+    };
+    wide.and_then(|i| T::try_from(i).ok())
+        .ok_or_else(|| Error::wrong_type(name, expected, value))
+}
+
+fn new_bytevector(bytes: Bytevector) -> Value {
+    Value::Bytevector(Rc::new(RefCell::new(bytes)))
+}
+
+fn is_vector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Vector(_))))
+}
+
+fn make_vector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let len = index("make-vector", &args[0])?;
+    let fill = args.get(1).cloned().unwrap_or(Value::Unspecified);
+    let mut items = reserve("make-vector", len)?;
+    items.resize(len, fill);
+    Ok(Value::vector(items))
+}
+
+fn vector_(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::vector(args.to_vec()))
+}
+
+fn vector_length(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::from(
+        vector("vector-length", &args[0])?.borrow().len() as i64,
+    ))
+}
+
+fn out_of_range(k: usize, len: usize) -> Error {
+    Error::from(crate::value::IndexError { index: k, len })
+}
+
+fn vector_ref(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let items = vector("vector-ref", &args[0])?.borrow();
+    let k = index("vector-ref", &args[1])?;
+    items
+        .get(k)
+        .cloned()
+        .ok_or_else(|| out_of_range(k, items.len()))
+}
+
+fn vector_set(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut items = vector("vector-set!", &args[0])?.borrow_mut();
+    let k = index("vector-set!", &args[1])?;
+    let len = items.len();
+    let slot = items.get_mut(k).ok_or_else(|| out_of_range(k, len))?;
+    *slot = args[2].clone();
+    Ok(Value::Unspecified)
+}
+
+fn vector_to_list(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let items = vector("vector->list", &args[0])?.borrow();
+    let range = range("vector->list", args, 1, items.len())?;
+    Ok(Value::list(items[range].iter().cloned()))
+}
+
+pub(crate) fn list_to_vector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::vector(list("list->vector", &args[0])?))
+}
+
+fn vector_fill(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut items = vector("vector-fill!", &args[0])?.borrow_mut();
+    let range = range("vector-fill!", args, 2, items.len())?;
+    items[range].fill(args[1].clone());
+    Ok(Value::Unspecified)
+}
+
+fn vector_copy(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let items = vector("vector-copy", &args[0])?.borrow();
+    let range = range("vector-copy", args, 1, items.len())?;
+    Ok(Value::vector(items[range].to_vec()))
+}
+
+fn vector_append(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut result = Vec::new();
+    for arg in args {
+        result.extend(vector("vector-append", arg)?.borrow().iter().cloned());
+    }
+    Ok(Value::vector(result))
+}
+
+fn vector_copy_to(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let to = vector("vector-copy!", &args[0])?;
+    let at = index("vector-copy!", &args[1])?;
+    let from = vector("vector-copy!", &args[2])?;
+    let items = from.borrow().clone();
+    let range = range("vector-copy!", args, 3, items.len())?;
+    let mut to = to.borrow_mut();
+    let len = to.len();
+    let end = at + range.len();
+    if end > len {
+        return Err(out_of_range(end, len));
+    }
+    to[at..end].clone_from_slice(&items[range]);
+    Ok(Value::Unspecified)
+}
+
+/// The elements of `vectors`, column by column up to the length of the
+/// shortest. They are copied, so procedures called on them may change the
+/// vectors.
+fn columns(name: &str, vectors: &[Value]) -> Result<Vec<Vec<Value>>, Error> {
+    let vectors = vectors
+        .iter()
+        .map(|arg| Ok(vector(name, arg)?.borrow().to_vec()))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let len = vectors.iter().map(Vec::len).min().unwrap_or(0);
+    Ok((0..len)
+        .map(|i| vectors.iter().map(|items| items[i].clone()).collect())
+        .collect())
+}
+
+fn vector_map(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("vector-map", &args[0])?;
+    let results = columns("vector-map", &args[1..])?
+        .into_iter()
+        .map(|column| interp.apply(&args[0], &column))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::vector(results))
+}
+
+fn vector_for_each(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("vector-for-each", &args[0])?;
+    for column in columns("vector-for-each", &args[1..])? {
+        interp.apply(&args[0], &column)?;
+    }
+    Ok(Value::Unspecified)
+}
+
+/// `(vector-fold kons state vector ...)`, calling `kons` with the state and
+/// then the elements, as in SRFI 133.
+fn vector_fold(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("vector-fold", &args[0])?;
+    let mut state = args[1].clone();
+    for column in columns("vector-fold", &args[2..])? {
+        let mut kons_args = vec![state];
+        kons_args.extend(column);
+        state = interp.apply(&args[0], &kons_args)?;
+    }
+    Ok(state)
+}
+
+/// `(vector-binary-search vector value cmp [start end])`, where
+/// `(cmp element value)` is negative, zero or positive as the element is
+/// less than, equal to or greater than `value`. Returns the index of an
+/// equal element, or #f.
+fn vector_binary_search(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    procedure("vector-binary-search", &args[2])?;
+    let items = vector("vector-binary-search", &args[0])?.borrow().clone();
+    let mut range = range("vector-binary-search", args, 3, items.len())?;
+    while !range.is_empty() {
+        let mid = range.start + range.len() / 2;
+        let order = interp.apply(&args[2], &[items[mid].clone(), args[1].clone()])?;
+        let order = integer("vector-binary-search", &order)?;
+        if order.is_zero() {
+            return Ok(Value::from(mid as i64));
+        } else if order.is_negative() {
+            range.start = mid + 1;
+        } else {
+            range.end = mid;
+        }
+    }
+    Ok(Value::Boolean(false))
+}
+
+/// `(vector-sort! vector < [start end])`, a stable merge sort with the
+/// order given by `<`, as in SRFI 132.
+fn vector_sort(interp: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let target = vector("vector-sort!", &args[0])?;
+    procedure("vector-sort!", &args[1])?;
+    let items = target.borrow().clone();
+    let range = range("vector-sort!", args, 2, items.len())?;
+    let mut sorted = items[range.clone()].to_vec();
+    merge_sort(interp, &args[1], &mut sorted)?;
+    let mut items = target.borrow_mut();
+    if items.len() < range.end {
+        return Err(out_of_range(range.end, items.len()));
+    }
+    items[range].clone_from_slice(&sorted);
+    Ok(Value::Unspecified)
+}
+
+/// Sorts `items` by calling `less`, which need not be a consistent order:
+/// the result is then some permutation of `items`.
+fn merge_sort(interp: &mut Interpreter, less: &Value, items: &mut [Value]) -> Result<(), Error> {
+    if items.len() < 2 {
+        return Ok(());
+    }
+    let mid = items.len() / 2;
+    merge_sort(interp, less, &mut items[..mid])?;
+    merge_sort(interp, less, &mut items[mid..])?;
+    let (left, right) = items.split_at(mid);
+    let (mut i, mut j) = (0, 0);
+    let mut merged = Vec::with_capacity(items.len());
+    while i < left.len() && j < right.len() {
+        if interp
+            .apply(less, &[right[j].clone(), left[i].clone()])?
+            .is_true()
+        {
+            merged.push(right[j].clone());
+            j += 1;
+        } else {
+            merged.push(left[i].clone());
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&left[i..]);
+    merged.extend_from_slice(&right[j..]);
+    items.clone_from_slice(&merged);
+    Ok(())
+}
+
+fn is_bytevector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::Boolean(matches!(args[0], Value::Bytevector(_))))
+}
+
+fn make_bytevector(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let len = index("make-bytevector", &args[0])?;
+    let fill = match args.get(1) {
+        Some(fill) => byte("make-bytevector", fill)?,
+        None => 0,
+    };
+    let mut bytes = reserve("make-bytevector", len)?;
+    bytes.resize(len, fill);
+    Ok(new_bytevector(Bytevector::from(bytes)))
+}
+
+fn bytevector_(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let bytes = args
+        .iter()
+        .map(|arg| byte("bytevector", arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::bytevector(bytes))
+}
+
+fn bytevector_length(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let bytes = bytevector("bytevector-length", &args[0])?.borrow();
+    Ok(Value::from(bytes.len() as i64))
+}
+
+fn bytevector_u8_ref(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let bytes = bytevector("bytevector-u8-ref", &args[0])?.borrow();
+    let k = index("bytevector-u8-ref", &args[1])?;
+    Ok(Value::from(bytes.u8_ref(k)? as i64))
+}
+
+fn bytevector_u8_set(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let k = index("bytevector-u8-set!", &args[1])?;
+    let b = byte("bytevector-u8-set!", &args[2])?;
+    bytevector("bytevector-u8-set!", &args[0])?
+        .borrow_mut()
+        .u8_set(k, b)?;
+    Ok(Value::Unspecified)
+}
+
+fn bytevector_s8_ref(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let bytes = bytevector("bytevector-s8-ref", &args[0])?.borrow();
+    let k = index("bytevector-s8-ref", &args[1])?;
+    Ok(Value::from(bytes.s8_ref(k)? as i64))
+}
+
+fn bytevector_s8_set(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let k = index("bytevector-s8-set!", &args[1])?;
+    let b = field(
+        "bytevector-s8-set!",
+        "an exact integer in the range of s8",
+        &args[2],
+    )?;
+    bytevector("bytevector-s8-set!", &args[0])?
+        .borrow_mut()
+        .s8_set(k, b)?;
+    Ok(Value::Unspecified)
+}
+
+fn native_endianness(_: &mut Interpreter, _: &[Value]) -> Result<Value, Error> {
+    Ok(Value::symbol(if cfg!(target_endian = "big") {
+        "big"
+    } else {
+        "little"
+    }))
+}
+
+/// Defines the `bytevector-<type>-ref` and `bytevector-<type>-set!`
+/// primitives for the multi-byte types, which take an endianness last.
+macro_rules! endian_accessors {
+    ($($ty:literal: $get:ident, $set:ident, $store:expr;)*) => {
+        const ENDIAN_ACCESSORS: &[(&str, usize, crate::proc::PrimitiveFn)] = &[
+            $(
+                (concat!("bytevector-", $ty, "-ref"), 3, |_, args| {
+                    const NAME: &str = concat!("bytevector-", $ty, "-ref");
+                    let bytes = bytevector(NAME, &args[0])?.borrow();
+                    let k = index(NAME, &args[1])?;
+                    let endianness = endianness(NAME, &args[2])?;
+                    Ok(bytes.$get(k, endianness)?.into_value())
+                }),
+                (concat!("bytevector-", $ty, "-set!"), 4, |_, args| {
+                    const NAME: &str = concat!("bytevector-", $ty, "-set!");
+                    let k = index(NAME, &args[1])?;
+                    let store: fn(&str, &Value) -> Result<_, Error> = $store;
+                    let value = store(NAME, &args[2])?;
+                    let endianness = endianness(NAME, &args[3])?;
+                    bytevector(NAME, &args[0])?
+                        .borrow_mut()
+                        .$set(k, value, endianness)?;
+                    Ok(Value::Unspecified)
+                }),
+            )*
+        ];
+    };
+}
+
+endian_accessors! {
+    "u16": u16_ref, u16_set, |name, v| field(name, "an exact integer in the range of u16", v);
+    "s16": s16_ref, s16_set, |name, v| field(name, "an exact integer in the range of s16", v);
+    "u32": u32_ref, u32_set, |name, v| field(name, "an exact integer in the range of u32", v);
+    "s32": s32_ref, s32_set, |name, v| field(name, "an exact integer in the range of s32", v);
+    "u64": u64_ref, u64_set, |name, v| field(name, "an exact integer in the range of u64", v);
+    "s64": s64_ref, s64_set, |name, v| field(name, "an exact integer in the range of s64", v);
+    "ieee-single": ieee_single_ref, ieee_single_set, |name, v| real(name, v).map(|r| r as f32);
+    "ieee-double": ieee_double_ref, ieee_double_set, real;
+}
+
+fn real(name: &str, value: &Value) -> Result<f64, Error> {
+    match value {
+        Value::Number(n) if n.is_real() => Ok(n.to_f64()),
+        _ => Err(Error::wrong_type(name, "a real number", value)),
+    }
+}
+
+fn bytevector_copy(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let bytes = bytevector("bytevector-copy", &args[0])?.borrow();
+    let range = range("bytevector-copy", args, 1, bytes.len())?;
+    Ok(new_bytevector(bytes.copy(range)?))
+}
+
+fn bytevector_copy_to(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let to = bytevector("bytevector-copy!", &args[0])?;
+    let at = index("bytevector-copy!", &args[1])?;
+    let from = bytevector("bytevector-copy!", &args[2])?;
+    if Rc::ptr_eq(to, from) {
+        let mut bytes = to.borrow_mut();
+        let range = range("bytevector-copy!", args, 3, bytes.len())?;
+        bytes.copy_within(at, range)?;
+    } else {
+        let from = from.borrow();
+        let range = range("bytevector-copy!", args, 3, from.len())?;
+        to.borrow_mut().copy_from(at, &from, range)?;
+    }
+    Ok(Value::Unspecified)
+}
+
+fn bytevector_append(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let mut result = Bytevector::default();
+    for arg in args {
+        result = result.append(&bytevector("bytevector-append", arg)?.borrow());
+    }
+    Ok(new_bytevector(result))
+}
+
+fn utf8_to_string(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let bytes = bytevector("utf8->string", &args[0])?.borrow();
+    let range = range("utf8->string", args, 1, bytes.len())?;
+    let s = bytes.utf8_to_string(range)?;
+    Ok(Value::String(Rc::new(RefCell::new(s))))
+}
+
+fn string_to_utf8(_: &mut Interpreter, args: &[Value]) -> Result<Value, Error> {
+    let s = string("string->utf8", &args[0])?.borrow();
+    let range = range("string->utf8", args, 1, s.len())?;
+    Ok(new_bytevector(Bytevector::from_string(&s, range)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    fn eval(source: &str) -> String {
+        Scheme::new().eval_str(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn vector_sort_takes_the_vector_first() {
+        assert_eq!(
+            eval("(let ((v (vector 3 1 2))) (vector-sort! v <) v)"),
+            "#(1 2 3)"
+        );
+        assert_eq!(
+            eval("(let ((v (vector 5 4 3 2 1))) (vector-sort! v < 1 4) v)"),
+            "#(5 2 3 4 1)"
+        );
+        assert!(Scheme::new()
+            .eval_str("(vector-sort! < (vector 3 1 2))")
+            .is_err());
+    }
+
+    #[test]
+    fn vector_sort_is_stable() {
+        assert_eq!(
+            eval(
+                "(let ((v (vector '(2 . a) '(1 . b) '(2 . c) '(1 . d))))
+                   (vector-sort! v (lambda (x y) (< (car x) (car y))))
+                   v)"
+            ),
+            "#((1 . b) (1 . d) (2 . a) (2 . c))"
+        );
+    }
+
+    #[test]
+    fn bytevector_accessors_take_an_endianness() {
+        let mut scheme = Scheme::new();
+        scheme.eval_str("(define b (make-bytevector 8 0))").unwrap();
+        for (text, expected) in [
+            ("(bytevector-u16-set! b 0 #xff01 (endianness big)) (bytevector-u16-ref b 0 (endianness little))", "511"),
+            ("(bytevector-u64-set! b 0 18446744073709551615 (endianness big)) (bytevector-u64-ref b 0 (endianness big))", "18446744073709551615"),
+            ("(bytevector-s64-ref b 0 (endianness little))", "-1"),
+            ("(bytevector-s8-set! b 0 -5) (bytevector-u8-ref b 0)", "251"),
+            ("(bytevector-ieee-double-set! b 0 1.5 (endianness little)) (bytevector-ieee-double-ref b 0 (endianness little))", "1.5"),
+            ("(bytevector-ieee-single-set! b 4 -2.25 (native-endianness)) (bytevector-ieee-single-ref b 4 (native-endianness))", "-2.25"),
+        ] {
+            assert_eq!(scheme.eval_str(text).unwrap().to_string(), expected, "{}", text);
+        }
+        for (text, kind) in [
+            (
+                "(bytevector-u32-ref b 6 (endianness big))",
+                ConditionKind::Range,
+            ),
+            ("(bytevector-s8-ref b 8)", ConditionKind::Range),
+            (
+                "(bytevector-u16-set! b 0 70000 (endianness big))",
+                ConditionKind::WrongType,
+            ),
+            ("(bytevector-u16-ref b 0 'middle)", ConditionKind::WrongType),
+        ] {
+            let err = scheme.eval_str(text).unwrap_err();
+            assert_eq!(
+                err.condition().map(|condition| condition.kind),
+                Some(kind),
+                "{}",
+                text
+            );
+        }
+        assert!(scheme.eval_str("(endianness middle)").is_err());
+    }
+}
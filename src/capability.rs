@@ -0,0 +1,118 @@
+//! Capabilities that gate access to the host system.
+//!
+//! Builtins that reach outside the interpreter are only defined when the
+//! interpreter is created with their capability, so untrusted code run
+//! with [`Capabilities::none`] can only compute.
+
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Capability {
+    /// Reading and writing files and directories.
+    Filesystem,
+    /// Opening sockets.
+    Network,
+    /// Running subprocesses, reading the environment and exiting.
+    Process,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 3] = [Self::Filesystem, Self::Network, Self::Process];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Filesystem => "filesystem",
+            Self::Network => "network",
+            Self::Process => "process",
+        })
+    }
+}
+
+/// A set of capabilities.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    /// Only the pure builtins.
+    pub fn none() -> Self {
+        Self(0)
+    }
+
+    pub fn all() -> Self {
+        Capability::ALL.into_iter().collect()
+    }
+
+    pub fn with(self, capability: Capability) -> Self {
+        Self(self.0 | capability.bit())
+    }
+
+    pub fn without(self, capability: Capability) -> Self {
+        Self(self.0 & !capability.bit())
+    }
+
+    pub fn contains(self, capability: Capability) -> bool {
+        self.0 & capability.bit() != 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Capability> {
+        Capability::ALL
+            .into_iter()
+            .filter(move |&capability| self.contains(capability))
+    }
+}
+
+impl FromIterator<Capability> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::none(), Self::with)
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    #[test]
+    fn sets_add_and_remove_capabilities() {
+        let set = Capabilities::none().with(Capability::Network);
+        assert!(set.contains(Capability::Network));
+        assert!(!set.contains(Capability::Filesystem));
+        assert_eq!(set.without(Capability::Network), Capabilities::none());
+        assert_eq!(
+            Capabilities::all().iter().collect::<Vec<_>>(),
+            Capability::ALL
+        );
+        assert_eq!(format!("{:?}", set), "{Network}");
+    }
+
+    #[test]
+    fn sandboxes_only_define_allowed_builtins() {
+        let mut scheme = Scheme::with_capabilities(Capabilities::none());
+        assert_eq!(scheme.eval_str("(+ 1 2)").unwrap().to_string(), "3");
+        for name in ["open-input-file", "tcp-connect", "spawn-process", "exit"] {
+            let err = scheme.eval_str(name).unwrap_err();
+            assert_eq!(
+                err.condition().map(|condition| condition.kind),
+                Some(ConditionKind::UnboundVariable),
+                "{}",
+                name
+            );
+        }
+        let scheme = Scheme::with_capabilities(Capabilities::none().with(Capability::Filesystem));
+        assert!(scheme.lookup("file-exists?").is_some());
+        assert!(scheme.lookup("spawn-process").is_none());
+    }
+}
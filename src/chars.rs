@@ -0,0 +1,111 @@
+//! Character procedures over Unicode scalar values.
+//!
+//! `char-upcase` and friends must return a single character, so case
+//! mappings that expand to several characters leave the character unchanged.
+
+/// As in `char-upcase`.
+pub fn upcase(c: char) -> char {
+    single(c.to_uppercase()).unwrap_or(c)
+}
+
+/// As in `char-downcase`.
+pub fn downcase(c: char) -> char {
+    single(c.to_lowercase()).unwrap_or(c)
+}
+
+/// As in `char-foldcase`.
+pub fn foldcase(c: char) -> char {
+    match c {
+        // Turkish dotted capital I and the final sigma fold like their
+        // ordinary counterparts.
+        '\u{130}' => c,
+        'ς' => 'σ',
+        _ => downcase(upcase(c)),
+    }
+}
+
+/// Appends the full case folding of `c` to `out`, which may be more than one
+/// character (e.g. `ß` folds to `ss`). Used by `string-foldcase` and the
+/// `-ci` comparisons.
+pub fn foldcase_full(c: char, out: &mut Vec<char>) {
+    match c {
+        '\u{130}' => out.push(c),
+        'ς' => out.push('σ'),
+        _ => out.extend(c.to_uppercase().flat_map(char::to_lowercase)),
+    }
+}
+
+/// The zero of every run of ten decimal digits (general category Nd) in
+/// Unicode 15, in order.
+const DIGIT_ZEROS: &[u32] = &[
+    0x30, 0x660, 0x6F0, 0x7C0, 0x966, 0x9E6, 0xA66, 0xAE6, 0xB66, 0xBE6, 0xC66, 0xCE6, 0xD66,
+    0xDE6, 0xE50, 0xED0, 0xF20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80, 0x1A90,
+    0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620, 0xA8D0, 0xA900, 0xA9D0, 0xA9F0, 0xAA50, 0xABF0, 0xFF10,
+    0x104A0, 0x10D30, 0x11066, 0x110F0, 0x11136, 0x111D0, 0x112F0, 0x11450, 0x114D0, 0x11650,
+    0x116C0, 0x11730, 0x118E0, 0x11950, 0x11C50, 0x11D50, 0x11DA0, 0x11F50, 0x16A60, 0x16AC0,
+    0x16B50, 0x1D7CE, 0x1D7D8, 0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140, 0x1E2F0, 0x1E4F0, 0x1E950,
+    0x1FBF0,
+];
+
+/// As in `digit-value`: the value of a decimal digit in any script, e.g. 4
+/// for `#\x0664`, or `None`.
+pub fn digit_value(c: char) -> Option<u32> {
+    let c = c as u32;
+    let zero = match DIGIT_ZEROS.binary_search(&c) {
+        Ok(_) => return Some(0),
+        Err(0) => return None,
+        Err(i) => DIGIT_ZEROS[i - 1],
+    };
+    (c - zero < 10).then_some(c - zero)
+}
+
+/// As in `char-alphabetic?`.
+pub fn is_alphabetic(c: char) -> bool {
+    c.is_alphabetic()
+}
+
+/// As in `char-numeric?`.
+pub fn is_numeric(c: char) -> bool {
+    c.is_numeric()
+}
+
+/// As in `char-whitespace?`.
+pub fn is_whitespace(c: char) -> bool {
+    c.is_whitespace()
+}
+
+/// As in `char-upper-case?`.
+pub fn is_upper_case(c: char) -> bool {
+    c.is_uppercase()
+}
+
+/// As in `char-lower-case?`.
+pub fn is_lower_case(c: char) -> bool {
+    c.is_lowercase()
+}
+
+fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    let first = chars.next()?;
+    chars.next().is_none().then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digit_values_cover_every_script() {
+        assert_eq!(digit_value('7'), Some(7));
+        assert_eq!(digit_value('\u{664}'), Some(4));
+        assert_eq!(digit_value('\u{FF19}'), Some(9));
+        assert_eq!(digit_value('\u{1D7FF}'), Some(9));
+        assert_eq!(digit_value('a'), None);
+        assert_eq!(digit_value('\u{BC}'), None);
+        assert_eq!(digit_value('\u{2160}'), None);
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            if digit_value(c).is_some() {
+                assert!(c.is_numeric(), "{:?}", c);
+            }
+        }
+    }
+}
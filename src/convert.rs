@@ -0,0 +1,263 @@
+//! Conversions between Rust data and Scheme values.
+//!
+//! Sequences become proper lists, 2-tuples become pairs (so a `Vec` of
+//! tuples is an association list) and `None` becomes `#f`.
+
+use crate::num::{BigInt, Number};
+use crate::symbol::Symbol;
+use crate::value::{ConversionError, Value};
+
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self, ConversionError>;
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        Ok(value)
+    }
+}
+
+/// Implements both traits with the `From` and `TryFrom` conversions on
+/// [`Value`].
+macro_rules! impl_via_value {
+    ($($ty:ty),*) => {
+        $(
+            impl IntoValue for $ty {
+                fn into_value(self) -> Value {
+                    Value::from(self)
+                }
+            }
+
+            impl FromValue for $ty {
+                fn from_value(value: Value) -> Result<Self, ConversionError> {
+                    Self::try_from(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_via_value!(bool, i64, f64, char, String, Symbol);
+
+/// An exact integer as an `i128`, if it fits.
+fn exact_integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(Number::Fixnum(i)) => Some(i128::from(*i)),
+        Value::Number(Number::Bignum(b)) => b.to_i128(),
+        _ => None,
+    }
+}
+
+/// Implements both traits for an integer type by way of `i128`, so that
+/// values too large for a fixnum are converted to and from bignums.
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl IntoValue for $ty {
+                fn into_value(self) -> Value {
+                    match i64::try_from(self as i128) {
+                        Ok(i) => Value::from(i),
+                        Err(_) => Value::Number(Number::from(BigInt::from(self as u64))),
+                    }
+                }
+            }
+
+            impl FromValue for $ty {
+                fn from_value(value: Value) -> Result<Self, ConversionError> {
+                    exact_integer(&value)
+                        .and_then(|i| Self::try_from(i).ok())
+                        .ok_or(ConversionError {
+                            expected: concat!("an exact integer in the range of ", stringify!($ty)),
+                            value,
+                        })
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, isize, u8, u16, u32, u64, usize);
+
+impl IntoValue for f32 {
+    fn into_value(self) -> Value {
+        Value::from(self as f64)
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        f64::from_value(value).map(|r| r as f32)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::string(self)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::list(self.into_iter().map(IntoValue::into_value))
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        Vec::<Value>::try_from(value)?
+            .into_iter()
+            .map(T::from_value)
+            .collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        match self {
+            Some(value) => value.into_value(),
+            None => Value::Boolean(false),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Boolean(false) => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<A: IntoValue, B: IntoValue> IntoValue for (A, B) {
+    fn into_value(self) -> Value {
+        Value::cons(self.0.into_value(), self.1.into_value())
+    }
+}
+
+impl<A: FromValue, B: FromValue> FromValue for (A, B) {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value.as_pair() {
+            Some(pair) => Ok((A::from_value(pair.car())?, B::from_value(pair.cdr())?)),
+            None => Err(ConversionError {
+                expected: "a pair",
+                value,
+            }),
+        }
+    }
+}
+
+/// Implements [`IntoValue`] and [`FromValue`] for a struct with named
+/// fields, represented as an association list from field names to values.
+///
+/// Converting back fails if a field is missing or has the wrong type, and
+/// ignores entries for other names.
+#[macro_export]
+macro_rules! impl_alist_conversions {
+    ($ty:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::convert::IntoValue for $ty {
+            fn into_value(self) -> $crate::value::Value {
+                $crate::value::Value::list([$(
+                    $crate::value::Value::cons(
+                        $crate::value::Value::symbol(stringify!($field)),
+                        $crate::convert::IntoValue::into_value(self.$field),
+                    ),
+                )*])
+            }
+        }
+
+        impl $crate::convert::FromValue for $ty {
+            fn from_value(
+                value: $crate::value::Value,
+            ) -> Result<Self, $crate::value::ConversionError> {
+                let entries: Vec<($crate::symbol::Symbol, $crate::value::Value)> =
+                    $crate::convert::FromValue::from_value(value.clone())?;
+                let field = |name: &str| {
+                    entries
+                        .iter()
+                        .find(|(key, _)| key.name() == name)
+                        .map(|(_, value)| value.clone())
+                        .ok_or_else(|| $crate::value::ConversionError {
+                            expected: concat!("an association list with the fields of ", stringify!($ty)),
+                            value: value.clone(),
+                        })
+                };
+                Ok(Self {
+                    $($field: $crate::convert::FromValue::from_value(field(stringify!($field))?)?,)*
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn datum(text: &str) -> Value {
+        parse::parse(text).unwrap().remove(0)
+    }
+
+    #[test]
+    fn integers_convert_through_bignums() {
+        assert_eq!(u64::MAX.into_value().to_string(), "18446744073709551615");
+        assert_eq!(u64::from_value(u64::MAX.into_value()).unwrap(), u64::MAX);
+        assert_eq!(i64::from_value(i64::MIN.into_value()).unwrap(), i64::MIN);
+        assert_eq!(usize::from_value(datum("4096")).unwrap(), 4096);
+        assert_eq!(i8::from_value(datum("-128")).unwrap(), -128);
+        for text in ["18446744073709551616", "-1", "1.0", "\"1\""] {
+            assert!(u64::from_value(datum(text)).is_err(), "{}", text);
+        }
+        assert!(u8::from_value(datum("256")).is_err());
+    }
+
+    #[test]
+    fn containers_convert_to_lists_and_pairs() {
+        let value = vec![(1i64, "a"), (2, "b")].into_value();
+        assert_eq!(value.to_string(), "((1 . a) (2 . b))");
+        let back = Vec::<(i64, String)>::from_value(value).unwrap();
+        assert_eq!(back, [(1, "a".to_string()), (2, "b".to_string())]);
+        assert_eq!(None::<i64>.into_value().to_string(), "#f");
+        assert_eq!(Option::<u32>::from_value(datum("7")).unwrap(), Some(7));
+        assert!(Vec::<i64>::from_value(datum("(1 . 2)")).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: i64,
+        label: String,
+    }
+
+    crate::impl_alist_conversions!(Point { x, label });
+
+    #[test]
+    fn structs_convert_to_association_lists() {
+        let point = Point {
+            x: 3,
+            label: "origin".to_string(),
+        };
+        let value = point.into_value();
+        assert_eq!(value.to_string(), "((x . 3) (label . origin))");
+        let back = Point::from_value(datum("((label . \"p\") (extra . 1) (x . -2))"));
+        assert_eq!(
+            back.unwrap(),
+            Point {
+                x: -2,
+                label: "p".to_string()
+            }
+        );
+        assert!(Point::from_value(datum("((x . 1))")).is_err());
+        assert!(Point::from_value(datum("((x . \"1\") (label . \"p\"))")).is_err());
+    }
+}
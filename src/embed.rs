@@ -0,0 +1,159 @@
+//! A high-level interface for embedding the interpreter.
+
+use crate::builtins;
+use crate::capability::Capabilities;
+use crate::convert::IntoValue;
+use crate::eval::{ConditionKind, Error, ExecutionHandle, Interpreter};
+use crate::parse;
+use crate::ports::InputPort;
+use crate::value::Value;
+use std::path::Path;
+use std::time::Duration;
+
+/// An interpreter with the standard procedures defined, driven by source
+/// text and global names rather than analyzed forms.
+#[derive(Default)]
+pub struct Scheme {
+    interp: Interpreter,
+}
+
+impl Scheme {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an interpreter with only the builtins allowed by
+    /// `capabilities`, e.g. [`Capabilities::none`] for untrusted code.
+    pub fn with_capabilities(capabilities: Capabilities) -> Self {
+        Self {
+            interp: Interpreter::with_capabilities(capabilities),
+        }
+    }
+
+    /// Reads and evaluates every form in `text`, returning the value of the
+    /// last one.
+    pub fn eval_str(&mut self, text: &str) -> Result<Value, Error> {
+        let mut result = Value::Unspecified;
+        for form in parse::parse_syntax(text, None)? {
+            result = self.interp.eval_syntax(&form)?;
+        }
+        Ok(result)
+    }
+
+    /// Reads and evaluates every form in a file, as in `load`. This works
+    /// without the filesystem capability, since the host chose the file.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        builtins::load::load_file(&mut self.interp, path.as_ref())
+    }
+
+    /// Calls the procedure bound to the global variable `name`.
+    pub fn call(&mut self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        let procedure = self.lookup(name).ok_or_else(|| {
+            Error::new(
+                ConditionKind::UnboundVariable,
+                format!("unbound variable {}", name),
+                Vec::new(),
+            )
+        })?;
+        self.interp.apply(&procedure, args)
+    }
+
+    pub fn define(&mut self, name: &str, value: impl IntoValue) {
+        self.interp.define(name, value.into_value());
+    }
+
+    /// The value of a global variable, if it is defined.
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        self.interp.lookup(name)
+    }
+
+    pub fn execution_handle(&self) -> ExecutionHandle {
+        self.interp.execution_handle()
+    }
+
+    /// Sets how much of the Rust stack evaluation may use, as in
+    /// [`Interpreter::set_stack_limit`].
+    pub fn set_stack_limit(&mut self, bytes: usize) {
+        self.interp.set_stack_limit(bytes);
+    }
+
+    /// Limits evaluation to `fuel` more steps, as in
+    /// [`Interpreter::set_fuel`].
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.interp.set_fuel(fuel);
+    }
+
+    pub fn fuel(&self) -> Option<u64> {
+        self.interp.fuel()
+    }
+
+    /// Runs `f`, interrupting any evaluation it does once `timeout` has
+    /// passed.
+    pub fn run_with_timeout<T>(
+        &mut self,
+        timeout: Duration,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let outer = self.interp.set_timeout(timeout);
+        let result = f(self);
+        self.interp.restore_deadline(outer);
+        result
+    }
+
+    /// Makes `port` the value of `current-input-port`.
+    pub fn set_current_input_port(&mut self, port: InputPort) {
+        self.interp.set_current_input_port(port);
+    }
+
+    /// The underlying interpreter, e.g. to define primitives.
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_str_returns_the_last_value() {
+        let mut scheme = Scheme::new();
+        let value = scheme
+            .eval_str("(define (square x) (* x x)) (square 12)")
+            .unwrap();
+        assert_eq!(i64::try_from(value).unwrap(), 144);
+        assert!(matches!(scheme.eval_str("").unwrap(), Value::Unspecified));
+        assert!(scheme.eval_str("(car '())").is_err());
+    }
+
+    #[test]
+    fn call_and_define_convert_rust_values() {
+        let mut scheme = Scheme::new();
+        scheme.define("greeting", "hello");
+        scheme
+            .eval_str("(define (greet name) (string-append greeting \", \" name))")
+            .unwrap();
+        let value = scheme.call("greet", &[Value::from("world")]).unwrap();
+        assert_eq!(String::try_from(value).unwrap(), "hello, world");
+        let value = scheme
+            .call("list", &[Value::from(1), Value::from(2.5)])
+            .unwrap();
+        let items = Vec::<Value>::try_from(value).unwrap();
+        assert_eq!(f64::try_from(items[1].clone()).unwrap(), 2.5);
+        assert!(i64::try_from(items[1].clone()).is_err());
+        let err = scheme.call("no-such-procedure", &[]).unwrap_err();
+        assert_eq!(
+            err.condition().map(|condition| condition.kind),
+            Some(ConditionKind::UnboundVariable)
+        );
+    }
+
+    #[test]
+    fn syntax_errors_give_the_source_location() {
+        let mut scheme = Scheme::new();
+        let err = scheme
+            .eval_str("(define x 1)\n(define (f)\n  (let ((a)) a))")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "3:3: malformed binding (let ((a)) a)");
+    }
+}
@@ -0,0 +1,1108 @@
+//! The evaluator.
+//!
+//! Each top-level form is first analyzed into an `Expr` tree, which
+//! resolves special forms and lexical variable references ahead of time,
+//! and then run by a tree-walking loop. Calls in tail position replace the
+//! current expression and frame instead of recursing, so tail calls run in
+//! constant space.
+
+mod analyze;
+mod unparse;
+
+pub(crate) use analyze::{Expr, Lambda};
+
+use crate::builtins;
+use crate::capability::Capabilities;
+use crate::limits::{self, Limits, Stop, Unwound};
+use crate::num::ArithmeticError;
+use crate::parse::ParseError;
+use crate::ports::{InputPort, OutputPort, Port, PortError};
+use crate::print;
+use crate::proc::{
+    Arity, CaseLambda, Closure, Continuation, Parameter, Primitive, PrimitiveFn, Procedure,
+};
+use crate::symbol::Symbol;
+use crate::syntax::{SourceMap, Syntax};
+use crate::value::{ConversionError, IndexError, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How many bytes of the Rust stack an evaluation may use by default before
+/// it is abandoned, so that runaway recursion is reported instead of
+/// overflowing the stack.
+///
+/// Non-tail calls recurse on the Rust stack. This leaves room for the host
+/// and for primitives even on a thread with the standard 2 MiB stack; hosts
+/// that run the interpreter on a larger stack can raise it with
+/// [`Interpreter::set_stack_limit`].
+pub const DEFAULT_STACK_LIMIT: usize = 1 << 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConditionKind {
+    /// Raised by `error`.
+    Error,
+    UnboundVariable,
+    WrongType,
+    WrongArity,
+    Syntax,
+    Arithmetic,
+    Range,
+    Io,
+    /// Source text that could not be read.
+    Read,
+    /// Non-tail calls nested more deeply than the interpreter allows.
+    RecursionLimit,
+    /// Evaluation stopped by an [`ExecutionHandle`] or a timeout.
+    Interrupted,
+    /// The step budget set with [`Interpreter::set_fuel`] ran out.
+    OutOfFuel,
+    /// A request for more memory or work than the interpreter allows in one
+    /// step, such as a huge `make-vector` or `expt`.
+    ImplementationRestriction,
+}
+
+/// An error object.
+pub struct Condition {
+    pub kind: ConditionKind,
+    pub message: String,
+    pub irritants: Vec<Value>,
+}
+
+/// The ways evaluation can stop early.
+#[derive(Clone)]
+pub enum Error {
+    /// An object raised by `raise`, or a condition signalled by `error` or
+    /// by the runtime.
+    Raise(Value),
+    /// Control unwinding to the `call/cc` with the given continuation id.
+    /// The interpreter never lets this escape from `eval` or `apply`.
+    Escape(u64, Value),
+    /// Control unwinding out of the program because of `exit`, running the
+    /// `dynamic-wind` after thunks on the way. The host should end the
+    /// program with the given status once `eval` or `apply` returns this.
+    Exit(i32),
+}
+
+impl Error {
+    pub fn new(kind: ConditionKind, message: impl Into<String>, irritants: Vec<Value>) -> Self {
+        Self::Raise(Value::Condition(Rc::new(Condition {
+            kind,
+            message: message.into(),
+            irritants,
+        })))
+    }
+
+    pub fn syntax(message: impl Into<String>, form: &Value) -> Self {
+        Self::new(ConditionKind::Syntax, message, vec![form.clone()])
+    }
+
+    /// An argument of the wrong type passed to the procedure `name`.
+    pub fn wrong_type(name: &str, expected: &str, value: &Value) -> Self {
+        Self::new(
+            ConditionKind::WrongType,
+            format!("{}: expected {}", name, expected),
+            vec![value.clone()],
+        )
+    }
+
+    pub fn wrong_arity(name: Option<&str>, arity: Arity, given: usize) -> Self {
+        Self::new(
+            ConditionKind::WrongArity,
+            format!(
+                "{}: expected {} argument{}, got {}",
+                name.unwrap_or("#<procedure>"),
+                arity,
+                if arity.min == 1 && arity.max == Some(1) {
+                    ""
+                } else {
+                    "s"
+                },
+                given
+            ),
+            Vec::new(),
+        )
+    }
+
+    /// The condition raised, if this error is one.
+    pub fn condition(&self) -> Option<&Condition> {
+        match self {
+            Self::Raise(Value::Condition(condition)) => Some(condition),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Raise(Value::Condition(condition)) => {
+                f.write_str(&condition.message)?;
+                for irritant in &condition.irritants {
+                    f.write_str(" ")?;
+                    print::write(irritant, f)?;
+                }
+                Ok(())
+            }
+            Self::Raise(value) => {
+                f.write_str("uncaught exception: ")?;
+                print::write(value, f)
+            }
+            Self::Escape(..) => f.write_str("continuation invoked outside its extent"),
+            Self::Exit(code) => write!(f, "exit with status {}", code),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error({})", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ArithmeticError> for Error {
+    fn from(err: ArithmeticError) -> Self {
+        let kind = match err {
+            ArithmeticError::ExponentTooLarge | ArithmeticError::TooLarge => {
+                ConditionKind::ImplementationRestriction
+            }
+            _ => ConditionKind::Arithmetic,
+        };
+        Self::new(kind, err.to_string(), Vec::new())
+    }
+}
+
+impl From<IndexError> for Error {
+    fn from(err: IndexError) -> Self {
+        Self::new(ConditionKind::Range, err.to_string(), Vec::new())
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Self::new(ConditionKind::Read, err.to_string(), Vec::new())
+    }
+}
+
+impl From<ConversionError> for Error {
+    fn from(err: ConversionError) -> Self {
+        Self::new(
+            ConditionKind::WrongType,
+            format!("expected {}", err.expected),
+            vec![err.value],
+        )
+    }
+}
+
+impl From<PortError> for Error {
+    fn from(err: PortError) -> Self {
+        Self::new(ConditionKind::Io, err.to_string(), Vec::new())
+    }
+}
+
+/// A top-level variable. Analyzed code refers to the cell directly, so
+/// redefining a global is seen by every procedure that uses it.
+pub(crate) struct Global {
+    pub(crate) name: Symbol,
+    pub(crate) value: RefCell<Option<Value>>,
+}
+
+/// The variables bound by one procedure call or `let`. Slots are `None`
+/// until initialized, which is how references to `letrec` variables and
+/// internal definitions before their definition are caught.
+pub struct Frame {
+    slots: RefCell<Vec<Option<Value>>>,
+    parent: Option<Rc<Frame>>,
+}
+
+type Env = Option<Rc<Frame>>;
+
+impl Frame {
+    fn ancestor(self: &Rc<Self>, depth: usize) -> &Rc<Self> {
+        let mut frame = self;
+        for _ in 0..depth {
+            frame = frame.parent.as_ref().expect("lexical address out of range");
+        }
+        frame
+    }
+}
+
+fn frame_at(env: &Env, depth: usize) -> &Rc<Frame> {
+    env.as_ref()
+        .expect("local variable outside any frame")
+        .ancestor(depth)
+}
+
+/// Stops an interpreter's evaluation from another thread.
+#[derive(Clone)]
+pub struct ExecutionHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl ExecutionHandle {
+    /// Makes the running evaluation raise an `Interrupted` condition at its
+    /// next procedure call or loop iteration, or part way through a
+    /// long-running primitive. If nothing is running, the next evaluation is
+    /// interrupted instead.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+}
+
+pub struct Interpreter {
+    globals: HashMap<Symbol, Rc<Global>>,
+    /// Handlers installed by `with-exception-handler`, innermost last.
+    pub(crate) handlers: Vec<Value>,
+    depth: usize,
+    /// The address of the stack when the outermost evaluation began.
+    stack_base: usize,
+    stack_limit: usize,
+    next_continuation: u64,
+    limits: Rc<Limits>,
+    capabilities: Capabilities,
+    /// Traced calls in progress, for indenting the trace.
+    trace_depth: usize,
+    /// Continuation marks as key and value, outermost first.
+    marks: Vec<(Value, Value)>,
+    /// The parameters behind `current-input-port`, `current-output-port`
+    /// and `current-error-port`.
+    pub(crate) current_input: Rc<Procedure>,
+    pub(crate) current_output: Rc<Procedure>,
+    pub(crate) current_error: Rc<Procedure>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// Creates an interpreter with the standard procedures defined.
+    pub fn new() -> Self {
+        Self::with_capabilities(Capabilities::all())
+    }
+
+    /// Creates an interpreter with the pure standard procedures and those
+    /// that need one of `capabilities`.
+    pub fn with_capabilities(capabilities: Capabilities) -> Self {
+        let mut interp = Self {
+            globals: HashMap::new(),
+            handlers: Vec::new(),
+            depth: 0,
+            stack_base: 0,
+            stack_limit: DEFAULT_STACK_LIMIT,
+            next_continuation: 0,
+            limits: Rc::default(),
+            capabilities,
+            trace_depth: 0,
+            marks: Vec::new(),
+            current_input: builtins::ports::input_parameter(InputPort::stdin()),
+            current_output: builtins::ports::output_parameter(OutputPort::stdout()),
+            current_error: builtins::ports::output_parameter(OutputPort::stderr()),
+        };
+        builtins::install(&mut interp);
+        interp
+    }
+
+    /// Makes `port` the value of `current-input-port`, e.g. to feed `read`
+    /// and `read-line` from somewhere other than stdin.
+    pub fn set_current_input_port(&mut self, port: InputPort) {
+        builtins::ports::set_current(&self.current_input, Port::from(port));
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    pub fn define(&mut self, name: &str, value: Value) {
+        *self.global(Symbol::intern(name)).value.borrow_mut() = Some(value);
+    }
+
+    pub fn define_primitive(&mut self, name: &'static str, arity: Arity, func: PrimitiveFn) {
+        let primitive = Procedure::Primitive(Primitive { name, arity, func });
+        self.define(name, Value::Procedure(Rc::new(primitive)));
+    }
+
+    /// The value of a global variable, if it is defined.
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        self.globals
+            .get(&Symbol::intern(name))
+            .and_then(|global| global.value.borrow().clone())
+    }
+
+    pub(crate) fn global(&mut self, name: Symbol) -> Rc<Global> {
+        self.globals
+            .entry(name)
+            .or_insert_with(|| {
+                Rc::new(Global {
+                    name,
+                    value: RefCell::new(None),
+                })
+            })
+            .clone()
+    }
+
+    pub fn execution_handle(&self) -> ExecutionHandle {
+        ExecutionHandle {
+            interrupted: self.limits.interrupted.clone(),
+        }
+    }
+
+    /// Sets how many bytes of the Rust stack an evaluation may use before
+    /// raising a `RecursionLimit` condition. The limit must leave room for
+    /// primitives below it on the stack of the thread evaluating.
+    pub fn set_stack_limit(&mut self, bytes: usize) {
+        self.stack_limit = bytes;
+    }
+
+    pub fn stack_limit(&self) -> usize {
+        self.stack_limit
+    }
+
+    /// Limits evaluation to `fuel` more steps, or lifts the limit with
+    /// `None`. A step is a procedure call or loop iteration; primitives also
+    /// charge a step for each list element they walk and for each thousand
+    /// or so digit operations of bignum arithmetic. Running out raises an
+    /// `OutOfFuel` condition, after which evaluation can continue once more
+    /// fuel is set.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.limits.fuel.set(fuel);
+    }
+
+    /// The steps left before evaluation runs out of fuel, if limited.
+    pub fn fuel(&self) -> Option<u64> {
+        self.limits.fuel.get()
+    }
+
+    /// Runs `f`, interrupting any evaluation it does once `timeout` has
+    /// passed.
+    pub fn run_with_timeout<T>(
+        &mut self,
+        timeout: Duration,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let outer = self.set_timeout(timeout);
+        let result = f(self);
+        self.restore_deadline(outer);
+        result
+    }
+
+    /// Sets a deadline `timeout` from now, unless an earlier one is already
+    /// set, and returns the previous deadline to restore afterwards.
+    pub(crate) fn set_timeout(&mut self, timeout: Duration) -> Option<Instant> {
+        let outer = self.limits.deadline.get();
+        let deadline = Instant::now() + timeout;
+        let deadline = outer.map_or(deadline, |outer| outer.min(deadline));
+        self.limits.deadline.set(Some(deadline));
+        outer
+    }
+
+    pub(crate) fn restore_deadline(&mut self, deadline: Option<Instant>) {
+        self.limits.deadline.set(deadline);
+    }
+
+    /// Evaluates a top-level form.
+    pub fn eval(&mut self, form: &Value) -> Result<Value, Error> {
+        self.eval_with_spans(form, None)
+    }
+
+    /// The form that `form` is lowered into before evaluation, with derived
+    /// forms such as `do`, named `let` and `quasiquote` expanded.
+    pub fn expand(&mut self, form: &Value) -> Result<Value, Error> {
+        analyze::analyze_toplevel(self, form, None).map(|expr| unparse::unparse(&expr))
+    }
+
+    /// Evaluates a form as read, so that syntax errors give its location.
+    pub fn eval_syntax(&mut self, syntax: &Syntax) -> Result<Value, Error> {
+        let (form, spans) = syntax.to_datum_with_spans();
+        self.eval_with_spans(&form, Some(&spans))
+    }
+
+    fn eval_with_spans(&mut self, form: &Value, spans: Option<&SourceMap>) -> Result<Value, Error> {
+        let _limits = limits::install(None);
+        let result = analyze::analyze_toplevel(self, form, spans)
+            .and_then(|expr| self.eval_expr(&expr, &None));
+        self.finish(&result);
+        result
+    }
+
+    /// Calls a procedure with the given arguments.
+    pub fn apply(&mut self, procedure: &Value, args: &[Value]) -> Result<Value, Error> {
+        let _limits = limits::install(None);
+        let result = self.apply_procedure(procedure, args);
+        self.finish(&result);
+        result
+    }
+
+    /// Clears an interrupt once it has unwound out of the outermost
+    /// evaluation, so that handlers cannot resume the interrupted program,
+    /// and flushes output before an `exit` reaches the host.
+    fn finish(&mut self, result: &Result<Value, Error>) {
+        if self.depth == 0 {
+            self.limits.interrupted.store(false, Ordering::Relaxed);
+            self.limits.take_tripped();
+            if let Err(Error::Exit(_)) = result {
+                builtins::ports::flush_current(self);
+            }
+        }
+    }
+
+    fn apply_procedure(&mut self, procedure: &Value, args: &[Value]) -> Result<Value, Error> {
+        self.enter()?;
+        let result = match procedure {
+            Value::Procedure(procedure) => match &**procedure {
+                Procedure::Closure(closure) if closure.traced.get() => {
+                    self.call_traced(procedure, closure, args)
+                }
+                Procedure::Closure(closure) => bind(&closure.lambda, args, &closure.env)
+                    .and_then(|env| self.run(closure.lambda.body.clone(), Some(env))),
+                Procedure::CaseLambda(case) => case.clause(args.len()).and_then(|lambda| {
+                    let env = bind(lambda, args, &case.env)?;
+                    self.run(lambda.body.clone(), Some(env))
+                }),
+                Procedure::Primitive(primitive) => self.call_primitive(primitive, args),
+                Procedure::Continuation(k) => throw(k, args),
+                Procedure::Parameter(parameter) => parameter_value(parameter, args),
+            },
+            _ => Err(not_a_procedure(procedure)),
+        };
+        self.depth -= 1;
+        result
+    }
+
+    /// Calls a closure marked by `trace`, writing the call and then its
+    /// result to the current output port at the same indentation. The body
+    /// is not run as a tail call, so that the result can be printed.
+    fn call_traced(
+        &mut self,
+        procedure: &Rc<Procedure>,
+        closure: &Closure,
+        args: &[Value],
+    ) -> Result<Value, Error> {
+        let head = match closure.name() {
+            Some(name) => Value::Symbol(name),
+            None => Value::Procedure(procedure.clone()),
+        };
+        let call = Value::list(std::iter::once(head).chain(args.iter().cloned()));
+        let indent = "| ".repeat(self.trace_depth);
+        self.trace_line(&indent, &call)?;
+        self.trace_depth += 1;
+        let result = bind(&closure.lambda, args, &closure.env)
+            .and_then(|env| self.run(closure.lambda.body.clone(), Some(env)));
+        self.trace_depth -= 1;
+        let value = result?;
+        self.trace_line(&indent, &value)?;
+        Ok(value)
+    }
+
+    fn trace_line(&mut self, indent: &str, value: &Value) -> Result<(), Error> {
+        let mut line = indent.to_string();
+        print::write(value, &mut line).expect("writing to a String cannot fail");
+        line.push('\n');
+        builtins::ports::write_current(self, "trace", &line)
+    }
+
+    /// The marks of the current continuation, as a list of key and value
+    /// pairs with the innermost first.
+    pub(crate) fn continuation_marks(&self) -> Value {
+        Value::list(
+            self.marks
+                .iter()
+                .rev()
+                .map(|(key, value)| Value::cons(key.clone(), value.clone())),
+        )
+    }
+
+    /// Runs `body` with an escape procedure, as in `call/cc`.
+    pub(crate) fn call_with_escape(&mut self, body: &Value) -> Result<Value, Error> {
+        let id = self.next_continuation;
+        self.next_continuation += 1;
+        let k = Rc::new(Procedure::Continuation(Continuation {
+            id,
+            active: Cell::new(true),
+        }));
+        let result = self.apply(body, &[Value::Procedure(k.clone())]);
+        if let Procedure::Continuation(k) = &*k {
+            k.active.set(false);
+        }
+        match result {
+            Err(Error::Escape(target, value)) if target == id => Ok(value),
+            result => result,
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), Error> {
+        self.safepoint()?;
+        if self.depth == 0 {
+            self.stack_base = stack_address();
+        } else if self.stack_base.abs_diff(stack_address()) > self.stack_limit {
+            return Err(Error::new(
+                ConditionKind::RecursionLimit,
+                "maximum recursion depth exceeded",
+                Vec::new(),
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Checks for interruption and spends a step of fuel. This runs on
+    /// every call and loop iteration.
+    fn safepoint(&mut self) -> Result<(), Error> {
+        self.limits.check(1).map_err(stop_error)
+    }
+
+    fn call_primitive(&mut self, primitive: &Primitive, args: &[Value]) -> Result<Value, Error> {
+        if !primitive.arity.accepts(args.len()) {
+            return Err(Error::wrong_arity(
+                Some(primitive.name),
+                primitive.arity,
+                args.len(),
+            ));
+        }
+        let limits = self.limits.clone();
+        let _limits = limits::install(Some(&limits));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| (primitive.func)(self, args)))
+            .unwrap_or_else(|payload| match payload.downcast::<Unwound>() {
+                Ok(_) => Ok(Value::Unspecified),
+                Err(payload) => panic::resume_unwind(payload),
+            });
+        // A primitive stopped part way through has no meaningful result.
+        match limits.take_tripped() {
+            Some(stop) => Err(stop_error(stop)),
+            None => result,
+        }
+    }
+
+    fn eval_expr(&mut self, expr: &Rc<Expr>, env: &Env) -> Result<Value, Error> {
+        self.enter()?;
+        let result = self.run(expr.clone(), env.clone());
+        self.depth -= 1;
+        result
+    }
+
+    fn eval_all(&mut self, exprs: &[Rc<Expr>], env: &Env) -> Result<Vec<Value>, Error> {
+        let mut values = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            values.push(self.eval_expr(expr, env)?);
+        }
+        Ok(values)
+    }
+
+    /// The evaluation loop. Each iteration either returns a value or moves
+    /// on to the expression in tail position.
+    ///
+    /// Arms that evaluate subexpressions live in their own methods so that
+    /// this frame, which sits on the stack once per nested call, stays small.
+    fn run(&mut self, expr: Rc<Expr>, env: Env) -> Result<Value, Error> {
+        // Marks set in this loop belong to the frame it evaluates and are
+        // dropped when it returns.
+        let marks = self.marks.len();
+        let result = self.run_frame(expr, env, marks);
+        self.marks.truncate(marks);
+        result
+    }
+
+    fn run_frame(
+        &mut self,
+        mut expr: Rc<Expr>,
+        mut env: Env,
+        marks: usize,
+    ) -> Result<Value, Error> {
+        loop {
+            let step = match &*expr {
+                Expr::Constant(value) => return Ok(value.clone()),
+                Expr::Local { depth, index, name } => {
+                    return local_value(&env, *depth, *index, *name)
+                }
+                Expr::Global(global) => return global_value(global),
+                Expr::SetLocal {
+                    depth,
+                    index,
+                    value,
+                    define,
+                } => self.set_local(&env, *depth, *index, value, *define)?,
+                Expr::SetGlobal(global, value) => self.set_global(&env, global, value, false)?,
+                Expr::DefineGlobal(global, value) => self.set_global(&env, global, value, true)?,
+                Expr::If(test, then, otherwise) => self.if_(&env, test, then, otherwise)?,
+                Expr::Lambda(lambda) => {
+                    return Ok(Value::Procedure(Rc::new(Procedure::Closure(Closure {
+                        lambda: lambda.clone(),
+                        env: env.clone(),
+                        traced: Cell::new(false),
+                    }))))
+                }
+                Expr::CaseLambda(clauses) => {
+                    return Ok(Value::Procedure(Rc::new(Procedure::CaseLambda(
+                        CaseLambda {
+                            clauses: clauses.clone(),
+                            env: env.clone(),
+                        },
+                    ))))
+                }
+                Expr::Sequence(exprs) => self.sequence(&env, exprs)?,
+                Expr::And(exprs) => self.and_or(&env, exprs, false)?,
+                Expr::Or(exprs) => self.and_or(&env, exprs, true)?,
+                Expr::Cond(clauses) => self.cond(&env, clauses)?,
+                Expr::Case(key, clauses, otherwise) => self.case(&env, key, clauses, otherwise)?,
+                Expr::Let(lambda, inits) => self.let_(&mut env, lambda, inits)?,
+                Expr::LetValues(lambda, inits) => self.let_values(&mut env, lambda, inits)?,
+                Expr::Call(operator, operands) => self.call(&mut env, operator, operands)?,
+                Expr::WithMark(key, value, body) => {
+                    self.with_mark(&env, marks, key, value, body)?
+                }
+            };
+            match step {
+                Step::Return(value) => return Ok(value),
+                Step::Tail(next) => {
+                    self.safepoint()?;
+                    expr = next;
+                }
+            }
+        }
+    }
+
+    fn set_local(
+        &mut self,
+        env: &Env,
+        depth: usize,
+        index: usize,
+        value: &Rc<Expr>,
+        define: bool,
+    ) -> Result<Step, Error> {
+        let value = self.eval_expr(value, env)?;
+        let frame = frame_at(env, depth);
+        let mut slots = frame.slots.borrow_mut();
+        if !define && slots[index].is_none() {
+            return Err(Error::new(
+                ConditionKind::UnboundVariable,
+                "assignment before definition",
+                Vec::new(),
+            ));
+        }
+        slots[index] = Some(value);
+        Ok(Step::Return(Value::Unspecified))
+    }
+
+    fn set_global(
+        &mut self,
+        env: &Env,
+        global: &Global,
+        value: &Rc<Expr>,
+        define: bool,
+    ) -> Result<Step, Error> {
+        let value = self.eval_expr(value, env)?;
+        if !define {
+            global_value(global)?;
+        }
+        *global.value.borrow_mut() = Some(value);
+        Ok(Step::Return(Value::Unspecified))
+    }
+
+    fn if_(
+        &mut self,
+        env: &Env,
+        test: &Rc<Expr>,
+        then: &Rc<Expr>,
+        otherwise: &Option<Rc<Expr>>,
+    ) -> Result<Step, Error> {
+        Ok(if self.eval_expr(test, env)?.is_true() {
+            Step::Tail(then.clone())
+        } else {
+            match otherwise {
+                Some(otherwise) => Step::Tail(otherwise.clone()),
+                None => Step::Return(Value::Unspecified),
+            }
+        })
+    }
+
+    /// Sets a mark on the current frame, whose own marks start at
+    /// `self.marks[frame]`, replacing any mark it already has for the key.
+    fn with_mark(
+        &mut self,
+        env: &Env,
+        frame: usize,
+        key: &Rc<Expr>,
+        value: &Rc<Expr>,
+        body: &Rc<Expr>,
+    ) -> Result<Step, Error> {
+        let key = self.eval_expr(key, env)?;
+        let value = self.eval_expr(value, env)?;
+        match self.marks[frame..].iter_mut().find(|(k, _)| k.is_eqv(&key)) {
+            Some(mark) => mark.1 = value,
+            None => self.marks.push((key, value)),
+        }
+        Ok(Step::Tail(body.clone()))
+    }
+
+    fn sequence(&mut self, env: &Env, exprs: &[Rc<Expr>]) -> Result<Step, Error> {
+        let (last, init) = exprs.split_last().expect("empty sequence");
+        for expr in init {
+            self.eval_expr(expr, env)?;
+        }
+        Ok(Step::Tail(last.clone()))
+    }
+
+    /// `and` when `stop_on` is false, `or` when it is true.
+    fn and_or(&mut self, env: &Env, exprs: &[Rc<Expr>], stop_on: bool) -> Result<Step, Error> {
+        let Some((last, init)) = exprs.split_last() else {
+            return Ok(Step::Return(Value::Boolean(!stop_on)));
+        };
+        for expr in init {
+            let value = self.eval_expr(expr, env)?;
+            if value.is_true() == stop_on {
+                return Ok(Step::Return(value));
+            }
+        }
+        Ok(Step::Tail(last.clone()))
+    }
+
+    fn cond(&mut self, env: &Env, clauses: &[analyze::Clause]) -> Result<Step, Error> {
+        for clause in clauses {
+            let value = self.eval_expr(&clause.test, env)?;
+            if value.is_true() {
+                return self.clause_body(env, &clause.body, value);
+            }
+        }
+        Ok(Step::Return(Value::Unspecified))
+    }
+
+    fn case(
+        &mut self,
+        env: &Env,
+        key: &Rc<Expr>,
+        clauses: &[(Vec<Value>, analyze::ClauseBody)],
+        otherwise: &Option<analyze::ClauseBody>,
+    ) -> Result<Step, Error> {
+        let key = self.eval_expr(key, env)?;
+        let clause = clauses
+            .iter()
+            .find(|(data, _)| data.iter().any(|datum| key.is_eqv(datum)))
+            .map(|(_, body)| body)
+            .or(otherwise.as_ref());
+        match clause {
+            Some(body) => self.clause_body(env, body, key),
+            None => Ok(Step::Return(Value::Unspecified)),
+        }
+    }
+
+    /// Continues with the body of a selected `cond` or `case` clause, where
+    /// `value` is the test result or key.
+    fn clause_body(
+        &mut self,
+        env: &Env,
+        body: &analyze::ClauseBody,
+        value: Value,
+    ) -> Result<Step, Error> {
+        match body {
+            analyze::ClauseBody::Test => Ok(Step::Return(value)),
+            analyze::ClauseBody::Sequence(body) => Ok(Step::Tail(body.clone())),
+            analyze::ClauseBody::Arrow(receiver) => {
+                let receiver = self.eval_expr(receiver, env)?;
+                self.apply(&receiver, &[value]).map(Step::Return)
+            }
+        }
+    }
+
+    fn let_(&mut self, env: &mut Env, lambda: &Lambda, inits: &[Rc<Expr>]) -> Result<Step, Error> {
+        let args = self.eval_all(inits, env)?;
+        *env = Some(bind(lambda, &args, env)?);
+        Ok(Step::Tail(lambda.body.clone()))
+    }
+
+    /// Binds the values of each initializer in turn, a surplus accepted by
+    /// a rest variable as a list.
+    fn let_values(
+        &mut self,
+        env: &mut Env,
+        lambda: &Lambda,
+        inits: &[(Rc<Expr>, Arity)],
+    ) -> Result<Step, Error> {
+        let mut args = Vec::with_capacity(lambda.required);
+        for (init, arity) in inits {
+            let values = match self.eval_expr(init, env)? {
+                Value::Values(values) => values.to_vec(),
+                value => vec![value],
+            };
+            if !arity.accepts(values.len()) {
+                return Err(Error::new(
+                    ConditionKind::WrongArity,
+                    format!(
+                        "let-values: expected {} values, got {}",
+                        arity,
+                        values.len()
+                    ),
+                    Vec::new(),
+                ));
+            }
+            let (required, rest) = values.split_at(arity.min);
+            args.extend_from_slice(required);
+            if arity.max.is_none() {
+                args.push(Value::list(rest.iter().cloned()));
+            }
+        }
+        *env = Some(bind(lambda, &args, env)?);
+        Ok(Step::Tail(lambda.body.clone()))
+    }
+
+    fn call(
+        &mut self,
+        env: &mut Env,
+        operator: &Rc<Expr>,
+        operands: &[Rc<Expr>],
+    ) -> Result<Step, Error> {
+        let operator = self.eval_expr(operator, env)?;
+        let args = self.eval_all(operands, env)?;
+        let Value::Procedure(procedure) = &operator else {
+            return Err(not_a_procedure(&operator));
+        };
+        match &**procedure {
+            Procedure::Closure(closure) if closure.traced.get() => {
+                self.apply_procedure(&operator, &args).map(Step::Return)
+            }
+            Procedure::Closure(closure) => {
+                *env = Some(bind(&closure.lambda, &args, &closure.env)?);
+                Ok(Step::Tail(closure.lambda.body.clone()))
+            }
+            Procedure::CaseLambda(case) => {
+                let lambda = case.clause(args.len())?;
+                *env = Some(bind(lambda, &args, &case.env)?);
+                Ok(Step::Tail(lambda.body.clone()))
+            }
+            Procedure::Primitive(primitive) => {
+                self.call_primitive(primitive, &args).map(Step::Return)
+            }
+            Procedure::Continuation(k) => throw(k, &args).map(Step::Return),
+            Procedure::Parameter(parameter) => parameter_value(parameter, &args).map(Step::Return),
+        }
+    }
+}
+
+/// An address in the current stack frame, for measuring how much of the
+/// stack is in use.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// What the evaluation loop does after one expression.
+enum Step {
+    Return(Value),
+    Tail(Rc<Expr>),
+}
+
+fn local_value(env: &Env, depth: usize, index: usize, name: Symbol) -> Result<Value, Error> {
+    let frame = frame_at(env, depth);
+    let slots = frame.slots.borrow();
+    slots[index].clone().ok_or_else(|| {
+        Error::new(
+            ConditionKind::UnboundVariable,
+            format!("{} used before its definition", name),
+            Vec::new(),
+        )
+    })
+}
+
+fn global_value(global: &Global) -> Result<Value, Error> {
+    global.value.borrow().clone().ok_or_else(|| {
+        Error::new(
+            ConditionKind::UnboundVariable,
+            format!("unbound variable {}", global.name),
+            Vec::new(),
+        )
+    })
+}
+
+fn stop_error(stop: Stop) -> Error {
+    let (kind, message) = match stop {
+        Stop::Interrupted => (ConditionKind::Interrupted, "evaluation interrupted"),
+        Stop::TimedOut => (ConditionKind::Interrupted, "evaluation timed out"),
+        Stop::OutOfFuel => (ConditionKind::OutOfFuel, "out of fuel"),
+    };
+    Error::new(kind, message, Vec::new())
+}
+
+fn not_a_procedure(value: &Value) -> Error {
+    Error::new(
+        ConditionKind::WrongType,
+        "not a procedure",
+        vec![value.clone()],
+    )
+}
+
+/// Creates the frame for a call to `lambda`.
+fn bind(lambda: &Lambda, args: &[Value], parent: &Env) -> Result<Rc<Frame>, Error> {
+    let arity = lambda.arity();
+    if !arity.accepts(args.len()) {
+        return Err(Error::wrong_arity(
+            lambda.name.map(|name| name.name()),
+            arity,
+            args.len(),
+        ));
+    }
+    let mut slots = Vec::with_capacity(lambda.names.len());
+    slots.extend(args[..lambda.required].iter().cloned().map(Some));
+    if lambda.rest {
+        slots.push(Some(Value::list(args[lambda.required..].iter().cloned())));
+    }
+    slots.resize(lambda.names.len(), None);
+    Ok(Rc::new(Frame {
+        slots: RefCell::new(slots),
+        parent: parent.clone(),
+    }))
+}
+
+fn parameter_value(parameter: &Parameter, args: &[Value]) -> Result<Value, Error> {
+    if !args.is_empty() {
+        return Err(Error::wrong_arity(
+            Some("parameter"),
+            Arity::exactly(0),
+            args.len(),
+        ));
+    }
+    Ok(parameter.get())
+}
+
+fn throw(k: &Continuation, args: &[Value]) -> Result<Value, Error> {
+    if !k.active.get() {
+        return Err(Error::new(
+            ConditionKind::Error,
+            "continuation can no longer be invoked",
+            Vec::new(),
+        ));
+    }
+    Err(Error::Escape(k.id, Value::values(args.to_vec())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+
+    fn kind(result: Result<Value, Error>) -> Option<ConditionKind> {
+        result
+            .unwrap_err()
+            .condition()
+            .map(|condition| condition.kind)
+    }
+
+    #[test]
+    fn deep_recursion_raises_a_condition() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str("(define (f n) (if (= n 0) 0 (+ 1 (f (- n 1)))))")
+            .unwrap();
+        let err = scheme.eval_str("(f 1000000)").unwrap_err();
+        let kind = err.condition().map(|condition| condition.kind);
+        assert_eq!(kind, Some(ConditionKind::RecursionLimit));
+        assert_eq!(scheme.eval_str("(f 10)").unwrap().to_string(), "10");
+    }
+
+    #[test]
+    fn applying_a_non_procedure_raises_a_condition() {
+        let mut scheme = Scheme::new();
+        for text in ["(5 6)", "(let ((f \"f\")) (f))", "(apply 'x '(1))"] {
+            let err = scheme.eval_str(text).unwrap_err();
+            let condition = err.condition().expect("a condition");
+            assert_eq!(condition.kind, ConditionKind::WrongType, "{}", text);
+            assert_eq!(condition.message, "not a procedure", "{}", text);
+        }
+        let value = scheme
+            .eval_str(
+                "(call/cc (lambda (k)
+                   (with-exception-handler
+                     (lambda (e) (k (error-object-irritants e)))
+                     (lambda () (+ 1 (5 6))))))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "(5)");
+    }
+
+    #[test]
+    fn fuel_runs_out_inside_bignum_arithmetic() {
+        let mut scheme = Scheme::new();
+        scheme.set_fuel(Some(100));
+        let result = scheme.eval_str(
+            "(let loop ((x (expt 3 600000)) (i 0))
+               (if (< i 3) (loop (* x x) (+ i 1)) 'done))",
+        );
+        assert_eq!(kind(result), Some(ConditionKind::OutOfFuel));
+        scheme.set_fuel(None);
+        assert_eq!(scheme.eval_str("(* 2 3)").unwrap().to_string(), "6");
+    }
+
+    #[test]
+    fn timeouts_stop_bignum_arithmetic() {
+        let mut scheme = Scheme::new();
+        let start = Instant::now();
+        let result = scheme.run_with_timeout(Duration::from_millis(200), |scheme| {
+            scheme.eval_str("(let loop ((x 3)) (loop (* x x)))")
+        });
+        assert_eq!(kind(result), Some(ConditionKind::Interrupted));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn interrupts_stop_bignum_arithmetic() {
+        let mut scheme = Scheme::new();
+        let handle = scheme.execution_handle();
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            handle.interrupt();
+        });
+        let result = scheme.eval_str("(let ((x (* (expt 3 600000) (expt 7 350000)))) (* x x))");
+        interrupter.join().unwrap();
+        assert_eq!(kind(result), Some(ConditionKind::Interrupted));
+        assert_eq!(scheme.eval_str("(+ 1 2)").unwrap().to_string(), "3");
+    }
+
+    #[test]
+    fn fuel_is_charged_for_list_walks() {
+        let mut scheme = Scheme::new();
+        scheme.eval_str("(define l (make-list 100000 0))").unwrap();
+        scheme.set_fuel(Some(1000));
+        assert_eq!(
+            kind(scheme.eval_str("(length l)")),
+            Some(ConditionKind::OutOfFuel)
+        );
+    }
+
+    #[test]
+    fn member_rejects_circular_lists() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str("(define circ (list 1 3 5)) (set-cdr! (cddr circ) circ)")
+            .unwrap();
+        assert_eq!(
+            kind(scheme.eval_str("(member 2 circ)")),
+            Some(ConditionKind::WrongType)
+        );
+        assert_eq!(
+            scheme.eval_str("(car (memv 5 circ))").unwrap().to_string(),
+            "5"
+        );
+    }
+
+    #[test]
+    fn trace_writes_to_the_current_output_port() {
+        let mut scheme = Scheme::new();
+        let output = scheme
+            .eval_str(
+                "(define (f n) (if (= n 0) 0 (+ 1 (f (- n 1)))))
+                 (trace f)
+                 (call-with-output-string
+                   (lambda (port) (parameterize ((current-output-port port)) (f 1))))",
+            )
+            .unwrap();
+        assert_eq!(output.to_string(), "(f 1)\n| (f 0)\n| 0\n1\n");
+    }
+}
@@ -0,0 +1,1412 @@
+//! Turns data into `Expr` trees.
+//!
+//! The special forms are recognized by name wherever they are not shadowed
+//! by a local variable. Derived forms such as `let*`, `do` and named `let`
+//! are lowered here into the same handful of core expressions, and
+//! `quasiquote` into calls to `cons`, `append` and `list->vector`.
+
+use super::{ConditionKind, Error, Global, Interpreter};
+use crate::builtins;
+use crate::proc::{Arity, Primitive, PrimitiveFn, Procedure};
+use crate::symbol::Symbol;
+use crate::syntax::SourceMap;
+use crate::value::Value;
+use std::rc::Rc;
+
+pub(crate) enum Expr {
+    Constant(Value),
+    Local {
+        depth: usize,
+        index: usize,
+        name: Symbol,
+    },
+    Global(Rc<Global>),
+    /// `set!` of a local variable or, with `define` set, its
+    /// initialization by `letrec` or an internal definition.
+    SetLocal {
+        depth: usize,
+        index: usize,
+        value: Rc<Expr>,
+        define: bool,
+    },
+    SetGlobal(Rc<Global>, Rc<Expr>),
+    DefineGlobal(Rc<Global>, Rc<Expr>),
+    If(Rc<Expr>, Rc<Expr>, Option<Rc<Expr>>),
+    Lambda(Rc<Lambda>),
+    /// `case-lambda`, with a lambda for each clause.
+    CaseLambda(Vec<Rc<Lambda>>),
+    /// A non-empty sequence; the last expression is in tail position.
+    Sequence(Vec<Rc<Expr>>),
+    And(Vec<Rc<Expr>>),
+    Or(Vec<Rc<Expr>>),
+    Cond(Vec<Clause>),
+    Case(Rc<Expr>, Vec<(Vec<Value>, ClauseBody)>, Option<ClauseBody>),
+    /// A call to a lambda expression, without creating the closure.
+    Let(Rc<Lambda>, Vec<Rc<Expr>>),
+    /// `let-values`: a call to a lambda expression whose arguments are the
+    /// values of each initializer, spread over as many parameters as its
+    /// arity says. An initializer that accepts any number of further values
+    /// passes them as a list in one more parameter.
+    LetValues(Rc<Lambda>, Vec<(Rc<Expr>, Arity)>),
+    Call(Rc<Expr>, Vec<Rc<Expr>>),
+    /// `with-continuation-mark`, with the key, the value and the body in
+    /// tail position.
+    WithMark(Rc<Expr>, Rc<Expr>, Rc<Expr>),
+}
+
+pub(crate) struct Clause {
+    pub(crate) test: Rc<Expr>,
+    pub(crate) body: ClauseBody,
+}
+
+pub(crate) enum ClauseBody {
+    /// `(test)`, which returns the value of the test.
+    Test,
+    Sequence(Rc<Expr>),
+    /// `(test => receiver)`.
+    Arrow(Rc<Expr>),
+}
+
+pub(crate) struct Lambda {
+    pub(crate) name: Option<Symbol>,
+    pub(crate) required: usize,
+    pub(crate) rest: bool,
+    /// The variables of the frame: the parameters followed by the internal
+    /// definitions.
+    pub(crate) names: Vec<Symbol>,
+    pub(crate) body: Rc<Expr>,
+}
+
+impl Lambda {
+    pub(crate) fn arity(&self) -> Arity {
+        if self.rest {
+            Arity::at_least(self.required)
+        } else {
+            Arity::exactly(self.required)
+        }
+    }
+}
+
+/// The variables of one frame, as known at analysis time.
+struct Scope<'a> {
+    names: Vec<Symbol>,
+    parent: Option<&'a Scope<'a>>,
+}
+
+impl Scope<'_> {
+    fn lookup(&self, name: Symbol) -> Option<(usize, usize)> {
+        let mut scope = Some(self);
+        let mut depth = 0;
+        while let Some(s) = scope {
+            if let Some(index) = s.names.iter().rposition(|n| *n == name) {
+                return Some((depth, index));
+            }
+            scope = s.parent;
+            depth += 1;
+        }
+        None
+    }
+}
+
+/// Analyzes a top-level form. Given the `spans` of the form as read, syntax
+/// errors are prefixed with the location of the form they are about.
+pub(crate) fn analyze_toplevel(
+    interp: &mut Interpreter,
+    form: &Value,
+    spans: Option<&SourceMap>,
+) -> Result<Rc<Expr>, Error> {
+    Analyzer {
+        interp,
+        spans,
+        located: false,
+    }
+    .analyze(form, None)
+}
+
+struct Analyzer<'a> {
+    interp: &'a mut Interpreter,
+    spans: Option<&'a SourceMap>,
+    /// Whether the error being returned already has a location.
+    located: bool,
+}
+
+/// The elements of a proper list, or a syntax error mentioning `form`.
+fn elements(list: &Value, form: &Value) -> Result<Vec<Value>, Error> {
+    list.list_to_vec()
+        .ok_or_else(|| Error::syntax("improper list in form", form))
+}
+
+fn symbol(value: &Value, form: &Value) -> Result<Symbol, Error> {
+    match value {
+        Value::Symbol(sym) => Ok(*sym),
+        _ => Err(Error::syntax("expected an identifier", form)),
+    }
+}
+
+fn constant(value: Value) -> Rc<Expr> {
+    Rc::new(Expr::Constant(value))
+}
+
+/// A call to a standard procedure that does not depend on what the program
+/// has bound its name to.
+fn primitive_call(
+    name: &'static str,
+    arity: Arity,
+    func: PrimitiveFn,
+    args: Vec<Rc<Expr>>,
+) -> Rc<Expr> {
+    let primitive = Procedure::Primitive(Primitive { name, arity, func });
+    Rc::new(Expr::Call(
+        constant(Value::Procedure(Rc::new(primitive))),
+        args,
+    ))
+}
+
+/// `(cons car cdr)`, folded into a constant pair if both parts are
+/// constant.
+fn cons(car: Rc<Expr>, cdr: Rc<Expr>) -> Rc<Expr> {
+    match (&*car, &*cdr) {
+        (Expr::Constant(car), Expr::Constant(cdr)) => {
+            constant(Value::cons(car.clone(), cdr.clone()))
+        }
+        _ => primitive_call("cons", Arity::exactly(2), builtins::cons, vec![car, cdr]),
+    }
+}
+
+/// The operand of `(keyword operand)`, if `value` has that form.
+fn operand_of(value: &Value, keyword: &str) -> Option<Value> {
+    let pair = value.as_pair()?;
+    match (pair.car(), pair.cdr().list_to_vec()?.as_slice()) {
+        (Value::Symbol(sym), [operand]) if sym.name() == keyword => Some(operand.clone()),
+        _ => None,
+    }
+}
+
+/// Splits `((name init) ...)` into names and initializers.
+fn bindings(list: &Value, form: &Value) -> Result<Vec<(Symbol, Value)>, Error> {
+    elements(list, form)?
+        .iter()
+        .map(|binding| match elements(binding, form)?.as_slice() {
+            [name, init] => Ok((symbol(name, form)?, init.clone())),
+            _ => Err(Error::syntax("malformed binding", form)),
+        })
+        .collect()
+}
+
+/// The variables of a parameter list such as `(a b)`, `(a . rest)` or
+/// `args`, and how many arguments it accepts. A rest variable comes last.
+fn formals(params: &Value, form: &Value) -> Result<(Vec<Symbol>, Arity), Error> {
+    let mut names = Vec::new();
+    let mut params = params.clone();
+    while let Value::Pair(pair) = params {
+        names.push(symbol(&pair.car(), form)?);
+        params = pair.cdr();
+    }
+    let required = names.len();
+    match params {
+        Value::Null => Ok((names, Arity::exactly(required))),
+        Value::Symbol(sym) => {
+            names.push(sym);
+            Ok((names, Arity::at_least(required)))
+        }
+        _ => Err(Error::syntax("malformed parameter list", form)),
+    }
+}
+
+fn check_distinct(names: &[Symbol], form: &Value) -> Result<(), Error> {
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            return Err(Error::syntax(format!("duplicate variable {}", name), form));
+        }
+    }
+    Ok(())
+}
+
+impl Analyzer<'_> {
+    /// The special form named by `head`, unless a local variable shadows it.
+    fn keyword(&self, head: &Value, scope: Option<&Scope>) -> Option<&'static str> {
+        let Value::Symbol(sym) = head else {
+            return None;
+        };
+        if scope.is_some_and(|scope| scope.lookup(*sym).is_some()) {
+            return None;
+        }
+        let name = sym.name();
+        matches!(
+            name,
+            "quote"
+                | "if"
+                | "define"
+                | "set!"
+                | "lambda"
+                | "begin"
+                | "let"
+                | "let*"
+                | "letrec"
+                | "letrec*"
+                | "case-lambda"
+                | "let-values"
+                | "let*-values"
+                | "define-values"
+                | "and"
+                | "or"
+                | "when"
+                | "unless"
+                | "cond"
+                | "case"
+                | "do"
+                | "with-continuation-mark"
+                | "quasiquote"
+                | "parameterize"
+                | "guard"
+                | "endianness"
+        )
+        .then_some(name)
+    }
+
+    fn is_keyword(&self, value: &Value, keyword: &str, scope: Option<&Scope>) -> bool {
+        match value {
+            Value::Symbol(sym) => {
+                sym.name() == keyword && scope.is_none_or(|scope| scope.lookup(*sym).is_none())
+            }
+            _ => false,
+        }
+    }
+
+    fn analyze(&mut self, form: &Value, scope: Option<&Scope>) -> Result<Rc<Expr>, Error> {
+        match form {
+            Value::Symbol(sym) => Ok(self.variable(*sym, scope)),
+            Value::Pair(pair) => {
+                let head = pair.car();
+                let result =
+                    elements(&pair.cdr(), form).and_then(|args| match self.keyword(&head, scope) {
+                        Some(keyword) => self.special_form(keyword, &args, form, scope),
+                        None => {
+                            let operator = self.analyze(&head, scope)?;
+                            let operands = self.analyze_all(&args, scope)?;
+                            Ok(Rc::new(Expr::Call(operator, operands)))
+                        }
+                    });
+                result.map_err(|err| self.locate(err, form))
+            }
+            Value::Null => Err(Error::syntax("missing procedure in call", form)),
+            _ => Ok(constant(form.clone())),
+        }
+    }
+
+    /// Prefixes a syntax error with the location of the form it mentions
+    /// or, failing that, of `form`, the innermost form around it that was
+    /// read from source.
+    fn locate(&mut self, err: Error, form: &Value) -> Error {
+        let (Some(spans), false) = (self.spans, self.located) else {
+            return err;
+        };
+        let Some(condition) = err.condition().filter(|c| c.kind == ConditionKind::Syntax) else {
+            return err;
+        };
+        let span = condition
+            .irritants
+            .first()
+            .and_then(|irritant| spans.get(irritant));
+        match span.or_else(|| spans.get(form)) {
+            Some(span) => {
+                self.located = true;
+                Error::new(
+                    ConditionKind::Syntax,
+                    format!("{}: {}", span, condition.message),
+                    condition.irritants.clone(),
+                )
+            }
+            None => err,
+        }
+    }
+
+    fn analyze_all(
+        &mut self,
+        forms: &[Value],
+        scope: Option<&Scope>,
+    ) -> Result<Vec<Rc<Expr>>, Error> {
+        forms.iter().map(|form| self.analyze(form, scope)).collect()
+    }
+
+    fn variable(&mut self, name: Symbol, scope: Option<&Scope>) -> Rc<Expr> {
+        match scope.and_then(|scope| scope.lookup(name)) {
+            Some((depth, index)) => Rc::new(Expr::Local { depth, index, name }),
+            None => Rc::new(Expr::Global(self.interp.global(name))),
+        }
+    }
+
+    /// A sequence of expressions, as in the arms of `cond` or `when`.
+    fn sequence(
+        &mut self,
+        forms: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let mut exprs = self.analyze_all(forms, scope)?;
+        match exprs.len() {
+            0 => Err(Error::syntax("empty sequence", form)),
+            1 => Ok(exprs.pop().unwrap()),
+            _ => Ok(Rc::new(Expr::Sequence(exprs))),
+        }
+    }
+
+    fn special_form(
+        &mut self,
+        keyword: &str,
+        args: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        match (keyword, args) {
+            ("quote", [datum]) => Ok(constant(datum.clone())),
+            ("if", [test, then]) => Ok(Rc::new(Expr::If(
+                self.analyze(test, scope)?,
+                self.analyze(then, scope)?,
+                None,
+            ))),
+            ("if", [test, then, otherwise]) => Ok(Rc::new(Expr::If(
+                self.analyze(test, scope)?,
+                self.analyze(then, scope)?,
+                Some(self.analyze(otherwise, scope)?),
+            ))),
+            ("define", [target, ..]) => {
+                let (name, value) = self.definition(target, &args[1..], form, scope)?;
+                match scope {
+                    None => Ok(Rc::new(Expr::DefineGlobal(self.interp.global(name), value))),
+                    // Internal definitions are found and handled by `body`.
+                    Some(_) => Err(Error::syntax("definition in expression context", form)),
+                }
+            }
+            ("define-values", [formals, init]) => match scope {
+                None => self.define_values(formals, init, form, None),
+                Some(_) => Err(Error::syntax("definition in expression context", form)),
+            },
+            ("set!", [target, value]) => {
+                let name = symbol(target, form)?;
+                let value = self.analyze(value, scope)?;
+                match scope.and_then(|scope| scope.lookup(name)) {
+                    Some((depth, index)) => Ok(Rc::new(Expr::SetLocal {
+                        depth,
+                        index,
+                        value,
+                        define: false,
+                    })),
+                    None => Ok(Rc::new(Expr::SetGlobal(self.interp.global(name), value))),
+                }
+            }
+            ("lambda", [params, body @ ..]) => Ok(Rc::new(Expr::Lambda(
+                self.lambda(None, params, body, form, scope)?,
+            ))),
+            ("case-lambda", clauses @ [_, ..]) => {
+                let clauses = clauses
+                    .iter()
+                    .map(|clause| match elements(clause, form)?.as_slice() {
+                        [params, body @ ..] => self.lambda(None, params, body, form, scope),
+                        [] => Err(Error::syntax("malformed case-lambda clause", form)),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Rc::new(Expr::CaseLambda(clauses)))
+            }
+            ("begin", []) if scope.is_none() => Ok(constant(Value::Unspecified)),
+            ("begin", forms) if scope.is_none() => {
+                // Top-level definitions inside `begin` stay top-level.
+                let exprs = self.analyze_all(forms, scope)?;
+                Ok(Rc::new(Expr::Sequence(exprs)))
+            }
+            ("begin", forms) => self.sequence(forms, form, scope),
+            ("let", [Value::Symbol(name), bindings_list, body @ ..]) => {
+                let bindings = bindings(bindings_list, form)?;
+                self.named_let(*name, &bindings, body, form, scope)
+            }
+            ("let", [bindings_list, body @ ..]) => {
+                let bindings = bindings(bindings_list, form)?;
+                self.let_(&bindings, body, form, scope)
+            }
+            ("let*", [bindings_list, body @ ..]) => {
+                let bindings = bindings(bindings_list, form)?;
+                self.let_star(&bindings, body, form, scope)
+            }
+            ("letrec" | "letrec*", [bindings_list, body @ ..]) => {
+                let bindings = bindings(bindings_list, form)?;
+                self.letrec(&bindings, body, form, scope)
+            }
+            ("let-values", [bindings_list, body @ ..]) => {
+                let bindings = elements(bindings_list, form)?;
+                self.let_values(&bindings, body, form, scope)
+            }
+            ("let*-values", [bindings_list, body @ ..]) => {
+                let bindings = elements(bindings_list, form)?;
+                self.let_star_values(&bindings, body, form, scope)
+            }
+            ("and", forms) => Ok(Rc::new(Expr::And(self.analyze_all(forms, scope)?))),
+            ("or", forms) => Ok(Rc::new(Expr::Or(self.analyze_all(forms, scope)?))),
+            ("when", [test, body @ ..]) => Ok(Rc::new(Expr::If(
+                self.analyze(test, scope)?,
+                self.sequence(body, form, scope)?,
+                None,
+            ))),
+            ("unless", [test, body @ ..]) => Ok(Rc::new(Expr::If(
+                self.analyze(test, scope)?,
+                constant(Value::Unspecified),
+                Some(self.sequence(body, form, scope)?),
+            ))),
+            ("cond", clauses) => self.cond(clauses, form, scope),
+            ("case", [key, clauses @ ..]) => self.case(key, clauses, form, scope),
+            ("do", [specs, exit, commands @ ..]) => {
+                self.do_loop(specs, exit, commands, form, scope)
+            }
+            ("parameterize", [bindings_list, body @ ..]) => {
+                self.parameterize(bindings_list, body, form, scope)
+            }
+            ("guard", [spec, body @ ..]) => self.guard(spec, body, form, scope),
+            ("quasiquote", [template]) => self.quasiquote(template, 1, form, scope),
+            ("endianness", [symbol @ Value::Symbol(sym)])
+                if matches!(sym.name(), "big" | "little") =>
+            {
+                Ok(constant(symbol.clone()))
+            }
+            ("with-continuation-mark", [key, value, body]) => Ok(Rc::new(Expr::WithMark(
+                self.analyze(key, scope)?,
+                self.analyze(value, scope)?,
+                self.analyze(body, scope)?,
+            ))),
+            _ => Err(Error::syntax(format!("malformed {}", keyword), form)),
+        }
+    }
+
+    /// Lowers `parameterize` into a call that runs the body as a thunk with
+    /// the parameters set.
+    fn parameterize(
+        &mut self,
+        bindings_list: &Value,
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let thunk = self.lambda(None, &Value::Null, body, form, scope)?;
+        let mut args = vec![Rc::new(Expr::Lambda(thunk))];
+        for binding in elements(bindings_list, form)? {
+            match elements(&binding, form)?.as_slice() {
+                [parameter, value] => {
+                    args.push(self.analyze(parameter, scope)?);
+                    args.push(self.analyze(value, scope)?);
+                }
+                _ => return Err(Error::syntax("malformed binding", form)),
+            }
+        }
+        Ok(primitive_call(
+            "parameterize",
+            Arity::at_least(1),
+            builtins::parameterize,
+            args,
+        ))
+    }
+
+    /// Lowers `(guard (var clause ...) body ...)` into a call that runs the
+    /// body as a thunk and, if it raises an object, a handler that binds the
+    /// object to `var` and chooses a clause as `cond` does. Without an
+    /// `else` clause, the handler raises the object again if no clause
+    /// applies.
+    fn guard(
+        &mut self,
+        spec: &Value,
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let spec = elements(spec, form)?;
+        let Some((var, clauses)) = spec.split_first() else {
+            return Err(Error::syntax("malformed guard", form));
+        };
+        let var = symbol(var, form)?;
+        let thunk = self.lambda(None, &Value::Null, body, form, scope)?;
+        let frame = Scope {
+            names: vec![var],
+            parent: scope,
+        };
+        let mut clauses = self.cond_clauses(clauses, form, Some(&frame))?;
+        let exhaustive = clauses.last().is_some_and(
+            |clause| matches!(&*clause.test, Expr::Constant(value) if value.is_true()),
+        );
+        if !exhaustive {
+            let object = Rc::new(Expr::Local {
+                depth: 0,
+                index: 0,
+                name: var,
+            });
+            clauses.push(Clause {
+                test: constant(Value::Boolean(true)),
+                body: ClauseBody::Sequence(primitive_call(
+                    "raise",
+                    Arity::exactly(1),
+                    builtins::raise,
+                    vec![object],
+                )),
+            });
+        }
+        let handler = Lambda {
+            name: None,
+            required: 1,
+            rest: false,
+            names: vec![var],
+            body: Rc::new(Expr::Cond(clauses)),
+        };
+        Ok(primitive_call(
+            "guard",
+            Arity::exactly(2),
+            builtins::guard,
+            vec![
+                Rc::new(Expr::Lambda(thunk)),
+                Rc::new(Expr::Lambda(Rc::new(handler))),
+            ],
+        ))
+    }
+
+    /// Lowers a quasiquote template at nesting level `depth`. Only unquotes
+    /// at level 1 are evaluated; nested ones are rebuilt with their level
+    /// lowered by one.
+    fn quasiquote(
+        &mut self,
+        template: &Value,
+        depth: usize,
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        if let Some(operand) = operand_of(template, "unquote") {
+            if depth == 1 {
+                return self.analyze(&operand, scope);
+            }
+            return self.quasi_form("unquote", &operand, depth - 1, form, scope);
+        }
+        if let Some(operand) = operand_of(template, "quasiquote") {
+            return self.quasi_form("quasiquote", &operand, depth + 1, form, scope);
+        }
+        if operand_of(template, "unquote-splicing").is_some() && depth == 1 {
+            return Err(Error::syntax("unquote-splicing outside a list", form));
+        }
+        match template {
+            Value::Pair(pair) => {
+                let car = pair.car();
+                let cdr = self.quasiquote(&pair.cdr(), depth, form, scope)?;
+                match operand_of(&car, "unquote-splicing") {
+                    Some(operand) if depth == 1 => {
+                        let spliced = self.analyze(&operand, scope)?;
+                        Ok(primitive_call(
+                            "append",
+                            Arity::at_least(0),
+                            builtins::append,
+                            vec![spliced, cdr],
+                        ))
+                    }
+                    Some(operand) => {
+                        let car =
+                            self.quasi_form("unquote-splicing", &operand, depth - 1, form, scope)?;
+                        Ok(cons(car, cdr))
+                    }
+                    None => Ok(cons(self.quasiquote(&car, depth, form, scope)?, cdr)),
+                }
+            }
+            Value::Vector(items) => {
+                let items = Value::list(items.borrow().iter().cloned());
+                let items = self.quasiquote(&items, depth, form, scope)?;
+                if matches!(&*items, Expr::Constant(_)) {
+                    return Ok(constant(template.clone()));
+                }
+                Ok(primitive_call(
+                    "list->vector",
+                    Arity::exactly(1),
+                    builtins::list_to_vector,
+                    vec![items],
+                ))
+            }
+            _ => Ok(constant(template.clone())),
+        }
+    }
+
+    /// Rebuilds `(keyword operand)` with the operand lowered at `depth`.
+    fn quasi_form(
+        &mut self,
+        keyword: &str,
+        operand: &Value,
+        depth: usize,
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let operand = self.quasiquote(operand, depth, form, scope)?;
+        Ok(cons(
+            constant(Value::symbol(keyword)),
+            cons(operand, constant(Value::Null)),
+        ))
+    }
+
+    /// The name and value of `(define name value)` or
+    /// `(define (name . params) body ...)`.
+    fn definition(
+        &mut self,
+        target: &Value,
+        rest: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<(Symbol, Rc<Expr>), Error> {
+        match (target, rest) {
+            (Value::Symbol(name), [value]) => Ok((*name, self.analyze(value, scope)?)),
+            (Value::Pair(pair), body) => {
+                let name = symbol(&pair.car(), form)?;
+                let lambda = self.lambda(Some(name), &pair.cdr(), body, form, scope)?;
+                Ok((name, Rc::new(Expr::Lambda(lambda))))
+            }
+            _ => Err(Error::syntax("malformed define", form)),
+        }
+    }
+
+    fn lambda(
+        &mut self,
+        name: Option<Symbol>,
+        params: &Value,
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Lambda>, Error> {
+        let (names, arity) = formals(params, form)?;
+        check_distinct(&names, form)?;
+        let (names, body) = self.body(names, Vec::new(), body, form, scope)?;
+        Ok(Rc::new(Lambda {
+            name,
+            required: arity.min,
+            rest: arity.max.is_none(),
+            names,
+            body,
+        }))
+    }
+
+    /// Analyzes a body in a new frame whose first slots are `names`. The
+    /// body's internal definitions get the following slots. `inits` are
+    /// evaluated, in the new frame, before the body; `letrec` uses them to
+    /// initialize its variables. Returns the frame's variables and the body.
+    fn body(
+        &mut self,
+        mut names: Vec<Symbol>,
+        inits: Vec<(Symbol, Value)>,
+        forms: &[Value],
+        form: &Value,
+        parent: Option<&Scope>,
+    ) -> Result<(Vec<Symbol>, Rc<Expr>), Error> {
+        let outer = Scope {
+            names: names.clone(),
+            parent,
+        };
+        let forms = self.flatten_body(forms, Some(&outer));
+        for body_form in &forms {
+            let defined = match self.definition_target(body_form, Some(&outer)) {
+                Some(Value::Pair(pair)) => vec![symbol(&pair.car(), form)?],
+                Some(target) => vec![symbol(&target, form)?],
+                None => match self.values_definition_target(body_form, Some(&outer)) {
+                    Some(target) => formals(&target, form)?.0,
+                    None => Vec::new(),
+                },
+            };
+            for name in defined {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        let scope = Scope { names, parent };
+        let mut exprs = Vec::new();
+        for (name, init) in &inits {
+            let (depth, index) = scope.lookup(*name).unwrap();
+            exprs.push(Rc::new(Expr::SetLocal {
+                depth,
+                index,
+                value: self.analyze(init, Some(&scope))?,
+                define: true,
+            }));
+        }
+        let mut has_expr = false;
+        for body_form in &forms {
+            if self.definition_target(body_form, Some(&outer)).is_some() {
+                let args = elements(body_form, form)?;
+                let (name, value) =
+                    self.definition(&args[1], &args[2..], body_form, Some(&scope))?;
+                let (depth, index) = scope.lookup(name).unwrap();
+                exprs.push(Rc::new(Expr::SetLocal {
+                    depth,
+                    index,
+                    value,
+                    define: true,
+                }));
+            } else if let Some(target) = self.values_definition_target(body_form, Some(&outer)) {
+                let args = elements(body_form, form)?;
+                let [_, _, init] = args.as_slice() else {
+                    return Err(Error::syntax("malformed define-values", body_form));
+                };
+                exprs.push(self.define_values(&target, init, body_form, Some(&scope))?);
+            } else {
+                has_expr = true;
+                exprs.push(self.analyze(body_form, Some(&scope))?);
+            }
+        }
+        if !has_expr {
+            return Err(Error::syntax("body has no expressions", form));
+        }
+        let body = if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Rc::new(Expr::Sequence(exprs))
+        };
+        Ok((scope.names, body))
+    }
+
+    /// Splices `(begin ...)` forms at the top of a body into the body.
+    fn flatten_body(&self, forms: &[Value], scope: Option<&Scope>) -> Vec<Value> {
+        let mut flat = Vec::new();
+        for form in forms {
+            if let Value::Pair(pair) = form {
+                if self.is_keyword(&pair.car(), "begin", scope) {
+                    if let Some(inner) = pair.cdr().list_to_vec() {
+                        flat.extend(self.flatten_body(&inner, scope));
+                        continue;
+                    }
+                }
+            }
+            flat.push(form.clone());
+        }
+        flat
+    }
+
+    /// The second element of a `define` form.
+    fn definition_target(&self, form: &Value, scope: Option<&Scope>) -> Option<Value> {
+        let pair = form.as_pair()?;
+        if !self.is_keyword(&pair.car(), "define", scope) {
+            return None;
+        }
+        Some(pair.cdr().as_pair()?.car())
+    }
+
+    /// The second element of a `define-values` form.
+    fn values_definition_target(&self, form: &Value, scope: Option<&Scope>) -> Option<Value> {
+        let pair = form.as_pair()?;
+        if !self.is_keyword(&pair.car(), "define-values", scope) {
+            return None;
+        }
+        Some(pair.cdr().as_pair()?.car())
+    }
+
+    /// Lowers `(define-values formals init)` into a `let-values` binding
+    /// the formals, whose body defines each variable of the same name: a
+    /// global at top level, or a slot of the body frame `scope`.
+    fn define_values(
+        &mut self,
+        target: &Value,
+        init: &Value,
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let (names, arity) = formals(target, form)?;
+        check_distinct(&names, form)?;
+        let init = self.analyze(init, scope)?;
+        let mut exprs = Vec::new();
+        for (index, name) in names.iter().enumerate() {
+            let value = Rc::new(Expr::Local {
+                depth: 0,
+                index,
+                name: *name,
+            });
+            exprs.push(match scope.and_then(|scope| scope.lookup(*name)) {
+                Some((depth, index)) => Rc::new(Expr::SetLocal {
+                    depth: depth + 1,
+                    index,
+                    value,
+                    define: true,
+                }),
+                None => Rc::new(Expr::DefineGlobal(self.interp.global(*name), value)),
+            });
+        }
+        exprs.push(constant(Value::Unspecified));
+        let lambda = Lambda {
+            name: None,
+            required: names.len(),
+            rest: false,
+            names,
+            body: Rc::new(Expr::Sequence(exprs)),
+        };
+        Ok(Rc::new(Expr::LetValues(
+            Rc::new(lambda),
+            vec![(init, arity)],
+        )))
+    }
+
+    fn let_(
+        &mut self,
+        bindings: &[(Symbol, Value)],
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let names: Vec<_> = bindings.iter().map(|(name, _)| *name).collect();
+        check_distinct(&names, form)?;
+        let inits: Vec<_> = bindings.iter().map(|(_, init)| init.clone()).collect();
+        let inits = self.analyze_all(&inits, scope)?;
+        let (names, body) = self.body(names, Vec::new(), body, form, scope)?;
+        let lambda = Lambda {
+            name: None,
+            required: bindings.len(),
+            rest: false,
+            names,
+            body,
+        };
+        Ok(Rc::new(Expr::Let(Rc::new(lambda), inits)))
+    }
+
+    fn let_star(
+        &mut self,
+        bindings: &[(Symbol, Value)],
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let [(name, init), rest @ ..] = bindings else {
+            return self.let_(bindings, body, form, scope);
+        };
+        if rest.is_empty() {
+            return self.let_(bindings, body, form, scope);
+        }
+        let init = self.analyze(init, scope)?;
+        let inner = Scope {
+            names: vec![*name],
+            parent: scope,
+        };
+        let lambda = Lambda {
+            name: None,
+            required: 1,
+            rest: false,
+            names: vec![*name],
+            body: self.let_star(rest, body, form, Some(&inner))?,
+        };
+        Ok(Rc::new(Expr::Let(Rc::new(lambda), vec![init])))
+    }
+
+    /// The variables of `let-values` bindings, in order, and their
+    /// initializers with the number of values each accepts.
+    #[allow(clippy::type_complexity)]
+    fn values_bindings(
+        &mut self,
+        bindings: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<(Vec<Symbol>, Vec<(Rc<Expr>, Arity)>), Error> {
+        let mut names = Vec::new();
+        let mut inits = Vec::new();
+        for binding in bindings {
+            let parts = elements(binding, form)?;
+            let [target, init] = parts.as_slice() else {
+                return Err(Error::syntax("malformed binding", form));
+            };
+            let (vars, arity) = formals(target, form)?;
+            names.extend(vars);
+            inits.push((self.analyze(init, scope)?, arity));
+        }
+        check_distinct(&names, form)?;
+        Ok((names, inits))
+    }
+
+    fn let_values(
+        &mut self,
+        bindings: &[Value],
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let (names, inits) = self.values_bindings(bindings, form, scope)?;
+        let required = names.len();
+        let (names, body) = self.body(names, Vec::new(), body, form, scope)?;
+        let lambda = Lambda {
+            name: None,
+            required,
+            rest: false,
+            names,
+            body,
+        };
+        Ok(Rc::new(Expr::LetValues(Rc::new(lambda), inits)))
+    }
+
+    fn let_star_values(
+        &mut self,
+        bindings: &[Value],
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let [first, rest @ ..] = bindings else {
+            return self.let_values(bindings, body, form, scope);
+        };
+        if rest.is_empty() {
+            return self.let_values(bindings, body, form, scope);
+        }
+        let (names, inits) = self.values_bindings(std::slice::from_ref(first), form, scope)?;
+        let inner = Scope {
+            names: names.clone(),
+            parent: scope,
+        };
+        let lambda = Lambda {
+            name: None,
+            required: names.len(),
+            rest: false,
+            names,
+            body: self.let_star_values(rest, body, form, Some(&inner))?,
+        };
+        Ok(Rc::new(Expr::LetValues(Rc::new(lambda), inits)))
+    }
+
+    fn letrec(
+        &mut self,
+        bindings: &[(Symbol, Value)],
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let names: Vec<_> = bindings.iter().map(|(name, _)| *name).collect();
+        check_distinct(&names, form)?;
+        let (names, body) = self.body(names, bindings.to_vec(), body, form, scope)?;
+        let lambda = Lambda {
+            name: None,
+            required: 0,
+            rest: false,
+            names,
+            body,
+        };
+        Ok(Rc::new(Expr::Let(Rc::new(lambda), Vec::new())))
+    }
+
+    /// Builds a frame holding only `name`, bound to the procedure made by
+    /// `procedure`, and calls that procedure with `args`. This is how named
+    /// `let` and `do` loops refer to themselves.
+    fn loop_call(
+        &mut self,
+        name: Symbol,
+        args: Vec<Rc<Expr>>,
+        scope: Option<&Scope>,
+        procedure: impl FnOnce(&mut Self, &Scope) -> Result<Rc<Lambda>, Error>,
+    ) -> Result<Rc<Expr>, Error> {
+        let frame = Scope {
+            names: vec![name],
+            parent: scope,
+        };
+        let lambda = procedure(self, &frame)?;
+        let reference = Rc::new(Expr::Local {
+            depth: 0,
+            index: 0,
+            name,
+        });
+        let init = Rc::new(Expr::SetLocal {
+            depth: 0,
+            index: 0,
+            value: Rc::new(Expr::Lambda(lambda)),
+            define: true,
+        });
+        let outer = Lambda {
+            name: None,
+            required: 0,
+            rest: false,
+            names: vec![name],
+            body: Rc::new(Expr::Sequence(vec![init, reference])),
+        };
+        let procedure = Rc::new(Expr::Let(Rc::new(outer), Vec::new()));
+        Ok(Rc::new(Expr::Call(procedure, args)))
+    }
+
+    fn named_let(
+        &mut self,
+        name: Symbol,
+        bindings: &[(Symbol, Value)],
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let inits: Vec<_> = bindings.iter().map(|(_, init)| init.clone()).collect();
+        let inits = self.analyze_all(&inits, scope)?;
+        let params = Value::list(bindings.iter().map(|(name, _)| Value::Symbol(*name)));
+        self.loop_call(name, inits, scope, |this, frame| {
+            this.lambda(Some(name), &params, body, form, Some(frame))
+        })
+    }
+
+    fn cond(
+        &mut self,
+        clauses: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        Ok(Rc::new(Expr::Cond(
+            self.cond_clauses(clauses, form, scope)?,
+        )))
+    }
+
+    fn cond_clauses(
+        &mut self,
+        clauses: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Vec<Clause>, Error> {
+        let mut analyzed = Vec::new();
+        for (i, clause) in clauses.iter().enumerate() {
+            let parts = elements(clause, form)?;
+            let Some((test, body)) = parts.split_first() else {
+                return Err(Error::syntax("empty cond clause", form));
+            };
+            let test = if self.is_keyword(test, "else", scope) {
+                if i + 1 != clauses.len() {
+                    return Err(Error::syntax("else clause is not last", form));
+                }
+                constant(Value::Boolean(true))
+            } else {
+                self.analyze(test, scope)?
+            };
+            let body = self.clause_body(body, form, scope)?;
+            analyzed.push(Clause { test, body });
+        }
+        Ok(analyzed)
+    }
+
+    fn clause_body(
+        &mut self,
+        body: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<ClauseBody, Error> {
+        match body {
+            [] => Ok(ClauseBody::Test),
+            [arrow, receiver] if self.is_keyword(arrow, "=>", scope) => {
+                Ok(ClauseBody::Arrow(self.analyze(receiver, scope)?))
+            }
+            body => Ok(ClauseBody::Sequence(self.sequence(body, form, scope)?)),
+        }
+    }
+
+    fn case(
+        &mut self,
+        key: &Value,
+        clauses: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let key = self.analyze(key, scope)?;
+        let mut analyzed = Vec::new();
+        let mut otherwise = None;
+        for (i, clause) in clauses.iter().enumerate() {
+            let parts = elements(clause, form)?;
+            let [data, body @ ..] = parts.as_slice() else {
+                return Err(Error::syntax("empty case clause", form));
+            };
+            if body.is_empty() {
+                return Err(Error::syntax("case clause has no body", form));
+            }
+            let body = self.clause_body(body, form, scope)?;
+            if self.is_keyword(data, "else", scope) {
+                if i + 1 != clauses.len() {
+                    return Err(Error::syntax("else clause is not last", form));
+                }
+                otherwise = Some(body);
+            } else {
+                analyzed.push((elements(data, form)?, body));
+            }
+        }
+        Ok(Rc::new(Expr::Case(key, analyzed, otherwise)))
+    }
+
+    /// `(do ((var init step) ...) (test result ...) command ...)` becomes a
+    /// loop procedure that is called with the initial values.
+    fn do_loop(
+        &mut self,
+        specs: &Value,
+        exit: &Value,
+        commands: &[Value],
+        form: &Value,
+        scope: Option<&Scope>,
+    ) -> Result<Rc<Expr>, Error> {
+        let mut names = Vec::new();
+        let mut inits = Vec::new();
+        let mut steps = Vec::new();
+        for spec in elements(specs, form)? {
+            match elements(&spec, form)?.as_slice() {
+                [name, init] => {
+                    names.push(symbol(name, form)?);
+                    inits.push(init.clone());
+                    steps.push(name.clone());
+                }
+                [name, init, step] => {
+                    names.push(symbol(name, form)?);
+                    inits.push(init.clone());
+                    steps.push(step.clone());
+                }
+                _ => return Err(Error::syntax("malformed do binding", form)),
+            }
+        }
+        check_distinct(&names, form)?;
+        let exit = elements(exit, form)?;
+        let Some((test, results)) = exit.split_first() else {
+            return Err(Error::syntax("do has no exit test", form));
+        };
+        let inits = self.analyze_all(&inits, scope)?;
+        // The loop variable's name cannot be written without `|...|`, so it
+        // does not capture anything in the user's code.
+        let name = Symbol::intern("do loop");
+        self.loop_call(name, inits, scope, |this, frame| {
+            let vars = Scope {
+                names: names.clone(),
+                parent: Some(frame),
+            };
+            let test = this.analyze(test, Some(&vars))?;
+            let result = if results.is_empty() {
+                constant(Value::Unspecified)
+            } else {
+                this.sequence(results, form, Some(&vars))?
+            };
+            let mut iteration = this.analyze_all(commands, Some(&vars))?;
+            let recur = Rc::new(Expr::Local {
+                depth: 1,
+                index: 0,
+                name,
+            });
+            iteration.push(Rc::new(Expr::Call(
+                recur,
+                this.analyze_all(&steps, Some(&vars))?,
+            )));
+            Ok(Rc::new(Lambda {
+                name: None,
+                required: names.len(),
+                rest: false,
+                names: names.clone(),
+                body: Rc::new(Expr::If(
+                    test,
+                    result,
+                    Some(Rc::new(Expr::Sequence(iteration))),
+                )),
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::ConditionKind;
+    use crate::Scheme;
+
+    #[test]
+    fn quasiquote_splices_into_lists_and_vectors() {
+        let mut scheme = Scheme::new();
+        scheme.eval_str("(define x 1) (define xs '(2 3))").unwrap();
+        for (text, expected) in [
+            ("`(a ,x ,@xs b)", "(a 1 2 3 b)"),
+            ("`(,@xs . ,x)", "(2 3 . 1)"),
+            ("`(a . ,xs)", "(a 2 3)"),
+            ("`#(a ,x ,@xs)", "#(a 1 2 3)"),
+            ("`(,@'() . tail)", "tail"),
+            ("`(1 ,@xs)", "(1 2 3)"),
+            ("(quasiquote (unquote x))", "1"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn nested_quasiquotes_keep_inner_unquotes() {
+        let mut scheme = Scheme::new();
+        scheme.eval_str("(define x 1) (define name 'y)").unwrap();
+        for (text, expected) in [
+            ("`(a `(b ,(c ,x)))", "(a (quasiquote (b (unquote (c 1)))))"),
+            ("`(a `(b ,,name))", "(a (quasiquote (b (unquote y))))"),
+            ("``,,x", "(quasiquote (unquote 1))"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        assert!(scheme.eval_str("`(a ,@x)").is_err());
+    }
+
+    #[test]
+    fn rest_parameters_collect_the_remaining_arguments() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("((lambda args args))", "()"),
+            ("((lambda args args) 1 2)", "(1 2)"),
+            ("((lambda (a . rest) (list a rest)) 1)", "(1 ())"),
+            (
+                "((lambda (a b . rest) (list a b rest)) 1 2 3 4)",
+                "(1 2 (3 4))",
+            ),
+            ("(define (f . xs) (length xs)) (f 1 2 3)", "3"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        let err = scheme.eval_str("((lambda (a . rest) a))").unwrap_err();
+        assert_eq!(
+            err.condition().map(|c| c.kind),
+            Some(ConditionKind::WrongArity)
+        );
+        assert!(scheme.eval_str("(lambda (a . 1) a)").is_err());
+    }
+
+    #[test]
+    fn case_lambda_runs_the_first_matching_clause() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str(
+                "(define f
+                   (case-lambda
+                     ((a) (list 'one a))
+                     ((a b) (list 'two a b))
+                     ((a . rest) (list 'many a rest))))
+                 (define count
+                   (case-lambda
+                     ((n) (count n 0))
+                     ((n acc) (if (= n 0) acc (count (- n 1) (+ acc 1))))))",
+            )
+            .unwrap();
+        for (text, expected) in [
+            ("(f 1)", "(one 1)"),
+            ("(f 1 2)", "(two 1 2)"),
+            ("(f 1 2 3)", "(many 1 (2 3))"),
+            ("(apply f '(4 5))", "(two 4 5)"),
+            ("(count 100000)", "100000"),
+            ("(let ((x 1)) ((case-lambda (() x) ((y) y))))", "1"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        for text in ["(f)", "((case-lambda ((a) a) ((a b c) c)) 1 2)"] {
+            let err = scheme.eval_str(text).unwrap_err();
+            assert_eq!(
+                err.condition().map(|c| c.kind),
+                Some(ConditionKind::WrongArity),
+                "{}",
+                text
+            );
+        }
+        assert!(scheme.eval_str("(case-lambda)").is_err());
+    }
+
+    #[test]
+    fn binding_forms_scope_their_variables() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(let ((x 1)) (let ((x 2) (y x)) (list x y)))", "(2 1)"),
+            ("(let* ((x 1) (y (+ x 1))) (list x y))", "(1 2)"),
+            ("(let* () 5)", "5"),
+            ("(letrec ((even? (lambda (n) (if (= n 0) #t (odd? (- n 1))))) (odd? (lambda (n) (if (= n 0) #f (even? (- n 1)))))) (even? 1000))", "#t"),
+            ("(letrec* ((a 1) (b (+ a 1))) b)", "2"),
+            ("(let () (define (g) y) (define y 1) (g))", "1"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        for text in [
+            "(letrec ((a b) (b 1)) a)",
+            "(letrec* ((a b) (b 1)) a)",
+            "(let () (define x y) (define y 1) x)",
+        ] {
+            let err = scheme.eval_str(text).unwrap_err();
+            assert_eq!(
+                err.condition().map(|c| c.kind),
+                Some(ConditionKind::UnboundVariable),
+                "{}",
+                text
+            );
+        }
+        assert!(scheme.eval_str("(let ((x 1) (x 2)) x)").is_err());
+    }
+
+    #[test]
+    fn loops_run_in_constant_space() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            ("(let loop ((i 0)) (if (< i 100000) (loop (+ i 1)) i))", "100000"),
+            ("(let loop ((i 0) (acc '())) (if (= i 3) acc (loop (+ i 1) (cons i acc))))", "(2 1 0)"),
+            ("(do ((i 0 (+ i 1)) (acc '() (cons i acc))) ((= i 3) acc))", "(2 1 0)"),
+            ("(do ((i 0 (+ i 1))) ((= i 100000) i))", "100000"),
+            ("(let ((v (make-vector 3 0))) (do ((i 0 (+ i 1))) ((= i 3) v) (vector-set! v i i)))", "#(0 1 2)"),
+            ("(let ((loop 1)) (let loop ((i loop)) i))", "1"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn let_values_binds_each_value() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            (
+                "(let-values (((a b) (values 1 2)) ((c) (values 3))) (list a b c))",
+                "(1 2 3)",
+            ),
+            (
+                "(let-values (((a . rest) (values 1 2 3)) (all (values))) (list a rest all))",
+                "(1 (2 3) ())",
+            ),
+            (
+                "(let ((a 1)) (let-values (((a b) (values 2 a))) (list a b)))",
+                "(2 1)",
+            ),
+            (
+                "(let ((a 1)) (let*-values (((a b) (values 2 a)) ((c) (values a))) (list a b c)))",
+                "(2 1 2)",
+            ),
+            ("(let-values () 1)", "1"),
+            (
+                "(let loop ((i 0))
+                   (if (= i 100000) i (let-values (((j) (values (+ i 1)))) (loop j))))",
+                "100000",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        let err = scheme
+            .eval_str("(let-values (((a b) (values 1 2 3))) a)")
+            .unwrap_err();
+        assert_eq!(
+            err.condition().map(|c| c.kind),
+            Some(ConditionKind::WrongArity)
+        );
+        assert!(scheme
+            .eval_str("(let-values (((a a) (values 1 2))) a)")
+            .is_err());
+    }
+
+    #[test]
+    fn define_values_defines_each_variable() {
+        let mut scheme = Scheme::new();
+        scheme
+            .eval_str("(define-values (q r) (floor/ 7 2))")
+            .unwrap();
+        for (text, expected) in [
+            ("(list q r)", "(3 1)"),
+            (
+                "(define-values (x . xs) (values 1 2 3)) (list x xs)",
+                "(1 (2 3))",
+            ),
+            (
+                "(define (f)
+                   (define-values (a b) (values 1 2))
+                   (define c (+ a b))
+                   (list a b c))
+                 (f)",
+                "(1 2 3)",
+            ),
+            (
+                "(let () (define (g) (* h 2)) (define-values (h) (values 21)) (g))",
+                "42",
+            ),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+        assert!(scheme
+            .eval_str("(lambda () (if #t (define-values (a) (values 1)) #f))")
+            .is_err());
+    }
+}
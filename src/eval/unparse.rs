@@ -0,0 +1,210 @@
+//! Turns `Expr` trees back into data, for the REPL's `,expand`.
+//!
+//! The result shows what the analyzer lowered a form into: named `let` and
+//! `do` become loop procedures bound in a frame of their own, `letrec` and
+//! internal definitions become `define`s at the top of a `let ()` body, and
+//! `quasiquote` becomes calls to `cons`, `append` and `list->vector`.
+
+use super::analyze::{ClauseBody, Expr, Lambda};
+use crate::proc::Procedure;
+use crate::symbol::Symbol;
+use crate::value::Value;
+
+pub(crate) fn unparse(expr: &Expr) -> Value {
+    Unparser { frames: Vec::new() }.expr(expr)
+}
+
+struct Unparser<'a> {
+    /// The variables of the frames around the expression, innermost last.
+    frames: Vec<&'a [Symbol]>,
+}
+
+fn form(keyword: &str, parts: impl IntoIterator<Item = Value>) -> Value {
+    Value::list(std::iter::once(Value::symbol(keyword)).chain(parts))
+}
+
+impl<'a> Unparser<'a> {
+    fn expr(&mut self, expr: &'a Expr) -> Value {
+        match expr {
+            Expr::Constant(value) => constant(value),
+            Expr::Local { name, .. } => Value::Symbol(*name),
+            Expr::Global(global) => Value::Symbol(global.name),
+            Expr::SetLocal {
+                depth,
+                index,
+                value,
+                define,
+            } => {
+                let frame = self.frames[self.frames.len() - 1 - depth];
+                let keyword = if *define { "define" } else { "set!" };
+                form(keyword, [Value::Symbol(frame[*index]), self.expr(value)])
+            }
+            Expr::SetGlobal(global, value) => {
+                form("set!", [Value::Symbol(global.name), self.expr(value)])
+            }
+            Expr::DefineGlobal(global, value) => {
+                form("define", [Value::Symbol(global.name), self.expr(value)])
+            }
+            Expr::If(test, then, otherwise) => {
+                let mut parts = vec![self.expr(test), self.expr(then)];
+                parts.extend(otherwise.iter().map(|otherwise| self.expr(otherwise)));
+                form("if", parts)
+            }
+            Expr::Lambda(lambda) => form("lambda", self.clause(lambda)),
+            Expr::CaseLambda(clauses) => {
+                let clauses: Vec<_> = clauses
+                    .iter()
+                    .map(|lambda| Value::list(self.clause(lambda)))
+                    .collect();
+                form("case-lambda", clauses)
+            }
+            Expr::Sequence(exprs) => form("begin", self.all(exprs)),
+            Expr::And(exprs) => form("and", self.all(exprs)),
+            Expr::Or(exprs) => form("or", self.all(exprs)),
+            Expr::Cond(clauses) => {
+                let clauses: Vec<_> = clauses
+                    .iter()
+                    .map(|clause| {
+                        let test = self.expr(&clause.test);
+                        Value::list(std::iter::once(test).chain(self.clause_body(&clause.body)))
+                    })
+                    .collect();
+                form("cond", clauses)
+            }
+            Expr::Case(key, clauses, otherwise) => {
+                let mut parts = vec![self.expr(key)];
+                for (data, body) in clauses {
+                    let data = Value::list(data.iter().cloned());
+                    parts.push(Value::list(
+                        std::iter::once(data).chain(self.clause_body(body)),
+                    ));
+                }
+                if let Some(body) = otherwise {
+                    let body = self.clause_body(body);
+                    parts.push(form("else", body));
+                }
+                form("case", parts)
+            }
+            Expr::Let(lambda, inits) => {
+                let inits = self.all(inits);
+                let bindings = lambda
+                    .names
+                    .iter()
+                    .zip(inits)
+                    .map(|(name, init)| Value::list([Value::Symbol(*name), init]));
+                let bindings = Value::list(bindings);
+                let body = self.body(lambda);
+                form("let", std::iter::once(bindings).chain(body))
+            }
+            Expr::LetValues(lambda, inits) => {
+                let mut names = lambda.names.iter().map(|name| Value::Symbol(*name));
+                let bindings: Vec<_> = inits
+                    .iter()
+                    .map(|(init, arity)| {
+                        let required: Vec<_> = names.by_ref().take(arity.min).collect();
+                        let formals = match arity.max {
+                            Some(_) => Value::list(required),
+                            None => Value::list_with_tail(required, names.next().unwrap()),
+                        };
+                        Value::list([formals, self.expr(init)])
+                    })
+                    .collect();
+                let body = self.body(lambda);
+                form(
+                    "let-values",
+                    std::iter::once(Value::list(bindings)).chain(body),
+                )
+            }
+            Expr::Call(operator, operands) => {
+                let operator = self.expr(operator);
+                Value::list(std::iter::once(operator).chain(self.all(operands)))
+            }
+            Expr::WithMark(key, value, body) => form(
+                "with-continuation-mark",
+                [self.expr(key), self.expr(value), self.expr(body)],
+            ),
+        }
+    }
+
+    /// The parameter list of `lambda` followed by its body.
+    fn clause(&mut self, lambda: &'a Lambda) -> Vec<Value> {
+        let names = &lambda.names;
+        let params = if lambda.rest {
+            let rest = Value::Symbol(names[lambda.required]);
+            Value::list_with_tail(symbols(&names[..lambda.required]), rest)
+        } else {
+            Value::list(symbols(&names[..lambda.required]))
+        };
+        std::iter::once(params).chain(self.body(lambda)).collect()
+    }
+
+    fn all(&mut self, exprs: &'a [std::rc::Rc<Expr>]) -> Vec<Value> {
+        exprs.iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    /// The forms of a body in the frame of `lambda`, with a sequence spliced
+    /// in.
+    fn body(&mut self, lambda: &'a Lambda) -> Vec<Value> {
+        self.frames.push(&lambda.names);
+        let body = match &*lambda.body {
+            Expr::Sequence(exprs) => self.all(exprs),
+            body => vec![self.expr(body)],
+        };
+        self.frames.pop();
+        body
+    }
+
+    fn clause_body(&mut self, body: &'a ClauseBody) -> Vec<Value> {
+        match body {
+            ClauseBody::Test => Vec::new(),
+            ClauseBody::Sequence(expr) => match &**expr {
+                Expr::Sequence(exprs) => self.all(exprs),
+                expr => vec![self.expr(expr)],
+            },
+            ClauseBody::Arrow(receiver) => vec![Value::symbol("=>"), self.expr(receiver)],
+        }
+    }
+}
+
+fn symbols(names: &[Symbol]) -> Vec<Value> {
+    names.iter().map(|name| Value::Symbol(*name)).collect()
+}
+
+/// A constant as an expression: data that would otherwise be evaluated are
+/// quoted, and the procedures the analyzer calls directly are shown by name.
+fn constant(value: &Value) -> Value {
+    match value {
+        Value::Symbol(_) | Value::Pair(_) | Value::Null => form("quote", [value.clone()]),
+        Value::Procedure(procedure) => match &**procedure {
+            Procedure::Primitive(primitive) => Value::symbol(primitive.name),
+            _ => value.clone(),
+        },
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+    use crate::Scheme;
+
+    #[test]
+    fn expand_shows_lowered_forms() {
+        let mut scheme = Scheme::new();
+        for (text, expected) in [
+            (
+                "(let loop ((i 0)) (loop (+ i 1)))",
+                "((let () (define loop (lambda (i) (loop (+ i 1)))) loop) 0)",
+            ),
+            (
+                "`(a ,b ,@c)",
+                "(cons (quote a) (cons b (append c (quote ()))))",
+            ),
+            ("(let* ((a 1) (b a)) b)", "(let ((a 1)) (let ((b a)) b))"),
+        ] {
+            let form = parse::parse(text).unwrap().remove(0);
+            let expanded = scheme.interpreter().expand(&form).unwrap();
+            assert_eq!(expanded.to_string(), expected);
+        }
+    }
+}
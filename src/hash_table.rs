@@ -0,0 +1,103 @@
+//! Hash tables, as in SRFI 69.
+//!
+//! A table only stores entries under the hashes it is given: computing
+//! hashes and comparing keys may call Scheme procedures, so that is left to
+//! the `hash-table` procedures, which do it without holding a borrow of the
+//! table. Like other values, entries are reference counted, so a table that
+//! ends up among its own keys or values is never freed.
+
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+/// Buckets keyed by the hashes of their keys. The hashes are hashed again
+/// with fixed keys, so that tables list their entries in the same order on
+/// every run.
+type Buckets = HashMap<u64, Vec<(Value, Value)>, BuildHasherDefault<DefaultHasher>>;
+
+pub struct HashTable {
+    /// The procedure that decides whether two keys are the same.
+    pub equivalence: Value,
+    /// The procedure that hashes keys, consistently with `equivalence`.
+    pub hash: Value,
+    buckets: RefCell<Buckets>,
+}
+
+impl HashTable {
+    pub fn new(equivalence: Value, hash: Value) -> Self {
+        Self {
+            equivalence,
+            hash,
+            buckets: RefCell::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.borrow().values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.borrow().is_empty()
+    }
+
+    /// The keys stored under `hash`, for the caller to compare with the key
+    /// it is looking for.
+    pub fn candidates(&self, hash: u64) -> Vec<Value> {
+        match self.buckets.borrow().get(&hash) {
+            Some(bucket) => bucket.iter().map(|(key, _)| key.clone()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The value of the entry whose key is `key` itself, one of the
+    /// [`candidates`](Self::candidates) for `hash`.
+    pub fn get(&self, hash: u64, key: &Value) -> Option<Value> {
+        let buckets = self.buckets.borrow();
+        let bucket = buckets.get(&hash)?;
+        let (_, value) = bucket.iter().find(|(stored, _)| stored.is_eq(key))?;
+        Some(value.clone())
+    }
+
+    /// Sets the value of the entry whose key is `key` itself, adding one if
+    /// there is none.
+    pub fn insert(&self, hash: u64, key: Value, value: Value) {
+        let mut buckets = self.buckets.borrow_mut();
+        let bucket = buckets.entry(hash).or_default();
+        match bucket.iter_mut().find(|(stored, _)| stored.is_eq(&key)) {
+            Some(entry) => entry.1 = value,
+            None => bucket.push((key, value)),
+        }
+    }
+
+    /// Removes the entry whose key is `key` itself, if there is one.
+    pub fn remove(&self, hash: u64, key: &Value) {
+        let mut buckets = self.buckets.borrow_mut();
+        if let Some(bucket) = buckets.get_mut(&hash) {
+            bucket.retain(|(stored, _)| !stored.is_eq(key));
+            if bucket.is_empty() {
+                buckets.remove(&hash);
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        self.buckets.borrow_mut().clear();
+    }
+
+    /// The entries as they are now, so that walking them can change the
+    /// table.
+    pub fn entries(&self) -> Vec<(Value, Value)> {
+        self.buckets.borrow().values().flatten().cloned().collect()
+    }
+
+    /// A table with the same procedures and entries.
+    pub fn copy(&self) -> Self {
+        Self {
+            equivalence: self.equivalence.clone(),
+            hash: self.hash.clone(),
+            buckets: RefCell::new(self.buckets.borrow().clone()),
+        }
+    }
+}
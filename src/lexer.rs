@@ -0,0 +1,411 @@
+//! Tokenizer for the R7RS datum syntax.
+
+use crate::chars;
+use crate::num::Number;
+use crate::parse::{ParseError, ParseErrorKind};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A source of characters with one character of lookahead.
+pub trait CharSource {
+    fn peek(&mut self) -> Option<char>;
+
+    fn next(&mut self) -> Option<char>;
+}
+
+pub struct StrSource<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> StrSource<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+        }
+    }
+}
+
+impl CharSource for StrSource<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Position {
+    /// Byte offset from the start of the input.
+    pub offset: usize,
+    /// One-based line number.
+    pub line: u32,
+    /// One-based column, counted in characters.
+    pub column: u32,
+}
+
+#[derive(Clone, Debug)]
+pub enum Token {
+    LeftParen,
+    RightParen,
+    VectorOpen,
+    BytevectorOpen,
+    Quote,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+    Dot,
+    DatumComment,
+    LabelDefine(u64),
+    LabelRef(u64),
+    Boolean(bool),
+    Character(char),
+    String(String),
+    Number(Number),
+    Identifier(String),
+}
+
+pub struct Lexer<S> {
+    source: S,
+    position: Position,
+    fold_case: bool,
+}
+
+impl<S: CharSource> Lexer<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            position: Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+            fold_case: false,
+        }
+    }
+
+    /// The position of the next character to be read.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn into_source(self) -> S {
+        self.source
+    }
+
+    /// Returns the next token and the position it starts at, or `None` at
+    /// the end of input.
+    pub fn next_token(&mut self) -> Result<Option<(Token, Position)>, ParseError> {
+        self.skip_atmosphere()?;
+        let start = self.position;
+        let Some(c) = self.advance() else {
+            return Ok(None);
+        };
+        let token = match c {
+            '(' | '[' => Token::LeftParen,
+            ')' | ']' => Token::RightParen,
+            '\'' => Token::Quote,
+            '`' => Token::Quasiquote,
+            ',' if self.source.peek() == Some('@') => {
+                self.advance();
+                Token::UnquoteSplicing
+            }
+            ',' => Token::Unquote,
+            '"' => Token::String(self.string(start)?),
+            '|' => Token::Identifier(self.pipe_identifier(start)?),
+            '#' => match self.hash(start)? {
+                Some(token) => token,
+                // A block comment or a directive such as #!fold-case.
+                None => return self.next_token(),
+            },
+            _ => self.atom(c),
+        };
+        Ok(Some((token, start)))
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.source.next()?;
+        self.position.offset += c.len_utf8();
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, kind: ParseErrorKind, position: Position) -> ParseError {
+        ParseError { kind, position }
+    }
+
+    fn eof(&self) -> ParseError {
+        self.error(ParseErrorKind::UnexpectedEof, self.position)
+    }
+
+    /// Skips whitespace, line comments and nested block comments.
+    fn skip_atmosphere(&mut self) -> Result<(), ParseError> {
+        while let Some(c) = self.source.peek() {
+            if c.is_whitespace() {
+                self.advance();
+            } else if c == ';' {
+                while !matches!(self.advance(), None | Some('\n')) {}
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn block_comment(&mut self) -> Result<(), ParseError> {
+        let mut depth = 1;
+        let mut prev = None;
+        while depth > 0 {
+            let c = self.advance().ok_or_else(|| self.eof())?;
+            match (prev, c) {
+                (Some('|'), '#') => {
+                    depth -= 1;
+                    prev = None;
+                }
+                (Some('#'), '|') => {
+                    depth += 1;
+                    prev = None;
+                }
+                _ => prev = Some(c),
+            }
+        }
+        Ok(())
+    }
+
+    fn hash(&mut self, start: Position) -> Result<Option<Token>, ParseError> {
+        let Some(c) = self.source.peek() else {
+            return Err(self.eof());
+        };
+        let token = match c {
+            '(' => {
+                self.advance();
+                Token::VectorOpen
+            }
+            '|' => {
+                self.advance();
+                self.block_comment()?;
+                return Ok(None);
+            }
+            ';' => {
+                self.advance();
+                Token::DatumComment
+            }
+            '\\' => {
+                self.advance();
+                Token::Character(self.character(start)?)
+            }
+            '!' => {
+                self.advance();
+                let directive = self.read_until_delimiter(String::new());
+                match directive.as_str() {
+                    "fold-case" => self.fold_case = true,
+                    "no-fold-case" => self.fold_case = false,
+                    "r6rs" | "r7rs" => (),
+                    _ => {
+                        return Err(self.error(
+                            ParseErrorKind::InvalidSyntax(format!("#!{}", directive)),
+                            start,
+                        ))
+                    }
+                }
+                return Ok(None);
+            }
+            'u' | 'U' => {
+                let text = self.read_until_delimiter(String::from("#"));
+                if text.eq_ignore_ascii_case("#u8") && self.source.peek() == Some('(') {
+                    self.advance();
+                    Token::BytevectorOpen
+                } else {
+                    return Err(self.error(ParseErrorKind::InvalidSyntax(text), start));
+                }
+            }
+            '0'..='9' => {
+                let mut digits = String::new();
+                while let Some(d) = self.source.peek().filter(char::is_ascii_digit) {
+                    digits.push(d);
+                    self.advance();
+                }
+                let label = digits.parse().map_err(|_| {
+                    self.error(ParseErrorKind::InvalidSyntax(format!("#{}", digits)), start)
+                })?;
+                match self.advance() {
+                    Some('=') => Token::LabelDefine(label),
+                    Some('#') => Token::LabelRef(label),
+                    _ => {
+                        return Err(self
+                            .error(ParseErrorKind::InvalidSyntax(format!("#{}", digits)), start))
+                    }
+                }
+            }
+            _ => {
+                let text = self.read_until_delimiter(String::from("#"));
+                match text.to_ascii_lowercase().as_str() {
+                    "#t" | "#true" => Token::Boolean(true),
+                    "#f" | "#false" => Token::Boolean(false),
+                    _ => match Number::parse(&text, 10) {
+                        Some(n) => Token::Number(n),
+                        None => return Err(self.error(ParseErrorKind::InvalidSyntax(text), start)),
+                    },
+                }
+            }
+        };
+        Ok(Some(token))
+    }
+
+    fn character(&mut self, start: Position) -> Result<char, ParseError> {
+        // The first character is taken literally, even if it is a delimiter.
+        let first = self.advance().ok_or_else(|| self.eof())?;
+        let name = self.read_until_delimiter(String::from(first));
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(c);
+        }
+        let lookup = if self.fold_case {
+            name.to_lowercase()
+        } else {
+            name.clone()
+        };
+        let c = match lookup.as_str() {
+            "alarm" => '\u{7}',
+            "backspace" => '\u{8}',
+            "delete" => '\u{7f}',
+            "escape" | "altmode" => '\u{1b}',
+            "newline" | "linefeed" => '\n',
+            "null" | "nul" => '\0',
+            "return" => '\r',
+            "space" => ' ',
+            "tab" => '\t',
+            "page" => '\u{c}',
+            "vtab" => '\u{b}',
+            _ => match lookup.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        self.error(ParseErrorKind::InvalidCharacter(name.clone()), start)
+                    })?,
+                None => return Err(self.error(ParseErrorKind::InvalidCharacter(name), start)),
+            },
+        };
+        Ok(c)
+    }
+
+    fn string(&mut self, start: Position) -> Result<String, ParseError> {
+        let mut s = String::new();
+        loop {
+            match self.advance().ok_or_else(|| self.eof())? {
+                '"' => return Ok(s),
+                '\\' => {
+                    if let Some(c) = self.escape('"', start)? {
+                        s.push(c);
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn pipe_identifier(&mut self, start: Position) -> Result<String, ParseError> {
+        let mut s = String::new();
+        loop {
+            match self.advance().ok_or_else(|| self.eof())? {
+                '|' => return Ok(s),
+                '\\' => {
+                    if let Some(c) = self.escape('|', start)? {
+                        s.push(c);
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Reads the rest of a backslash escape inside a string or `|...|`
+    /// identifier. Returns `None` for a line continuation.
+    fn escape(&mut self, quote: char, start: Position) -> Result<Option<char>, ParseError> {
+        let c = self.advance().ok_or_else(|| self.eof())?;
+        let escaped = match c {
+            'a' => '\u{7}',
+            'b' => '\u{8}',
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            '\\' | '"' | '|' => c,
+            'x' | 'X' => {
+                let mut hex = String::new();
+                loop {
+                    match self.advance().ok_or_else(|| self.eof())? {
+                        ';' => break,
+                        d => hex.push(d),
+                    }
+                }
+                u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        self.error(ParseErrorKind::InvalidEscape(format!("\\x{};", hex)), start)
+                    })?
+            }
+            ' ' | '\t' | '\n' | '\r' if quote == '"' => {
+                // A line continuation: skip trailing whitespace, the line
+                // ending, then leading whitespace on the next line.
+                let mut seen_newline = c == '\n';
+                while let Some(w) = self.source.peek() {
+                    match w {
+                        '\n' if !seen_newline => seen_newline = true,
+                        ' ' | '\t' | '\r' => (),
+                        _ => break,
+                    }
+                    self.advance();
+                }
+                if !seen_newline {
+                    return Err(self.error(ParseErrorKind::InvalidEscape("\\ ".into()), start));
+                }
+                return Ok(None);
+            }
+            _ => return Err(self.error(ParseErrorKind::InvalidEscape(format!("\\{}", c)), start)),
+        };
+        Ok(Some(escaped))
+    }
+
+    /// Reads a number, an identifier or `.`.
+    fn atom(&mut self, first: char) -> Token {
+        let text = self.read_until_delimiter(String::from(first));
+        if text == "." {
+            return Token::Dot;
+        }
+        if let Some(n) = Number::parse(&text, 10) {
+            return Token::Number(n);
+        }
+        if !self.fold_case {
+            return Token::Identifier(text);
+        }
+        let mut folded = Vec::new();
+        for c in text.chars() {
+            chars::foldcase_full(c, &mut folded);
+        }
+        Token::Identifier(folded.into_iter().collect())
+    }
+
+    /// Reads characters up to the next delimiter, appending them to `text`.
+    fn read_until_delimiter(&mut self, mut text: String) -> String {
+        while let Some(c) = self.source.peek() {
+            if is_delimiter(c) {
+                break;
+            }
+            text.push(c);
+            self.advance();
+        }
+        text
+    }
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '"' | ';' | '|')
+}
@@ -0,0 +1,22 @@
+mod builtins;
+pub mod capability;
+pub mod chars;
+pub mod convert;
+pub mod embed;
+pub mod eval;
+pub mod hash_table;
+pub mod lexer;
+mod limits;
+pub mod net;
+pub mod num;
+pub mod parse;
+pub mod ports;
+pub mod pretty_print;
+pub mod print;
+pub mod proc;
+pub mod process;
+pub mod symbol;
+pub mod syntax;
+pub mod value;
+
+pub use embed::Scheme;
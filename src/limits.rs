@@ -0,0 +1,135 @@
+//! How long an evaluation may run: interruption, timeouts and fuel.
+//!
+//! The interpreter checks its limits at every procedure call and loop
+//! iteration. Primitives that can do a lot of work in one call, walking long
+//! lists or multiplying bignums, [`charge`] for it as they go. Most of that
+//! work happens where no error can be returned, so a charge that finds the
+//! evaluation has to stop unwinds to the primitive call instead, and the
+//! interpreter raises the reason there.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// How many steps pass between checks of the clock when a deadline is set.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Why evaluation has to stop.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Stop {
+    Interrupted,
+    TimedOut,
+    OutOfFuel,
+}
+
+pub(crate) struct Limits {
+    pub(crate) interrupted: Arc<AtomicBool>,
+    pub(crate) deadline: Cell<Option<Instant>>,
+    /// Steps until the clock is next checked against the deadline.
+    clock_countdown: Cell<u64>,
+    /// Steps left before evaluation stops, if limited.
+    pub(crate) fuel: Cell<Option<u64>>,
+    /// Why a [`charge`] inside a primitive failed.
+    tripped: Cell<Option<Stop>>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            interrupted: Arc::default(),
+            deadline: Cell::new(None),
+            clock_countdown: Cell::new(CLOCK_INTERVAL),
+            fuel: Cell::new(None),
+            tripped: Cell::new(None),
+        }
+    }
+}
+
+impl Limits {
+    /// Spends `steps` of fuel, failing if the evaluation has been
+    /// interrupted, has timed out or has run out of fuel.
+    pub(crate) fn check(&self, steps: u64) -> Result<(), Stop> {
+        if let Some(stop) = self.tripped.take() {
+            return Err(stop);
+        }
+        if self.interrupted.load(Ordering::Relaxed) {
+            return Err(Stop::Interrupted);
+        }
+        if let Some(fuel) = self.fuel.get() {
+            if fuel < steps {
+                self.fuel.set(Some(0));
+                return Err(Stop::OutOfFuel);
+            }
+            self.fuel.set(Some(fuel - steps));
+        }
+        if let Some(deadline) = self.deadline.get() {
+            let countdown = self.clock_countdown.get().saturating_sub(steps);
+            if countdown > 0 {
+                self.clock_countdown.set(countdown);
+            } else {
+                self.clock_countdown.set(CLOCK_INTERVAL);
+                if Instant::now() >= deadline {
+                    // Stays set until the outermost evaluation returns.
+                    self.interrupted.store(true, Ordering::Relaxed);
+                    return Err(Stop::TimedOut);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Why a charge inside the last primitive failed, if one did.
+    pub(crate) fn take_tripped(&self) -> Option<Stop> {
+        self.tripped.take()
+    }
+}
+
+thread_local! {
+    /// The limits of the primitive running on this thread, if any.
+    static CURRENT: RefCell<Option<Rc<Limits>>> = const { RefCell::new(None) };
+}
+
+/// Makes `limits` the ones [`charge`] spends until the returned guard is
+/// dropped. The interpreter installs its limits around each primitive call,
+/// and `None` while a primitive calls back into it.
+pub(crate) fn install(limits: Option<&Rc<Limits>>) -> Installed {
+    Installed(CURRENT.with(|current| current.replace(limits.cloned())))
+}
+
+/// Restores the limits that were current before [`install`].
+pub(crate) struct Installed(Option<Rc<Limits>>);
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// Spends `steps` of the installed limits, recording why if evaluation has
+/// to stop.
+fn poll(steps: u64) -> bool {
+    CURRENT.with(|current| match &*current.borrow() {
+        Some(limits) => match limits.check(steps) {
+            Ok(()) => true,
+            Err(stop) => {
+                limits.tripped.set(Some(stop));
+                false
+            }
+        },
+        None => true,
+    })
+}
+
+/// The payload of the unwinding started by [`charge`].
+pub(crate) struct Unwound;
+
+/// Charges `steps` to the running primitive, unwinding out of it once
+/// evaluation has to stop. Where unwinding is not available, the work runs
+/// to completion and evaluation stops afterwards.
+pub(crate) fn charge(steps: u64) {
+    if !poll(steps) && cfg!(panic = "unwind") {
+        std::panic::resume_unwind(Box::new(Unwound));
+    }
+}
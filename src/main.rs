@@ -1,5 +1,461 @@
+use scheme::eval::{Error, ExecutionHandle};
+use scheme::parse;
+use scheme::ports::{InputPort, PortKind};
+use scheme::pretty_print::pretty_print;
+use scheme::value::Value;
+use scheme::Scheme;
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
 const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = ". ";
+
+/// The line width results are pretty-printed to.
+const WIDTH: usize = 79;
+
+/// Non-tail calls recurse on the Rust stack, so the REPL runs on a thread
+/// with room for deep recursion.
+const STACK_SIZE: usize = 256 << 20;
+
+/// How much of that stack evaluation may use, leaving the rest for the REPL
+/// and for primitives.
+const STACK_LIMIT: usize = STACK_SIZE - (16 << 20);
+
+/// How often `--watch` checks the file for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+const USAGE: &str = "usage: scheme [--watch] [file]";
 
 fn main() {
-    println!("{}", PROMPT)
-}
\ No newline at end of file
+    let mut watch = false;
+    let mut file = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--watch" => watch = true,
+            _ if file.is_none() && !arg.starts_with('-') => file = Some(PathBuf::from(arg)),
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+    if watch && file.is_none() {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+    let main = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || match file {
+            Some(file) if !watch => run_file(&file),
+            file => repl(file),
+        })
+        .expect("failed to start the interpreter thread");
+    match main.join() {
+        Ok(true) => {}
+        _ => std::process::exit(1),
+    }
+}
+
+/// Loads a file and exits, returning whether it ran without error.
+fn run_file(file: &Path) -> bool {
+    let mut scheme = Scheme::new();
+    scheme.set_stack_limit(STACK_LIMIT);
+    interrupt::init(scheme.execution_handle());
+    interrupt::enable();
+    let result = scheme.load(file);
+    io::stdout().flush().ok();
+    match result {
+        Ok(()) => true,
+        Err(Error::Exit(code)) => exit(code),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            false
+        }
+    }
+}
+
+/// What the REPL waits for between evaluations.
+enum Event {
+    Line(String),
+    EndOfInput,
+    /// The watched file changed on disk.
+    Changed,
+}
+
+/// Runs the REPL, first loading `watched` and then reloading it whenever it
+/// changes.
+fn repl(watched: Option<PathBuf>) -> bool {
+    let mut scheme = Scheme::new();
+    scheme.set_stack_limit(STACK_LIMIT);
+    interrupt::init(scheme.execution_handle());
+    let (events, receiver) = mpsc::channel();
+    let terminal = Rc::new(RefCell::new(Terminal {
+        events: receiver,
+        requests: spawn_reader(events.clone()),
+        requested: false,
+        ended: false,
+        changed: false,
+    }));
+    scheme.set_current_input_port(InputPort::from_reader(
+        TerminalInput {
+            terminal: terminal.clone(),
+            line: Vec::new(),
+            at: 0,
+        },
+        PortKind::Textual,
+    ));
+    if let Some(file) = &watched {
+        load(&mut scheme, file);
+        spawn_watcher(file.clone(), events);
+    }
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        print!("{}", prompt);
+        io::stdout().flush().ok();
+        let event = terminal.borrow_mut().next_event();
+        let line = match event {
+            Event::Line(line) => line,
+            Event::Changed => {
+                println!();
+                if let Some(file) = &watched {
+                    load(&mut scheme, file);
+                }
+                continue;
+            }
+            Event::EndOfInput => {
+                println!();
+                return true;
+            }
+        };
+        input.push_str(&line);
+        let (command, source) = split_command(&input);
+        // Keep reading lines until the input holds only complete data.
+        let forms = match parse::parse(source) {
+            Ok(forms) => forms,
+            Err(err) if err.is_incomplete() => continue,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                input.clear();
+                continue;
+            }
+        };
+        let command = command.map(str::to_string);
+        input.clear();
+        match command {
+            Some(command) => {
+                if !run_command(&mut scheme, &command, forms) {
+                    return true;
+                }
+            }
+            None => {
+                for form in forms {
+                    match eval(&mut scheme, &form) {
+                        Some(value) => print_result(&value),
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Evaluates a form with Ctrl-C enabled, reporting any error.
+fn eval(scheme: &mut Scheme, form: &Value) -> Option<Value> {
+    interrupt::enable();
+    let result = scheme.interpreter().eval(form);
+    interrupt::disable();
+    match result {
+        Ok(value) => Some(value),
+        Err(Error::Exit(code)) => exit(code),
+        Err(err) => {
+            io::stdout().flush().ok();
+            eprintln!("error: {}", err);
+            None
+        }
+    }
+}
+
+/// REPL commands, entered as `,name` followed by any forms they act on.
+/// Commands are recognized before the reader sees the input, so they have
+/// no meaning as Scheme syntax.
+const COMMANDS: &[(&str, &str)] = &[
+    ("help", "list the commands"),
+    ("time expr ...", "evaluate and report the time taken"),
+    (
+        "type expr ...",
+        "evaluate and report the type of each value",
+    ),
+    (
+        "expand expr ...",
+        "show each expression with its derived forms lowered",
+    ),
+    ("quit", "leave the REPL"),
+];
+
+/// Splits input starting with `,name` into the command name and the rest.
+fn split_command(input: &str) -> (Option<&str>, &str) {
+    let Some(rest) = input.trim_start().strip_prefix(',') else {
+        return (None, input);
+    };
+    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    (Some(&rest[..end]), &rest[end..])
+}
+
+/// Runs a REPL command, returning false if the REPL should stop.
+fn run_command(scheme: &mut Scheme, command: &str, forms: Vec<Value>) -> bool {
+    match command {
+        "help" => {
+            for (usage, description) in COMMANDS {
+                println!(",{:<16} {}", usage, description);
+            }
+        }
+        "time" => {
+            for form in forms {
+                let start = Instant::now();
+                let Some(value) = eval(scheme, &form) else {
+                    break;
+                };
+                let elapsed = start.elapsed();
+                print_result(&value);
+                println!("; {:?}", elapsed);
+            }
+        }
+        "type" => {
+            for form in forms {
+                let Some(value) = eval(scheme, &form) else {
+                    break;
+                };
+                println!("{}", value.type_name());
+            }
+        }
+        "expand" => {
+            for form in forms {
+                match scheme.interpreter().expand(&form) {
+                    Ok(expanded) => print_result(&expanded),
+                    Err(err) => {
+                        eprintln!("error: {}", err);
+                        break;
+                    }
+                }
+            }
+        }
+        "quit" => return false,
+        _ => eprintln!("error: unknown command ,{} (try ,help)", command),
+    }
+    true
+}
+
+fn load(scheme: &mut Scheme, file: &Path) {
+    interrupt::enable();
+    let result = scheme.load(file);
+    interrupt::disable();
+    io::stdout().flush().ok();
+    match result {
+        Ok(()) => println!("; loaded {}", file.display()),
+        Err(Error::Exit(code)) => exit(code),
+        Err(err) => eprintln!("error: {}", err),
+    }
+}
+
+/// Ends the process once `exit` has unwound out of the program.
+fn exit(code: i32) -> ! {
+    io::stdout().flush().ok();
+    std::process::exit(code)
+}
+
+/// The lines typed at the terminal, shared by the REPL and the current
+/// input port. Stdin is read a line at a time, only when one of them asks,
+/// so `read-line` and `read` get the lines typed after the expression that
+/// calls them rather than racing the REPL for input.
+struct Terminal {
+    events: Receiver<Event>,
+    requests: Sender<()>,
+    /// Whether a line has been asked for and not yet received.
+    requested: bool,
+    ended: bool,
+    /// Whether the watched file changed while a program was reading input.
+    changed: bool,
+}
+
+impl Terminal {
+    /// Waits for the next thing the REPL should act on.
+    fn next_event(&mut self) -> Event {
+        if self.changed {
+            self.changed = false;
+            return Event::Changed;
+        }
+        self.receive()
+    }
+
+    /// The next line for the current input port, or `None` at the end of
+    /// input.
+    fn read_line(&mut self) -> Option<String> {
+        loop {
+            match self.receive() {
+                Event::Line(line) => return Some(line),
+                Event::EndOfInput => return None,
+                Event::Changed => self.changed = true,
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Event {
+        if self.ended {
+            return Event::EndOfInput;
+        }
+        if !self.requested {
+            self.requested = self.requests.send(()).is_ok();
+        }
+        let event = self.events.recv().unwrap_or(Event::EndOfInput);
+        match event {
+            Event::Changed => {}
+            Event::EndOfInput => self.ended = true,
+            Event::Line(_) => self.requested = false,
+        }
+        event
+    }
+}
+
+/// Reads the current input port's text from the terminal.
+struct TerminalInput {
+    terminal: Rc<RefCell<Terminal>>,
+    line: Vec<u8>,
+    at: usize,
+}
+
+impl Read for TerminalInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.at == self.line.len() {
+            // Show any prompt the program wrote before waiting for input.
+            io::stdout().flush()?;
+            match self.terminal.borrow_mut().read_line() {
+                Some(line) => {
+                    self.line = line.into_bytes();
+                    self.at = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.line.len() - self.at);
+        buf[..n].copy_from_slice(&self.line[self.at..self.at + n]);
+        self.at += n;
+        Ok(n)
+    }
+}
+
+/// Reads stdin on its own thread, a line for each request, so that the REPL
+/// can also wait for file changes. Returns the sender for requests.
+fn spawn_reader(events: Sender<Event>) -> Sender<()> {
+    let (requests, received) = mpsc::channel();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for () in received {
+            let mut line = String::new();
+            let event = match stdin.lock().read_line(&mut line) {
+                Ok(0) => Event::EndOfInput,
+                Ok(_) => Event::Line(line),
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    Event::EndOfInput
+                }
+            };
+            let done = matches!(event, Event::EndOfInput);
+            if events.send(event).is_err() || done {
+                return;
+            }
+        }
+    });
+    requests
+}
+
+/// Polls the modification time of `file`, sending `Changed` when it moves.
+fn spawn_watcher(file: PathBuf, events: Sender<Event>) {
+    thread::spawn(move || {
+        let modified = |file: &Path| fs::metadata(file).and_then(|meta| meta.modified()).ok();
+        let mut last = modified(&file);
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let current = modified(&file);
+            if current != last {
+                last = current;
+                if events.send(Event::Changed).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+fn print_result(value: &Value) {
+    let print = |value: &Value| {
+        let mut out = String::new();
+        pretty_print(value, WIDTH, &mut out).expect("writing to a String cannot fail");
+        println!("{}", out);
+    };
+    match value {
+        Value::Unspecified => {}
+        Value::Values(values) => values.iter().for_each(print),
+        value => print(value),
+    }
+}
+
+/// Ctrl-C interrupts the running evaluation instead of exiting. At the
+/// prompt it keeps its default behaviour.
+#[cfg(unix)]
+mod interrupt {
+    use super::*;
+    use std::os::raw::c_int;
+
+    const SIGINT: c_int = 2;
+    const SIG_DFL: usize = 0;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: usize) -> usize;
+    }
+
+    static HANDLE: OnceLock<ExecutionHandle> = OnceLock::new();
+
+    extern "C" fn on_sigint(_: c_int) {
+        // Only an atomic store, so this is safe in a signal handler.
+        if let Some(handle) = HANDLE.get() {
+            handle.interrupt();
+        }
+    }
+
+    pub fn init(handle: ExecutionHandle) {
+        HANDLE.set(handle).ok();
+    }
+
+    pub fn enable() {
+        // SAFETY: `on_sigint` only performs an atomic store.
+        unsafe { signal(SIGINT, on_sigint as extern "C" fn(c_int) as usize) };
+    }
+
+    pub fn disable() {
+        // SAFETY: restoring the default disposition.
+        unsafe { signal(SIGINT, SIG_DFL) };
+    }
+}
+
+#[cfg(not(unix))]
+mod interrupt {
+    use super::*;
+
+    pub fn init(_: ExecutionHandle) {}
+
+    pub fn enable() {}
+
+    pub fn disable() {}
+}
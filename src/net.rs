@@ -0,0 +1,134 @@
+//! TCP and UDP sockets, on blocking `std::net` sockets.
+//!
+//! A TCP connection is used through a pair of textual ports, one for each
+//! direction. Closing the output port shuts down the sending side of the
+//! connection, so the peer sees the end of its input.
+
+use crate::ports::{InputPort, OutputPort, PortKind};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+
+/// A socket that is not a connection: a TCP listener or a UDP socket.
+/// Either is closed when [`Socket::close`] is called or it is dropped.
+pub enum Socket {
+    TcpListener(RefCell<Option<TcpListener>>),
+    Udp(RefCell<Option<UdpSocket>>),
+}
+
+impl Socket {
+    /// A TCP listener on `addr`.
+    pub fn listen(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        Ok(Self::TcpListener(RefCell::new(Some(listener))))
+    }
+
+    /// A UDP socket bound to `addr`.
+    pub fn udp(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        Ok(Self::Udp(RefCell::new(Some(socket))))
+    }
+
+    pub fn is_open(&self) -> bool {
+        match self {
+            Self::TcpListener(listener) => listener.borrow().is_some(),
+            Self::Udp(socket) => socket.borrow().is_some(),
+        }
+    }
+
+    pub fn close(&self) {
+        match self {
+            Self::TcpListener(listener) => *listener.borrow_mut() = None,
+            Self::Udp(socket) => *socket.borrow_mut() = None,
+        }
+    }
+
+    /// The address the socket is bound to, which gives the port chosen
+    /// when binding to port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Self::TcpListener(listener) => open(&listener.borrow())?.local_addr(),
+            Self::Udp(socket) => open(&socket.borrow())?.local_addr(),
+        }
+    }
+
+    /// Waits for a connection to a listener, returning its ports and the
+    /// address it came from.
+    pub fn accept(&self) -> io::Result<(InputPort, OutputPort, SocketAddr)> {
+        let Self::TcpListener(listener) = self else {
+            return Err(not(self, "a TCP listener"));
+        };
+        let (stream, addr) = open(&listener.borrow())?.accept()?;
+        let (input, output) = ports(stream)?;
+        Ok((input, output, addr))
+    }
+
+    /// Sends one datagram from a UDP socket.
+    pub fn send_to(&self, bytes: &[u8], addr: impl ToSocketAddrs) -> io::Result<()> {
+        let Self::Udp(socket) = self else {
+            return Err(not(self, "a UDP socket"));
+        };
+        open(&socket.borrow())?.send_to(bytes, addr)?;
+        Ok(())
+    }
+
+    /// Waits for one datagram on a UDP socket, keeping at most `size`
+    /// bytes of it.
+    pub fn receive(&self, size: usize) -> io::Result<(Vec<u8>, SocketAddr)> {
+        let Self::Udp(socket) = self else {
+            return Err(not(self, "a UDP socket"));
+        };
+        let mut buf = vec![0; size];
+        let (n, addr) = open(&socket.borrow())?.recv_from(&mut buf)?;
+        buf.truncate(n);
+        Ok((buf, addr))
+    }
+}
+
+/// Connects to `addr` over TCP, returning the connection's ports.
+pub fn connect(addr: impl ToSocketAddrs) -> io::Result<(InputPort, OutputPort)> {
+    ports(TcpStream::connect(addr)?)
+}
+
+fn ports(stream: TcpStream) -> io::Result<(InputPort, OutputPort)> {
+    let input = InputPort::from_reader(stream.try_clone()?, PortKind::Textual);
+    let output = OutputPort::from_writer(Sender(stream), PortKind::Textual);
+    Ok((input, output))
+}
+
+fn open<T>(socket: &Option<T>) -> io::Result<&T> {
+    socket
+        .as_ref()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "socket is closed"))
+}
+
+fn not(socket: &Socket, expected: &str) -> io::Error {
+    let actual = match socket {
+        Socket::TcpListener(_) => "a TCP listener",
+        Socket::Udp(_) => "a UDP socket",
+    };
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("expected {}, not {}", expected, actual),
+    )
+}
+
+/// The sending side of a connection, shut down when the output port is
+/// closed or dropped.
+struct Sender(TcpStream);
+
+impl Write for Sender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown(Shutdown::Write);
+    }
+}
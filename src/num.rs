@@ -0,0 +1,916 @@
+//! The numeric tower.
+//!
+//! Integers start out as fixnums and are promoted to bignums whenever an
+//! operation would overflow an `i64`. Bignum results that fit back into a
+//! fixnum are demoted again, and rationals with a denominator of one are
+//! demoted to integers, so two equal exact numbers always have the same
+//! representation.
+
+mod bigint;
+mod complex;
+mod parse;
+mod rational;
+
+pub use bigint::BigInt;
+pub use complex::Complex;
+pub use rational::Rational;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// The largest exact result, in bits, that `expt` computes. Bigger powers
+/// would take so long to compute in one step that they could not be
+/// interrupted, so they are refused instead.
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+#[derive(Clone, Debug)]
+pub enum Number {
+    Fixnum(i64),
+    Bignum(BigInt),
+    /// An exact ratio that is never an integer.
+    Rational(Rational),
+    Real(f64),
+    /// A complex number with an imaginary part that is not an exact zero.
+    Complex(Box<Complex>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArithmeticError {
+    DivisionByZero,
+    ExponentTooLarge,
+    /// An exact result too big to compute in one step.
+    TooLarge,
+    NonInteger,
+    NonReal,
+    NoExactRepresentation,
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::ExponentTooLarge => write!(f, "exponent too large"),
+            Self::TooLarge => write!(f, "result too large"),
+            Self::NonInteger => write!(f, "expected an integer"),
+            Self::NonReal => write!(f, "expected a real number"),
+            Self::NoExactRepresentation => write!(f, "number has no exact representation"),
+        }
+    }
+}
+
+impl std::error::Error for ArithmeticError {}
+
+impl Number {
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Self::Fixnum(i) => *i == 0,
+            Self::Bignum(b) => b.is_zero(),
+            Self::Rational(_) | Self::Complex(_) => false,
+            Self::Real(r) => *r == 0.0,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Self::Fixnum(i) => *i < 0,
+            Self::Bignum(b) => b.is_negative(),
+            Self::Rational(q) => q.is_negative(),
+            Self::Real(r) => *r < 0.0,
+            Self::Complex(_) => false,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match self {
+            Self::Fixnum(_) | Self::Bignum(_) => true,
+            Self::Rational(_) | Self::Complex(_) => false,
+            Self::Real(r) => r.is_finite() && r.fract() == 0.0,
+        }
+    }
+
+    pub fn is_real(&self) -> bool {
+        !matches!(self, Self::Complex(_))
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Self::Fixnum(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        match self {
+            Self::Real(_) => false,
+            Self::Complex(z) => !matches!(z.re(), Self::Real(_)),
+            _ => true,
+        }
+    }
+
+    pub fn is_inexact(&self) -> bool {
+        !self.is_exact()
+    }
+
+    pub fn is_exact_integer(&self) -> bool {
+        matches!(self, Self::Fixnum(_) | Self::Bignum(_))
+    }
+
+    /// Converts a real number to an `f64`. Complex numbers convert their
+    /// real part.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Self::Fixnum(i) => *i as f64,
+            Self::Bignum(b) => b.to_f64(),
+            Self::Rational(q) => q.to_f64(),
+            Self::Real(r) => *r,
+            Self::Complex(z) => z.re().to_f64(),
+        }
+    }
+
+    /// Converts to an inexact number, as in `exact->inexact`.
+    pub fn to_inexact(&self) -> Number {
+        match self {
+            Self::Complex(z) => Self::from(Complex::new(z.re().to_inexact(), z.im().clone())),
+            _ => Self::Real(self.to_f64()),
+        }
+    }
+
+    /// Converts to an exact number, as in `inexact->exact`. Inexact reals are
+    /// converted to the exact binary fraction they represent.
+    pub fn to_exact(&self) -> Result<Number, ArithmeticError> {
+        match self {
+            Self::Real(r) => exact_from_f64(*r),
+            Self::Complex(z) => Ok(Self::from(Complex::new(
+                z.re().to_exact()?,
+                z.im().to_exact()?,
+            ))),
+            _ => Ok(self.clone()),
+        }
+    }
+
+    fn to_bigint(&self) -> BigInt {
+        match self {
+            Self::Fixnum(i) => BigInt::from(*i),
+            Self::Bignum(b) => b.clone(),
+            _ => unreachable!("to_bigint called on a non-integer"),
+        }
+    }
+
+    fn to_rational(&self) -> Rational {
+        match self {
+            Self::Rational(q) => q.clone(),
+            _ => Rational::from(self.to_bigint()),
+        }
+    }
+
+    fn to_complex(&self) -> Complex {
+        match self {
+            Self::Complex(z) => (**z).clone(),
+            _ => Complex::new(self.clone(), Self::Fixnum(0)),
+        }
+    }
+
+    pub fn make_rectangular(re: &Number, im: &Number) -> Number {
+        Self::from(Complex::new(re.clone(), im.clone()))
+    }
+
+    pub fn make_polar(magnitude: &Number, angle: &Number) -> Number {
+        if angle.is_exact_zero() {
+            return magnitude.clone();
+        }
+        Self::from(Complex::from_polar(magnitude, angle))
+    }
+
+    pub fn real_part(&self) -> Number {
+        match self {
+            Self::Complex(z) => z.re().clone(),
+            _ => self.clone(),
+        }
+    }
+
+    pub fn imag_part(&self) -> Number {
+        match self {
+            Self::Complex(z) => z.im().clone(),
+            _ => Self::Fixnum(0),
+        }
+    }
+
+    pub fn magnitude(&self) -> Number {
+        match self {
+            Self::Complex(z) => z.magnitude(),
+            _ => self.abs(),
+        }
+    }
+
+    pub fn angle(&self) -> Number {
+        match self {
+            Self::Complex(z) => z.angle(),
+            _ if self.is_negative() => Self::Real(std::f64::consts::PI),
+            Self::Real(_) => Self::Real(0.0),
+            _ => Self::Fixnum(0),
+        }
+    }
+
+    /// The numerator of a real number in lowest terms. Inexact arguments
+    /// give inexact results.
+    pub fn numerator(&self) -> Number {
+        match self {
+            Self::Rational(q) => Self::from(q.numer().clone()),
+            Self::Real(r) => match exact_from_f64(*r) {
+                Ok(exact) => exact.numerator().to_inexact(),
+                Err(_) => self.clone(),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    pub fn denominator(&self) -> Number {
+        match self {
+            Self::Rational(q) => Self::from(q.denom().clone()),
+            Self::Real(r) => match exact_from_f64(*r) {
+                Ok(exact) => exact.denominator().to_inexact(),
+                Err(_) => Self::Real(1.0),
+            },
+            _ => Self::Fixnum(1),
+        }
+    }
+
+    pub fn abs(&self) -> Number {
+        if self.is_negative() {
+            -self
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Exact division, producing a rational when the result is not an
+    /// integer.
+    pub fn div(&self, rhs: &Number) -> Result<Number, ArithmeticError> {
+        match (self, rhs) {
+            (Self::Complex(_), _) | (_, Self::Complex(_)) => {
+                Ok(Self::from(self.to_complex().div(&rhs.to_complex())?))
+            }
+            (Self::Real(_), _) | (_, Self::Real(_)) if !rhs.is_exact_zero() => {
+                Ok(Self::Real(self.to_f64() / rhs.to_f64()))
+            }
+            _ if rhs.is_zero() => Err(ArithmeticError::DivisionByZero),
+            _ => Ok(Self::from(self.to_rational().div(&rhs.to_rational()))),
+        }
+    }
+
+    fn is_exact_zero(&self) -> bool {
+        !matches!(self, Self::Real(_)) && self.is_zero()
+    }
+
+    /// Truncating integer division, as in `quotient`.
+    pub fn quotient(&self, rhs: &Number) -> Result<Number, ArithmeticError> {
+        Ok(self.div_rem(rhs)?.0)
+    }
+
+    /// The remainder of truncating division, as in `remainder`. The result
+    /// has the sign of the dividend.
+    pub fn remainder(&self, rhs: &Number) -> Result<Number, ArithmeticError> {
+        Ok(self.div_rem(rhs)?.1)
+    }
+
+    /// The remainder of flooring division, as in `modulo`. The result has the
+    /// sign of the divisor.
+    pub fn modulo(&self, rhs: &Number) -> Result<Number, ArithmeticError> {
+        let rem = self.remainder(rhs)?;
+        if !rem.is_zero() && rem.is_negative() != rhs.is_negative() {
+            Ok(&rem + rhs)
+        } else {
+            Ok(rem)
+        }
+    }
+
+    fn div_rem(&self, rhs: &Number) -> Result<(Number, Number), ArithmeticError> {
+        if !self.is_integer() || !rhs.is_integer() {
+            return Err(ArithmeticError::NonInteger);
+        }
+        if rhs.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        match (self, rhs) {
+            (Self::Real(_), _) | (_, Self::Real(_)) => {
+                let (l, r) = (self.to_f64(), rhs.to_f64());
+                Ok((Self::Real((l / r).trunc()), Self::Real(l % r)))
+            }
+            (Self::Fixnum(l), Self::Fixnum(r)) if l.checked_div(*r).is_some() => {
+                Ok((Self::Fixnum(l / r), Self::Fixnum(l % r)))
+            }
+            _ => {
+                let (q, r) = self.to_bigint().div_rem(&rhs.to_bigint());
+                Ok((Self::from(q), Self::from(r)))
+            }
+        }
+    }
+
+    /// Raises `self` to a power. Exact bases raised to exact integer powers
+    /// stay exact; everything else is computed inexactly. Negative bases
+    /// raised to non-integer powers produce complex results.
+    pub fn expt(&self, exponent: &Number) -> Result<Number, ArithmeticError> {
+        let exact_integer_exponent = exponent.is_integer() && exponent.is_exact();
+        if let Self::Complex(z) = self {
+            if exact_integer_exponent && !exponent.is_negative() {
+                let exponent = exponent
+                    .to_i64()
+                    .and_then(|e| u32::try_from(e).ok())
+                    .ok_or(ArithmeticError::ExponentTooLarge)?;
+                self.check_power_size(exponent)?;
+                return Ok(self.pow_by_squaring(exponent));
+            } else if exact_integer_exponent {
+                return Self::Fixnum(1).div(&self.expt(&-exponent)?);
+            }
+            return Ok(Self::from(z.powc(&exponent.to_complex())));
+        }
+        if !exponent.is_real() || (self.is_negative() && !exponent.is_integer()) {
+            return Ok(Self::from(self.to_complex().powc(&exponent.to_complex())));
+        }
+        if matches!(self, Self::Real(_))
+            || !exponent.is_integer()
+            || matches!(exponent, Self::Real(_))
+        {
+            return Ok(Self::Real(self.to_f64().powf(exponent.to_f64())));
+        }
+        if exponent.is_negative() {
+            return Self::Fixnum(1).div(&self.expt(&-exponent)?);
+        }
+        match self.to_i64() {
+            Some(0 | 1) if !exponent.is_zero() => return Ok(self.clone()),
+            Some(-1) => {
+                let even = exponent.remainder(&Number::Fixnum(2))?.is_zero();
+                return Ok(Number::Fixnum(if even { 1 } else { -1 }));
+            }
+            _ => (),
+        }
+        let exponent = exponent
+            .to_i64()
+            .and_then(|e| u32::try_from(e).ok())
+            .ok_or(ArithmeticError::ExponentTooLarge)?;
+        self.check_power_size(exponent)?;
+        match self {
+            Self::Fixnum(base) => match base.checked_pow(exponent) {
+                Some(result) => Ok(Self::Fixnum(result)),
+                None => Ok(Self::from(BigInt::from(*base).pow(exponent))),
+            },
+            Self::Bignum(base) => Ok(Self::from(base.pow(exponent))),
+            Self::Rational(base) => Ok(Self::from(base.pow(exponent))),
+            Self::Real(_) | Self::Complex(_) => unreachable!(),
+        }
+    }
+
+    /// Rounds towards negative infinity, as in `floor`.
+    pub fn floor(&self) -> Result<Number, ArithmeticError> {
+        match self {
+            Self::Fixnum(_) | Self::Bignum(_) => Ok(self.clone()),
+            Self::Rational(q) => Ok(Self::from(q.floor())),
+            Self::Real(r) => Ok(Self::Real(r.floor())),
+            Self::Complex(_) => Err(ArithmeticError::NonReal),
+        }
+    }
+
+    /// Rounds towards positive infinity, as in `ceiling`.
+    pub fn ceiling(&self) -> Result<Number, ArithmeticError> {
+        match self {
+            Self::Real(r) => Ok(Self::Real(r.ceil())),
+            _ => Ok(-(-self).floor()?),
+        }
+    }
+
+    /// Rounds towards zero, as in `truncate`.
+    pub fn truncate(&self) -> Result<Number, ArithmeticError> {
+        match self {
+            Self::Real(r) => Ok(Self::Real(r.trunc())),
+            _ if self.is_negative() => self.ceiling(),
+            _ => self.floor(),
+        }
+    }
+
+    /// Rounds to the nearest integer, choosing the even one on ties, as in
+    /// `round`.
+    pub fn round(&self) -> Result<Number, ArithmeticError> {
+        match self {
+            Self::Real(r) => {
+                let rounded = r.round();
+                if (r - r.trunc()).abs() == 0.5 {
+                    Ok(Self::Real(2.0 * (r / 2.0).round()))
+                } else {
+                    Ok(Self::Real(rounded))
+                }
+            }
+            Self::Rational(q) => {
+                let half = Rational::new(BigInt::from(1i64), BigInt::from(2i64));
+                let shifted = q.add(&half);
+                let floor = Self::from(shifted.floor());
+                let tie = Self::from(shifted) == floor;
+                if tie && !floor.remainder(&Self::Fixnum(2))?.is_zero() {
+                    Ok(&floor - &Self::Fixnum(1))
+                } else {
+                    Ok(floor)
+                }
+            }
+            _ => self.floor(),
+        }
+    }
+
+    /// The simplest rational number that differs from `self` by no more
+    /// than `tolerance`, as in `rationalize`. The result is inexact if
+    /// either argument is.
+    pub fn rationalize(&self, tolerance: &Number) -> Result<Number, ArithmeticError> {
+        if !self.is_real() || !tolerance.is_real() {
+            return Err(ArithmeticError::NonReal);
+        }
+        if self.is_exact() && tolerance.is_exact() {
+            let tolerance = tolerance.abs();
+            return simplest_between(&(self - &tolerance), &(self + &tolerance));
+        }
+        let (x, y) = (self.to_f64(), tolerance.to_f64());
+        if x.is_nan() || y.is_nan() || (x.is_infinite() && y.is_infinite()) {
+            return Ok(Self::Real(f64::NAN));
+        }
+        if y.is_infinite() {
+            return Ok(Self::Real(0.0));
+        }
+        if x.is_infinite() {
+            return Ok(Self::Real(x));
+        }
+        let exact = self.to_exact()?.rationalize(&tolerance.to_exact()?)?;
+        Ok(exact.to_inexact())
+    }
+
+    /// The greatest common divisor of two integers. The result is never
+    /// negative.
+    pub fn gcd(&self, rhs: &Number) -> Result<Number, ArithmeticError> {
+        if !self.is_integer() || !rhs.is_integer() {
+            return Err(ArithmeticError::NonInteger);
+        }
+        if !self.is_exact() || !rhs.is_exact() {
+            let gcd = self.to_exact()?.gcd(&rhs.to_exact()?)?;
+            return Ok(gcd.to_inexact());
+        }
+        Ok(Self::from(self.to_bigint().gcd(&rhs.to_bigint())))
+    }
+
+    /// The least common multiple of two integers. The result is never
+    /// negative.
+    pub fn lcm(&self, rhs: &Number) -> Result<Number, ArithmeticError> {
+        if self.is_zero() || rhs.is_zero() {
+            return Ok(if self.is_exact() && rhs.is_exact() {
+                Self::Fixnum(0)
+            } else {
+                Self::Real(0.0)
+            });
+        }
+        (self * rhs).abs().quotient(&self.gcd(rhs)?)
+    }
+
+    /// The principal square root. Exact perfect squares, including rational
+    /// ones, have exact roots; negative reals have complex ones.
+    pub fn sqrt(&self) -> Number {
+        match self {
+            Self::Complex(z) => Self::from(z.powc(&Complex::new(Self::Real(0.5), Self::Real(0.0)))),
+            _ if self.is_negative() => {
+                let root = (-self).sqrt();
+                if root.is_exact() {
+                    Self::make_rectangular(&Self::Fixnum(0), &root)
+                } else {
+                    Self::make_rectangular(&Self::Real(0.0), &root)
+                }
+            }
+            Self::Fixnum(_) | Self::Bignum(_) => match self.exact_integer_sqrt() {
+                Ok((root, rem)) if rem.is_zero() => root,
+                _ => Self::Real(self.to_f64().sqrt()),
+            },
+            Self::Rational(q) => {
+                let numer = Self::from(q.numer().clone()).sqrt();
+                let denom = Self::from(q.denom().clone()).sqrt();
+                if numer.is_exact() && denom.is_exact() {
+                    numer.div(&denom).unwrap_or(numer)
+                } else {
+                    Self::Real(q.to_f64().sqrt())
+                }
+            }
+            Self::Real(r) => Self::Real(r.sqrt()),
+        }
+    }
+
+    /// Returns `s` and `r` such that `s * s + r` is `self` and `s` is as
+    /// large as possible, as in `exact-integer-sqrt`.
+    pub fn exact_integer_sqrt(&self) -> Result<(Number, Number), ArithmeticError> {
+        if !self.is_exact_integer() || self.is_negative() {
+            return Err(ArithmeticError::NonInteger);
+        }
+        let n = self.to_bigint();
+        if n.is_zero() {
+            return Ok((Self::Fixnum(0), Self::Fixnum(0)));
+        }
+        // Newton's method from an initial guess no smaller than the root.
+        let two = BigInt::from(2i64);
+        let mut x = BigInt::from(2i64).pow((n.bits() as u32).div_ceil(2));
+        loop {
+            let y = (&x + &n.div_rem(&x).0).div_rem(&two).0;
+            if y >= x {
+                break;
+            }
+            x = y;
+        }
+        let rem = &n - &(&x * &x);
+        Ok((Self::from(x), Self::from(rem)))
+    }
+
+    /// The exponential and trigonometric functions are computed inexactly
+    /// and defined on reals only.
+    pub fn exp(&self) -> Result<Number, ArithmeticError> {
+        self.real_op(f64::exp)
+    }
+
+    /// The natural logarithm. The logarithm of a negative real is complex.
+    pub fn log(&self) -> Result<Number, ArithmeticError> {
+        if self.is_real() && self.is_negative() {
+            let magnitude = (-self).log()?;
+            return Ok(Self::make_rectangular(
+                &magnitude,
+                &Self::Real(std::f64::consts::PI),
+            ));
+        }
+        if self.is_exact() && self.to_i64() == Some(1) {
+            return Ok(Self::Fixnum(0));
+        }
+        self.real_op(f64::ln)
+    }
+
+    pub fn sin(&self) -> Result<Number, ArithmeticError> {
+        self.real_op(f64::sin)
+    }
+
+    pub fn cos(&self) -> Result<Number, ArithmeticError> {
+        self.real_op(f64::cos)
+    }
+
+    pub fn tan(&self) -> Result<Number, ArithmeticError> {
+        self.real_op(f64::tan)
+    }
+
+    pub fn asin(&self) -> Result<Number, ArithmeticError> {
+        self.real_op(f64::asin)
+    }
+
+    pub fn acos(&self) -> Result<Number, ArithmeticError> {
+        self.real_op(f64::acos)
+    }
+
+    pub fn atan(&self) -> Result<Number, ArithmeticError> {
+        self.real_op(f64::atan)
+    }
+
+    /// The angle of the point `(x, y)`, as in two-argument `atan`.
+    pub fn atan2(y: &Number, x: &Number) -> Result<Number, ArithmeticError> {
+        if !y.is_real() || !x.is_real() {
+            return Err(ArithmeticError::NonReal);
+        }
+        Ok(Self::Real(y.to_f64().atan2(x.to_f64())))
+    }
+
+    fn real_op(&self, op: fn(f64) -> f64) -> Result<Number, ArithmeticError> {
+        if !self.is_real() {
+            return Err(ArithmeticError::NonReal);
+        }
+        // An exact zero argument gives an exact result where the function
+        // is zero there.
+        if self.is_exact_zero() && op(0.0) == 0.0 {
+            return Ok(Self::Fixnum(0));
+        }
+        Ok(Self::Real(op(self.to_f64())))
+    }
+
+    /// Refuses exact powers whose result would have more than
+    /// [`MAX_POWER_BITS`] bits.
+    fn check_power_size(&self, exponent: u32) -> Result<(), ArithmeticError> {
+        // A lower bound, so that e.g. powers of two are allowed right up to
+        // the limit.
+        let bits = self.exact_bits().saturating_sub(1);
+        if bits.saturating_mul(exponent as u64) > MAX_POWER_BITS {
+            return Err(ArithmeticError::TooLarge);
+        }
+        Ok(())
+    }
+
+    /// Roughly how many bits an exact number takes, counting the larger of
+    /// a ratio's parts and of a complex number's parts.
+    fn exact_bits(&self) -> u64 {
+        match self {
+            Self::Fixnum(i) => 64 - i.unsigned_abs().leading_zeros() as u64,
+            Self::Bignum(b) => b.bits(),
+            Self::Rational(q) => q.numer().bits().max(q.denom().bits()),
+            Self::Real(_) => 0,
+            Self::Complex(z) => z.re().exact_bits().max(z.im().exact_bits()),
+        }
+    }
+
+    fn pow_by_squaring(&self, mut exp: u32) -> Number {
+        let mut base = self.clone();
+        let mut result = Self::Fixnum(1);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+}
+
+impl Number {
+    /// Formats the number in the given radix, as in `number->string`.
+    /// Inexact numbers are always written in decimal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is not in the range 2 to 36.
+    pub fn to_string_radix(&self, radix: u32) -> String {
+        match self {
+            Self::Fixnum(i) if radix == 10 => i.to_string(),
+            Self::Fixnum(i) => BigInt::from(*i).to_string_radix(radix),
+            Self::Bignum(b) => b.to_string_radix(radix),
+            Self::Rational(q) => format!(
+                "{}/{}",
+                q.numer().to_string_radix(radix),
+                q.denom().to_string_radix(radix)
+            ),
+            Self::Real(_) => self.to_string(),
+            Self::Complex(z) => {
+                // An exact zero real part is left out and an exact unit
+                // imaginary part is written as just its sign, as in `+i`.
+                let re = match z.re() {
+                    Self::Fixnum(0) => String::new(),
+                    re => re.to_string_radix(radix),
+                };
+                let im = match z.im() {
+                    Self::Fixnum(1) => "+".to_string(),
+                    Self::Fixnum(-1) => "-".to_string(),
+                    im => im.to_string_radix(radix),
+                };
+                let sign = if im.starts_with(['+', '-']) { "" } else { "+" };
+                format!("{}{}{}i", re, sign, im)
+            }
+        }
+    }
+}
+
+/// The rational with the smallest denominator, and then the smallest
+/// numerator, between the exact numbers `lo` and `hi` inclusive. It is
+/// found from the continued fractions of the two bounds, which agree up to
+/// the last term.
+fn simplest_between(lo: &Number, hi: &Number) -> Result<Number, ArithmeticError> {
+    if hi.is_negative() {
+        return Ok(-simplest_between(&-hi, &-lo)?);
+    }
+    if lo.is_negative() || lo.is_zero() {
+        return Ok(Number::Fixnum(0));
+    }
+    let one = Number::Fixnum(1);
+    let (mut lo, mut hi) = (lo.clone(), hi.clone());
+    let mut terms = Vec::new();
+    let last = loop {
+        let floor = lo.floor()?;
+        if floor == lo {
+            break floor;
+        }
+        if floor < hi.floor()? {
+            break &floor + &one;
+        }
+        (lo, hi) = (one.div(&(&hi - &floor))?, one.div(&(&lo - &floor))?);
+        terms.push(floor);
+    };
+    terms
+        .into_iter()
+        .rev()
+        .try_fold(last, |rest, term| Ok(&term + &one.div(&rest)?))
+}
+
+fn exact_from_f64(r: f64) -> Result<Number, ArithmeticError> {
+    if !r.is_finite() {
+        return Err(ArithmeticError::NoExactRepresentation);
+    }
+    if r.fract() == 0.0 && r.abs() < i64::MAX as f64 {
+        return Ok(Number::Fixnum(r as i64));
+    }
+    // Decompose into mantissa * 2^exponent.
+    let bits = r.to_bits();
+    let negative = bits >> 63 == 1;
+    let biased_exponent = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    let (mantissa, exponent) = if biased_exponent == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | (1 << 52), biased_exponent - 1075)
+    };
+    let mut mantissa = BigInt::from(mantissa);
+    if negative {
+        mantissa = -&mantissa;
+    }
+    let two = BigInt::from(2i64);
+    if exponent >= 0 {
+        Ok(Number::from(&mantissa * &two.pow(exponent as u32)))
+    } else {
+        Ok(Number::from(Rational::new(
+            mantissa,
+            two.pow(-exponent as u32),
+        )))
+    }
+}
+
+impl From<i64> for Number {
+    fn from(i: i64) -> Self {
+        Self::Fixnum(i)
+    }
+}
+
+impl From<f64> for Number {
+    fn from(r: f64) -> Self {
+        Self::Real(r)
+    }
+}
+
+impl From<BigInt> for Number {
+    fn from(b: BigInt) -> Self {
+        match b.to_i64() {
+            Some(i) => Self::Fixnum(i),
+            None => Self::Bignum(b),
+        }
+    }
+}
+
+impl From<Rational> for Number {
+    fn from(q: Rational) -> Self {
+        if q.is_integer() {
+            Self::from(q.numer().clone())
+        } else {
+            Self::Rational(q)
+        }
+    }
+}
+
+impl From<Complex> for Number {
+    fn from(z: Complex) -> Self {
+        if z.im().is_exact_zero() {
+            z.re().clone()
+        } else {
+            Self::Complex(Box::new(z))
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Fixnum(l), Self::Fixnum(r)) => Some(l.cmp(r)),
+            (Self::Complex(_), _) | (_, Self::Complex(_)) => {
+                (self.to_complex() == other.to_complex()).then_some(Ordering::Equal)
+            }
+            (Self::Real(_), _) | (_, Self::Real(_)) => self.to_f64().partial_cmp(&other.to_f64()),
+            (Self::Rational(_), _) | (_, Self::Rational(_)) => {
+                Some(self.to_rational().cmp(&other.to_rational()))
+            }
+            _ => Some(self.to_bigint().cmp(&other.to_bigint())),
+        }
+    }
+}
+
+impl Neg for &Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        match self {
+            Number::Fixnum(i) => match i.checked_neg() {
+                Some(i) => Number::Fixnum(i),
+                None => Number::from(-&BigInt::from(*i)),
+            },
+            Number::Bignum(b) => Number::from(-b),
+            Number::Rational(q) => Number::Rational(q.neg()),
+            Number::Real(r) => Number::Real(-r),
+            Number::Complex(z) => Number::from(z.neg()),
+        }
+    }
+}
+
+macro_rules! impl_arith_op {
+    ($trait:ident, $method:ident, $checked:ident) => {
+        impl $trait for &Number {
+            type Output = Number;
+
+            fn $method(self, rhs: &Number) -> Number {
+                match (self, rhs) {
+                    (Number::Fixnum(l), Number::Fixnum(r)) => match l.$checked(*r) {
+                        Some(result) => Number::Fixnum(result),
+                        None => Number::from($trait::$method(&BigInt::from(*l), &BigInt::from(*r))),
+                    },
+                    (Number::Complex(_), _) | (_, Number::Complex(_)) => {
+                        Number::from(self.to_complex().$method(&rhs.to_complex()))
+                    }
+                    (Number::Real(_), _) | (_, Number::Real(_)) => {
+                        Number::Real($trait::$method(self.to_f64(), rhs.to_f64()))
+                    }
+                    (Number::Rational(_), _) | (_, Number::Rational(_)) => {
+                        Number::from(self.to_rational().$method(&rhs.to_rational()))
+                    }
+                    _ => Number::from($trait::$method(&self.to_bigint(), &rhs.to_bigint())),
+                }
+            }
+        }
+
+        impl $trait for Number {
+            type Output = Number;
+
+            fn $method(self, rhs: Number) -> Number {
+                $trait::$method(&self, &rhs)
+            }
+        }
+    };
+}
+
+impl_arith_op!(Add, add, checked_add);
+impl_arith_op!(Sub, sub, checked_sub);
+impl_arith_op!(Mul, mul, checked_mul);
+
+impl Neg for Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        -&self
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Real(r) if r.is_nan() => write!(f, "+nan.0"),
+            Self::Real(r) if r.is_infinite() => {
+                write!(f, "{}inf.0", if *r > 0.0 { '+' } else { '-' })
+            }
+            Self::Real(r) => write!(f, "{:?}", r),
+            _ => f.write_str(&self.to_string_radix(10)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(re: i64, im: i64) -> Number {
+        Number::make_rectangular(&Number::Fixnum(re), &Number::Fixnum(im))
+    }
+
+    #[test]
+    fn writes_complex_numbers_in_shortest_form() {
+        assert_eq!(exact(0, 2).to_string(), "+2i");
+        assert_eq!(exact(0, 1).to_string(), "+i");
+        assert_eq!(exact(0, -1).to_string(), "-i");
+        assert_eq!(exact(3, -1).to_string(), "3-i");
+        assert_eq!(exact(3, 4).to_string(), "3+4i");
+        assert_eq!(exact(0, -5).to_string_radix(2), "-101i");
+        let inexact = Number::make_rectangular(&Number::Real(0.0), &Number::Real(1.0));
+        assert_eq!(inexact.to_string(), "0.0+1.0i");
+    }
+
+    #[test]
+    fn fixnum_overflow_promotes_to_bignums() {
+        let max = Number::Fixnum(i64::MAX);
+        let one = Number::Fixnum(1);
+        assert_eq!((&max + &one).to_string(), "9223372036854775808");
+        assert_eq!((&(&max + &one) - &one).to_i64(), Some(i64::MAX));
+        assert_eq!(
+            (&Number::Fixnum(i64::MIN) - &one).to_string(),
+            "-9223372036854775809"
+        );
+        assert_eq!(
+            (&max * &max).to_string(),
+            "85070591730234615847396907784232501249"
+        );
+        assert_eq!(
+            (-&Number::Fixnum(i64::MIN)).to_string(),
+            "9223372036854775808"
+        );
+        let power = Number::Fixnum(2).expt(&Number::Fixnum(100)).unwrap();
+        assert_eq!(power.to_string(), "1267650600228229401496703205376");
+    }
+
+    #[test]
+    fn magnitude_stays_exact_when_it_can() {
+        assert_eq!(exact(3, 4).magnitude().to_string(), "5");
+        assert_eq!(exact(-5, 12).magnitude().to_string(), "13");
+        assert!(!exact(1, 1).magnitude().is_exact());
+    }
+}
@@ -0,0 +1,534 @@
+//! Arbitrary-precision integers.
+//!
+//! Magnitudes are stored as little-endian base 2^32 digits with no trailing
+//! zero digits, so zero is the empty vector and is never negative.
+//!
+//! The quadratic operations charge the running evaluation a step for every
+//! thousand or so digit operations, so that fuel, timeouts and interrupts
+//! can stop them part way through.
+
+use crate::limits;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    mag: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self {
+            negative: false,
+            mag: Vec::new(),
+        }
+    }
+
+    fn from_parts(negative: bool, mut mag: Vec<u32>) -> Self {
+        while mag.last() == Some(&0) {
+            mag.pop();
+        }
+        Self {
+            negative: negative && !mag.is_empty(),
+            mag,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.mag.first().is_none_or(|d| d & 1 == 0)
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            negative: false,
+            mag: self.mag.clone(),
+        }
+    }
+
+    /// Returns the value as an `i64` if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.mag.len() > 2 {
+            return None;
+        }
+        let mut mag = 0u64;
+        for (i, d) in self.mag.iter().enumerate() {
+            mag |= (*d as u64) << (32 * i);
+        }
+        if self.negative {
+            if mag <= i64::MAX as u64 + 1 {
+                Some((mag as i64).wrapping_neg())
+            } else {
+                None
+            }
+        } else {
+            i64::try_from(mag).ok()
+        }
+    }
+
+    /// Returns the value as an `i128` if it fits.
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.mag.len() > 4 {
+            return None;
+        }
+        let mut mag = 0u128;
+        for (i, d) in self.mag.iter().enumerate() {
+            mag |= (*d as u128) << (32 * i);
+        }
+        if self.negative {
+            if mag <= i128::MAX as u128 + 1 {
+                Some((mag as i128).wrapping_neg())
+            } else {
+                None
+            }
+        } else {
+            i128::try_from(mag).ok()
+        }
+    }
+
+    /// The number of bits in the magnitude.
+    pub fn bits(&self) -> u64 {
+        match self.mag.last() {
+            Some(top) => self.mag.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let mut result = 0.0;
+        for d in self.mag.iter().rev() {
+            result = result * 4294967296.0 + *d as f64;
+        }
+        if self.negative {
+            -result
+        } else {
+            result
+        }
+    }
+
+    /// Truncating division, returning the quotient and remainder. The
+    /// remainder takes the sign of the dividend.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rhs` is zero.
+    pub fn div_rem(&self, rhs: &Self) -> (Self, Self) {
+        assert!(!rhs.is_zero(), "BigInt division by zero");
+        let (q, r) = div_rem_mag(&self.mag, &rhs.mag);
+        (
+            Self::from_parts(self.negative != rhs.negative, q),
+            Self::from_parts(self.negative, r),
+        )
+    }
+
+    pub fn gcd(&self, other: &Self) -> Self {
+        let mut a = self.abs();
+        let mut b = other.abs();
+        while !b.is_zero() {
+            let (_, r) = a.div_rem(&b);
+            a = b;
+            b = r;
+        }
+        a
+    }
+
+    /// Parses a string of digits in the given radix, with an optional
+    /// leading sign.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is not in the range 2 to 36.
+    pub fn parse_radix(text: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match text.as_bytes().first()? {
+            b'-' => (true, &text[1..]),
+            b'+' => (false, &text[1..]),
+            _ => (false, text),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+        let mut mag = Vec::new();
+        let mut work = Work::default();
+        for c in digits.chars() {
+            work.add(mag.len());
+            let digit = c.to_digit(radix)?;
+            let mut carry = digit as u64;
+            for d in mag.iter_mut() {
+                let t = *d as u64 * radix as u64 + carry;
+                *d = t as u32;
+                carry = t >> 32;
+            }
+            if carry > 0 {
+                mag.push(carry as u32);
+            }
+        }
+        Some(Self::from_parts(negative, mag))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `radix` is not in the range 2 to 36.
+    pub fn to_string_radix(&self, radix: u32) -> String {
+        assert!((2..=36).contains(&radix), "invalid radix {}", radix);
+        if self.is_zero() {
+            return "0".to_string();
+        }
+        // Divide by the largest power of the radix that fits in a digit.
+        let mut chunk = radix;
+        let mut chunk_digits = 1;
+        while let Some(next) = chunk.checked_mul(radix) {
+            chunk = next;
+            chunk_digits += 1;
+        }
+        let mut digits = Vec::new();
+        let mut mag = self.mag.clone();
+        let mut work = Work::default();
+        while !mag.is_empty() {
+            work.add(mag.len());
+            let (q, mut r) = div_rem_small(&mag, chunk);
+            mag = q;
+            for _ in 0..chunk_digits {
+                digits.push(char::from_digit(r % radix, radix).unwrap());
+                r /= radix;
+                if mag.is_empty() && r == 0 {
+                    break;
+                }
+            }
+        }
+        if self.negative {
+            digits.push('-');
+        }
+        digits.iter().rev().collect()
+    }
+
+    pub fn pow(&self, mut exp: u32) -> Self {
+        let mut base = self.clone();
+        let mut result = Self::from(1i64);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(i: i64) -> Self {
+        let mut big = Self::from(i.unsigned_abs());
+        big.negative = i < 0;
+        big
+    }
+}
+
+impl From<u64> for BigInt {
+    fn from(u: u64) -> Self {
+        Self::from_parts(false, vec![u as u32, (u >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.mag.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: &BigInt) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::from_parts(self.negative, add_mag(&self.mag, &rhs.mag));
+        }
+        match cmp_mag(&self.mag, &rhs.mag) {
+            Ordering::Equal => BigInt::zero(),
+            Ordering::Greater => BigInt::from_parts(self.negative, sub_mag(&self.mag, &rhs.mag)),
+            Ordering::Less => BigInt::from_parts(rhs.negative, sub_mag(&rhs.mag, &self.mag)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: &BigInt) -> BigInt {
+        self + &-rhs
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: &BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != rhs.negative, mul_mag(&self.mag, &rhs.mag))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_radix(10))
+    }
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, d) in long.iter().enumerate() {
+        let sum = *d as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// Computes `a - b`, where `a` must be at least `b`.
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, d) in a.iter().enumerate() {
+        let diff = *d as i64 - b.get(i).copied().unwrap_or(0) as i64 - borrow;
+        result.push(diff as u32);
+        borrow = (diff < 0) as i64;
+    }
+    result
+}
+
+/// Digit operations not yet charged to the running evaluation.
+#[derive(Default)]
+struct Work(usize);
+
+impl Work {
+    const PER_STEP: usize = 1024;
+
+    fn add(&mut self, ops: usize) {
+        self.0 += ops;
+        if self.0 >= Self::PER_STEP {
+            limits::charge((self.0 / Self::PER_STEP) as u64);
+            self.0 %= Self::PER_STEP;
+        }
+    }
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut result = vec![0u32; a.len() + b.len()];
+    let mut work = Work::default();
+    for (i, x) in a.iter().enumerate() {
+        work.add(b.len());
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let t = *x as u64 * *y as u64 + result[i + j] as u64 + carry;
+            result[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    result
+}
+
+fn div_rem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut rem = 0u64;
+    for (i, d) in a.iter().enumerate().rev() {
+        let cur = (rem << 32) | *d as u64;
+        quotient[i] = (cur / divisor as u64) as u32;
+        rem = cur % divisor as u64;
+    }
+    while quotient.last() == Some(&0) {
+        quotient.pop();
+    }
+    (quotient, rem as u32)
+}
+
+/// Long division of magnitudes (Knuth, TAOCP vol. 2, algorithm D).
+fn div_rem_mag(u: &[u32], v: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    const BASE: u64 = 1 << 32;
+    if cmp_mag(u, v) == Ordering::Less {
+        return (Vec::new(), u.to_vec());
+    }
+    if v.len() == 1 {
+        let (q, r) = div_rem_small(u, v[0]);
+        return (q, if r == 0 { Vec::new() } else { vec![r] });
+    }
+
+    // Normalize so the top digit of the divisor has its high bit set.
+    let shift = v[v.len() - 1].leading_zeros();
+    let vn = shl_bits(v, shift);
+    let mut un = shl_bits(u, shift);
+    let vn = &vn[..v.len()];
+    let n = vn.len();
+    let m = u.len() - n;
+
+    let mut q = vec![0u32; m + 1];
+    let mut work = Work::default();
+    for j in (0..=m).rev() {
+        work.add(n);
+        let num = ((un[j + n] as u64) << 32) | un[j + n - 1] as u64;
+        let mut qhat = num / vn[n - 1] as u64;
+        let mut rhat = num % vn[n - 1] as u64;
+        while qhat >= BASE || qhat * vn[n - 2] as u64 > ((rhat << 32) | un[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += vn[n - 1] as u64;
+            if rhat >= BASE {
+                break;
+            }
+        }
+
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let p = qhat * vn[i] as u64 + carry;
+            carry = p >> 32;
+            let t = un[i + j] as i64 - borrow - (p & 0xffff_ffff) as i64;
+            un[i + j] = t as u32;
+            borrow = (t < 0) as i64;
+        }
+        let t = un[j + n] as i64 - borrow - carry as i64;
+        un[j + n] = t as u32;
+
+        if t < 0 {
+            // qhat was one too large; add the divisor back.
+            qhat -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let s = un[i + j] as u64 + vn[i] as u64 + carry;
+                un[i + j] = s as u32;
+                carry = s >> 32;
+            }
+            un[j + n] = un[j + n].wrapping_add(carry as u32);
+        }
+        q[j] = qhat as u32;
+    }
+
+    let mut r = shr_bits(&un[..n], shift);
+    while q.last() == Some(&0) {
+        q.pop();
+    }
+    while r.last() == Some(&0) {
+        r.pop();
+    }
+    (q, r)
+}
+
+/// Shifts left by fewer than 32 bits. The result always has one more digit
+/// than the input to hold the overflow, even if that digit is zero.
+fn shl_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u32;
+    for d in a {
+        result.push((d << shift) | carry);
+        carry = if shift == 0 { 0 } else { d >> (32 - shift) };
+    }
+    result.push(carry);
+    result
+}
+
+fn shr_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut result = vec![0u32; a.len()];
+    for i in 0..a.len() {
+        let hi = a.get(i + 1).map_or(0, |d| d << (32 - shift));
+        result[i] = (a[i] >> shift) | hi;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        BigInt::parse_radix(text, 10).unwrap()
+    }
+
+    #[test]
+    fn parses_and_prints_in_any_radix() {
+        let n = big("-123456789012345678901234567890");
+        assert_eq!(n.to_string(), "-123456789012345678901234567890");
+        assert_eq!(
+            BigInt::parse_radix("ffffffffffffffffffff", 16)
+                .unwrap()
+                .to_string_radix(16),
+            "ffffffffffffffffffff"
+        );
+        assert_eq!(big("-0"), BigInt::zero());
+        assert!(!big("-0").is_negative());
+        assert_eq!(BigInt::parse_radix("12z", 10), None);
+    }
+
+    #[test]
+    fn multiplies_and_divides_past_64_bits() {
+        let a = big("340282366920938463463374607431768211457");
+        let b = big("-18446744073709551629");
+        let product = &a * &b;
+        let (quotient, remainder) = product.div_rem(&b);
+        assert_eq!(quotient, a);
+        assert!(remainder.is_zero());
+        let (quotient, remainder) = big("-7").div_rem(&big("2"));
+        assert_eq!(
+            (quotient.to_i64(), remainder.to_i64()),
+            (Some(-3), Some(-1))
+        );
+        assert_eq!(
+            BigInt::from(2i64).pow(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+    }
+
+    #[test]
+    fn converts_to_machine_numbers_when_it_fits() {
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(
+            (&BigInt::from(i64::MAX) + &BigInt::from(1i64)).to_i64(),
+            None
+        );
+        assert_eq!(BigInt::from(2i64).pow(70).to_f64(), 2f64.powi(70));
+        assert_eq!(big("12").gcd(&big("-18")).to_string(), "6");
+    }
+}
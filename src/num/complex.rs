@@ -0,0 +1,100 @@
+//! Complex numbers with real components from the rest of the tower.
+
+use super::{ArithmeticError, Number};
+
+/// A complex number whose parts are either both exact or both inexact.
+#[derive(Clone, Debug)]
+pub struct Complex {
+    re: Number,
+    im: Number,
+}
+
+impl Complex {
+    /// Creates a complex number from two real parts, making both inexact if
+    /// either one is.
+    pub fn new(re: Number, im: Number) -> Self {
+        if matches!(re, Number::Real(_)) || matches!(im, Number::Real(_)) {
+            Self {
+                re: re.to_inexact(),
+                im: im.to_inexact(),
+            }
+        } else {
+            Self { re, im }
+        }
+    }
+
+    pub fn from_polar(magnitude: &Number, angle: &Number) -> Self {
+        let (m, a) = (magnitude.to_f64(), angle.to_f64());
+        Self::new(Number::Real(m * a.cos()), Number::Real(m * a.sin()))
+    }
+
+    pub fn re(&self) -> &Number {
+        &self.re
+    }
+
+    pub fn im(&self) -> &Number {
+        &self.im
+    }
+
+    /// The magnitude, which is exact when both parts are and the square
+    /// root of the sum of their squares is, as for `3+4i`.
+    pub fn magnitude(&self) -> Number {
+        if self.re.is_exact() {
+            let root = (&(&self.re * &self.re) + &(&self.im * &self.im)).sqrt();
+            if root.is_exact() {
+                return root;
+            }
+        }
+        Number::Real(self.re.to_f64().hypot(self.im.to_f64()))
+    }
+
+    pub fn angle(&self) -> Number {
+        Number::Real(self.im.to_f64().atan2(self.re.to_f64()))
+    }
+
+    pub fn add(&self, rhs: &Self) -> Self {
+        Self::new(&self.re + &rhs.re, &self.im + &rhs.im)
+    }
+
+    pub fn sub(&self, rhs: &Self) -> Self {
+        Self::new(&self.re - &rhs.re, &self.im - &rhs.im)
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Self {
+        Self::new(
+            &(&self.re * &rhs.re) - &(&self.im * &rhs.im),
+            &(&self.re * &rhs.im) + &(&self.im * &rhs.re),
+        )
+    }
+
+    pub fn div(&self, rhs: &Self) -> Result<Self, ArithmeticError> {
+        let denom = &(&rhs.re * &rhs.re) + &(&rhs.im * &rhs.im);
+        let re = &(&self.re * &rhs.re) + &(&self.im * &rhs.im);
+        let im = &(&self.im * &rhs.re) - &(&self.re * &rhs.im);
+        Ok(Self::new(re.div(&denom)?, im.div(&denom)?))
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::new(-&self.re, -&self.im)
+    }
+
+    /// Computes `self` raised to `exponent` as `exp(exponent * log(self))`.
+    pub fn powc(&self, exponent: &Self) -> Self {
+        let (re, im) = (self.re.to_f64(), self.im.to_f64());
+        let (ln_re, ln_im) = (re.hypot(im).ln(), im.atan2(re));
+        let (e_re, e_im) = (exponent.re.to_f64(), exponent.im.to_f64());
+        let w_re = e_re * ln_re - e_im * ln_im;
+        let w_im = e_re * ln_im + e_im * ln_re;
+        let scale = w_re.exp();
+        Self::new(
+            Number::Real(scale * w_im.cos()),
+            Number::Real(scale * w_im.sin()),
+        )
+    }
+}
+
+impl PartialEq for Complex {
+    fn eq(&self, other: &Self) -> bool {
+        self.re == other.re && self.im == other.im
+    }
+}
@@ -0,0 +1,181 @@
+//! Parsing of the R7RS numeric syntax, shared by `string->number` and the
+//! reader.
+
+use super::{BigInt, Number, Rational, MAX_POWER_BITS};
+
+impl Number {
+    /// Parses a number in R7RS syntax, as in `string->number`. `radix` is
+    /// the default radix and may be overridden by a `#x`, `#o`, `#b` or `#d`
+    /// prefix, and `#e` or `#i` force the exactness of the result. Returns
+    /// `None` if `text` is not a valid number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is not in the range 2 to 36.
+    pub fn parse(text: &str, radix: u32) -> Option<Number> {
+        let mut radix_prefix = None;
+        let mut exactness = None;
+        let mut rest = text;
+        while let Some(prefix) = rest.strip_prefix('#') {
+            let mut chars = prefix.chars();
+            match chars.next()?.to_ascii_lowercase() {
+                'x' if radix_prefix.is_none() => radix_prefix = Some(16),
+                'o' if radix_prefix.is_none() => radix_prefix = Some(8),
+                'b' if radix_prefix.is_none() => radix_prefix = Some(2),
+                'd' if radix_prefix.is_none() => radix_prefix = Some(10),
+                'e' if exactness.is_none() => exactness = Some(Exactness::Exact),
+                'i' if exactness.is_none() => exactness = Some(Exactness::Inexact),
+                _ => return None,
+            }
+            rest = chars.as_str();
+        }
+        let parser = Parser {
+            radix: radix_prefix.unwrap_or(radix),
+            exactness,
+        };
+        let number = parser.complex(rest)?;
+        match exactness {
+            Some(Exactness::Exact) => number.to_exact().ok(),
+            Some(Exactness::Inexact) => Some(number.to_inexact()),
+            None => Some(number),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Exactness {
+    Exact,
+    Inexact,
+}
+
+struct Parser {
+    radix: u32,
+    exactness: Option<Exactness>,
+}
+
+impl Parser {
+    fn complex(&self, text: &str) -> Option<Number> {
+        if let Some(body) = text.strip_suffix(['i', 'I']) {
+            if let Some(z) = self.rectangular(body) {
+                return Some(z);
+            }
+        }
+        if let Some((magnitude, angle)) = text.split_once('@') {
+            return Some(Number::make_polar(
+                &self.real(magnitude)?,
+                &self.real(angle)?,
+            ));
+        }
+        self.real(text)
+    }
+
+    /// Parses `a+bi`, `+bi`, `a+i` and friends, with the trailing `i`
+    /// already removed.
+    fn rectangular(&self, body: &str) -> Option<Number> {
+        let (split, _) = body.char_indices().rev().find(|&(i, c)| {
+            (c == '+' || c == '-') && (i == 0 || !self.is_exponent_marker(&body[..i]))
+        })?;
+        let (re, im) = body.split_at(split);
+        let re = if re.is_empty() {
+            Number::Fixnum(0)
+        } else {
+            self.real(re)?
+        };
+        let im = match im {
+            "+" => Number::Fixnum(1),
+            "-" => Number::Fixnum(-1),
+            _ => self.real(im)?,
+        };
+        Some(Number::make_rectangular(&re, &im))
+    }
+
+    /// Whether the text before a sign ends in a decimal exponent marker, in
+    /// which case the sign belongs to the exponent.
+    fn is_exponent_marker(&self, before: &str) -> bool {
+        self.radix == 10
+            && before.ends_with(['e', 'E'])
+            && before[..before.len() - 1]
+                .chars()
+                .last()
+                .is_some_and(|c| c.is_ascii_digit() || c == '.')
+    }
+
+    fn real(&self, text: &str) -> Option<Number> {
+        match text.to_ascii_lowercase().as_str() {
+            "+inf.0" => return Some(Number::Real(f64::INFINITY)),
+            "-inf.0" => return Some(Number::Real(f64::NEG_INFINITY)),
+            "+nan.0" | "-nan.0" => return Some(Number::Real(f64::NAN)),
+            _ => (),
+        }
+        let (negative, unsigned) = match text.as_bytes().first()? {
+            b'+' => (false, &text[1..]),
+            b'-' => (true, &text[1..]),
+            _ => (false, text),
+        };
+        let magnitude = self.ureal(unsigned)?;
+        Some(if negative { -magnitude } else { magnitude })
+    }
+
+    fn ureal(&self, text: &str) -> Option<Number> {
+        if let Some((numer, denom)) = text.split_once('/') {
+            let numer = self.uinteger(numer)?;
+            let denom = self.uinteger(denom)?;
+            if denom.is_zero() {
+                return None;
+            }
+            return Some(Number::from(Rational::new(numer, denom)));
+        }
+        if let Some(integer) = self.uinteger(text) {
+            return Some(Number::from(integer));
+        }
+        if self.radix == 10 {
+            return self.decimal(text);
+        }
+        None
+    }
+
+    fn uinteger(&self, text: &str) -> Option<BigInt> {
+        if text.starts_with(['+', '-']) {
+            return None;
+        }
+        BigInt::parse_radix(text, self.radix)
+    }
+
+    fn decimal(&self, text: &str) -> Option<Number> {
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(i) => (&text[..i], Some(&text[i + 1..])),
+            None => (text, None),
+        };
+        let (integral, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if integral.len() + fraction.len() == 0 || !all_digits(integral) || !all_digits(fraction) {
+            return None;
+        }
+        let exponent: i64 = match exponent {
+            Some(e) => {
+                let digits = e.strip_prefix(['+', '-']).unwrap_or(e);
+                if digits.is_empty() || !all_digits(digits) {
+                    return None;
+                }
+                e.parse().ok()?
+            }
+            None => 0,
+        };
+        if self.exactness != Some(Exactness::Exact) {
+            return text.parse().ok().map(Number::Real);
+        }
+        // Build the exact value digits * 10^(exponent - fraction digits).
+        let digits = BigInt::parse_radix(&format!("{}{}", integral, fraction), 10)?;
+        let scale = exponent.checked_sub(fraction.len() as i64)?;
+        // Refuse powers of ten that `expt` would refuse too.
+        if scale.unsigned_abs() as f64 * std::f64::consts::LOG2_10 > MAX_POWER_BITS as f64 {
+            return None;
+        }
+        let power = BigInt::from(10i64).pow(u32::try_from(scale.unsigned_abs()).ok()?);
+        Some(if scale >= 0 {
+            Number::from(&digits * &power)
+        } else {
+            Number::from(Rational::new(digits, power))
+        })
+    }
+}
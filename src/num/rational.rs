@@ -0,0 +1,199 @@
+//! Exact rational numbers.
+
+use super::BigInt;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A ratio of two integers in lowest terms with a positive denominator.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numer: BigInt,
+    denom: BigInt,
+}
+
+impl Rational {
+    /// Creates a new rational in lowest terms.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `denom` is zero.
+    pub fn new(numer: BigInt, denom: BigInt) -> Self {
+        assert!(!denom.is_zero(), "Rational with zero denominator");
+        let gcd = numer.gcd(&denom);
+        let (mut numer, _) = numer.div_rem(&gcd);
+        let (mut denom, _) = denom.div_rem(&gcd);
+        if denom.is_negative() {
+            numer = -&numer;
+            denom = -&denom;
+        }
+        Self { numer, denom }
+    }
+
+    pub fn numer(&self) -> &BigInt {
+        &self.numer
+    }
+
+    pub fn denom(&self) -> &BigInt {
+        &self.denom
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denom == BigInt::from(1i64)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.numer.is_negative()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let (n, d) = (self.numer.to_f64(), self.denom.to_f64());
+        if n.is_finite() && d.is_finite() && d < (1u64 << 53) as f64 {
+            return n / d;
+        }
+        // Scale so that the integer quotient carries 64 bits of precision,
+        // then undo the scaling in floating point.
+        let shift = 64 + self.denom.bits() as i64 - self.numer.bits() as i64;
+        let two = BigInt::from(2i64);
+        let (quotient, _) = if shift >= 0 {
+            (&self.numer * &two.pow(shift as u32)).div_rem(&self.denom)
+        } else {
+            self.numer.div_rem(&(&self.denom * &two.pow(-shift as u32)))
+        };
+        let mut result = quotient.to_f64();
+        let mut shift = shift;
+        while shift > 0 {
+            let step = shift.min(1000);
+            result /= 2f64.powi(step as i32);
+            shift -= step;
+        }
+        while shift < 0 {
+            let step = (-shift).min(1000);
+            result *= 2f64.powi(step as i32);
+            shift += step;
+        }
+        result
+    }
+
+    pub fn add(&self, rhs: &Self) -> Self {
+        Self::new(
+            &(&self.numer * &rhs.denom) + &(&rhs.numer * &self.denom),
+            &self.denom * &rhs.denom,
+        )
+    }
+
+    pub fn sub(&self, rhs: &Self) -> Self {
+        Self::new(
+            &(&self.numer * &rhs.denom) - &(&rhs.numer * &self.denom),
+            &self.denom * &rhs.denom,
+        )
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Self {
+        Self::new(&self.numer * &rhs.numer, &self.denom * &rhs.denom)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `rhs` is zero.
+    pub fn div(&self, rhs: &Self) -> Self {
+        Self::new(&self.numer * &rhs.denom, &self.denom * &rhs.numer)
+    }
+
+    pub fn neg(&self) -> Self {
+        Self {
+            numer: -&self.numer,
+            denom: self.denom.clone(),
+        }
+    }
+
+    pub fn pow(&self, exp: u32) -> Self {
+        Self {
+            numer: self.numer.pow(exp),
+            denom: self.denom.pow(exp),
+        }
+    }
+
+    /// Rounds towards negative infinity.
+    pub fn floor(&self) -> BigInt {
+        let (q, r) = self.numer.div_rem(&self.denom);
+        if r.is_negative() {
+            &q - &BigInt::from(1i64)
+        } else {
+            q
+        }
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(i: BigInt) -> Self {
+        Self {
+            numer: i,
+            denom: BigInt::from(1i64),
+        }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.numer * &other.denom).cmp(&(&other.numer * &self.denom))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.numer, self.denom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::num::Number;
+
+    fn ratio(numer: i64, denom: i64) -> Rational {
+        Rational::new(BigInt::from(numer), BigInt::from(denom))
+    }
+
+    #[test]
+    fn keeps_ratios_in_lowest_terms() {
+        assert_eq!(ratio(6, -4).to_string(), "-3/2");
+        assert_eq!(ratio(1, 3).add(&ratio(1, 6)).to_string(), "1/2");
+        assert_eq!(
+            ratio(2, 3).mul(&ratio(3, 2)),
+            Rational::from(BigInt::from(1i64))
+        );
+        assert!(ratio(1, 3).sub(&ratio(1, 3)).numer().is_zero());
+        assert_eq!(ratio(-7, 2).floor().to_i64(), Some(-4));
+        assert_eq!(ratio(-2, 3).pow(3).to_string(), "-8/27");
+    }
+
+    #[test]
+    fn compares_and_converts() {
+        assert!(ratio(1, 3) < ratio(1, 2));
+        assert!(ratio(-1, 2) < ratio(-1, 3));
+        assert_eq!(ratio(1, 4).to_f64(), 0.25);
+        let tiny = Rational::new(BigInt::from(1i64), BigInt::from(10i64).pow(400));
+        assert_eq!(tiny.to_f64(), 0.0);
+        let close = Rational::new(
+            BigInt::from(10i64).pow(400),
+            &BigInt::from(3i64) * &BigInt::from(10i64).pow(399),
+        );
+        assert!((close.to_f64() - 10.0 / 3.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn dividing_integers_gives_exact_ratios() {
+        let third = Number::Fixnum(1).div(&Number::Fixnum(3)).unwrap();
+        assert_eq!(third.to_string(), "1/3");
+        assert!(third.is_exact());
+        assert_eq!(third.to_inexact().to_string(), "0.3333333333333333");
+        let whole = Number::Fixnum(6).div(&Number::Fixnum(3)).unwrap();
+        assert_eq!(whole.to_i64(), Some(2));
+    }
+}
@@ -0,0 +1,435 @@
+//! The datum reader.
+
+use crate::lexer::{CharSource, Lexer, Position, StrSource, Token};
+use crate::syntax::{Span, Syntax, SyntaxKind};
+use crate::value::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+/// How deeply lists, vectors, abbreviations and labels may nest. Reading
+/// does not recurse, but converting syntax to data and freeing it do, so
+/// deeper input is rejected.
+const MAX_DEPTH: usize = 1000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The input ended in the middle of a datum.
+    UnexpectedEof,
+    UnexpectedToken(String),
+    InvalidSyntax(String),
+    InvalidCharacter(String),
+    InvalidEscape(String),
+    InvalidByte,
+    UndefinedLabel(u64),
+    TooDeep,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+}
+
+impl ParseError {
+    /// Whether more input could complete the datum, e.g. for multi-line
+    /// REPL input.
+    pub fn is_incomplete(&self) -> bool {
+        self.kind == ParseErrorKind::UnexpectedEof
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.position.line, self.position.column)?;
+        match &self.kind {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedToken(token) => write!(f, "unexpected {}", token),
+            ParseErrorKind::InvalidSyntax(text) => write!(f, "invalid syntax {}", text),
+            ParseErrorKind::InvalidCharacter(name) => write!(f, "unknown character #\\{}", name),
+            ParseErrorKind::InvalidEscape(escape) => write!(f, "invalid escape {}", escape),
+            ParseErrorKind::InvalidByte => write!(f, "bytevector element is not a byte"),
+            ParseErrorKind::UndefinedLabel(label) => write!(f, "undefined label #{}#", label),
+            ParseErrorKind::TooDeep => write!(f, "nested too deeply"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses every datum in `text`.
+pub fn parse(text: &str) -> Result<Vec<Value>, ParseError> {
+    let mut parser = Parser::new(StrSource::new(text));
+    let mut data = Vec::new();
+    while let Some(datum) = parser.next_datum()? {
+        data.push(datum);
+    }
+    Ok(data)
+}
+
+/// Parses every datum in `text`, keeping source locations. `file` names the
+/// source in the resulting spans.
+pub fn parse_syntax(text: &str, file: Option<&str>) -> Result<Vec<Syntax>, ParseError> {
+    let mut parser = Parser::new(StrSource::new(text));
+    if let Some(file) = file {
+        parser = parser.with_file(file);
+    }
+    let mut syntax = Vec::new();
+    while let Some(datum) = parser.next_syntax()? {
+        syntax.push(datum);
+    }
+    Ok(syntax)
+}
+
+/// A datum the reader has started but not finished.
+enum Open {
+    List {
+        start: Position,
+        items: Vec<Syntax>,
+        tail: Tail,
+    },
+    Vector {
+        start: Position,
+        items: Vec<Syntax>,
+    },
+    Bytevector {
+        start: Position,
+        bytes: Vec<u8>,
+    },
+    /// `'`, `` ` ``, `,` or `,@`, waiting for the datum it applies to.
+    Abbreviation {
+        start: Position,
+        keyword: Syntax,
+    },
+    /// `#n=`, waiting for the datum it labels.
+    Label {
+        start: Position,
+        label: u64,
+    },
+    /// `#;`, waiting for the datum to skip.
+    Comment,
+}
+
+/// The dotted tail of an open list.
+enum Tail {
+    None,
+    /// After the dot.
+    Expected,
+    Read(Box<Syntax>),
+}
+
+pub struct Parser<S> {
+    lexer: Lexer<S>,
+    file: Option<Rc<str>>,
+    /// Labels defined so far in the current top-level datum.
+    labels: HashSet<u64>,
+}
+
+impl<S: CharSource> Parser<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            lexer: Lexer::new(source),
+            file: None,
+            labels: HashSet::new(),
+        }
+    }
+
+    /// Names the source in the spans of syntax read by this parser.
+    pub fn with_file(mut self, file: &str) -> Self {
+        self.file = Some(Rc::from(file));
+        self
+    }
+
+    pub fn into_source(self) -> S {
+        self.lexer.into_source()
+    }
+
+    /// Reads the next datum, or returns `None` at the end of input.
+    pub fn next_datum(&mut self) -> Result<Option<Value>, ParseError> {
+        Ok(self.next_syntax()?.map(|syntax| syntax.to_datum()))
+    }
+
+    /// Reads the next datum with its source locations, or returns `None` at
+    /// the end of input.
+    pub fn next_syntax(&mut self) -> Result<Option<Syntax>, ParseError> {
+        // Labels are scoped to the outermost datum.
+        self.labels.clear();
+        match self.next_token()? {
+            Some((token, position)) => self.read(token, position),
+            None => Ok(None),
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<(Token, Position)>, ParseError> {
+        self.lexer.next_token()
+    }
+
+    /// The span from `start` to the end of the last token read.
+    fn span_from(&self, start: Position) -> Span {
+        Span {
+            file: self.file.clone(),
+            start,
+            end: self.lexer.position(),
+        }
+    }
+
+    /// Reads the datum that starts with `token`. Data that are still open
+    /// are kept on a stack rather than read by recursion, so that deep
+    /// nesting is reported as an error instead of overflowing the stack.
+    /// Returns `None` if the input ends after a datum comment.
+    fn read(&mut self, token: Token, start: Position) -> Result<Option<Syntax>, ParseError> {
+        let mut open = Vec::new();
+        let mut first = Some((token, start));
+        loop {
+            let (token, start) = match first.take() {
+                Some(first) => first,
+                None => match self.next_token()? {
+                    Some(next) => next,
+                    None if open.is_empty() => return Ok(None),
+                    None => {
+                        return Err(ParseError {
+                            kind: ParseErrorKind::UnexpectedEof,
+                            position: self.lexer.position(),
+                        })
+                    }
+                },
+            };
+            let unexpected = |what: &str| ParseError {
+                kind: ParseErrorKind::UnexpectedToken(what.to_string()),
+                position: start,
+            };
+            match open.last_mut() {
+                Some(Open::List {
+                    tail: Tail::Read(_),
+                    ..
+                }) if !matches!(token, Token::RightParen | Token::DatumComment) => {
+                    return Err(unexpected("datum after dotted tail"));
+                }
+                Some(Open::Bytevector { bytes, .. })
+                    if !matches!(token, Token::RightParen | Token::DatumComment) =>
+                {
+                    bytes.push(Self::byte(token, start)?);
+                    continue;
+                }
+                _ => {}
+            }
+            let opened = match token {
+                Token::LeftParen => Open::List {
+                    start,
+                    items: Vec::new(),
+                    tail: Tail::None,
+                },
+                Token::VectorOpen => Open::Vector {
+                    start,
+                    items: Vec::new(),
+                },
+                Token::BytevectorOpen => Open::Bytevector {
+                    start,
+                    bytes: Vec::new(),
+                },
+                Token::Quote => self.abbreviation("quote", start),
+                Token::Quasiquote => self.abbreviation("quasiquote", start),
+                Token::Unquote => self.abbreviation("unquote", start),
+                Token::UnquoteSplicing => self.abbreviation("unquote-splicing", start),
+                Token::DatumComment => Open::Comment,
+                Token::LabelDefine(label) => {
+                    self.labels.insert(label);
+                    Open::Label { start, label }
+                }
+                Token::Dot => match open.last_mut() {
+                    Some(Open::List { items, tail, .. })
+                        if !items.is_empty() && matches!(tail, Tail::None) =>
+                    {
+                        *tail = Tail::Expected;
+                        continue;
+                    }
+                    _ => return Err(unexpected("\".\"")),
+                },
+                Token::RightParen => {
+                    let (start, kind) = match open.pop() {
+                        Some(Open::List {
+                            start,
+                            items,
+                            tail: Tail::None,
+                        }) => (start, SyntaxKind::List(items, None)),
+                        Some(Open::List {
+                            start,
+                            items,
+                            tail: Tail::Read(tail),
+                        }) => (start, SyntaxKind::List(items, Some(tail))),
+                        Some(Open::Vector { start, items }) => (start, SyntaxKind::Vector(items)),
+                        Some(Open::Bytevector { start, bytes }) => {
+                            (start, SyntaxKind::Atom(Value::bytevector(bytes)))
+                        }
+                        _ => return Err(unexpected("\")\"")),
+                    };
+                    let datum = Syntax {
+                        kind,
+                        span: self.span_from(start),
+                    };
+                    match self.close(&mut open, datum)? {
+                        Some(datum) => return Ok(Some(datum)),
+                        None => continue,
+                    }
+                }
+                token => {
+                    let datum = Syntax {
+                        kind: self.atom(token, start)?,
+                        span: self.span_from(start),
+                    };
+                    match self.close(&mut open, datum)? {
+                        Some(datum) => return Ok(Some(datum)),
+                        None => continue,
+                    }
+                }
+            };
+            if open.len() == MAX_DEPTH {
+                return Err(ParseError {
+                    kind: ParseErrorKind::TooDeep,
+                    position: start,
+                });
+            }
+            open.push(opened);
+        }
+    }
+
+    /// Adds a finished datum to the innermost open one, finishing that in
+    /// turn if it only needed one datum. Returns the datum once nothing is
+    /// left open.
+    fn close(
+        &mut self,
+        open: &mut Vec<Open>,
+        mut datum: Syntax,
+    ) -> Result<Option<Syntax>, ParseError> {
+        loop {
+            match open.last_mut() {
+                None => return Ok(Some(datum)),
+                Some(Open::List { items, tail, .. }) => {
+                    match tail {
+                        Tail::Expected => *tail = Tail::Read(Box::new(datum)),
+                        _ => items.push(datum),
+                    }
+                    return Ok(None);
+                }
+                Some(Open::Vector { items, .. }) => {
+                    items.push(datum);
+                    return Ok(None);
+                }
+                Some(Open::Comment) => {
+                    open.pop();
+                    return Ok(None);
+                }
+                // Bytevectors take their elements directly in `read`.
+                Some(Open::Bytevector { .. }) => unreachable!(),
+                Some(Open::Abbreviation { .. } | Open::Label { .. }) => {}
+            }
+            let (start, kind) = match open.pop() {
+                Some(Open::Abbreviation { start, keyword }) => {
+                    (start, SyntaxKind::List(vec![keyword, datum], None))
+                }
+                Some(Open::Label { start, label }) => {
+                    // `#0=#0#` has no datum to refer to.
+                    if matches!(datum.kind, SyntaxKind::LabelRef(l) if l == label) {
+                        return Err(ParseError {
+                            kind: ParseErrorKind::UndefinedLabel(label),
+                            position: start,
+                        });
+                    }
+                    (start, SyntaxKind::Labelled(label, Box::new(datum)))
+                }
+                _ => unreachable!(),
+            };
+            datum = Syntax {
+                kind,
+                span: self.span_from(start),
+            };
+        }
+    }
+
+    fn abbreviation(&self, name: &str, start: Position) -> Open {
+        let keyword = Syntax {
+            kind: SyntaxKind::Atom(Value::symbol(name)),
+            span: self.span_from(start),
+        };
+        Open::Abbreviation { start, keyword }
+    }
+
+    /// Reads a datum that does not contain other data.
+    fn atom(&mut self, token: Token, start: Position) -> Result<SyntaxKind, ParseError> {
+        Ok(match token {
+            Token::Boolean(b) => SyntaxKind::Atom(Value::Boolean(b)),
+            Token::Character(c) => SyntaxKind::Atom(Value::Character(c)),
+            Token::String(s) => SyntaxKind::Atom(Value::string(&s)),
+            Token::Number(n) => SyntaxKind::Atom(Value::Number(n)),
+            Token::Identifier(name) => SyntaxKind::Atom(Value::symbol(&name)),
+            Token::LabelRef(label) if self.labels.contains(&label) => SyntaxKind::LabelRef(label),
+            Token::LabelRef(label) => {
+                return Err(ParseError {
+                    kind: ParseErrorKind::UndefinedLabel(label),
+                    position: start,
+                })
+            }
+            _ => unreachable!("delimiters are handled by `read`"),
+        })
+    }
+
+    /// The byte a bytevector element stands for.
+    fn byte(token: Token, position: Position) -> Result<u8, ParseError> {
+        match token {
+            Token::Number(n) if n.is_exact() => {
+                if let Some(byte) = n.to_i64().and_then(|i| u8::try_from(i).ok()) {
+                    return Ok(byte);
+                }
+            }
+            _ => {}
+        }
+        Err(ParseError {
+            kind: ParseErrorKind::InvalidByte,
+            position,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested(depth: usize) -> String {
+        "(".repeat(depth) + &")".repeat(depth)
+    }
+
+    #[test]
+    fn reads_nesting_up_to_the_limit() {
+        assert_eq!(parse(&nested(MAX_DEPTH)).unwrap().len(), 1);
+        assert_eq!(
+            parse(&"#(".repeat(MAX_DEPTH)).unwrap_err().kind,
+            ParseErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn rejects_deeper_nesting() {
+        let err = parse(&nested(20_000)).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::TooDeep);
+        assert_eq!(
+            parse(&"'".repeat(20_000)).unwrap_err().kind,
+            ParseErrorKind::TooDeep
+        );
+    }
+
+    #[test]
+    fn skips_datum_comments_anywhere() {
+        let data = parse("(1 #;2 . #;x 3) #u8(1 #;#u8(5) 2) #;(4)").unwrap();
+        let text: Vec<_> = data.iter().map(Value::to_string).collect();
+        assert_eq!(text, ["(1 . 3)", "#u8(1 2)"]);
+        let err = parse("(a . b c)").unwrap_err();
+        assert!(matches!(err.kind, ParseErrorKind::UnexpectedToken(_)));
+    }
+
+    #[test]
+    fn reads_long_cyclic_lists() {
+        let text = format!("#0=({} . #0#)", "1 ".repeat(100_000));
+        let list = parse(&text).unwrap().remove(0);
+        assert!(list.list_to_vec().is_none());
+    }
+}
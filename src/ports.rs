@@ -0,0 +1,1105 @@
+//! Input and output ports.
+//!
+//! Every port is either textual or binary, as in R7RS. Textual ports decode
+//! and encode text with a [`Transcoder`], which defaults to UTF-8 with line
+//! endings left alone and U+FFFD substituted for invalid input. A binary
+//! port can be turned into a textual one with another transcoder, as in
+//! R6RS. All ports are buffered. End of file is reported as `Ok(None)`.
+//!
+//! Ports over anything else are made with a [`PortBackend`], which is
+//! called between port operations so that it can run Scheme code.
+
+use crate::eval::{Error, Interpreter};
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PortKind {
+    Textual,
+    Binary,
+}
+
+#[derive(Debug)]
+pub enum PortError {
+    Closed,
+    NotTextual,
+    NotBinary,
+    NotAccumulating,
+    /// Input that is invalid in the port's codec, with [`ErrorMode::Raise`].
+    Decode,
+    /// A character the port's codec cannot encode, with
+    /// [`ErrorMode::Raise`].
+    Encode(char),
+    /// A custom port has read all the input its backend has supplied so
+    /// far. The operation should be retried from a [`Checkpoint`] once the
+    /// backend has supplied more.
+    Starved,
+    Io(io::Error),
+}
+
+impl fmt::Display for PortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => write!(f, "port is closed"),
+            Self::NotTextual => write!(f, "expected a textual port"),
+            Self::NotBinary => write!(f, "expected a binary port"),
+            Self::NotAccumulating => write!(f, "not a string or bytevector output port"),
+            Self::Decode => write!(f, "invalid input for the port's codec"),
+            Self::Encode(c) => write!(f, "cannot encode {:?} in the port's codec", c),
+            Self::Starved => write!(f, "port needs more input from its backend"),
+            Self::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for PortError {}
+
+impl From<io::Error> for PortError {
+    fn from(err: io::Error) -> Self {
+        if err.get_ref().is_some_and(|inner| inner.is::<NeedsInput>()) {
+            Self::Starved
+        } else {
+            Self::Io(err)
+        }
+    }
+}
+
+/// The source or destination of a custom port's bytes. It is called with
+/// the interpreter between port operations rather than during them, so it
+/// can call Scheme procedures and raise Scheme errors.
+pub trait PortBackend {
+    /// Reads into `buf`, returning the number of bytes read or 0 at the end
+    /// of input. Only called for input ports.
+    fn read(&mut self, _interp: &mut Interpreter, _buf: &mut [u8]) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    /// Writes all of `bytes`. Only called for output ports.
+    fn write(&mut self, _interp: &mut Interpreter, _bytes: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once, when the port is closed from Scheme.
+    fn close(&mut self, _interp: &mut Interpreter) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Codec {
+    Utf8,
+    /// Reads a byte order mark if there is one and otherwise big-endian;
+    /// writes big-endian without a byte order mark.
+    Utf16,
+    Latin1,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 3] = [Self::Utf8, Self::Utf16, Self::Latin1];
+
+    /// The codec's name in Scheme, as returned by `utf-8-codec` and the
+    /// others.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16 => "utf-16",
+            Self::Latin1 => "latin-1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|codec| codec.name() == name)
+    }
+}
+
+/// How line endings are translated. On input, any style but `None` turns
+/// every line ending into `\n`; on output, `\n` is written as the style's
+/// ending.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EolStyle {
+    None,
+    Lf,
+    Cr,
+    Crlf,
+    Nel,
+    Crnel,
+    Ls,
+}
+
+impl EolStyle {
+    pub const ALL: [EolStyle; 7] = [
+        Self::None,
+        Self::Lf,
+        Self::Cr,
+        Self::Crlf,
+        Self::Nel,
+        Self::Crnel,
+        Self::Ls,
+    ];
+
+    /// The style's name in Scheme, as in R6RS `eol-style`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Lf => "lf",
+            Self::Cr => "cr",
+            Self::Crlf => "crlf",
+            Self::Nel => "nel",
+            Self::Crnel => "crnel",
+            Self::Ls => "ls",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|style| style.name() == name)
+    }
+
+    fn ending(self) -> &'static str {
+        match self {
+            Self::None | Self::Lf => "\n",
+            Self::Cr => "\r",
+            Self::Crlf => "\r\n",
+            Self::Nel => "\u{85}",
+            Self::Crnel => "\r\u{85}",
+            Self::Ls => "\u{2028}",
+        }
+    }
+}
+
+/// What happens to input that cannot be decoded and characters that
+/// cannot be encoded.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ErrorMode {
+    /// Input becomes U+FFFD and characters become `?`.
+    Replace,
+    /// They are skipped.
+    Ignore,
+    Raise,
+}
+
+impl ErrorMode {
+    pub const ALL: [ErrorMode; 3] = [Self::Replace, Self::Ignore, Self::Raise];
+
+    /// The mode's name in Scheme, as in R6RS `error-handling-mode`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Replace => "replace",
+            Self::Ignore => "ignore",
+            Self::Raise => "raise",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Transcoder {
+    pub codec: Codec,
+    pub eol_style: EolStyle,
+    pub error_mode: ErrorMode,
+}
+
+impl Transcoder {
+    pub fn new(codec: Codec) -> Self {
+        Self {
+            codec,
+            ..Self::default()
+        }
+    }
+
+    /// Encodes `s` with line endings translated.
+    pub fn encode(&self, s: &str) -> Result<Vec<u8>, PortError> {
+        let mut bytes = Vec::with_capacity(s.len());
+        for c in s.chars() {
+            if c == '\n' && self.eol_style != EolStyle::None {
+                for c in self.eol_style.ending().chars() {
+                    self.encode_char(c, &mut bytes)?;
+                }
+            } else {
+                self.encode_char(c, &mut bytes)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn encode_char(&self, c: char, bytes: &mut Vec<u8>) -> Result<(), PortError> {
+        match self.codec {
+            Codec::Utf8 => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            Codec::Utf16 => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    bytes.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            Codec::Latin1 => match u8::try_from(c) {
+                Ok(byte) => bytes.push(byte),
+                Err(_) => match self.error_mode {
+                    ErrorMode::Replace => bytes.push(b'?'),
+                    ErrorMode::Ignore => {}
+                    ErrorMode::Raise => return Err(PortError::Encode(c)),
+                },
+            },
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Transcoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.codec.name(),
+            self.eol_style.name(),
+            self.error_mode.name()
+        )
+    }
+}
+
+impl Default for Transcoder {
+    /// UTF-8 with line endings left alone, replacing invalid input.
+    fn default() -> Self {
+        Self {
+            codec: Codec::Utf8,
+            eol_style: EolStyle::None,
+            error_mode: ErrorMode::Replace,
+        }
+    }
+}
+
+/// A port as a Scheme value.
+pub enum Port {
+    Input(RefCell<InputPort>),
+    Output(RefCell<OutputPort>),
+}
+
+impl Port {
+    pub fn kind(&self) -> PortKind {
+        match self {
+            Self::Input(port) => port.borrow().kind(),
+            Self::Output(port) => port.borrow().kind(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        match self {
+            Self::Input(port) => port.borrow().is_open(),
+            Self::Output(port) => port.borrow().is_open(),
+        }
+    }
+
+    /// As in `close-port`.
+    pub fn close(&self) -> Result<(), PortError> {
+        match self {
+            Self::Input(port) => {
+                port.borrow_mut().close();
+                Ok(())
+            }
+            Self::Output(port) => port.borrow_mut().close(),
+        }
+    }
+}
+
+impl From<InputPort> for Port {
+    fn from(port: InputPort) -> Self {
+        Self::Input(RefCell::new(port))
+    }
+}
+
+impl From<OutputPort> for Port {
+    fn from(port: OutputPort) -> Self {
+        Self::Output(RefCell::new(port))
+    }
+}
+
+pub struct InputPort {
+    kind: PortKind,
+    reader: Option<Box<dyn BufRead>>,
+    transcoder: Transcoder,
+    peeked: Option<char>,
+    /// A decoded character read ahead to translate a line ending.
+    pending: Option<char>,
+    /// Whether UTF-16 input is little-endian, once the start of the input
+    /// has been checked for a byte order mark.
+    little_endian: Option<bool>,
+    custom: Option<Custom>,
+    /// Whether reading may wait for input, as from stdin or a socket,
+    /// rather than from memory or a file.
+    may_block: bool,
+}
+
+/// Input that could not be decoded.
+struct Invalid;
+
+struct Custom {
+    backend: Rc<RefCell<dyn PortBackend>>,
+    feed: Rc<RefCell<Feed>>,
+}
+
+/// The input a custom port's backend has supplied.
+#[derive(Default)]
+struct Feed {
+    bytes: Vec<u8>,
+    /// The offset in the whole input of `bytes[0]`.
+    start: u64,
+    /// The offset of the next byte to read.
+    pos: u64,
+    ended: bool,
+}
+
+/// Reads a [`Feed`], failing with [`NeedsInput`] when it runs out before
+/// its end.
+struct FeedReader {
+    feed: Rc<RefCell<Feed>>,
+    /// A copy of the feed's bytes from offset `window_at`, since the bytes
+    /// in the `RefCell` cannot be lent out by `fill_buf`.
+    window: Vec<u8>,
+    window_at: u64,
+}
+
+#[derive(Debug)]
+struct NeedsInput;
+
+impl fmt::Display for NeedsInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("needs more input")
+    }
+}
+
+impl std::error::Error for NeedsInput {}
+
+impl Read for FeedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for FeedReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let feed = self.feed.borrow();
+        let window_end = self.window_at + self.window.len() as u64;
+        if feed.pos < self.window_at || feed.pos >= window_end {
+            let from = (feed.pos - feed.start) as usize;
+            if from == feed.bytes.len() && !feed.ended {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, NeedsInput));
+            }
+            self.window = feed.bytes[from..].to_vec();
+            self.window_at = feed.pos;
+        }
+        let offset = (feed.pos - self.window_at) as usize;
+        drop(feed);
+        Ok(&self.window[offset..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.feed.borrow_mut().pos += amt as u64;
+    }
+}
+
+/// The state of an input port before an operation, to rewind to if the
+/// operation fails with [`PortError::Starved`].
+pub struct Checkpoint {
+    pos: Option<u64>,
+    peeked: Option<char>,
+    pending: Option<char>,
+    little_endian: Option<bool>,
+}
+
+impl InputPort {
+    pub fn from_reader(reader: impl Read + 'static, kind: PortKind) -> Self {
+        Self {
+            kind,
+            reader: Some(Box::new(BufReader::new(reader))),
+            transcoder: Transcoder::default(),
+            peeked: None,
+            pending: None,
+            little_endian: None,
+            custom: None,
+            may_block: true,
+        }
+    }
+
+    /// A port reading from `backend`. Operations on it fail with
+    /// [`PortError::Starved`] whenever they need more input than has been
+    /// passed to [`InputPort::supply`].
+    pub fn custom(backend: impl PortBackend + 'static, kind: PortKind) -> Self {
+        let feed = Rc::new(RefCell::new(Feed::default()));
+        let reader = FeedReader {
+            feed: feed.clone(),
+            window: Vec::new(),
+            window_at: 0,
+        };
+        Self {
+            kind,
+            reader: Some(Box::new(reader)),
+            transcoder: Transcoder::default(),
+            peeked: None,
+            pending: None,
+            little_endian: None,
+            custom: Some(Custom {
+                backend: Rc::new(RefCell::new(backend)),
+                feed,
+            }),
+            may_block: false,
+        }
+    }
+
+    /// As in R6RS `transcoded-port`: a textual port reading this binary
+    /// port's input with `transcoder`. This port is left closed.
+    pub fn transcoded(&mut self, transcoder: Transcoder) -> Result<Self, PortError> {
+        self.expect(PortKind::Binary)?;
+        Ok(Self {
+            kind: PortKind::Textual,
+            reader: self.reader.take(),
+            transcoder,
+            peeked: None,
+            pending: None,
+            little_endian: None,
+            custom: self.custom.take(),
+            may_block: self.may_block,
+        })
+    }
+
+    pub fn transcoder(&self) -> Transcoder {
+        self.transcoder
+    }
+
+    /// The backend of a custom port that is still open.
+    pub fn backend(&self) -> Option<Rc<RefCell<dyn PortBackend>>> {
+        self.custom.as_ref().map(|custom| custom.backend.clone())
+    }
+
+    /// Saves the port's state before an operation. Input read before the
+    /// last checkpoint may be discarded.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        let pos = self.custom.as_ref().map(|custom| {
+            let mut feed = custom.feed.borrow_mut();
+            // Only once it is at least half the input kept, so that a
+            // checkpoint before every character does not copy the rest each
+            // time.
+            let read = (feed.pos - feed.start) as usize;
+            if read * 2 >= feed.bytes.len() {
+                feed.bytes.drain(..read);
+                feed.start = feed.pos;
+            }
+            feed.pos
+        });
+        Checkpoint {
+            pos,
+            peeked: self.peeked,
+            pending: self.pending,
+            little_endian: self.little_endian,
+        }
+    }
+
+    /// Puts the port back in the state saved by `checkpoint`.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        if let (Some(custom), Some(pos)) = (&self.custom, checkpoint.pos) {
+            custom.feed.borrow_mut().pos = pos;
+        }
+        self.peeked = checkpoint.peeked;
+        self.pending = checkpoint.pending;
+        self.little_endian = checkpoint.little_endian;
+    }
+
+    /// Adds input read from a custom port's backend, where no bytes mean
+    /// the end of input.
+    pub fn supply(&mut self, bytes: &[u8]) {
+        if let Some(custom) = &self.custom {
+            let mut feed = custom.feed.borrow_mut();
+            feed.bytes.extend_from_slice(bytes);
+            feed.ended |= bytes.is_empty();
+        }
+    }
+
+    /// As in `open-input-file` and `open-binary-input-file`.
+    pub fn open_file(path: impl AsRef<Path>, kind: PortKind) -> io::Result<Self> {
+        Ok(Self::from_reader(File::open(path)?, kind).never_blocking())
+    }
+
+    /// As in `open-input-string`.
+    pub fn from_string(s: &str) -> Self {
+        Self::from_reader(Cursor::new(s.as_bytes().to_vec()), PortKind::Textual).never_blocking()
+    }
+
+    /// As in `open-input-bytevector`.
+    pub fn from_bytevector(bytes: Vec<u8>) -> Self {
+        Self::from_reader(Cursor::new(bytes), PortKind::Binary).never_blocking()
+    }
+
+    fn never_blocking(mut self) -> Self {
+        self.may_block = false;
+        self
+    }
+
+    pub fn stdin() -> Self {
+        Self::from_reader(io::stdin(), PortKind::Textual)
+    }
+
+    pub fn kind(&self) -> PortKind {
+        self.kind
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.reader.is_some()
+    }
+
+    /// As in `close-port` and `close-input-port`.
+    pub fn close(&mut self) {
+        self.reader = None;
+        self.peeked = None;
+        self.pending = None;
+        self.custom = None;
+    }
+
+    /// As in `read-char`.
+    pub fn read_char(&mut self) -> Result<Option<char>, PortError> {
+        self.expect(PortKind::Textual)?;
+        match self.peeked.take() {
+            Some(c) => Ok(Some(c)),
+            None => self.decode_char(),
+        }
+    }
+
+    /// As in `peek-char`.
+    pub fn peek_char(&mut self) -> Result<Option<char>, PortError> {
+        self.expect(PortKind::Textual)?;
+        if self.peeked.is_none() {
+            self.peeked = self.decode_char()?;
+        }
+        Ok(self.peeked)
+    }
+
+    /// As in `read-line`. The line terminator is consumed but not returned;
+    /// `\n`, `\r` and `\r\n` are all recognized.
+    pub fn read_line(&mut self) -> Result<Option<String>, PortError> {
+        let mut line = String::new();
+        loop {
+            match self.read_char()? {
+                None if line.is_empty() => return Ok(None),
+                None | Some('\n') => return Ok(Some(line)),
+                Some('\r') => {
+                    if self.peek_char()? == Some('\n') {
+                        self.read_char()?;
+                    }
+                    return Ok(Some(line));
+                }
+                Some(c) => line.push(c),
+            }
+        }
+    }
+
+    /// As in `read-string`: reads up to `k` characters.
+    pub fn read_string(&mut self, k: usize) -> Result<Option<String>, PortError> {
+        let mut s = String::new();
+        for _ in 0..k {
+            match self.read_char()? {
+                Some(c) => s.push(c),
+                None => break,
+            }
+        }
+        Ok((k == 0 || !s.is_empty()).then_some(s))
+    }
+
+    /// As in `read-u8`.
+    pub fn read_u8(&mut self) -> Result<Option<u8>, PortError> {
+        self.expect(PortKind::Binary)?;
+        next_byte(self.reader()?, true)
+    }
+
+    /// As in `peek-u8`.
+    pub fn peek_u8(&mut self) -> Result<Option<u8>, PortError> {
+        self.expect(PortKind::Binary)?;
+        next_byte(self.reader()?, false)
+    }
+
+    /// As in `read-bytevector`: reads up to `k` bytes.
+    pub fn read_bytevector(&mut self, k: usize) -> Result<Option<Vec<u8>>, PortError> {
+        self.expect(PortKind::Binary)?;
+        let mut bytes = Vec::with_capacity(k);
+        self.reader()?.take(k as u64).read_to_end(&mut bytes)?;
+        Ok((k == 0 || !bytes.is_empty()).then_some(bytes))
+    }
+
+    /// Whether input of `kind` can be read without waiting, as in
+    /// `char-ready?` and `u8-ready?`. A port at the end of its input is
+    /// ready. Ports that may wait, such as stdin and sockets, only count as
+    /// ready once a character has been peeked from them, and custom ports
+    /// once their backend has supplied input.
+    pub fn is_ready(&mut self, kind: PortKind) -> Result<bool, PortError> {
+        self.expect(kind)?;
+        if self.peeked.is_some() || self.pending.is_some() {
+            return Ok(true);
+        }
+        if self.custom.is_some() {
+            return match self.reader()?.fill_buf().map_err(PortError::from) {
+                Ok(_) => Ok(true),
+                Err(PortError::Starved) => Ok(false),
+                Err(err) => Err(err),
+            };
+        }
+        Ok(!self.may_block)
+    }
+
+    /// Reads between one and `k` bytes, as many as are available without
+    /// waiting for more input if there are any. Returns `None` at the end of
+    /// input.
+    pub fn read_some(&mut self, k: usize) -> Result<Option<Vec<u8>>, PortError> {
+        self.expect(PortKind::Binary)?;
+        let reader = self.reader()?;
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(None);
+        }
+        let bytes = available[..available.len().min(k)].to_vec();
+        reader.consume(bytes.len());
+        Ok(Some(bytes))
+    }
+
+    fn reader(&mut self) -> Result<&mut Box<dyn BufRead>, PortError> {
+        self.reader.as_mut().ok_or(PortError::Closed)
+    }
+
+    fn expect(&self, kind: PortKind) -> Result<(), PortError> {
+        if self.reader.is_none() {
+            Err(PortError::Closed)
+        } else if self.kind != kind {
+            Err(match kind {
+                PortKind::Textual => PortError::NotTextual,
+                PortKind::Binary => PortError::NotBinary,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Decodes the next character, translating line endings.
+    fn decode_char(&mut self) -> Result<Option<char>, PortError> {
+        let c = match self.pending.take() {
+            Some(c) => Some(c),
+            None => self.decode()?,
+        };
+        if self.transcoder.eol_style == EolStyle::None {
+            return Ok(c);
+        }
+        match c {
+            Some('\r') => {
+                match self.decode()? {
+                    Some('\n' | '\u{85}') => {}
+                    next => self.pending = next,
+                }
+                Ok(Some('\n'))
+            }
+            Some('\u{85}' | '\u{2028}') => Ok(Some('\n')),
+            c => Ok(c),
+        }
+    }
+
+    /// Decodes the next character as the error mode says.
+    fn decode(&mut self) -> Result<Option<char>, PortError> {
+        loop {
+            let decoded = match self.transcoder.codec {
+                Codec::Utf8 => decode_utf8(self.reader()?)?,
+                Codec::Utf16 => self.decode_utf16()?,
+                Codec::Latin1 => next_byte(self.reader()?, true)?.map(|b| Ok(char::from(b))),
+            };
+            match decoded {
+                None => return Ok(None),
+                Some(Ok(c)) => return Ok(Some(c)),
+                Some(Err(Invalid)) => match self.transcoder.error_mode {
+                    ErrorMode::Replace => return Ok(Some(char::REPLACEMENT_CHARACTER)),
+                    ErrorMode::Ignore => continue,
+                    ErrorMode::Raise => return Err(PortError::Decode),
+                },
+            }
+        }
+    }
+
+    fn decode_utf16(&mut self) -> Result<Option<Result<char, Invalid>>, PortError> {
+        let mut unit = match self.next_unit()? {
+            Some(Ok(unit)) => unit,
+            Some(Err(Invalid)) => return Ok(Some(Err(Invalid))),
+            None => return Ok(None),
+        };
+        // A byte order mark, read as big-endian, says which order follows.
+        if self.little_endian.is_none() {
+            self.little_endian = Some(unit == 0xfffe);
+            if unit == 0xfeff || unit == 0xfffe {
+                unit = match self.next_unit()? {
+                    Some(Ok(unit)) => unit,
+                    Some(Err(Invalid)) => return Ok(Some(Err(Invalid))),
+                    None => return Ok(None),
+                };
+            }
+        }
+        Ok(Some(self.decode_units(unit)))
+    }
+
+    /// The character starting with the code unit `first`, reading its low
+    /// surrogate if it has one.
+    fn decode_units(&mut self, first: u16) -> Result<char, Invalid> {
+        if !(0xd800..0xdc00).contains(&first) {
+            return char::from_u32(u32::from(first)).ok_or(Invalid);
+        }
+        match self.next_unit() {
+            Ok(Some(Ok(second))) => char::decode_utf16([first, second])
+                .next()
+                .and_then(Result::ok)
+                .ok_or(Invalid),
+            _ => Err(Invalid),
+        }
+    }
+
+    /// The next UTF-16 code unit, or `Invalid` if the input ends halfway
+    /// through one.
+    fn next_unit(&mut self) -> Result<Option<Result<u16, Invalid>>, PortError> {
+        let little_endian = self.little_endian == Some(true);
+        let reader = self.reader()?;
+        let Some(first) = next_byte(reader, true)? else {
+            return Ok(None);
+        };
+        let Some(second) = next_byte(reader, true)? else {
+            return Ok(Some(Err(Invalid)));
+        };
+        let bytes = [first, second];
+        Ok(Some(Ok(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })))
+    }
+}
+
+fn decode_utf8(reader: &mut dyn BufRead) -> Result<Option<Result<char, Invalid>>, PortError> {
+    let Some(first) = next_byte(reader, true)? else {
+        return Ok(None);
+    };
+    let len = match first {
+        0x00..=0x7f => return Ok(Some(Ok(first as char))),
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Ok(Some(Err(Invalid))),
+    };
+    let mut buf = [first, 0, 0, 0];
+    for slot in buf.iter_mut().take(len).skip(1) {
+        match next_byte(reader, false)? {
+            Some(b) if b & 0xc0 == 0x80 => {
+                *slot = b;
+                next_byte(reader, true)?;
+            }
+            // Leave the offending byte to start the next character.
+            _ => return Ok(Some(Err(Invalid))),
+        }
+    }
+    Ok(Some(
+        std::str::from_utf8(&buf[..len])
+            .ok()
+            .and_then(|s| s.chars().next())
+            .ok_or(Invalid),
+    ))
+}
+
+fn next_byte(reader: &mut dyn BufRead, consume: bool) -> Result<Option<u8>, PortError> {
+    let byte = reader.fill_buf()?.first().copied();
+    if consume && byte.is_some() {
+        reader.consume(1);
+    }
+    Ok(byte)
+}
+
+enum Sink {
+    /// Accumulates output for `get-output-string` or
+    /// `get-output-bytevector`.
+    Buffer(Vec<u8>),
+    Writer(BufWriter<Box<dyn Write>>),
+    /// Holds output for a custom port until it is taken for the backend.
+    Backend(Vec<u8>, Rc<RefCell<dyn PortBackend>>),
+}
+
+pub struct OutputPort {
+    kind: PortKind,
+    sink: Option<Sink>,
+    transcoder: Transcoder,
+}
+
+impl OutputPort {
+    pub fn from_writer(writer: impl Write + 'static, kind: PortKind) -> Self {
+        let writer: Box<dyn Write> = Box::new(writer);
+        Self {
+            kind,
+            sink: Some(Sink::Writer(BufWriter::new(writer))),
+            transcoder: Transcoder::default(),
+        }
+    }
+
+    /// As in `open-output-file` and `open-binary-output-file`.
+    pub fn open_file(path: impl AsRef<Path>, kind: PortKind) -> io::Result<Self> {
+        Ok(Self::from_writer(File::create(path)?, kind))
+    }
+
+    /// As in `open-output-string`.
+    pub fn string() -> Self {
+        Self {
+            kind: PortKind::Textual,
+            sink: Some(Sink::Buffer(Vec::new())),
+            transcoder: Transcoder::default(),
+        }
+    }
+
+    /// As in `open-output-bytevector`.
+    pub fn bytevector() -> Self {
+        Self {
+            kind: PortKind::Binary,
+            sink: Some(Sink::Buffer(Vec::new())),
+            transcoder: Transcoder::default(),
+        }
+    }
+
+    /// A port writing to `backend`, whose output is held until taken by
+    /// [`OutputPort::take_output`].
+    pub fn custom(backend: impl PortBackend + 'static, kind: PortKind) -> Self {
+        Self {
+            kind,
+            sink: Some(Sink::Backend(Vec::new(), Rc::new(RefCell::new(backend)))),
+            transcoder: Transcoder::default(),
+        }
+    }
+
+    /// Standard output, without buffering beyond that of [`io::Stdout`], so
+    /// that the port's output interleaves with other writes to it.
+    pub fn stdout() -> Self {
+        Self::unbuffered(io::stdout())
+    }
+
+    pub fn stderr() -> Self {
+        Self::unbuffered(io::stderr())
+    }
+
+    fn unbuffered(writer: impl Write + 'static) -> Self {
+        let writer: Box<dyn Write> = Box::new(writer);
+        Self {
+            kind: PortKind::Textual,
+            sink: Some(Sink::Writer(BufWriter::with_capacity(0, writer))),
+            transcoder: Transcoder::default(),
+        }
+    }
+
+    pub fn kind(&self) -> PortKind {
+        self.kind
+    }
+
+    /// As in R6RS `transcoded-port`: a textual port writing to this binary
+    /// port's destination with `transcoder`. This port is left closed.
+    pub fn transcoded(&mut self, transcoder: Transcoder) -> Result<Self, PortError> {
+        self.expect(PortKind::Binary)?;
+        Ok(Self {
+            kind: PortKind::Textual,
+            sink: self.sink.take(),
+            transcoder,
+        })
+    }
+
+    pub fn transcoder(&self) -> Transcoder {
+        self.transcoder
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// The backend of a custom port that is still open.
+    pub fn backend(&self) -> Option<Rc<RefCell<dyn PortBackend>>> {
+        match &self.sink {
+            Some(Sink::Backend(_, backend)) => Some(backend.clone()),
+            _ => None,
+        }
+    }
+
+    /// The output written to a custom port since this was last called.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        match &mut self.sink {
+            Some(Sink::Backend(bytes, _)) => std::mem::take(bytes),
+            _ => Vec::new(),
+        }
+    }
+
+    /// As in `close-port` and `close-output-port`. Buffered output is
+    /// flushed first.
+    pub fn close(&mut self) -> Result<(), PortError> {
+        let result = self.flush();
+        self.sink = None;
+        match result {
+            Err(PortError::Closed) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// As in `flush-output-port`.
+    pub fn flush(&mut self) -> Result<(), PortError> {
+        match self.sink.as_mut().ok_or(PortError::Closed)? {
+            Sink::Buffer(_) | Sink::Backend(..) => Ok(()),
+            Sink::Writer(writer) => Ok(writer.flush()?),
+        }
+    }
+
+    /// As in `write-char`.
+    pub fn write_char(&mut self, c: char) -> Result<(), PortError> {
+        self.write_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// As in `write-string`.
+    pub fn write_str(&mut self, s: &str) -> Result<(), PortError> {
+        self.expect(PortKind::Textual)?;
+        if self.transcoder == Transcoder::default() {
+            return self.write_raw(s.as_bytes());
+        }
+        let bytes = self.transcoder.encode(s)?;
+        self.write_raw(&bytes)
+    }
+
+    /// As in `write-u8`.
+    pub fn write_u8(&mut self, byte: u8) -> Result<(), PortError> {
+        self.write_bytevector(&[byte])
+    }
+
+    /// As in `write-bytevector`.
+    pub fn write_bytevector(&mut self, bytes: &[u8]) -> Result<(), PortError> {
+        self.expect(PortKind::Binary)?;
+        self.write_raw(bytes)
+    }
+
+    /// As in `get-output-string`.
+    pub fn get_output_string(&self) -> Result<String, PortError> {
+        self.expect(PortKind::Textual)?;
+        match &self.sink {
+            Some(Sink::Buffer(bytes)) => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            _ => Err(PortError::NotAccumulating),
+        }
+    }
+
+    /// As in `get-output-bytevector`. Textual ports that accumulate output
+    /// give the bytes they have encoded, so the output of a port transcoded
+    /// from a bytevector port can be recovered.
+    pub fn get_output_bytevector(&self) -> Result<Vec<u8>, PortError> {
+        match self.sink.as_ref().ok_or(PortError::Closed)? {
+            Sink::Buffer(bytes) => Ok(bytes.clone()),
+            Sink::Writer(_) | Sink::Backend(..) => Err(PortError::NotAccumulating),
+        }
+    }
+
+    fn write_raw(&mut self, bytes: &[u8]) -> Result<(), PortError> {
+        match self.sink.as_mut().ok_or(PortError::Closed)? {
+            Sink::Buffer(buf) | Sink::Backend(buf, _) => buf.extend_from_slice(bytes),
+            Sink::Writer(writer) => writer.write_all(bytes)?,
+        }
+        Ok(())
+    }
+
+    fn expect(&self, kind: PortKind) -> Result<(), PortError> {
+        if self.sink.is_none() {
+            Err(PortError::Closed)
+        } else if self.kind != kind {
+            Err(match kind {
+                PortKind::Textual => PortError::NotTextual,
+                PortKind::Binary => PortError::NotBinary,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for OutputPort {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], transcoder: Transcoder) -> Result<String, PortError> {
+        let mut port = InputPort::from_bytevector(bytes.to_vec()).transcoded(transcoder)?;
+        Ok(port.read_string(usize::MAX)?.unwrap_or_default())
+    }
+
+    fn transcoder(codec: Codec, eol_style: EolStyle, error_mode: ErrorMode) -> Transcoder {
+        Transcoder {
+            codec,
+            eol_style,
+            error_mode,
+        }
+    }
+
+    #[test]
+    fn decodes_each_codec() {
+        let utf16 = Transcoder::new(Codec::Utf16);
+        assert_eq!(
+            decode(&[0, b'h', 0xd8, 0x3d, 0xde, 0x00], utf16).unwrap(),
+            "h😀"
+        );
+        assert_eq!(
+            decode(&[0xff, 0xfe, b'h', 0, b'i', 0], utf16).unwrap(),
+            "hi"
+        );
+        let latin1 = Transcoder::new(Codec::Latin1);
+        assert_eq!(decode(&[b'c', 0xe9], latin1).unwrap(), "cé");
+        assert_eq!(
+            decode("añ".as_bytes(), Transcoder::default()).unwrap(),
+            "añ"
+        );
+    }
+
+    #[test]
+    fn translates_line_endings() {
+        let crlf = transcoder(Codec::Utf8, EolStyle::Crlf, ErrorMode::Replace);
+        assert_eq!(decode(b"a\r\nb\rc\n", crlf).unwrap(), "a\nb\nc\n");
+        assert_eq!(crlf.encode("a\nb").unwrap(), b"a\r\nb");
+        let none = Transcoder::default();
+        assert_eq!(decode(b"a\r\nb", none).unwrap(), "a\r\nb");
+        let utf16 = transcoder(Codec::Utf16, EolStyle::Lf, ErrorMode::Replace);
+        assert_eq!(utf16.encode("é\n").unwrap(), [0, 0xe9, 0, b'\n']);
+    }
+
+    #[test]
+    fn error_modes_replace_skip_or_raise() {
+        let bad = [b'a', 0xff, b'b'];
+        let mode = |error_mode| transcoder(Codec::Utf8, EolStyle::None, error_mode);
+        assert_eq!(
+            decode(&bad, mode(ErrorMode::Replace)).unwrap(),
+            "a\u{fffd}b"
+        );
+        assert_eq!(decode(&bad, mode(ErrorMode::Ignore)).unwrap(), "ab");
+        assert!(matches!(
+            decode(&bad, mode(ErrorMode::Raise)),
+            Err(PortError::Decode)
+        ));
+        let latin1 = |error_mode| transcoder(Codec::Latin1, EolStyle::None, error_mode);
+        assert_eq!(latin1(ErrorMode::Replace).encode("a€").unwrap(), b"a?");
+        assert_eq!(latin1(ErrorMode::Ignore).encode("a€").unwrap(), b"a");
+        assert!(matches!(
+            latin1(ErrorMode::Raise).encode("a€"),
+            Err(PortError::Encode('€'))
+        ));
+    }
+
+    #[test]
+    fn only_binary_ports_can_be_transcoded() {
+        let mut port = InputPort::from_string("text");
+        assert!(matches!(
+            port.transcoded(Transcoder::default()),
+            Err(PortError::NotBinary)
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+//! A width-limited pretty printer for data, behind `pretty-print` and the
+//! REPL.
+//!
+//! Values are first turned into a document of text, possible line breaks
+//! and groups, in the style of Wadler's "A prettier printer". A group is
+//! printed on one line if it fits in the remaining width and otherwise has
+//! each of its breaks turned into a newline. Lists headed by a body form
+//! such as `define` or `lambda` indent their body by two columns; other
+//! lists align their arguments with the first one.
+//!
+//! Circular structures are printed on one line by [`print::write`].
+
+use crate::print;
+use crate::symbol::Symbol;
+use crate::value::Value;
+use std::fmt::{self, Write};
+
+/// Forms whose last arguments are a body, paired with the number of
+/// arguments that come before it and stay on the first line.
+const BODY_FORMS: &[(&str, usize)] = &[
+    ("begin", 0),
+    ("case", 1),
+    ("case-lambda", 0),
+    ("define", 1),
+    ("define-values", 1),
+    ("do", 2),
+    ("dynamic-wind", 0),
+    ("guard", 1),
+    ("lambda", 1),
+    ("let", 1),
+    ("let*", 1),
+    ("let*-values", 1),
+    ("let-values", 1),
+    ("letrec", 1),
+    ("letrec*", 1),
+    ("parameterize", 1),
+    ("unless", 1),
+    ("when", 1),
+    ("with-continuation-mark", 2),
+    ("with-exception-handler", 0),
+];
+
+enum Doc {
+    Text(String),
+    /// A space, or a newline followed by the current indentation if the
+    /// enclosing group is broken.
+    Line,
+    /// Indents the lines `doc` breaks onto to the column `doc` starts at,
+    /// plus the given number of columns.
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+/// Writes `value` as `write` would, breaking lines to keep within `width`
+/// columns where possible.
+pub fn pretty_print(value: &Value, width: usize, out: &mut impl Write) -> fmt::Result {
+    if print::has_cycles(value) {
+        return print::write(value, out);
+    }
+    render(&doc(value), width, out)
+}
+
+fn text(value: &Value) -> Doc {
+    let mut s = String::new();
+    print::write(value, &mut s).expect("writing to a String cannot fail");
+    Doc::Text(s)
+}
+
+/// Joins `docs` with line breaks.
+fn lines(docs: Vec<Doc>) -> Doc {
+    let mut joined = Vec::with_capacity(docs.len() * 2);
+    for (i, doc) in docs.into_iter().enumerate() {
+        if i > 0 {
+            joined.push(Doc::Line);
+        }
+        joined.push(doc);
+    }
+    Doc::Concat(joined)
+}
+
+/// Joins `docs` with line breaks that are each taken only if the next
+/// document does not fit on the current line, to pack short items.
+fn fill(docs: Vec<Doc>) -> Doc {
+    let mut joined = Vec::with_capacity(docs.len());
+    for (i, doc) in docs.into_iter().enumerate() {
+        if i == 0 {
+            joined.push(doc);
+        } else {
+            joined.push(Doc::Group(Box::new(Doc::Concat(vec![Doc::Line, doc]))));
+        }
+    }
+    Doc::Concat(joined)
+}
+
+/// Lays out `docs` with [`fill`] if the values they came from are all
+/// atoms, and with [`lines`] otherwise.
+fn items(values: &[Value], docs: Vec<Doc>) -> Doc {
+    if values
+        .iter()
+        .all(|value| !matches!(value, Value::Pair(_) | Value::Vector(_)))
+    {
+        fill(docs)
+    } else {
+        lines(docs)
+    }
+}
+
+/// A group whose lines are indented by `indent` relative to its start.
+fn group(indent: usize, parts: Vec<Doc>) -> Doc {
+    Doc::Group(Box::new(Doc::Nest(indent, Box::new(Doc::Concat(parts)))))
+}
+
+fn doc(value: &Value) -> Doc {
+    match value {
+        Value::Pair(_) => list(value),
+        Value::Vector(items) => {
+            let items = items.borrow();
+            let docs = items.iter().map(doc).collect();
+            group(
+                2,
+                vec![
+                    Doc::Text("#(".into()),
+                    self::items(&items, docs),
+                    Doc::Text(")".into()),
+                ],
+            )
+        }
+        _ => text(value),
+    }
+}
+
+fn list(value: &Value) -> Doc {
+    let mut items = Vec::new();
+    let mut tail = value.clone();
+    while let Value::Pair(pair) = tail {
+        items.push(pair.car());
+        tail = pair.cdr();
+    }
+    let mut docs: Vec<Doc> = items.iter().map(doc).collect();
+    if !tail.is_null() {
+        docs.push(Doc::Text(".".into()));
+        docs.push(doc(&tail));
+        items.push(Value::Null);
+        items.push(tail);
+    }
+    let open = Doc::Text("(".into());
+    let close = Doc::Text(")".into());
+    let head = match &items[0] {
+        Value::Symbol(sym) if docs.len() > 1 => *sym,
+        _ => return group(1, vec![open, self::items(&items, docs), close]),
+    };
+    let mut args = docs.split_off(1);
+    let mut parts = vec![open, docs.pop().expect("list has a head")];
+    match body_form(head) {
+        Some(before) => {
+            let body = args.split_off(before.min(args.len()));
+            for arg in args {
+                parts.push(Doc::Text(" ".into()));
+                parts.push(arg);
+            }
+            if !body.is_empty() {
+                parts.push(Doc::Line);
+                parts.push(lines(body));
+            }
+            parts.push(close);
+            group(2, parts)
+        }
+        None => {
+            parts.push(Doc::Text(" ".into()));
+            parts.push(self::items(&items[1..], args));
+            parts.push(close);
+            group(head.name().chars().count() + 2, parts)
+        }
+    }
+}
+
+/// The number of arguments before the body, if `head` names a body form.
+fn body_form(head: Symbol) -> Option<usize> {
+    let name = head.name();
+    BODY_FORMS
+        .iter()
+        .find(|(form, _)| *form == name)
+        .map(|(_, before)| *before)
+}
+
+/// Whether the rest of the line fits in `width` columns, starting with the
+/// group `doc` printed flat and followed by the pending documents `rest`.
+fn fits(width: usize, doc: &Doc, rest: &[(usize, bool, &Doc)]) -> bool {
+    let mut remaining = width as isize;
+    let mut stack = vec![(true, doc)];
+    let mut rest = rest.iter().rev();
+    while remaining >= 0 {
+        let (flat, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some(&(_, flat, doc)) => (flat, doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(s) => remaining -= s.chars().count() as isize,
+            Doc::Line if flat => remaining -= 1,
+            Doc::Line => return true,
+            Doc::Nest(_, doc) | Doc::Group(doc) => stack.push((flat, doc)),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (flat, doc))),
+        }
+    }
+    false
+}
+
+fn render(doc: &Doc, width: usize, out: &mut impl Write) -> fmt::Result {
+    let mut column = 0;
+    // Documents still to print, last first, with their indentation and
+    // whether their enclosing group is flat.
+    let mut stack = vec![(0, false, doc)];
+    while let Some((indent, flat, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) => {
+                out.write_str(s)?;
+                column += s.chars().count();
+            }
+            Doc::Line if flat => {
+                out.write_char(' ')?;
+                column += 1;
+            }
+            Doc::Line => {
+                out.write_char('\n')?;
+                write!(out, "{:indent$}", "")?;
+                column = indent;
+            }
+            Doc::Nest(more, doc) => stack.push((column + more, flat, doc)),
+            Doc::Group(doc) => {
+                let flat = flat || fits(width.saturating_sub(column), doc, &stack);
+                stack.push((indent, flat, doc));
+            }
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, flat, doc))),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn pretty(text: &str, width: usize) -> String {
+        let value = parse::parse(text).unwrap().remove(0);
+        let mut out = String::new();
+        pretty_print(&value, width, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn short_data_stay_on_one_line() {
+        assert_eq!(
+            pretty("(a (b c) #(1 2) \"s\")", 80),
+            "(a (b c) #(1 2) \"s\")"
+        );
+    }
+
+    #[test]
+    fn long_lists_align_their_arguments() {
+        assert_eq!(
+            pretty("(list (f alpha) (g beta) (h gamma))", 20),
+            "(list (f alpha)\n      (g beta)\n      (h gamma))"
+        );
+    }
+
+    #[test]
+    fn atoms_are_packed_onto_lines() {
+        assert_eq!(
+            pretty("(list alpha beta gamma delta)", 20),
+            "(list alpha beta\n      gamma delta)"
+        );
+        assert_eq!(
+            pretty("#(alpha beta gamma delta)", 14),
+            "#(alpha beta\n  gamma delta)"
+        );
+    }
+
+    #[test]
+    fn body_forms_indent_their_bodies() {
+        assert_eq!(
+            pretty("(define (f x) (let ((y (* x x))) (+ y y)))", 24),
+            "(define (f x)\n  (let ((y (* x x)))\n    (+ y y)))"
+        );
+    }
+
+    #[test]
+    fn circular_data_print_on_one_line() {
+        let value = parse::parse("(a b)").unwrap().remove(0);
+        let Value::Pair(pair) = &value else {
+            unreachable!()
+        };
+        let Value::Pair(last) = pair.cdr() else {
+            unreachable!()
+        };
+        last.set_cdr(value.clone());
+        let mut out = String::new();
+        pretty_print(&value, 2, &mut out).unwrap();
+        assert_eq!(out, "#0=(a b . #0#)");
+    }
+}
@@ -0,0 +1,340 @@
+//! The printer behind `write`, `display`, `write-shared` and `write-simple`.
+//!
+//! Pairs and vectors that are reached more than once are written with datum
+//! labels (`#0=` and `#0#`). `write` and `display` label only the objects
+//! that make a structure circular, so they always terminate; `write-shared`
+//! labels every shared object; `write-simple` labels nothing and loops
+//! forever on circular input.
+
+use crate::net::Socket;
+use crate::num::Number;
+use crate::ports::Port;
+use crate::value::{Bytevector, Value};
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+/// Which objects get datum labels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Labels {
+    None,
+    Cycles,
+    Shared,
+}
+
+/// As in `write`.
+pub fn write(value: &Value, out: &mut impl Write) -> fmt::Result {
+    Printer::new(value, true, Labels::Cycles).print(value, out)
+}
+
+/// As in `write-shared`.
+pub fn write_shared(value: &Value, out: &mut impl Write) -> fmt::Result {
+    Printer::new(value, true, Labels::Shared).print(value, out)
+}
+
+/// As in `write-simple`.
+pub fn write_simple(value: &Value, out: &mut impl Write) -> fmt::Result {
+    Printer::new(value, true, Labels::None).print(value, out)
+}
+
+/// As in `display`: strings and characters are written as their contents
+/// and symbols without `|` quoting.
+pub fn display(value: &Value, out: &mut impl Write) -> fmt::Result {
+    Printer::new(value, false, Labels::Cycles).print(value, out)
+}
+
+/// Whether `value` contains a circular structure, which `write` would
+/// print with datum labels.
+pub(crate) fn has_cycles(value: &Value) -> bool {
+    !Printer::new(value, true, Labels::Cycles).labels.is_empty()
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display(self, f)
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write(self, f)
+    }
+}
+
+/// The identity of a pair or vector.
+fn address(value: &Value) -> Option<usize> {
+    match value {
+        Value::Pair(pair) => Some(Rc::as_ptr(pair) as usize),
+        Value::Vector(items) => Some(Rc::as_ptr(items) as *const u8 as usize),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    /// The object is being scanned, so reaching it again means a cycle.
+    Active,
+    Done,
+}
+
+struct Printer {
+    /// Whether to write strings, characters and symbols as literals.
+    literal: bool,
+    /// The objects to label, and the label assigned once the first
+    /// occurrence has been printed.
+    labels: HashMap<usize, Option<u64>>,
+    next_label: u64,
+}
+
+impl Printer {
+    fn new(value: &Value, literal: bool, mode: Labels) -> Self {
+        let mut printer = Self {
+            literal,
+            labels: HashMap::new(),
+            next_label: 0,
+        };
+        if mode != Labels::None {
+            printer.scan(value, mode, &mut HashMap::new());
+        }
+        printer
+    }
+
+    /// Finds the objects that need labels.
+    fn scan(&mut self, value: &Value, mode: Labels, visits: &mut HashMap<usize, Visit>) {
+        let parts = match value {
+            Value::Values(values) => &values[..],
+            Value::Condition(condition) => &condition.irritants[..],
+            _ => &[],
+        };
+        for part in parts {
+            self.scan(part, mode, visits);
+        }
+        // Walk down the cdrs iteratively so that long lists do not exhaust the
+        // stack; the whole spine stays active until the list is finished.
+        let mut spine = Vec::new();
+        let mut value = value.clone();
+        while let Some(addr) = address(&value) {
+            match visits.get(&addr) {
+                Some(visit) => {
+                    if *visit == Visit::Active || mode == Labels::Shared {
+                        self.labels.insert(addr, None);
+                    }
+                    break;
+                }
+                None => {
+                    visits.insert(addr, Visit::Active);
+                    spine.push(addr);
+                }
+            }
+            match value {
+                Value::Pair(pair) => {
+                    self.scan(&pair.car(), mode, visits);
+                    value = pair.cdr();
+                }
+                Value::Vector(items) => {
+                    for item in items.borrow().iter() {
+                        self.scan(item, mode, visits);
+                    }
+                    break;
+                }
+                _ => unreachable!(),
+            }
+        }
+        for addr in spine {
+            visits.insert(addr, Visit::Done);
+        }
+    }
+
+    /// Writes `#n=` before the first occurrence of a labelled object and
+    /// returns `true` if `#n#` was written instead.
+    fn label(&mut self, value: &Value, out: &mut impl Write) -> Result<bool, fmt::Error> {
+        let Some(label) = address(value).and_then(|addr| self.labels.get_mut(&addr)) else {
+            return Ok(false);
+        };
+        match label {
+            Some(n) => {
+                write!(out, "#{}#", n)?;
+                Ok(true)
+            }
+            None => {
+                *label = Some(self.next_label);
+                write!(out, "#{}=", self.next_label)?;
+                self.next_label += 1;
+                Ok(false)
+            }
+        }
+    }
+
+    fn is_labelled(&self, value: &Value) -> bool {
+        address(value).is_some_and(|addr| self.labels.contains_key(&addr))
+    }
+
+    fn print(&mut self, value: &Value, out: &mut impl Write) -> fmt::Result {
+        if self.label(value, out)? {
+            return Ok(());
+        }
+        match value {
+            Value::Null => out.write_str("()"),
+            Value::Boolean(true) => out.write_str("#t"),
+            Value::Boolean(false) => out.write_str("#f"),
+            Value::Number(n) => write!(out, "{}", n),
+            Value::Character(c) if self.literal => write_char_literal(*c, out),
+            Value::Character(c) => out.write_char(*c),
+            Value::String(s) if self.literal => write_string_literal(s.borrow().chars(), out),
+            Value::String(s) => write!(out, "{}", s.borrow()),
+            Value::Symbol(sym) if self.literal => write_symbol_literal(sym.name(), out),
+            Value::Symbol(sym) => out.write_str(sym.name()),
+            Value::Pair(pair) => {
+                out.write_char('(')?;
+                self.print(&pair.car(), out)?;
+                let mut tail = pair.cdr();
+                loop {
+                    match tail {
+                        Value::Null => break,
+                        // A labelled cdr has to be written in dotted form so
+                        // that the label has a datum to attach to.
+                        Value::Pair(next) if !self.is_labelled(&tail) => {
+                            out.write_char(' ')?;
+                            self.print(&next.car(), out)?;
+                            tail = next.cdr();
+                        }
+                        _ => {
+                            out.write_str(" . ")?;
+                            self.print(&tail, out)?;
+                            break;
+                        }
+                    }
+                }
+                out.write_char(')')
+            }
+            Value::Vector(items) => {
+                out.write_str("#(")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(' ')?;
+                    }
+                    self.print(item, out)?;
+                }
+                out.write_char(')')
+            }
+            Value::Bytevector(bytes) => write_bytevector(&bytes.borrow(), out),
+            Value::Procedure(procedure) => match procedure.name() {
+                Some(name) => write!(out, "#<procedure {}>", name),
+                None => out.write_str("#<procedure>"),
+            },
+            Value::Port(port) => match **port {
+                Port::Input(_) => out.write_str("#<input port>"),
+                Port::Output(_) => out.write_str("#<output port>"),
+            },
+            Value::Transcoder(transcoder) => write!(out, "#<transcoder {}>", transcoder),
+            Value::Socket(socket) => match **socket {
+                Socket::TcpListener(_) => out.write_str("#<tcp listener>"),
+                Socket::Udp(_) => out.write_str("#<udp socket>"),
+            },
+            Value::Process(process) => write!(out, "#<process {}>", process.id()),
+            Value::HashTable(_) => out.write_str("#<hash-table>"),
+            Value::Unspecified => out.write_str("#<unspecified>"),
+            Value::Eof => out.write_str("#<eof>"),
+            Value::Environment => out.write_str("#<environment>"),
+            Value::Values(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(' ')?;
+                    }
+                    self.print(value, out)?;
+                }
+                Ok(())
+            }
+            Value::Condition(condition) => {
+                out.write_str("#<error ")?;
+                write_string_literal(&condition.message.chars().collect::<Vec<_>>(), out)?;
+                for irritant in &condition.irritants {
+                    out.write_char(' ')?;
+                    self.print(irritant, out)?;
+                }
+                out.write_char('>')
+            }
+        }
+    }
+}
+
+fn write_char_literal(c: char, out: &mut impl Write) -> fmt::Result {
+    let name = match c {
+        '\u{7}' => "alarm",
+        '\u{8}' => "backspace",
+        '\u{7f}' => "delete",
+        '\u{1b}' => "escape",
+        '\n' => "newline",
+        '\0' => "null",
+        '\r' => "return",
+        ' ' => "space",
+        '\t' => "tab",
+        c if c.is_control() || c.is_whitespace() => {
+            return write!(out, "#\\x{:x}", c as u32);
+        }
+        c => return write!(out, "#\\{}", c),
+    };
+    write!(out, "#\\{}", name)
+}
+
+/// Writes the characters escaped as they would appear between `quote`
+/// characters.
+fn write_escaped(chars: &[char], quote: char, out: &mut impl Write) -> fmt::Result {
+    for &c in chars {
+        match c {
+            '\\' => out.write_str("\\\\")?,
+            '\u{7}' => out.write_str("\\a")?,
+            '\u{8}' => out.write_str("\\b")?,
+            '\t' => out.write_str("\\t")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            c if c == quote => write!(out, "\\{}", c)?,
+            c if c.is_control() => write!(out, "\\x{:x};", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_string_literal(chars: &[char], out: &mut impl Write) -> fmt::Result {
+    out.write_char('"')?;
+    write_escaped(chars, '"', out)?;
+    out.write_char('"')
+}
+
+fn write_symbol_literal(name: &str, out: &mut impl Write) -> fmt::Result {
+    if !needs_bars(name) {
+        return out.write_str(name);
+    }
+    let chars: Vec<char> = name.chars().collect();
+    out.write_char('|')?;
+    write_escaped(&chars, '|', out)?;
+    out.write_char('|')
+}
+
+/// Whether a symbol would read back as something else if written plainly.
+fn needs_bars(name: &str) -> bool {
+    name.is_empty()
+        || name == "."
+        || name.starts_with('#')
+        || Number::parse(name, 10).is_some()
+        || name.chars().any(|c| {
+            c.is_whitespace()
+                || c.is_control()
+                || matches!(
+                    c,
+                    '(' | ')' | '[' | ']' | '"' | ';' | '|' | '\'' | '`' | ','
+                )
+        })
+}
+
+fn write_bytevector(bytes: &Bytevector, out: &mut impl Write) -> fmt::Result {
+    out.write_str("#u8(")?;
+    for (i, byte) in bytes.as_bytes().iter().enumerate() {
+        if i > 0 {
+            out.write_char(' ')?;
+        }
+        write!(out, "{}", byte)?;
+    }
+    out.write_char(')')
+}
@@ -0,0 +1,250 @@
+//! Procedures.
+
+use crate::eval::{Error, Frame, Interpreter, Lambda};
+use crate::symbol::Symbol;
+use crate::value::Value;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+pub enum Procedure {
+    Closure(Closure),
+    CaseLambda(CaseLambda),
+    Primitive(Primitive),
+    Continuation(Continuation),
+    Parameter(Parameter),
+}
+
+impl Procedure {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Closure(closure) => closure.lambda.name.map(|name| name.name()),
+            Self::CaseLambda(_) => None,
+            Self::Primitive(primitive) => Some(primitive.name),
+            Self::Continuation(_) => Some("continuation"),
+            Self::Parameter(_) => Some("parameter"),
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        match self {
+            Self::Closure(closure) => closure.lambda.arity(),
+            Self::CaseLambda(case) => case.arity(),
+            Self::Primitive(primitive) => primitive.arity,
+            Self::Continuation(_) => Arity::at_least(0),
+            Self::Parameter(_) => Arity::exactly(0),
+        }
+    }
+}
+
+/// The number of arguments a procedure accepts.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Arity {
+    pub min: usize,
+    /// `None` if any number of further arguments is accepted.
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exactly(n: usize) -> Self {
+        Self {
+            min: n,
+            max: Some(n),
+        }
+    }
+
+    pub fn at_least(n: usize) -> Self {
+        Self { min: n, max: None }
+    }
+
+    pub fn between(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max: Some(max),
+        }
+    }
+
+    pub fn accepts(&self, n: usize) -> bool {
+        n >= self.min && self.max.is_none_or(|max| n <= max)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{} to {}", self.min, max),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+/// A procedure created by `lambda`.
+pub struct Closure {
+    pub(crate) lambda: Rc<Lambda>,
+    pub(crate) env: Option<Rc<Frame>>,
+    /// Set by `trace` to print each call and its result.
+    pub(crate) traced: Cell<bool>,
+}
+
+impl Closure {
+    pub fn name(&self) -> Option<Symbol> {
+        self.lambda.name
+    }
+}
+
+/// A procedure created by `case-lambda`. A call runs the first clause that
+/// accepts its number of arguments.
+pub struct CaseLambda {
+    pub(crate) clauses: Vec<Rc<Lambda>>,
+    pub(crate) env: Option<Rc<Frame>>,
+}
+
+impl CaseLambda {
+    /// The range from the fewest arguments any clause accepts to the most,
+    /// though a count between two clauses' arities may fit neither.
+    pub fn arity(&self) -> Arity {
+        let arities = self.clauses.iter().map(|lambda| lambda.arity());
+        Arity {
+            min: arities.clone().map(|arity| arity.min).min().unwrap_or(0),
+            max: arities
+                .map(|arity| arity.max)
+                .try_fold(0, |most, max| Some(most.max(max?))),
+        }
+    }
+
+    /// The clause to run for `n` arguments.
+    pub(crate) fn clause(&self, n: usize) -> Result<&Rc<Lambda>, Error> {
+        self.clauses
+            .iter()
+            .find(|lambda| lambda.arity().accepts(n))
+            .ok_or_else(|| Error::wrong_arity(None, self.arity(), n))
+    }
+}
+
+/// The signature of procedures implemented in Rust. The arguments have
+/// already been checked against the primitive's arity.
+pub type PrimitiveFn = fn(&mut Interpreter, &[Value]) -> Result<Value, Error>;
+
+pub struct Primitive {
+    pub name: &'static str,
+    pub arity: Arity,
+    pub func: PrimitiveFn,
+}
+
+/// An escape procedure captured by `call/cc`. It can only be used to leave
+/// the extent of the `call/cc` that created it, not to re-enter it.
+pub struct Continuation {
+    pub(crate) id: u64,
+    pub(crate) active: Cell<bool>,
+}
+
+/// A parameter object made by `make-parameter`. Calling it returns its
+/// value, which `parameterize` changes for the extent of its body.
+pub struct Parameter {
+    pub(crate) value: RefCell<Value>,
+    /// Applied to the values given by `parameterize`.
+    pub(crate) converter: Option<Value>,
+}
+
+impl Parameter {
+    pub fn get(&self) -> Value {
+        self.value.borrow().clone()
+    }
+
+    /// Runs `f` with the parameter set to `value`, restoring the previous
+    /// value afterwards whether or not `f` succeeds.
+    pub(crate) fn with<T>(&self, value: Value, f: impl FnOnce() -> T) -> T {
+        let previous = self.value.replace(value);
+        let result = f();
+        *self.value.borrow_mut() = previous;
+        result
+    }
+}
+
+/// Defines a primitive from a Rust function body over typed arguments.
+///
+/// Each argument is converted with [`FromValue`](crate::convert::FromValue),
+/// reporting a wrong-type error naming the primitive if that fails, and the
+/// result is converted with [`IntoValue`](crate::convert::IntoValue). A
+/// body declared to return `Result<T>` may use `?` on [`Error`]s.
+///
+/// For example, `builtin!(interp, "hypot", fn(x: f64, y: f64) -> f64 { x.hypot(y) })`.
+#[macro_export]
+macro_rules! builtin {
+    ($interp:expr, $name:literal, fn($($arg:ident: $ty:ty),* $(,)?) -> Result<$ret:ty> $body:block) => {{
+        fn primitive(
+            _: &mut $crate::eval::Interpreter,
+            args: &[$crate::value::Value],
+        ) -> Result<$crate::value::Value, $crate::eval::Error> {
+            let mut args = args.iter();
+            $(
+                let $arg = <$ty as $crate::convert::FromValue>::from_value(args.next().expect("arity checked").clone())
+                    .map_err(|err| $crate::eval::Error::wrong_type($name, err.expected, &err.value))?;
+            )*
+            fn body($($arg: $ty),*) -> Result<$ret, $crate::eval::Error> $body
+            body($($arg),*).map($crate::convert::IntoValue::into_value)
+        }
+        let arity = $crate::proc::Arity::exactly(<[&str]>::len(&[$(stringify!($arg)),*]));
+        $interp.define_primitive($name, arity, primitive);
+    }};
+    ($interp:expr, $name:literal, fn($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty $body:block) => {
+        $crate::builtin!($interp, $name, fn($($arg: $ty),*) -> Result<$ret> { Ok::<$ret, $crate::eval::Error>($body) })
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::{ConditionKind, Error};
+    use crate::Scheme;
+
+    fn scheme() -> Scheme {
+        let mut scheme = Scheme::new();
+        let interp = scheme.interpreter();
+        crate::builtin!(interp, "hypot", fn(x: f64, y: f64) -> f64 { x.hypot(y) });
+        crate::builtin!(interp, "repeat", fn(s: String, n: usize) -> String { s.repeat(n) });
+        crate::builtin!(interp, "checked-div", fn(a: i64, b: i64) -> Result<i64> {
+            a.checked_div(b).ok_or_else(|| {
+                Error::new(ConditionKind::Arithmetic, "checked-div: division by zero", Vec::new())
+            })
+        });
+        scheme
+    }
+
+    fn kind(scheme: &mut Scheme, text: &str) -> Option<ConditionKind> {
+        let err = scheme.eval_str(text).unwrap_err();
+        err.condition().map(|condition| condition.kind)
+    }
+
+    #[test]
+    fn builtins_convert_arguments_and_results() {
+        let mut scheme = scheme();
+        for (text, expected) in [
+            ("(hypot 3.0 4.0)", "5.0"),
+            ("(repeat \"ab\" 3)", "ababab"),
+            ("(checked-div 7 2)", "3"),
+        ] {
+            let value = scheme.eval_str(text).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn builtins_report_errors() {
+        let mut scheme = scheme();
+        assert_eq!(
+            kind(&mut scheme, "(checked-div 1 0)"),
+            Some(ConditionKind::Arithmetic)
+        );
+        assert_eq!(
+            kind(&mut scheme, "(repeat \"ab\" -1)"),
+            Some(ConditionKind::WrongType)
+        );
+        assert_eq!(
+            kind(&mut scheme, "(hypot 3.0)"),
+            Some(ConditionKind::WrongArity)
+        );
+        let err = scheme.eval_str("(hypot 'x 1.0)").unwrap_err();
+        assert!(err.to_string().contains("hypot"), "{}", err);
+    }
+}
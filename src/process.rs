@@ -0,0 +1,141 @@
+//! Subprocesses, with their standard streams as textual ports.
+
+use crate::ports::{InputPort, OutputPort, Port, PortError, PortKind};
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::io::{self, Cursor, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::rc::Rc;
+use std::thread::{self, JoinHandle};
+
+pub struct Process {
+    child: RefCell<Child>,
+    /// An output port writing to the process's standard input.
+    pub stdin: Rc<Port>,
+    /// Input ports reading the process's standard output and error.
+    pub stdout: Rc<Port>,
+    pub stderr: Rc<Port>,
+    /// The pipes behind `stdout` and `stderr`.
+    pipes: [Pipe; 2],
+}
+
+impl Process {
+    /// Starts `program` with `args`, with all three streams piped.
+    pub fn spawn(
+        program: impl AsRef<OsStr>,
+        args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    ) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = Pipe::new(child.stdout.take().expect("stdout is piped"));
+        let stderr = Pipe::new(child.stderr.take().expect("stderr is piped"));
+        Ok(Self {
+            child: RefCell::new(child),
+            stdin: Rc::new(Port::from(OutputPort::from_writer(
+                stdin,
+                PortKind::Textual,
+            ))),
+            stdout: Rc::new(Port::from(InputPort::from_reader(
+                stdout.clone(),
+                PortKind::Textual,
+            ))),
+            stderr: Rc::new(Port::from(InputPort::from_reader(
+                stderr.clone(),
+                PortKind::Textual,
+            ))),
+            pipes: [stdout, stderr],
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child.borrow().id()
+    }
+
+    /// Waits for the process to exit. Its standard input is closed first,
+    /// so that a process reading to the end of it can finish. Whatever it
+    /// writes to its standard output and error meanwhile is kept in memory,
+    /// so that it cannot block on a full pipe, and read from the ports
+    /// afterwards.
+    pub fn wait(&self) -> Result<ExitStatus, PortError> {
+        self.stdin.close()?;
+        let drains = self.pipes.each_ref().map(Pipe::drain);
+        let status = self.child.borrow_mut().wait();
+        for (pipe, drain) in self.pipes.iter().zip(drains) {
+            pipe.finish(drain)?;
+        }
+        Ok(status?)
+    }
+
+    /// Kills the process, unless it has already exited.
+    pub fn kill(&self) -> io::Result<()> {
+        let mut child = self.child.borrow_mut();
+        match child.try_wait()? {
+            Some(_) => Ok(()),
+            None => child.kill(),
+        }
+    }
+}
+
+/// A pipe from the process, read directly until [`Process::wait`] reads the
+/// rest of it into memory.
+#[derive(Clone)]
+struct Pipe(Rc<RefCell<PipeState>>);
+
+enum PipeState {
+    Open(Box<dyn Read + Send>),
+    /// Being read to the end by a [`Pipe::drain`] thread.
+    Draining,
+    Drained(Cursor<Vec<u8>>),
+}
+
+impl Pipe {
+    fn new(source: impl Read + Send + 'static) -> Self {
+        Self(Rc::new(RefCell::new(PipeState::Open(Box::new(source)))))
+    }
+
+    /// Starts reading the rest of the pipe on another thread, if it is
+    /// still open.
+    fn drain(&self) -> Option<JoinHandle<io::Result<Vec<u8>>>> {
+        let mut state = self.0.borrow_mut();
+        match std::mem::replace(&mut *state, PipeState::Draining) {
+            PipeState::Open(mut source) => Some(thread::spawn(move || {
+                let mut bytes = Vec::new();
+                source.read_to_end(&mut bytes)?;
+                Ok(bytes)
+            })),
+            drained => {
+                *state = drained;
+                None
+            }
+        }
+    }
+
+    /// Keeps what a [`Pipe::drain`] thread read.
+    fn finish(&self, drain: Option<JoinHandle<io::Result<Vec<u8>>>>) -> io::Result<()> {
+        if let Some(drain) = drain {
+            let bytes = drain.join().expect("draining a pipe does not panic");
+            let (bytes, result) = match bytes {
+                Ok(bytes) => (bytes, Ok(())),
+                Err(err) => (Vec::new(), Err(err)),
+            };
+            *self.0.borrow_mut() = PipeState::Drained(Cursor::new(bytes));
+            return result;
+        }
+        Ok(())
+    }
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut *self.0.borrow_mut() {
+            PipeState::Open(source) => source.read(buf),
+            PipeState::Draining => Ok(0),
+            PipeState::Drained(bytes) => bytes.read(buf),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Interned symbols.
+//!
+//! Every distinct symbol name is stored exactly once in a process-wide
+//! table, so comparing two symbols is an integer comparison. Names are never
+//! freed.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct SymbolTable {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, Symbol>,
+}
+
+static SYMBOL_TABLE: LazyLock<Mutex<SymbolTable>> = LazyLock::new(Default::default);
+
+impl Symbol {
+    /// Returns the symbol with the given name, as in `string->symbol`.
+    pub fn intern(name: &str) -> Self {
+        let mut table = SYMBOL_TABLE.lock().unwrap();
+        if let Some(sym) = table.ids.get(name) {
+            return *sym;
+        }
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        let sym = Symbol(table.names.len() as u32);
+        table.names.push(name);
+        table.ids.insert(name, sym);
+        sym
+    }
+
+    /// Returns the name of the symbol, as in `symbol->string`.
+    pub fn name(self) -> &'static str {
+        SYMBOL_TABLE.lock().unwrap().names[self.0 as usize]
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
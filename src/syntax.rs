@@ -0,0 +1,208 @@
+//! Syntax objects: data annotated with where they were read from.
+
+use crate::lexer::Position;
+use crate::value::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+/// A region of source text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub file: Option<Rc<str>>,
+    pub start: Position,
+    /// The position just after the last character of the region.
+    pub end: Position,
+}
+
+impl Span {
+    /// The byte range of the region within its source.
+    pub fn byte_range(&self) -> std::ops::Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:{}", self.start.line, self.start.column)
+    }
+}
+
+#[derive(Clone)]
+pub enum SyntaxKind {
+    /// Any datum without parts: booleans, numbers, characters, strings,
+    /// symbols and bytevectors.
+    Atom(Value),
+    /// A list, with a tail if it was written with a dot.
+    List(Vec<Syntax>, Option<Box<Syntax>>),
+    Vector(Vec<Syntax>),
+    /// `#n=datum`.
+    Labelled(u64, Box<Syntax>),
+    /// `#n#`.
+    LabelRef(u64),
+}
+
+/// A datum with the span of every sub-datum.
+#[derive(Clone)]
+pub struct Syntax {
+    pub kind: SyntaxKind,
+    pub span: Span,
+}
+
+/// The spans of the lists within a datum built by
+/// [`Syntax::to_datum_with_spans`], keyed by their first pair. Entries are
+/// only meaningful while the datum is alive.
+#[derive(Default)]
+pub struct SourceMap {
+    lists: HashMap<usize, Span>,
+}
+
+impl SourceMap {
+    /// The span of `value`, if it is one of the lists that was read.
+    pub fn get(&self, value: &Value) -> Option<&Span> {
+        match value {
+            Value::Pair(pair) => self.lists.get(&(Rc::as_ptr(pair) as usize)),
+            _ => None,
+        }
+    }
+}
+
+impl Syntax {
+    /// Strips the source information, resolving datum labels into shared
+    /// (and possibly circular) structure.
+    pub fn to_datum(&self) -> Value {
+        self.build(None)
+    }
+
+    /// Like [`to_datum`](Self::to_datum), also returning where each list
+    /// in the datum was read from.
+    pub fn to_datum_with_spans(&self) -> (Value, SourceMap) {
+        let mut spans = SourceMap::default();
+        let datum = self.build(Some(&mut spans));
+        (datum, spans)
+    }
+
+    fn build(&self, mut spans: Option<&mut SourceMap>) -> Value {
+        let mut labels: HashMap<u64, Value> = HashMap::new();
+        let mut placeholders = Vec::new();
+        let mut values = Vec::new();
+        let mut pending = vec![Build::Enter(self)];
+        while let Some(task) = pending.pop() {
+            match task {
+                Build::Enter(syntax) => match &syntax.kind {
+                    SyntaxKind::Atom(value) => values.push(value.clone()),
+                    SyntaxKind::LabelRef(label) => values.push(labels[label].clone()),
+                    SyntaxKind::List(items, tail) => {
+                        pending.push(Build::Exit(syntax));
+                        pending.extend(tail.iter().map(|tail| Build::Enter(tail)));
+                        pending.extend(items.iter().rev().map(Build::Enter));
+                    }
+                    SyntaxKind::Vector(items) => {
+                        pending.push(Build::Exit(syntax));
+                        pending.extend(items.iter().rev().map(Build::Enter));
+                    }
+                    SyntaxKind::Labelled(label, inner) => {
+                        let placeholder = Value::cons(Value::Null, Value::Null);
+                        labels.insert(*label, placeholder.clone());
+                        placeholders.push(placeholder);
+                        pending.push(Build::Exit(syntax));
+                        pending.push(Build::Enter(inner));
+                    }
+                },
+                Build::Exit(syntax) => {
+                    let value = match &syntax.kind {
+                        SyntaxKind::List(items, tail) => {
+                            let tail = match tail {
+                                Some(_) => values.pop().expect("list tail"),
+                                None => Value::Null,
+                            };
+                            let items = values.split_off(values.len() - items.len());
+                            let list = Value::list_with_tail(items, tail);
+                            if let (Some(spans), Value::Pair(pair)) = (spans.as_deref_mut(), &list)
+                            {
+                                spans
+                                    .lists
+                                    .insert(Rc::as_ptr(pair) as usize, syntax.span.clone());
+                            }
+                            list
+                        }
+                        SyntaxKind::Vector(items) => {
+                            Value::vector(values.split_off(values.len() - items.len()))
+                        }
+                        SyntaxKind::Labelled(label, _) => {
+                            let placeholder = placeholders.pop().expect("label placeholder");
+                            let datum = values.pop().expect("labelled datum");
+                            patch(&datum, &placeholder, &datum, &mut HashSet::new());
+                            labels.insert(*label, datum.clone());
+                            datum
+                        }
+                        _ => unreachable!(),
+                    };
+                    values.push(value);
+                }
+            }
+        }
+        values.pop().expect("built datum")
+    }
+}
+
+/// A step of [`Syntax::build`], which builds data without recursion:
+/// entering syntax queues its parts, and exiting it assembles their values.
+enum Build<'a> {
+    Enter(&'a Syntax),
+    Exit(&'a Syntax),
+}
+
+fn is_same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Pair(a), Value::Pair(b)) => Rc::ptr_eq(a, b),
+        _ => false,
+    }
+}
+
+/// Replaces every occurrence of `placeholder` within `value` by
+/// `replacement`. Lists are followed down their cdrs in a loop, so only
+/// nesting in the car recurses.
+fn patch(value: &Value, placeholder: &Value, replacement: &Value, seen: &mut HashSet<usize>) {
+    let mut value = value.clone();
+    loop {
+        match &value {
+            Value::Pair(pair) => {
+                if !seen.insert(Rc::as_ptr(pair) as usize) {
+                    return;
+                }
+                let car = pair.car();
+                if is_same(&car, placeholder) {
+                    pair.set_car(replacement.clone());
+                } else {
+                    patch(&car, placeholder, replacement, seen);
+                }
+                let cdr = pair.cdr();
+                if is_same(&cdr, placeholder) {
+                    pair.set_cdr(replacement.clone());
+                    return;
+                }
+                value = cdr;
+            }
+            Value::Vector(items) => {
+                if !seen.insert(Rc::as_ptr(items) as usize) {
+                    return;
+                }
+                let len = items.borrow().len();
+                for i in 0..len {
+                    let item = items.borrow()[i].clone();
+                    if is_same(&item, placeholder) {
+                        items.borrow_mut()[i] = replacement.clone();
+                    } else {
+                        patch(&item, placeholder, replacement, seen);
+                    }
+                }
+                return;
+            }
+            _ => return,
+        }
+    }
+}
@@ -0,0 +1,1123 @@
+//! Scheme values.
+
+use crate::chars;
+use crate::eval::Condition;
+use crate::hash_table::HashTable;
+use crate::net::Socket;
+use crate::num::Number;
+use crate::ports::{Port, Transcoder};
+use crate::proc::Procedure;
+use crate::process::Process;
+use crate::symbol::Symbol;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::ops::{Deref, DerefMut, Range};
+use std::rc::Rc;
+
+/// A Scheme value.
+///
+/// Pairs, strings, vectors and bytevectors are mutable and shared by
+/// reference, so cloning a `Value` never copies them. Since they are
+/// reference counted, cyclic structures are never freed.
+#[derive(Clone)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Number(Number),
+    Character(char),
+    String(Rc<RefCell<SchemeString>>),
+    Symbol(Symbol),
+    Pair(Rc<Pair>),
+    Vector(Rc<RefCell<Vector>>),
+    Bytevector(Rc<RefCell<Bytevector>>),
+    Procedure(Rc<Procedure>),
+    Port(Rc<Port>),
+    Transcoder(Transcoder),
+    Socket(Rc<Socket>),
+    Process(Rc<Process>),
+    HashTable(Rc<HashTable>),
+    /// The result of expressions whose value R7RS leaves unspecified.
+    Unspecified,
+    /// The end-of-file object returned by input procedures.
+    Eof,
+    /// The interpreter's global environment, the only one `eval` takes.
+    Environment,
+    /// Zero or several values returned by `values`. A single value is never
+    /// wrapped.
+    Values(Rc<[Value]>),
+    /// An error object, as raised by `error` or by the runtime.
+    Condition(Rc<Condition>),
+}
+
+pub struct Pair {
+    car: RefCell<Value>,
+    cdr: RefCell<Value>,
+}
+
+impl Pair {
+    pub fn car(&self) -> Value {
+        self.car.borrow().clone()
+    }
+
+    pub fn cdr(&self) -> Value {
+        self.cdr.borrow().clone()
+    }
+
+    pub fn set_car(&self, car: Value) {
+        *self.car.borrow_mut() = car;
+    }
+
+    pub fn set_cdr(&self, cdr: Value) {
+        *self.cdr.borrow_mut() = cdr;
+    }
+}
+
+/// Frees the rest of a list, and any vectors in it, in a loop rather than
+/// by recursion, which would overflow the stack on long lists.
+impl Drop for Pair {
+    fn drop(&mut self) {
+        let car = mem::replace(self.car.get_mut(), Value::Null);
+        let cdr = mem::replace(self.cdr.get_mut(), Value::Null);
+        if has_children(&car) || has_children(&cdr) {
+            release(vec![car, cdr]);
+        }
+    }
+}
+
+/// The items of a vector, which are freed without recursion like the
+/// contents of a pair.
+pub struct Vector(Vec<Value>);
+
+impl Deref for Vector {
+    type Target = Vec<Value>;
+
+    fn deref(&self) -> &Vec<Value> {
+        &self.0
+    }
+}
+
+impl DerefMut for Vector {
+    fn deref_mut(&mut self) -> &mut Vec<Value> {
+        &mut self.0
+    }
+}
+
+impl From<Vec<Value>> for Vector {
+    fn from(items: Vec<Value>) -> Self {
+        Self(items)
+    }
+}
+
+impl Drop for Vector {
+    fn drop(&mut self) {
+        if self.0.iter().any(has_children) {
+            release(mem::take(&mut self.0));
+        }
+    }
+}
+
+/// Whether freeing `value` could free further pairs or vectors.
+fn has_children(value: &Value) -> bool {
+    matches!(value, Value::Pair(_) | Value::Vector(_))
+}
+
+/// Drops `pending`, taking apart the pairs and vectors only it refers to
+/// so that their contents are freed here rather than recursively.
+fn release(mut pending: Vec<Value>) {
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::Pair(pair) => {
+                if let Ok(pair) = Rc::try_unwrap(pair) {
+                    pending.push(pair.car.replace(Value::Null));
+                    pending.push(pair.cdr.replace(Value::Null));
+                }
+            }
+            Value::Vector(items) => {
+                if let Ok(items) = Rc::try_unwrap(items) {
+                    pending.append(&mut items.borrow_mut());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Value {
+    pub fn cons(car: Value, cdr: Value) -> Self {
+        Self::Pair(Rc::new(Pair {
+            car: RefCell::new(car),
+            cdr: RefCell::new(cdr),
+        }))
+    }
+
+    /// Builds a proper list.
+    pub fn list(items: impl IntoIterator<Item = Value>) -> Self {
+        Self::list_with_tail(items, Self::Null)
+    }
+
+    /// Builds a list ending in `tail` rather than the empty list.
+    pub fn list_with_tail(items: impl IntoIterator<Item = Value>, tail: Value) -> Self {
+        let items: Vec<_> = items.into_iter().collect();
+        items
+            .into_iter()
+            .rev()
+            .fold(tail, |cdr, car| Self::cons(car, cdr))
+    }
+
+    pub fn string(s: &str) -> Self {
+        Self::String(Rc::new(RefCell::new(SchemeString::from(s))))
+    }
+
+    pub fn symbol(name: &str) -> Self {
+        Self::Symbol(Symbol::intern(name))
+    }
+
+    pub fn vector(items: Vec<Value>) -> Self {
+        Self::Vector(Rc::new(RefCell::new(Vector(items))))
+    }
+
+    pub fn bytevector(bytes: Vec<u8>) -> Self {
+        Self::Bytevector(Rc::new(RefCell::new(Bytevector::from(bytes))))
+    }
+
+    /// Packs the results of `values`.
+    pub fn values(mut values: Vec<Value>) -> Self {
+        if values.len() == 1 {
+            values.pop().unwrap()
+        } else {
+            Self::Values(values.into())
+        }
+    }
+
+    /// Everything except `#f` counts as true.
+    pub fn is_true(&self) -> bool {
+        !matches!(self, Self::Boolean(false))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// A short name for the type of the value, for messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean(_) => "boolean",
+            Self::Number(_) => "number",
+            Self::Character(_) => "character",
+            Self::String(_) => "string",
+            Self::Symbol(_) => "symbol",
+            Self::Pair(_) => "pair",
+            Self::Vector(_) => "vector",
+            Self::Bytevector(_) => "bytevector",
+            Self::Procedure(_) => "procedure",
+            Self::Port(_) => "port",
+            Self::Transcoder(_) => "transcoder",
+            Self::Socket(_) => "socket",
+            Self::Process(_) => "process",
+            Self::HashTable(_) => "hash table",
+            Self::Unspecified => "unspecified",
+            Self::Eof => "eof object",
+            Self::Environment => "environment",
+            Self::Values(_) => "multiple values",
+            Self::Condition(_) => "error object",
+        }
+    }
+
+    pub fn as_pair(&self) -> Option<&Rc<Pair>> {
+        match self {
+            Self::Pair(pair) => Some(pair),
+            _ => None,
+        }
+    }
+
+    /// Collects the elements of a proper list, or returns `None` if `self`
+    /// is an improper or circular list.
+    pub fn list_to_vec(&self) -> Option<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut slow = self.clone();
+        let mut fast = self.clone();
+        loop {
+            for _ in 0..2 {
+                match fast {
+                    Self::Null => return Some(items),
+                    Self::Pair(pair) => {
+                        items.push(pair.car());
+                        fast = pair.cdr();
+                    }
+                    _ => return None,
+                }
+            }
+            slow = slow.as_pair()?.cdr();
+            if let (Self::Pair(a), Self::Pair(b)) = (&slow, &fast) {
+                if Rc::ptr_eq(a, b) {
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// As in `eq?`. Numbers and characters are not boxed, so they have no
+    /// identity apart from their value and compare as in `eqv?`.
+    pub fn is_eq(&self, other: &Self) -> bool {
+        self.is_eqv(other)
+    }
+
+    /// As in `eqv?`: objects with state are the same if they are the same
+    /// object, and numbers if they have the same exactness and value.
+    pub fn is_eqv(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Null, Self::Null)
+            | (Self::Unspecified, Self::Unspecified)
+            | (Self::Eof, Self::Eof)
+            | (Self::Environment, Self::Environment) => true,
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::Number(a), Self::Number(b)) => eqv_numbers(a, b),
+            (Self::Character(a), Self::Character(b)) => a == b,
+            (Self::Symbol(a), Self::Symbol(b)) => a == b,
+            (Self::String(a), Self::String(b)) => Rc::ptr_eq(a, b),
+            (Self::Pair(a), Self::Pair(b)) => Rc::ptr_eq(a, b),
+            (Self::Vector(a), Self::Vector(b)) => Rc::ptr_eq(a, b),
+            (Self::Bytevector(a), Self::Bytevector(b)) => Rc::ptr_eq(a, b),
+            (Self::Procedure(a), Self::Procedure(b)) => Rc::ptr_eq(a, b),
+            (Self::Port(a), Self::Port(b)) => Rc::ptr_eq(a, b),
+            (Self::Transcoder(a), Self::Transcoder(b)) => a == b,
+            (Self::Socket(a), Self::Socket(b)) => Rc::ptr_eq(a, b),
+            (Self::Process(a), Self::Process(b)) => Rc::ptr_eq(a, b),
+            (Self::HashTable(a), Self::HashTable(b)) => Rc::ptr_eq(a, b),
+            (Self::Condition(a), Self::Condition(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// As in `equal?`: pairs, vectors, strings and bytevectors are compared
+    /// by their contents and everything else by `eqv?`. Terminates on
+    /// circular structures.
+    pub fn is_equal(&self, other: &Self) -> bool {
+        // Most comparisons are of small acyclic data, which a bounded walk
+        // settles without the bookkeeping that cycles need.
+        equal_walk(self, other, Some(EQUAL_BUDGET))
+            .or_else(|| equal_walk(self, other, None))
+            .expect("an unbounded walk always finishes")
+    }
+}
+
+/// Inexact numbers are only `eqv?` if they are the same float, so that
+/// `0.0` and `-0.0` differ while a NaN is `eqv?` to itself.
+fn eqv_numbers(a: &Number, b: &Number) -> bool {
+    match (a, b) {
+        (Number::Real(x), Number::Real(y)) => x.to_bits() == y.to_bits(),
+        (Number::Complex(x), Number::Complex(y)) => {
+            eqv_numbers(x.re(), y.re()) && eqv_numbers(x.im(), y.im())
+        }
+        _ => a.is_exact() && b.is_exact() && a == b,
+    }
+}
+
+/// The pairs and vectors `equal?` visits before it starts tracking them to
+/// detect cycles.
+const EQUAL_BUDGET: usize = 1000;
+
+/// Compares `a` and `b` structurally without recursion. With a budget, gives
+/// up with `None` after visiting that many pairs and vectors. Without one,
+/// it merges the objects it compares into equivalence classes and does not
+/// compare two objects of the same class again, which finishes on cycles.
+fn equal_walk(a: &Value, b: &Value, mut budget: Option<usize>) -> Option<bool> {
+    let mut classes = Classes::default();
+    let mut pending = vec![(a.clone(), b.clone())];
+    while let Some((a, b)) = pending.pop() {
+        let (x, y) = match (&a, &b) {
+            (Value::Pair(x), Value::Pair(y)) => (Rc::as_ptr(x) as usize, Rc::as_ptr(y) as usize),
+            (Value::Vector(x), Value::Vector(y)) => (
+                Rc::as_ptr(x) as *const u8 as usize,
+                Rc::as_ptr(y) as *const u8 as usize,
+            ),
+            (Value::String(x), Value::String(y)) => {
+                if *x.borrow() != *y.borrow() {
+                    return Some(false);
+                }
+                continue;
+            }
+            (Value::Bytevector(x), Value::Bytevector(y)) => {
+                if *x.borrow() != *y.borrow() {
+                    return Some(false);
+                }
+                continue;
+            }
+            _ => {
+                if !a.is_eqv(&b) {
+                    return Some(false);
+                }
+                continue;
+            }
+        };
+        if x == y {
+            continue;
+        }
+        match &mut budget {
+            Some(0) => return None,
+            Some(left) => *left -= 1,
+            None => {
+                if !classes.union(x, y) {
+                    continue;
+                }
+            }
+        }
+        match (&a, &b) {
+            (Value::Pair(x), Value::Pair(y)) => {
+                pending.push((x.cdr(), y.cdr()));
+                pending.push((x.car(), y.car()));
+            }
+            (Value::Vector(x), Value::Vector(y)) => {
+                let (x, y) = (x.borrow(), y.borrow());
+                if x.len() != y.len() {
+                    return Some(false);
+                }
+                pending.extend(x.iter().cloned().zip(y.iter().cloned()).rev());
+            }
+            _ => unreachable!(),
+        }
+    }
+    Some(true)
+}
+
+/// A union-find forest over object addresses.
+#[derive(Default)]
+struct Classes {
+    parents: HashMap<usize, usize>,
+}
+
+impl Classes {
+    fn find(&mut self, addr: usize) -> usize {
+        let mut root = addr;
+        while let Some(&parent) = self.parents.get(&root) {
+            root = parent;
+        }
+        // Point everything on the path straight at the root.
+        let mut node = addr;
+        while let Some(&parent) = self.parents.get(&node) {
+            self.parents.insert(node, root);
+            node = parent;
+        }
+        root
+    }
+
+    /// Merges the classes of `a` and `b`, returning false if they were
+    /// already the same class.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        self.parents.insert(a, b);
+        true
+    }
+}
+
+/// The pairs and vectors [`hash_equal`] looks into. Values that only differ
+/// past this point hash the same, which also makes hashing finish on
+/// circular structures.
+const HASH_BUDGET: usize = 64;
+
+/// A hash consistent with `equal?`: values that are `equal?` have the same
+/// hash.
+pub fn hash_equal(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let mut budget = HASH_BUDGET;
+    let mut pending = vec![value.clone()];
+    while let Some(value) = pending.pop() {
+        mem::discriminant(&value).hash(&mut hasher);
+        match &value {
+            Value::Null | Value::Unspecified | Value::Eof | Value::Environment => {}
+            Value::Boolean(b) => b.hash(&mut hasher),
+            // Numbers print the same exactly when they are `eqv?`, apart
+            // from NaNs, which only need to hash alike.
+            Value::Number(n) => {
+                write!(HashWriter(&mut hasher), "{}", n).expect("hashing cannot fail")
+            }
+            Value::Character(c) => c.hash(&mut hasher),
+            Value::String(s) => s.borrow().hash(&mut hasher),
+            Value::Symbol(sym) => sym.hash(&mut hasher),
+            Value::Bytevector(bytes) => bytes.borrow().hash(&mut hasher),
+            Value::Pair(pair) => {
+                if budget > 0 {
+                    budget -= 1;
+                    pending.push(pair.cdr());
+                    pending.push(pair.car());
+                }
+            }
+            Value::Vector(items) => {
+                let items = items.borrow();
+                items.len().hash(&mut hasher);
+                if budget > 0 {
+                    budget -= 1;
+                    pending.extend(items.iter().rev().cloned());
+                }
+            }
+            Value::Procedure(procedure) => Rc::as_ptr(procedure).hash(&mut hasher),
+            Value::Port(port) => Rc::as_ptr(port).hash(&mut hasher),
+            Value::Transcoder(transcoder) => transcoder.hash(&mut hasher),
+            Value::Socket(socket) => Rc::as_ptr(socket).hash(&mut hasher),
+            Value::Process(process) => Rc::as_ptr(process).hash(&mut hasher),
+            Value::HashTable(table) => Rc::as_ptr(table).hash(&mut hasher),
+            Value::Values(values) => (Rc::as_ptr(values) as *const u8).hash(&mut hasher),
+            Value::Condition(condition) => Rc::as_ptr(condition).hash(&mut hasher),
+        }
+    }
+    hasher.finish()
+}
+
+/// A hash consistent with `eqv?`: objects with state hash by identity, so
+/// that changing them does not change their hash.
+pub fn hash_eqv(value: &Value) -> u64 {
+    let addr = match value {
+        Value::String(s) => Rc::as_ptr(s) as *const u8,
+        Value::Pair(pair) => Rc::as_ptr(pair) as *const u8,
+        Value::Vector(items) => Rc::as_ptr(items) as *const u8,
+        Value::Bytevector(bytes) => Rc::as_ptr(bytes) as *const u8,
+        _ => return hash_equal(value),
+    };
+    let mut hasher = DefaultHasher::new();
+    addr.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Feeds formatted text to a hasher.
+struct HashWriter<'a, H>(&'a mut H);
+
+impl<H: Hasher> fmt::Write for HashWriter<'_, H> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Copies the pairs, vectors, strings and bytevectors in `value`, keeping
+/// the sharing and cycles between them. Everything else, including the
+/// contents of procedures and multiple values, is shared with the original.
+pub fn deep_copy(value: &Value) -> Value {
+    let mut copies = HashMap::new();
+    let mut pending = Vec::new();
+    let copy = copy_of(value, &mut copies, &mut pending);
+    while let Some((original, copy)) = pending.pop() {
+        match (&original, &copy) {
+            (Value::Pair(original), Value::Pair(copy)) => {
+                copy.set_car(copy_of(&original.car(), &mut copies, &mut pending));
+                copy.set_cdr(copy_of(&original.cdr(), &mut copies, &mut pending));
+            }
+            (Value::Vector(original), Value::Vector(copy)) => {
+                let items = original
+                    .borrow()
+                    .iter()
+                    .map(|item| copy_of(item, &mut copies, &mut pending))
+                    .collect();
+                *copy.borrow_mut() = Vector(items);
+            }
+            _ => unreachable!(),
+        }
+    }
+    copy
+}
+
+/// The copy of `value`, made the first time it is reached. New pairs and
+/// vectors start out empty and are queued on `pending` to be filled in.
+fn copy_of(
+    value: &Value,
+    copies: &mut HashMap<usize, Value>,
+    pending: &mut Vec<(Value, Value)>,
+) -> Value {
+    let addr = match value {
+        Value::Pair(pair) => Rc::as_ptr(pair) as usize,
+        Value::Vector(items) => Rc::as_ptr(items) as *const u8 as usize,
+        Value::String(s) => Rc::as_ptr(s) as *const u8 as usize,
+        Value::Bytevector(bytes) => Rc::as_ptr(bytes) as *const u8 as usize,
+        _ => return value.clone(),
+    };
+    if let Some(copy) = copies.get(&addr) {
+        return copy.clone();
+    }
+    let copy = match value {
+        Value::Pair(_) => Value::cons(Value::Null, Value::Null),
+        Value::Vector(_) => Value::vector(Vec::new()),
+        Value::String(s) => Value::String(Rc::new(RefCell::new(s.borrow().clone()))),
+        Value::Bytevector(bytes) => {
+            Value::Bytevector(Rc::new(RefCell::new(bytes.borrow().clone())))
+        }
+        _ => unreachable!(),
+    };
+    if matches!(value, Value::Pair(_) | Value::Vector(_)) {
+        pending.push((value.clone(), copy.clone()));
+    }
+    copies.insert(addr, copy.clone());
+    copy
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Boolean(b)
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Self::Number(Number::from(i))
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Self {
+        Self::Character(c)
+    }
+}
+
+impl From<Symbol> for Value {
+    fn from(sym: Symbol) -> Self {
+        Self::Symbol(sym)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(r: f64) -> Self {
+        Self::Number(Number::from(r))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::string(s)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::string(&s)
+    }
+}
+
+/// Converts to a proper list.
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Self {
+        Self::list(items)
+    }
+}
+
+/// A value that does not have the type a conversion from [`Value`] asked
+/// for.
+#[derive(Clone, Debug)]
+pub struct ConversionError {
+    /// What the value should have been, e.g. "an exact integer".
+    pub expected: &'static str,
+    pub value: Value,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, got {:?}", self.expected, self.value)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Implements `TryFrom<Value>` with a pattern whose bindings produce the
+/// converted value, or `None` to reject it.
+macro_rules! impl_try_from_value {
+    ($($ty:ty, $expected:literal, $pattern:pat => $convert:expr;)*) => {
+        $(
+            impl TryFrom<Value> for $ty {
+                type Error = ConversionError;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    let converted = match &value {
+                        $pattern => $convert,
+                        _ => None,
+                    };
+                    converted.ok_or(ConversionError {
+                        expected: $expected,
+                        value,
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_value! {
+    bool, "a boolean", Value::Boolean(b) => Some(*b);
+    i64, "an exact integer", Value::Number(n) => n.to_i64();
+    f64, "a real number", Value::Number(n) => n.is_real().then(|| n.to_f64());
+    char, "a character", Value::Character(c) => Some(*c);
+    String, "a string", Value::String(s) => Some(s.borrow().to_string());
+    Symbol, "a symbol", Value::Symbol(sym) => Some(*sym);
+    Vec<Value>, "a proper list", list @ (Value::Pair(_) | Value::Null) => list.list_to_vec();
+}
+
+/// A mutable Scheme string.
+///
+/// Strings are stored as a vector of characters rather than UTF-8 so that
+/// `string-ref` and `string-set!` are constant time.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SchemeString {
+    chars: Vec<char>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexError {
+    pub index: usize,
+    pub len: usize,
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "index {} out of range for length {}",
+            self.index, self.len
+        )
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+impl SchemeString {
+    /// As in `make-string`.
+    pub fn filled(len: usize, c: char) -> Self {
+        Self {
+            chars: vec![c; len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// As in `string-ref`.
+    pub fn get(&self, k: usize) -> Result<char, IndexError> {
+        self.chars.get(k).copied().ok_or(IndexError {
+            index: k,
+            len: self.len(),
+        })
+    }
+
+    /// As in `string-set!`.
+    pub fn set(&mut self, k: usize, c: char) -> Result<(), IndexError> {
+        let len = self.len();
+        let slot = self.chars.get_mut(k).ok_or(IndexError { index: k, len })?;
+        *slot = c;
+        Ok(())
+    }
+
+    /// As in `string-fill!`.
+    pub fn fill(&mut self, c: char, range: Range<usize>) -> Result<(), IndexError> {
+        self.check_range(&range)?;
+        self.chars[range].fill(c);
+        Ok(())
+    }
+
+    /// As in `substring` and `string-copy`.
+    pub fn substring(&self, range: Range<usize>) -> Result<Self, IndexError> {
+        self.check_range(&range)?;
+        Ok(Self {
+            chars: self.chars[range].to_vec(),
+        })
+    }
+
+    /// As in `string-copy!`: copies `from[range]` into `self` starting at
+    /// `at`. Use [`SchemeString::copy_within`] when the source is `self`.
+    pub fn copy_from(
+        &mut self,
+        at: usize,
+        from: &Self,
+        range: Range<usize>,
+    ) -> Result<(), IndexError> {
+        from.check_range(&range)?;
+        let end = at + range.len();
+        self.check_range(&(at..end))?;
+        self.chars[at..end].copy_from_slice(&from.chars[range]);
+        Ok(())
+    }
+
+    /// As in `string-copy!` when the source and destination are the same
+    /// string.
+    pub fn copy_within(&mut self, at: usize, range: Range<usize>) -> Result<(), IndexError> {
+        self.check_range(&range)?;
+        self.check_range(&(at..at + range.len()))?;
+        self.chars.copy_within(range, at);
+        Ok(())
+    }
+
+    /// As in `string-append`.
+    pub fn append(&self, other: &Self) -> Self {
+        let mut chars = self.chars.clone();
+        chars.extend_from_slice(&other.chars);
+        Self { chars }
+    }
+
+    /// As in `string-upcase`.
+    pub fn upcase(&self) -> Self {
+        self.chars.iter().flat_map(|c| c.to_uppercase()).collect()
+    }
+
+    /// As in `string-downcase`.
+    pub fn downcase(&self) -> Self {
+        self.chars.iter().flat_map(|c| c.to_lowercase()).collect()
+    }
+
+    /// As in `string-foldcase`.
+    pub fn foldcase(&self) -> Self {
+        let mut chars = Vec::with_capacity(self.len());
+        for c in &self.chars {
+            chars::foldcase_full(*c, &mut chars);
+        }
+        Self { chars }
+    }
+
+    /// Compares two strings after case folding, as in `string-ci=?`,
+    /// `string-ci<?` and the rest of the `-ci` family.
+    pub fn cmp_ci(&self, other: &Self) -> Ordering {
+        self.foldcase().cmp(&other.foldcase())
+    }
+
+    fn check_range(&self, range: &Range<usize>) -> Result<(), IndexError> {
+        let len = self.len();
+        if range.start > range.end {
+            Err(IndexError {
+                index: range.start,
+                len,
+            })
+        } else if range.end > len {
+            Err(IndexError {
+                index: range.end,
+                len,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl From<&str> for SchemeString {
+    fn from(s: &str) -> Self {
+        s.chars().collect()
+    }
+}
+
+impl From<Vec<char>> for SchemeString {
+    fn from(chars: Vec<char>) -> Self {
+        Self { chars }
+    }
+}
+
+impl FromIterator<char> for SchemeString {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        Self {
+            chars: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for SchemeString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in &self.chars {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+/// A mutable vector of bytes.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Bytevector {
+    bytes: Vec<u8>,
+}
+
+macro_rules! impl_bytevector_accessors {
+    ($($ty:ident: $get:ident, $set:ident;)*) => {
+        impl Bytevector {
+            $(
+                #[doc = concat!("As in `bytevector-", stringify!($ty), "-ref`.")]
+                pub fn $get(&self, k: usize, endianness: Endianness) -> Result<$ty, IndexError> {
+                    let bytes = self.window(k, std::mem::size_of::<$ty>())?;
+                    let bytes = bytes.try_into().unwrap();
+                    Ok(match endianness {
+                        Endianness::Big => $ty::from_be_bytes(bytes),
+                        Endianness::Little => $ty::from_le_bytes(bytes),
+                    })
+                }
+
+                #[doc = concat!("As in `bytevector-", stringify!($ty), "-set!`.")]
+                pub fn $set(&mut self, k: usize, value: $ty, endianness: Endianness) -> Result<(), IndexError> {
+                    let bytes = match endianness {
+                        Endianness::Big => value.to_be_bytes(),
+                        Endianness::Little => value.to_le_bytes(),
+                    };
+                    self.window(k, bytes.len())?;
+                    self.bytes[k..k + bytes.len()].copy_from_slice(&bytes);
+                    Ok(())
+                }
+            )*
+        }
+    };
+}
+
+impl_bytevector_accessors! {
+    u16: u16_ref, u16_set;
+    i16: s16_ref, s16_set;
+    u32: u32_ref, u32_set;
+    i32: s32_ref, s32_set;
+    u64: u64_ref, u64_set;
+    i64: s64_ref, s64_set;
+    f32: ieee_single_ref, ieee_single_set;
+    f64: ieee_double_ref, ieee_double_set;
+}
+
+impl Bytevector {
+    /// As in `make-bytevector`.
+    pub fn filled(len: usize, fill: u8) -> Self {
+        Self {
+            bytes: vec![fill; len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// As in `bytevector-u8-ref`.
+    pub fn u8_ref(&self, k: usize) -> Result<u8, IndexError> {
+        Ok(self.window(k, 1)?[0])
+    }
+
+    /// As in `bytevector-u8-set!`.
+    pub fn u8_set(&mut self, k: usize, byte: u8) -> Result<(), IndexError> {
+        self.window(k, 1)?;
+        self.bytes[k] = byte;
+        Ok(())
+    }
+
+    /// As in `bytevector-s8-ref`.
+    pub fn s8_ref(&self, k: usize) -> Result<i8, IndexError> {
+        Ok(self.u8_ref(k)? as i8)
+    }
+
+    /// As in `bytevector-s8-set!`.
+    pub fn s8_set(&mut self, k: usize, byte: i8) -> Result<(), IndexError> {
+        self.u8_set(k, byte as u8)
+    }
+
+    /// As in `bytevector-copy`.
+    pub fn copy(&self, range: Range<usize>) -> Result<Self, IndexError> {
+        self.check_range(&range)?;
+        Ok(Self {
+            bytes: self.bytes[range].to_vec(),
+        })
+    }
+
+    /// As in `bytevector-copy!`. Use [`Bytevector::copy_within`] when the
+    /// source is `self`.
+    pub fn copy_from(
+        &mut self,
+        at: usize,
+        from: &Self,
+        range: Range<usize>,
+    ) -> Result<(), IndexError> {
+        from.check_range(&range)?;
+        let end = at + range.len();
+        self.check_range(&(at..end))?;
+        self.bytes[at..end].copy_from_slice(&from.bytes[range]);
+        Ok(())
+    }
+
+    /// As in `bytevector-copy!` when the source and destination are the
+    /// same bytevector.
+    pub fn copy_within(&mut self, at: usize, range: Range<usize>) -> Result<(), IndexError> {
+        self.check_range(&range)?;
+        self.check_range(&(at..at + range.len()))?;
+        self.bytes.copy_within(range, at);
+        Ok(())
+    }
+
+    /// As in `bytevector-append`.
+    pub fn append(&self, other: &Self) -> Self {
+        let mut bytes = self.bytes.clone();
+        bytes.extend_from_slice(&other.bytes);
+        Self { bytes }
+    }
+
+    /// As in `utf8->string`. Invalid sequences decode to U+FFFD.
+    pub fn utf8_to_string(&self, range: Range<usize>) -> Result<SchemeString, IndexError> {
+        self.check_range(&range)?;
+        Ok(SchemeString::from(
+            String::from_utf8_lossy(&self.bytes[range]).as_ref(),
+        ))
+    }
+
+    /// As in `string->utf8`.
+    pub fn from_string(s: &SchemeString, range: Range<usize>) -> Result<Self, IndexError> {
+        let chars = s.substring(range)?;
+        Ok(Self {
+            bytes: chars.to_string().into_bytes(),
+        })
+    }
+
+    fn window(&self, k: usize, size: usize) -> Result<&[u8], IndexError> {
+        self.bytes.get(k..k + size).ok_or(IndexError {
+            index: k,
+            len: self.len(),
+        })
+    }
+
+    fn check_range(&self, range: &Range<usize>) -> Result<(), IndexError> {
+        let len = self.len();
+        if range.start > range.end {
+            Err(IndexError {
+                index: range.start,
+                len,
+            })
+        } else if range.end > len {
+            Err(IndexError {
+                index: range.end,
+                len,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl From<Vec<u8>> for Bytevector {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_long_lists() {
+        let list = Value::list((0..1_000_000).map(Value::from));
+        drop(list);
+    }
+
+    #[test]
+    fn drops_deeply_nested_data() {
+        let mut value = Value::Null;
+        for i in 0..1_000_000 {
+            value = if i % 2 == 0 {
+                Value::vector(vec![value])
+            } else {
+                Value::cons(value, Value::Null)
+            };
+        }
+        drop(value);
+    }
+
+    fn datum(text: &str) -> Value {
+        crate::parse::parse(text).unwrap().remove(0)
+    }
+
+    /// `(a b)` with the last pair pointing back at the first.
+    fn cycle() -> Value {
+        let value = datum("(a b)");
+        let last = value.as_pair().unwrap().cdr();
+        last.as_pair().unwrap().set_cdr(value.clone());
+        value
+    }
+
+    #[test]
+    fn eqv_compares_numbers_by_exactness_and_value() {
+        assert!(datum("2").is_eqv(&datum("2")));
+        assert!(!datum("2").is_eqv(&datum("2.0")));
+        assert!(datum("100000000000000000000").is_eqv(&datum("100000000000000000000")));
+        assert!(datum("1/2").is_eqv(&datum("2/4")));
+        assert!(!datum("0.0").is_eqv(&datum("-0.0")));
+        assert!(datum("+nan.0").is_eqv(&datum("+nan.0")));
+        assert!(!datum("\"a\"").is_eqv(&datum("\"a\"")));
+        let pair = datum("(1)");
+        assert!(pair.is_eq(&pair.clone()));
+    }
+
+    #[test]
+    fn equal_compares_structure_and_finishes_on_cycles() {
+        assert!(datum("(1 #(2 \"x\") . #u8(3))").is_equal(&datum("(1 #(2 \"x\") . #u8(3))")));
+        assert!(!datum("(1 2)").is_equal(&datum("(1 2.0)")));
+        assert!(!datum("#(1 2)").is_equal(&datum("#(1 2 3)")));
+        assert!(cycle().is_equal(&cycle()));
+        let long = datum("(a b a b)");
+        let tail = long.as_pair().unwrap().cdr().as_pair().unwrap().cdr();
+        tail.as_pair()
+            .unwrap()
+            .cdr()
+            .as_pair()
+            .unwrap()
+            .set_cdr(long.clone());
+        assert!(cycle().is_equal(&long));
+        assert!(!cycle().is_equal(&datum("(a b a b)")));
+        let deep = Value::list((0..5000).map(Value::from));
+        assert!(deep.is_equal(&Value::list((0..5000).map(Value::from))));
+    }
+
+    #[test]
+    fn equal_values_hash_alike() {
+        for (a, b) in [
+            ("(1 \"two\" #(3.5 #\\c))", "(1 \"two\" #(3.5 #\\c))"),
+            ("100000000000000000000", "100000000000000000000"),
+            ("+nan.0", "+nan.0"),
+        ] {
+            assert_eq!(hash_equal(&datum(a)), hash_equal(&datum(b)), "{}", a);
+        }
+        assert_ne!(hash_equal(&datum("(1 2)")), hash_equal(&datum("(2 1)")));
+        assert_eq!(hash_equal(&cycle()), hash_equal(&cycle()));
+    }
+
+    #[test]
+    fn deep_copies_keep_sharing_and_cycles() {
+        let shared = datum("(x)");
+        let original = Value::list([shared.clone(), shared, datum("#(\"s\")")]);
+        let copy = deep_copy(&original);
+        assert!(copy.is_equal(&original));
+        let items = copy.list_to_vec().unwrap();
+        assert!(items[0].is_eq(&items[1]));
+        assert!(!items[0].is_eq(&original.list_to_vec().unwrap()[0]));
+        items[0].as_pair().unwrap().set_car(Value::from(1));
+        assert_eq!(original.to_string(), "((x) (x) #(s))");
+        let looped = deep_copy(&cycle());
+        assert!(looped.is_equal(&cycle()));
+        let second = looped.as_pair().unwrap().cdr();
+        assert!(second.as_pair().unwrap().cdr().is_eq(&looped));
+    }
+}